@@ -15,17 +15,14 @@ use rand::{rngs::OsRng, Rng};
 const BLOCK_SIZE: BlockSize = 4096;
 const DB_SIZE: Address = 64;
 // A stand-in for the indexed database you want to obliviously serve.
-const DATABASE: [[u8; BLOCK_SIZE as usize]; DB_SIZE as usize] =
-    [[0; BLOCK_SIZE as usize]; DB_SIZE as usize];
+static DATABASE: [[u8; BLOCK_SIZE]; DB_SIZE as usize] = [[0; BLOCK_SIZE]; DB_SIZE as usize];
 
 fn main() -> Result<(), OramError> {
     let mut rng = OsRng;
     let mut oram = DefaultOram::<BlockValue<4096>>::new(DB_SIZE, &mut rng)?;
 
     // Read DATABASE into oram.
-    for (i, bytes) in DATABASE.iter().enumerate() {
-        oram.write(i as Address, BlockValue::new(*bytes), &mut rng)?;
-    }
+    oram.load_bytes(&DATABASE.concat(), false, &mut rng)?;
 
     // Now oram can be used to obliviously serve the contents of DATABASE.
     let num_operations = 100;
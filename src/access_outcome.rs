@@ -0,0 +1,49 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! The result of an access, together with flags describing degraded conditions.
+
+/// The result of an access made with `access_checked` (see [`crate::PathOram::access_checked`]).
+///
+/// The flags let an application react to degraded conditions, such as by alerting or resizing,
+/// without parsing logs. They depend on the stash occupancy, which depends on the history of accesses,
+/// so they should not be revealed to untrusted parties.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AccessOutcome<V> {
+    /// The value previously stored at the accessed address.
+    pub value: V,
+    /// Whether a stash (of the ORAM or of its recursive position map) overflowed and grew during the access.
+    /// This is also recorded in the audit log.
+    pub stash_grew: bool,
+    /// Whether, after the access, the overflow area of the stash is at least
+    /// [`NEAR_CAPACITY_PERCENT`] percent full, so that it is likely to grow soon.
+    pub near_capacity: bool,
+    /// Whether a failed or corrupted copy of the data was detected during the access,
+    /// and the access was recovered from a healthy copy (see [`crate::ReplicatedOram::access_checked`]).
+    /// This is never set by an ORAM that keeps a single copy of its data.
+    pub integrity_repaired: bool,
+}
+
+/// The stash occupancy, in percent of the overflow capacity, above which [`AccessOutcome::near_capacity`] is set.
+pub const NEAR_CAPACITY_PERCENT: u64 = 75;
+
+impl<V> AccessOutcome<V> {
+    /// Returns an outcome with no flags set.
+    pub fn new(value: V) -> Self {
+        Self {
+            value,
+            stash_grew: false,
+            near_capacity: false,
+            integrity_repaired: false,
+        }
+    }
+
+    /// Returns true if any flag is set.
+    pub fn is_degraded(&self) -> bool {
+        self.stash_grew | self.near_capacity | self.integrity_repaired
+    }
+}
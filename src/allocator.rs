@@ -0,0 +1,158 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious block allocator over the ORAM address space.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramError, OramRng};
+use rand::Rng;
+
+/// Hands out and reclaims addresses `0 <= address < block_capacity`,
+/// so that dynamic data structures (linked lists, trees) can be built on top of an ORAM
+/// of the same capacity without leaking their allocation patterns.
+///
+/// The free list is a linked list whose links are stored in an ORAM,
+/// so each call to `allocate` or `free` makes exactly one ORAM access.
+/// The only information leaked is the sequence of `allocate` and `free` calls,
+/// and hence the number of blocks in use.
+///
+/// Freeing an address that is not currently allocated corrupts the free list;
+/// it is the caller's responsibility to avoid double frees.
+#[derive(Debug)]
+pub struct BlockAllocator {
+    /// Entry `i` stores `next(i) - (i + 1)` (wrapping), where `next(i)` is the free block following `i`.
+    /// This encoding makes the initial free list `0 -> 1 -> 2 -> ...` correspond to an all-zero ORAM.
+    free_list: DefaultOram<Address>,
+    /// The first block on the free list.
+    head: Address,
+    /// The number of blocks currently allocated.
+    allocated: Address,
+}
+
+impl BlockAllocator {
+    /// Returns a new `BlockAllocator` managing addresses `0 <= address < block_capacity`, all initially free.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(block_capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        Ok(Self {
+            free_list: DefaultOram::new(block_capacity, rng)?,
+            head: 0,
+            allocated: 0,
+        })
+    }
+
+    /// Returns the number of addresses managed by this allocator.
+    pub fn block_capacity(&self) -> Result<Address, OramError> {
+        self.free_list.block_capacity()
+    }
+
+    /// Returns the number of addresses currently allocated.
+    pub fn allocated(&self) -> Address {
+        self.allocated
+    }
+
+    /// Obliviously removes an address from the free list and returns it.
+    ///
+    /// # Errors
+    ///
+    /// If every address is already allocated, returns an `OutOfMemoryError`.
+    pub fn allocate<R: OramRng>(&mut self, rng: &mut R) -> Result<Address, OramError> {
+        let capacity = self.block_capacity()?;
+
+        // This check is not constant-time, but only leaks the number of blocks in use,
+        // which is already revealed by the sequence of calls.
+        if self.allocated == capacity {
+            return Err(OramError::OutOfMemoryError { capacity });
+        }
+
+        let result = self.head;
+        let link = self.free_list.read(result, rng)?;
+        self.head = link.wrapping_add(result).wrapping_add(1);
+        self.allocated += 1;
+
+        Ok(result)
+    }
+
+    /// Obliviously returns `address` to the free list.
+    ///
+    /// # Errors
+    ///
+    /// If `address` is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn free<R: OramRng>(&mut self, address: Address, rng: &mut R) -> Result<(), OramError> {
+        let link = self.head.wrapping_sub(address).wrapping_sub(1);
+        self.free_list.write(address, link, rng)?;
+        self.head = address;
+        self.allocated = self.allocated.saturating_sub(1);
+
+        Ok(())
+    }
+
+    /// Makes one ORAM access to the free list, indistinguishable from that of `allocate` or `free`,
+    /// without changing it.
+    pub(crate) fn dummy_access<R: OramRng>(&mut self, rng: &mut R) -> Result<(), OramError> {
+        let capacity = self.block_capacity()?;
+        self.free_list.read(rng.gen_range(0..capacity), rng)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn allocator_hands_out_every_address_once() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let capacity = 64;
+        let mut allocator = BlockAllocator::new(capacity, &mut rng).unwrap();
+
+        let mut seen = HashSet::new();
+        for _ in 0..capacity {
+            let address = allocator.allocate(&mut rng).unwrap();
+            assert!(address < capacity);
+            assert!(seen.insert(address));
+        }
+
+        assert!(matches!(
+            allocator.allocate(&mut rng),
+            Err(OramError::OutOfMemoryError { capacity: 64 })
+        ));
+    }
+
+    #[test]
+    fn allocator_random_workload() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let capacity = 32;
+        let mut allocator = BlockAllocator::new(capacity, &mut rng).unwrap();
+        let mut live: Vec<Address> = Vec::new();
+
+        for _ in 0..1000 {
+            let should_allocate = rng.gen::<bool>() && live.len() < capacity as usize;
+            if should_allocate || live.is_empty() {
+                let address = allocator.allocate(&mut rng).unwrap();
+                assert!(!live.contains(&address));
+                live.push(address);
+            } else {
+                let victim = live.swap_remove(rng.gen_range(0..live.len()));
+                allocator.free(victim, &mut rng).unwrap();
+            }
+            assert_eq!(allocator.allocated(), live.len() as Address);
+        }
+    }
+
+    #[test]
+    fn allocator_free_out_of_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut allocator = BlockAllocator::new(16, &mut rng).unwrap();
+        allocator.allocate(&mut rng).unwrap();
+        assert!(allocator.free(16, &mut rng).is_err());
+        assert_eq!(allocator.allocated(), 1);
+    }
+}
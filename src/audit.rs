@@ -0,0 +1,149 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A process-wide audit log of security-relevant events.
+//!
+//! Unlike the debug logging emitted through the `log` crate, the audit log is always enabled,
+//! does not depend on a logger being installed, and can be queried programmatically,
+//! for example to export counters to a monitoring system or to fail a deployment health check.
+//!
+//! The crate records stash overflows (which weaken obliviousness), overrides of default parameters,
+//! and reseeds of [`BufferedRng`](crate::rng::BufferedRng)s.
+//! Applications can record further events, such as integrity failures detected by their storage layer
+//! or reseeds of other RNGs they pass to ORAMs, using [`record`].
+
+use std::{collections::VecDeque, sync::Mutex, time::SystemTime};
+
+/// The maximum number of events retained by the audit log. Older events are discarded,
+/// but still counted by [`count`].
+pub const AUDIT_LOG_CAPACITY: usize = 1024;
+
+/// The kinds of security-relevant events recorded in the audit log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AuditEventKind {
+    /// A stash overflowed and was resized, revealing that the overflow occurred.
+    StashOverflow,
+    /// Stored data failed an integrity check.
+    IntegrityFailure,
+    /// A parameter was changed from its default value after construction.
+    ParameterOverride,
+    /// An RNG used by an ORAM was reseeded.
+    RngReseed,
+}
+
+impl AuditEventKind {
+    const ALL: [AuditEventKind; 4] = [
+        AuditEventKind::StashOverflow,
+        AuditEventKind::IntegrityFailure,
+        AuditEventKind::ParameterOverride,
+        AuditEventKind::RngReseed,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A security-relevant event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// The kind of the event.
+    pub kind: AuditEventKind,
+    /// The time at which the event was recorded.
+    pub timestamp: SystemTime,
+    /// A human-readable description of the event.
+    pub detail: String,
+}
+
+struct AuditLog {
+    events: VecDeque<AuditEvent>,
+    counts: [u64; AuditEventKind::ALL.len()],
+}
+
+static AUDIT_LOG: Mutex<AuditLog> = Mutex::new(AuditLog {
+    events: VecDeque::new(),
+    counts: [0; AuditEventKind::ALL.len()],
+});
+
+fn lock() -> std::sync::MutexGuard<'static, AuditLog> {
+    // The audit log is always left consistent, so a panic while holding the lock does not invalidate it.
+    AUDIT_LOG
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records an event of the given kind in the audit log.
+pub fn record(kind: AuditEventKind, detail: impl Into<String>) {
+    let event = AuditEvent {
+        kind,
+        timestamp: SystemTime::now(),
+        detail: detail.into(),
+    };
+
+    let mut log = lock();
+    log.counts[kind.index()] += 1;
+    if log.events.len() == AUDIT_LOG_CAPACITY {
+        log.events.pop_front();
+    }
+    log.events.push_back(event);
+}
+
+/// Returns the number of events of the given kind recorded since the process started
+/// (or since the last call to [`clear`]), including events no longer retained.
+pub fn count(kind: AuditEventKind) -> u64 {
+    lock().counts[kind.index()]
+}
+
+/// Returns the most recent events (at most [`AUDIT_LOG_CAPACITY`]), oldest first.
+pub fn events() -> Vec<AuditEvent> {
+    lock().events.iter().cloned().collect()
+}
+
+/// Discards all recorded events and resets all counters.
+pub fn clear() {
+    let mut log = lock();
+    log.events.clear();
+    log.counts = [0; AuditEventKind::ALL.len()];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path_oram::PathOram, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // The audit log is shared by all tests running in this process, so these tests only check
+    // that counters increase and that their own events are present.
+
+    #[test]
+    fn audit_log_records_events() {
+        let before = count(AuditEventKind::RngReseed);
+        record(AuditEventKind::RngReseed, "audit_log_records_events");
+        assert!(count(AuditEventKind::RngReseed) > before);
+        assert!(events()
+            .iter()
+            .any(|event| event.kind == AuditEventKind::RngReseed
+                && event.detail == "audit_log_records_events"));
+    }
+
+    #[test]
+    fn audit_log_records_stash_overflows_and_overrides() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let before_overflows = count(AuditEventKind::StashOverflow);
+        let before_overrides = count(AuditEventKind::ParameterOverride);
+
+        // A tiny stash with infrequent evictions is certain to overflow.
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 1, 1).unwrap();
+        oram.set_eviction_period(8).unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+
+        assert!(count(AuditEventKind::StashOverflow) > before_overflows);
+        assert!(count(AuditEventKind::ParameterOverride) > before_overrides);
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious bitmap.
+
+use crate::{bucket::BlockValue, path_oram::DefaultOram, Address, Oram, OramError, OramRng};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeLess};
+
+/// The number of bytes in each ORAM block of a bitmap.
+const BITMAP_BLOCK_SIZE: usize = 64;
+/// The number of bits in each ORAM block of a bitmap.
+const BITS_PER_BLOCK: u64 = 8 * BITMAP_BLOCK_SIZE as u64;
+
+/// A set of `num_bits` bits packed densely into [`BlockValue`]s of a [`DefaultOram`],
+/// for membership and allocation bookkeeping that must not reveal which bits are accessed.
+///
+/// ## Security
+///
+/// `set`, `clear`, `test`, and `assign` make exactly one ORAM access and run in constant time.
+/// `count_ones` reads every block of the bitmap, so that neither the range nor the result is revealed.
+#[derive(Debug)]
+pub struct ObliviousBitmap {
+    blocks: DefaultOram<BlockValue<BITMAP_BLOCK_SIZE>>,
+    num_bits: u64,
+}
+
+impl ObliviousBitmap {
+    /// Returns a new bitmap of `num_bits` bits, all 0.
+    ///
+    /// # Errors
+    ///
+    /// If `num_bits` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(num_bits: u64, rng: &mut R) -> Result<Self, OramError> {
+        if num_bits == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of bits".to_string(),
+                parameter_value: num_bits.to_string(),
+            });
+        }
+        let num_blocks = num_bits.div_ceil(BITS_PER_BLOCK).next_power_of_two();
+        Ok(Self {
+            blocks: DefaultOram::new(num_blocks, rng)?,
+            num_bits,
+        })
+    }
+
+    /// Returns the number of bits in the bitmap.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Obliviously sets bit `index` to 1. Returns its previous value.
+    pub fn set<R: OramRng>(&mut self, index: u64, rng: &mut R) -> Result<Choice, OramError> {
+        self.assign(index, 1.into(), rng)
+    }
+
+    /// Obliviously sets bit `index` to 0. Returns its previous value.
+    pub fn clear<R: OramRng>(&mut self, index: u64, rng: &mut R) -> Result<Choice, OramError> {
+        self.assign(index, 0.into(), rng)
+    }
+
+    /// Obliviously sets bit `index` to `value`. Returns its previous value.
+    pub fn assign<R: OramRng>(
+        &mut self,
+        index: u64,
+        value: Choice,
+        rng: &mut R,
+    ) -> Result<Choice, OramError> {
+        self.check_bounds(index)?;
+        let (block, byte, bit) = Self::locate(index)?;
+        let value = value.unwrap_u8() << bit;
+        let callback = |bits: &BlockValue<BITMAP_BLOCK_SIZE>| {
+            let mut result = *bits;
+            for (i, b) in result.data.iter_mut().enumerate() {
+                let updated = (*b & !(1 << bit)) | value;
+                b.conditional_assign(&updated, i.ct_eq(&byte));
+            }
+            result
+        };
+        let bits = self.blocks.access(block, callback, rng)?;
+        Ok(Self::select_bit(&bits, byte, bit))
+    }
+
+    /// Obliviously reads bit `index`.
+    pub fn test<R: OramRng>(&mut self, index: u64, rng: &mut R) -> Result<Choice, OramError> {
+        self.check_bounds(index)?;
+        let (block, byte, bit) = Self::locate(index)?;
+        let bits = self.blocks.read(block, rng)?;
+        Ok(Self::select_bit(&bits, byte, bit))
+    }
+
+    /// Obliviously counts the bits set to 1 among bits `start <= index < end`.
+    /// With `start == 0`, this is the rank of `end`.
+    ///
+    /// # Errors
+    ///
+    /// If `start > end` or `end > num_bits`, returns an `AddressOutOfBoundsError`.
+    pub fn count_ones<R: OramRng>(
+        &mut self,
+        start: u64,
+        end: u64,
+        rng: &mut R,
+    ) -> Result<u64, OramError> {
+        // This check is not constant-time, but only leaks whether the range is well-formed or not.
+        if start > end || end > self.num_bits {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: end.max(start),
+                capacity: self.num_bits,
+            });
+        }
+
+        let mut count = 0u64;
+        let mut index = 0u64;
+        for block in 0..self.blocks.block_capacity()? {
+            let bits = self.blocks.read(block, rng)?;
+            for byte in bits.data {
+                for bit in 0..8 {
+                    let in_range = !index.ct_lt(&start) & index.ct_lt(&end);
+                    let is_set = Choice::from((byte >> bit) & 1);
+                    count += u64::from((in_range & is_set).unwrap_u8());
+                    index += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the block, byte within the block, and bit within the byte of bit `index`.
+    fn locate(index: u64) -> Result<(Address, usize, u8), OramError> {
+        Ok((
+            index / BITS_PER_BLOCK,
+            usize::try_from((index % BITS_PER_BLOCK) / 8)?,
+            u8::try_from(index % 8)?,
+        ))
+    }
+
+    fn select_bit(bits: &BlockValue<BITMAP_BLOCK_SIZE>, byte: usize, bit: u8) -> Choice {
+        let mut result = 0u8;
+        for (i, b) in bits.data.iter().enumerate() {
+            result.conditional_assign(b, i.ct_eq(&byte));
+        }
+        Choice::from((result >> bit) & 1)
+    }
+
+    fn check_bounds(&self, index: u64) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the index is well-formed or not.
+        if index >= self.num_bits {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: self.num_bits,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn bitmap_set_clear_test_and_count() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let num_bits = 1500;
+        let mut bitmap = ObliviousBitmap::new(num_bits, &mut rng).unwrap();
+        let mut mirror = vec![false; num_bits as usize];
+
+        for _ in 0..2000 {
+            let index = rng.gen_range(0..num_bits);
+            let value = rng.gen_bool(0.5);
+            let previous = bitmap
+                .assign(index, Choice::from(u8::from(value)), &mut rng)
+                .unwrap();
+            assert_eq!(bool::from(previous), mirror[index as usize]);
+            mirror[index as usize] = value;
+        }
+        for index in (0..num_bits).step_by(7) {
+            assert_eq!(
+                bool::from(bitmap.test(index, &mut rng).unwrap()),
+                mirror[index as usize]
+            );
+        }
+
+        for (start, end) in [(0, num_bits), (0, 0), (13, 700), (511, 513), (1499, 1500)] {
+            let expected = mirror[start as usize..end as usize]
+                .iter()
+                .filter(|bit| **bit)
+                .count() as u64;
+            assert_eq!(bitmap.count_ones(start, end, &mut rng).unwrap(), expected);
+        }
+
+        bitmap.set(3, &mut rng).unwrap();
+        assert!(bool::from(bitmap.clear(3, &mut rng).unwrap()));
+        assert!(!bool::from(bitmap.test(3, &mut rng).unwrap()));
+        assert!(bitmap.set(num_bits, &mut rng).is_err());
+        assert!(bitmap.count_ones(5, 4, &mut rng).is_err());
+    }
+}
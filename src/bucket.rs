@@ -8,6 +8,8 @@
 //! Block and bucket structures for Path ORAM.
 
 use crate::{BlockSize, OramBlock};
+use std::array::TryFromSliceError;
+use std::ops::{Index, IndexMut};
 use subtle::{Choice, ConditionallySelectable};
 
 use rand::{
@@ -20,59 +22,237 @@ use crate::BucketSize;
 use crate::{utils::TreeIndex, Address};
 use subtle::ConstantTimeEq;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-/// An `OramBlock` consisting of unstructured bytes.
-pub struct BlockValue<const B: BlockSize> {
+#[derive(Clone, Copy, Debug)]
+/// An `OramBlock` consisting of an array of `N` elements of type `T`,
+/// such as the fields of a fixed-size record, accessed without packing them into bytes.
+///
+/// Equality is constant-time: both `==` and [`ConstantTimeEq::ct_eq`] compare every element,
+/// so comparing a value read from an ORAM against a secret does not reveal where they first differ.
+pub struct TypedBlock<T, const N: usize> {
     /// The block's data payload.
-    pub data: [u8; B],
+    pub data: [T; N],
 }
-impl<const B: BlockSize> BlockValue<B> {
-    /// Instantiates a `BlockValue` from an array of `BLOCK_SIZE` bytes.
-    pub fn new(data: [u8; B]) -> Self {
+
+/// An `OramBlock` consisting of `B` unstructured bytes.
+pub type BlockValue<const B: BlockSize> = TypedBlock<u8, B>;
+
+impl<T: OramBlock + ConstantTimeEq, const N: usize> TypedBlock<T, N> {
+    /// Instantiates a `TypedBlock` from an array of `N` elements.
+    pub fn new(data: [T; N]) -> Self {
         Self { data }
     }
+
+    /// Instantiates a `TypedBlock` from a slice of exactly `N` elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TryFromSliceError` if `elements.len() != N`.
+    pub fn from_slice(elements: &[T]) -> Result<Self, TryFromSliceError> {
+        Ok(Self {
+            data: elements.try_into()?,
+        })
+    }
 }
 
-impl<const B: BlockSize> Default for BlockValue<B> {
+impl<const B: BlockSize> BlockValue<B> {
+    /// Returns the bytes of the block.
+    pub fn as_bytes(&self) -> &[u8; B] {
+        &self.data
+    }
+
+    /// Returns the bytes of the block, mutably.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8; B] {
+        &mut self.data
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for TypedBlock<T, N> {
+    fn as_ref(&self) -> &[T] {
+        &self.data
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for TypedBlock<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+impl<T, const N: usize> Index<usize> for TypedBlock<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.data[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for TypedBlock<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.data[index]
+    }
+}
+
+impl<T: OramBlock + ConstantTimeEq, const N: usize> TryFrom<&[T]> for TypedBlock<T, N> {
+    type Error = TryFromSliceError;
+
+    fn try_from(elements: &[T]) -> Result<Self, Self::Error> {
+        Self::from_slice(elements)
+    }
+}
+
+impl<T: OramBlock + ConstantTimeEq, const N: usize> From<[T; N]> for TypedBlock<T, N> {
+    fn from(data: [T; N]) -> Self {
+        Self::new(data)
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for TypedBlock<T, N> {
     fn default() -> Self {
-        BlockValue::<B> { data: [0u8; B] }
+        Self {
+            data: [T::default(); N],
+        }
     }
 }
 
-impl<const B: BlockSize> OramBlock for BlockValue<B> {}
+impl<T: OramBlock + ConstantTimeEq, const N: usize> OramBlock for TypedBlock<T, N> {}
 
-impl<const B: BlockSize> ConditionallySelectable for BlockValue<B> {
+impl<T: ConstantTimeEq, const N: usize> ConstantTimeEq for TypedBlock<T, N> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.data.ct_eq(&other.data)
+    }
+}
+
+impl<T: ConstantTimeEq, const N: usize> PartialEq for TypedBlock<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<T: ConstantTimeEq, const N: usize> Eq for TypedBlock<T, N> {}
+
+impl<T: ConditionallySelectable + Default, const N: usize> ConditionallySelectable
+    for TypedBlock<T, N>
+{
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
-        let mut result = BlockValue::default();
-        for i in 0..B {
-            result.data[i] = u8::conditional_select(&a.data[i], &b.data[i], choice);
+        let mut result = Self::default();
+        for i in 0..N {
+            result.data[i] = T::conditional_select(&a.data[i], &b.data[i], choice);
         }
         result
     }
 }
 
-impl<const B: BlockSize> Distribution<BlockValue<B>> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> BlockValue<B> {
-        let mut result = BlockValue::default();
-        for i in 0..B {
+impl<T: Copy + Default, const N: usize> Distribution<TypedBlock<T, N>> for Standard
+where
+    Standard: Distribution<T>,
+{
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> TypedBlock<T, N> {
+        let mut result = TypedBlock::default();
+        for i in 0..N {
             result.data[i] = rng.gen();
         }
         result
     }
 }
 
+/// An `OramBlock` storing a `bool`, encoded as a byte that is always 0 or 1.
+/// (`bool` itself cannot implement `OramBlock`, since `ConditionallySelectable` is a foreign trait.)
+///
+/// Conversions to and from `bool`, `Choice`, and bytes are constant-time,
+/// and any nonzero byte is normalized to 1, so equality never depends on which nonzero byte encoded `true`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OramBool(u8);
+
+impl OramBool {
+    /// Returns an `OramBool` holding `value`.
+    pub fn new(value: bool) -> Self {
+        Self(u8::from(value))
+    }
+
+    /// Returns an `OramBool` holding whether `byte` is nonzero, in constant time.
+    pub fn from_byte(byte: u8) -> Self {
+        Self((byte | byte.wrapping_neg()) >> 7)
+    }
+
+    /// Returns the value held.
+    pub fn get(self) -> bool {
+        self.0 == 1
+    }
+
+    /// Returns the value held as a `Choice`, for use with the constant-time operations of [`subtle`].
+    pub fn choice(self) -> Choice {
+        Choice::from(self.0)
+    }
+}
+
+impl From<bool> for OramBool {
+    fn from(value: bool) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<OramBool> for bool {
+    fn from(value: OramBool) -> Self {
+        value.get()
+    }
+}
+
+impl From<Choice> for OramBool {
+    fn from(choice: Choice) -> Self {
+        Self(choice.unwrap_u8())
+    }
+}
+
+impl From<OramBool> for Choice {
+    fn from(value: OramBool) -> Self {
+        value.choice()
+    }
+}
+
+impl std::ops::Not for OramBool {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0 ^ 1)
+    }
+}
+
+impl ConditionallySelectable for OramBool {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self(u8::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for OramBool {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl OramBlock for OramBool {}
+
+impl Distribution<OramBool> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> OramBool {
+        OramBool::new(rng.gen())
+    }
+}
+
 #[derive(Clone, Copy, Default, PartialEq)]
 /// A Path ORAM block combines an `OramBlock` V with two metadata fields; its ORAM `address` and its `position` in the tree.
-pub(crate) struct PathOramBlock<V> {
+pub struct PathOramBlock<V> {
+    /// The value of the block.
     pub value: V,
+    /// The ORAM address of the block.
     pub address: Address,
+    /// The leaf to which the block is mapped, or 0 if the block is a dummy.
     pub position: TreeIndex,
 }
 
 impl<V: OramBlock> PathOramBlock<V> {
-    const DUMMY_ADDRESS: Address = Address::MAX;
+    pub(crate) const DUMMY_ADDRESS: Address = Address::MAX;
     const DUMMY_POSITION: TreeIndex = 0;
 
+    /// Returns a dummy block.
     pub fn dummy() -> Self {
         Self {
             value: V::default(),
@@ -81,11 +261,12 @@ impl<V: OramBlock> PathOramBlock<V> {
         }
     }
 
+    /// Returns whether the block is a dummy, in constant time.
     pub fn ct_is_dummy(&self) -> Choice {
         self.position.ct_eq(&Self::DUMMY_POSITION)
     }
 
-    #[cfg(test)]
+    /// Returns whether the block is a dummy. This is not constant-time.
     pub fn is_dummy(&self) -> bool {
         self.position == Self::DUMMY_POSITION
     }
@@ -121,8 +302,9 @@ impl<V: ConditionallySelectable> ConditionallySelectable for PathOramBlock<V> {
 }
 
 #[repr(align(64))]
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, Debug)]
 /// An `OramBlock` storing addresses, intended for use in a position map ORAM.
+/// As for [`BlockValue`], equality is constant-time.
 pub struct PositionBlock<const B: BlockSize> {
     /// The Path ORAM positions stored in this block.
     pub data: [TreeIndex; B],
@@ -156,11 +338,25 @@ impl<const B: BlockSize> Distribution<PositionBlock<B>> for Standard {
 
 impl<const B: BlockSize> OramBlock for PositionBlock<B> {}
 
+impl<const B: BlockSize> ConstantTimeEq for PositionBlock<B> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.data.ct_eq(&other.data)
+    }
+}
+
+impl<const B: BlockSize> PartialEq for PositionBlock<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<const B: BlockSize> Eq for PositionBlock<B> {}
+
 #[derive(Clone, Copy, PartialEq)]
 /// A Path ORAM bucket.
 pub struct Bucket<V: OramBlock, const Z: BucketSize> {
     /// The Path ORAM blocks stored by this bucket.
-    pub(crate) blocks: [PathOramBlock<V>; Z],
+    pub blocks: [PathOramBlock<V>; Z],
 }
 
 impl<V: OramBlock, const Z: BucketSize> std::fmt::Debug for Bucket<V, Z> {
@@ -203,3 +399,235 @@ impl<V: OramBlock, const Z: BucketSize> ConditionallySelectable for Bucket<V, Z>
 }
 
 impl<V: OramBlock, const Z: BucketSize> OramBlock for Bucket<V, Z> {}
+
+#[cfg(feature = "serde")]
+impl<const B: BlockSize> serde::Serialize for BlockValue<B> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.data)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const B: BlockSize> serde::Deserialize<'de> for BlockValue<B> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BlockValueVisitor<const B: BlockSize>;
+
+        impl<'de, const B: BlockSize> serde::de::Visitor<'de> for BlockValueVisitor<B> {
+            type Value = BlockValue<B>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{B} bytes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                BlockValue::from_slice(bytes).map_err(|_| E::invalid_length(bytes.len(), &self))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut result = BlockValue::default();
+                for (i, byte) in result.data.iter_mut().enumerate() {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(serde::de::Error::invalid_length(B + 1, &self));
+                }
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_bytes(BlockValueVisitor::<B>)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const B: BlockSize> serde::Serialize for PositionBlock<B> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(B)?;
+        for position in &self.data {
+            tuple.serialize_element(position)?;
+        }
+        tuple.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const B: BlockSize> serde::Deserialize<'de> for PositionBlock<B> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PositionBlockVisitor<const B: BlockSize>;
+
+        impl<'de, const B: BlockSize> serde::de::Visitor<'de> for PositionBlockVisitor<B> {
+            type Value = PositionBlock<B>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{B} positions")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut result = PositionBlock::default();
+                for (i, position) in result.data.iter_mut().enumerate() {
+                    *position = seq
+                        .next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_tuple(B, PositionBlockVisitor::<B>)
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl<T: proptest::arbitrary::Arbitrary + Copy + Default, const N: usize>
+    proptest::arbitrary::Arbitrary for TypedBlock<T, N>
+where
+    T::Strategy: 'static,
+{
+    type Parameters = T::Parameters;
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(parameters: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::collection::vec(proptest::arbitrary::any_with::<T>(parameters), N)
+            .prop_map(|data| {
+                let mut result = Self::default();
+                result.data.copy_from_slice(&data);
+                result
+            })
+            .boxed()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl<const B: BlockSize> proptest::arbitrary::Arbitrary for PositionBlock<B> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::collection::vec(proptest::arbitrary::any::<TreeIndex>(), B)
+            .prop_map(|data| {
+                let mut result = Self::default();
+                result.data.copy_from_slice(&data);
+                result
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{path_oram::PathOram, test_utils::random_workload, DefaultOram, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn block_value_byte_accessors() {
+        let bytes = [1u8, 2, 3, 4];
+        let mut block = BlockValue::<4>::from_slice(&bytes).unwrap();
+        assert_eq!(block.as_bytes(), &bytes);
+        assert_eq!(block.as_ref(), &bytes[..]);
+
+        block.as_bytes_mut()[0] = 9;
+        assert_eq!(block, BlockValue::from([9, 2, 3, 4]));
+
+        assert!(BlockValue::<4>::try_from(&bytes[..3]).is_err());
+        assert_eq!(BlockValue::<2>::try_from(&bytes[2..]).unwrap().data, [3, 4]);
+    }
+
+    #[test]
+    fn block_equality_is_constant_time() {
+        let block = BlockValue::new([1u8, 2, 3]);
+        assert!(bool::from(block.ct_eq(&BlockValue::new([1, 2, 3]))));
+        assert!(!bool::from(block.ct_eq(&BlockValue::new([1, 2, 4]))));
+        assert!(!bool::from(block.ct_eq(&BlockValue::new([0, 2, 3]))));
+        assert_ne!(block, BlockValue::default());
+
+        let positions = PositionBlock { data: [5, 6] };
+        assert!(bool::from(positions.ct_eq(&PositionBlock { data: [5, 6] })));
+        assert!(!bool::from(
+            positions.ct_eq(&PositionBlock { data: [5, 7] })
+        ));
+        assert_eq!(positions, PositionBlock { data: [5, 6] });
+    }
+
+    #[test]
+    fn typed_block_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<TypedBlock<u32, 3>>::new(64, &mut rng).unwrap();
+        oram.write(5, TypedBlock::new([1, 2, 3]), &mut rng).unwrap();
+        let previous = oram
+            .access(
+                5,
+                |record| {
+                    let mut record = *record;
+                    record[1] += 40;
+                    record
+                },
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(previous.data, [1, 2, 3]);
+        assert_eq!(oram.read(5, &mut rng).unwrap()[1], 42);
+        assert!(TypedBlock::<u64, 2>::from_slice(&[1, 2, 3]).is_err());
+
+        let mut oram =
+            PathOram::<TypedBlock<u64, 2>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        random_workload(&mut oram, 500);
+    }
+
+    #[test]
+    fn oram_bool_correctness() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(OramBool::from_byte(byte).get(), byte != 0);
+        }
+        assert_eq!(OramBool::from_byte(7), OramBool::new(true));
+        assert!(!bool::from(!OramBool::new(true)));
+        assert_eq!(OramBool::from(Choice::from(1)), OramBool::new(true));
+        assert_eq!(OramBool::new(false).choice().unwrap_u8(), 0);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<OramBool>::new(64, &mut rng).unwrap();
+        random_workload(&mut oram, 500);
+        let previous = oram.access(3, |flag| !*flag, &mut rng).unwrap();
+        assert_eq!(oram.read(3, &mut rng).unwrap(), !previous);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_blocks_round_trip(block: BlockValue<5>, positions: PositionBlock<3>) {
+            proptest::prop_assert_eq!(BlockValue::<5>::from_slice(block.as_bytes()).unwrap(), block);
+            proptest::prop_assert_eq!(PositionBlock::<3> { data: positions.data }, positions);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let block = BlockValue::new([7u8, 0, 255, 3, 1]);
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(serde_json::from_str::<BlockValue<5>>(&json).unwrap(), block);
+        assert!(serde_json::from_str::<BlockValue<4>>(&json).is_err());
+
+        let positions = PositionBlock {
+            data: [1, 2, u64::MAX],
+        };
+        let json = serde_json::to_string(&positions).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PositionBlock<3>>(&json)
+                .unwrap()
+                .data,
+            positions.data
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An analytic model of the cost of Path ORAM accesses.
+
+use crate::{
+    path_oram::MAXIMUM_TREE_HEIGHT, position_map::collapses_into_base, utils::TreeIndex, Address,
+    BlockSize, BucketSize, OramError, RecursionCutoff,
+};
+use std::mem::size_of;
+
+/// The physical operations performed on one level of a (recursive) ORAM by a single logical access.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelCost {
+    /// The number of blocks stored by this level.
+    pub block_capacity: Address,
+    /// The number of buckets (or, for the linear-scan base of the position map, entries) read.
+    pub physical_reads: u64,
+    /// The number of buckets (or entries) written.
+    pub physical_writes: u64,
+    /// The number of bytes read, including block metadata.
+    pub bytes_read: u64,
+    /// The number of bytes written, including block metadata.
+    pub bytes_written: u64,
+}
+
+/// The predicted cost of a single logical access to a [`crate::PathOram`].
+///
+/// Path ORAM reads and writes the same number of buckets on every access, whatever the address,
+/// so these costs are exact for the default eviction strategy; they do not include scheduled evictions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessCost {
+    /// The cost of each level. The first level stores the data blocks; each subsequent level
+    /// is the position map of the previous one, and the last level is a linear-scan position map.
+    pub levels: Vec<LevelCost>,
+}
+
+impl AccessCost {
+    /// Returns the number of recursive position map ORAMs accessed. This is 0 when the position map is a linear scan.
+    pub fn recursion_depth(&self) -> u64 {
+        self.levels.len().saturating_sub(2) as u64
+    }
+
+    /// Returns the total number of physical reads, across all levels.
+    pub fn physical_reads(&self) -> u64 {
+        self.levels.iter().map(|level| level.physical_reads).sum()
+    }
+
+    /// Returns the total number of physical writes, across all levels.
+    pub fn physical_writes(&self) -> u64 {
+        self.levels.iter().map(|level| level.physical_writes).sum()
+    }
+
+    /// Returns the total number of bytes read, across all levels.
+    pub fn bytes_read(&self) -> u64 {
+        self.levels.iter().map(|level| level.bytes_read).sum()
+    }
+
+    /// Returns the total number of bytes written, across all levels.
+    pub fn bytes_written(&self) -> u64 {
+        self.levels.iter().map(|level| level.bytes_written).sum()
+    }
+}
+
+/// Returns the predicted cost of a logical access to a `PathOram` storing `block_capacity` values
+/// of `block_size` bytes, with `Z` blocks per bucket, `AB` positions per position block, and a recursion cutoff
+/// of `recursion_cutoff`, without allocating it.
+///
+/// Buckets are counted as `Z` blocks of their value plus a 64-bit address and position,
+/// and the entries of the linear-scan position map as blocks of `AB` 64-bit positions.
+///
+/// # Errors
+///
+/// Returns an `InvalidConfigurationError` under the same conditions as [`crate::PathOram::new_with_parameters`].
+pub fn cost_model(
+    block_capacity: Address,
+    block_size: BlockSize,
+    blocks_per_bucket: BucketSize,
+    positions_per_block: BlockSize,
+    recursion_cutoff: RecursionCutoff,
+) -> Result<AccessCost, OramError> {
+    if !block_capacity.is_power_of_two() | (block_capacity <= 1) {
+        return Err(OramError::InvalidConfigurationError {
+            parameter_name: "ORAM capacity".to_string(),
+            parameter_value: block_capacity.to_string(),
+        });
+    }
+    if blocks_per_bucket <= 1 {
+        return Err(OramError::InvalidConfigurationError {
+            parameter_name: "Bucket size Z".to_string(),
+            parameter_value: blocks_per_bucket.to_string(),
+        });
+    }
+    if (positions_per_block < 2) | !positions_per_block.is_power_of_two() {
+        return Err(OramError::InvalidConfigurationError {
+            parameter_name: "Position block size AB".to_string(),
+            parameter_value: positions_per_block.to_string(),
+        });
+    }
+    if recursion_cutoff == 0 {
+        return Err(OramError::InvalidConfigurationError {
+            parameter_name: "Recursion cutoff".to_string(),
+            parameter_value: recursion_cutoff.to_string(),
+        });
+    }
+    if u64::from(block_capacity.ilog2() - 1) > MAXIMUM_TREE_HEIGHT {
+        return Err(OramError::InvalidConfigurationError {
+            parameter_name: "ORAM capacity".to_string(),
+            parameter_value: block_capacity.to_string(),
+        });
+    }
+
+    let z = u64::try_from(blocks_per_bucket)?;
+    let ab = Address::try_from(positions_per_block)?;
+    let metadata_bytes = u64::try_from(2 * size_of::<Address>())?;
+    let position_block_bytes = ab * u64::try_from(size_of::<TreeIndex>())?;
+    let tree_level = |capacity: Address, value_bytes: u64| {
+        let path_length = u64::from(capacity.ilog2());
+        let path_bytes = path_length * z * (value_bytes + metadata_bytes);
+        LevelCost {
+            block_capacity: capacity,
+            physical_reads: path_length,
+            physical_writes: path_length,
+            bytes_read: path_bytes,
+            bytes_written: path_bytes,
+        }
+    };
+
+    let mut levels = vec![tree_level(block_capacity, u64::try_from(block_size)?)];
+    let mut capacity = block_capacity;
+    while (capacity / ab > recursion_cutoff)
+        & !collapses_into_base(capacity / ab, blocks_per_bucket)
+    {
+        capacity /= ab;
+        levels.push(tree_level(capacity, position_block_bytes));
+    }
+
+    let base_entries = capacity.div_ceil(ab);
+    levels.push(LevelCost {
+        block_capacity: base_entries,
+        physical_reads: base_entries,
+        physical_writes: base_entries,
+        bytes_read: base_entries * position_block_bytes,
+        bytes_written: base_entries * position_block_bytes,
+    });
+    Ok(AccessCost { levels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::{PathOramSimulator, SimulationParameters};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn cost_model_matches_simulation() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for (block_capacity, block_size, z, ab, cutoff) in [
+            (1 << 12, 64, 4, 8, 8),
+            (1 << 10, 4096, 3, 2, 1),
+            (1 << 14, 1, 5, 64, 1 << 14),
+        ] {
+            let cost = cost_model(block_capacity, block_size, z, ab, cutoff).unwrap();
+            let parameters = SimulationParameters {
+                block_capacity,
+                block_size,
+                blocks_per_bucket: z,
+                positions_per_block: ab,
+                recursion_cutoff: cutoff,
+                ..Default::default()
+            };
+            let mut simulator = PathOramSimulator::new(parameters, &mut rng).unwrap();
+            let report = simulator.run([0, block_capacity - 1], &mut rng).unwrap();
+
+            assert_eq!(cost.levels.len(), report.levels.len());
+            for (level, traffic) in cost.levels.iter().zip(&report.levels) {
+                assert_eq!(2 * level.physical_reads, traffic.physical_reads);
+                assert_eq!(2 * level.physical_writes, traffic.physical_writes);
+                assert_eq!(2 * level.bytes_read, traffic.bytes_read);
+                assert_eq!(2 * level.bytes_written, traffic.bytes_written);
+            }
+        }
+    }
+
+    #[test]
+    fn cost_model_counts_recursion() {
+        let cost = cost_model(1 << 20, 64, 4, 8, 1 << 10).unwrap();
+        // Trees of 2^20, 2^17, 2^14 and 2^11 blocks, then a linear scan of 2^8 entries.
+        let capacities: Vec<Address> = cost.levels.iter().map(|l| l.block_capacity).collect();
+        assert_eq!(capacities, vec![1 << 20, 1 << 17, 1 << 14, 1 << 11, 1 << 8]);
+        assert_eq!(cost.recursion_depth(), 3);
+        assert_eq!(cost.physical_reads(), 20 + 17 + 14 + 11 + (1 << 8));
+        assert_eq!(cost.bytes_read(), cost.bytes_written());
+        assert_eq!(cost.levels[0].bytes_read, 20 * 4 * (64 + 16));
+
+        assert!(cost_model(1 << 20, 64, 4, 6, 1 << 10).is_err());
+        assert!(cost_model(1000, 64, 4, 8, 1 << 10).is_err());
+    }
+}
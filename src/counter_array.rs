@@ -0,0 +1,175 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious array of small saturating counters.
+
+use crate::{bucket::BlockValue, path_oram::DefaultOram, Address, Oram, OramError, OramRng};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+/// The number of (one-byte) counters packed into each ORAM block.
+pub const COUNTERS_PER_BLOCK: usize = 64;
+
+/// A compact array of `num_counters` one-byte counters, packed [`COUNTERS_PER_BLOCK`] per block of a [`DefaultOram`],
+/// for example for private telemetry or frequency counting.
+///
+/// Counters saturate at `u8::MAX` instead of wrapping.
+///
+/// ## Security
+///
+/// Every operation makes exactly one ORAM access and runs in constant time,
+/// regardless of the counter accessed or its value.
+#[derive(Debug)]
+pub struct ObliviousCounterArray {
+    blocks: DefaultOram<BlockValue<COUNTERS_PER_BLOCK>>,
+    num_counters: Address,
+}
+
+impl ObliviousCounterArray {
+    /// Returns a new array of `num_counters` counters, all 0.
+    ///
+    /// # Errors
+    ///
+    /// If `num_counters` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(num_counters: Address, rng: &mut R) -> Result<Self, OramError> {
+        if num_counters == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of counters".to_string(),
+                parameter_value: num_counters.to_string(),
+            });
+        }
+        let num_blocks = num_counters
+            .div_ceil(Address::try_from(COUNTERS_PER_BLOCK)?)
+            .next_power_of_two();
+        Ok(Self {
+            blocks: DefaultOram::new(num_blocks, rng)?,
+            num_counters,
+        })
+    }
+
+    /// Returns the number of counters.
+    pub fn num_counters(&self) -> Address {
+        self.num_counters
+    }
+
+    /// Obliviously increments counter `key` by 1, saturating. Returns the new value.
+    pub fn increment<R: OramRng>(&mut self, key: Address, rng: &mut R) -> Result<u8, OramError> {
+        self.add(key, 1, rng)
+    }
+
+    /// Obliviously adds `delta` to counter `key`, saturating. Returns the new value.
+    pub fn add<R: OramRng>(
+        &mut self,
+        key: Address,
+        delta: u8,
+        rng: &mut R,
+    ) -> Result<u8, OramError> {
+        Ok(self.update(key, rng, delta)?.saturating_add(delta))
+    }
+
+    /// Obliviously reads counter `key`.
+    pub fn read<R: OramRng>(&mut self, key: Address, rng: &mut R) -> Result<u8, OramError> {
+        self.update(key, rng, 0)
+    }
+
+    /// Obliviously resets counter `key` to 0. Returns its previous value.
+    pub fn reset<R: OramRng>(&mut self, key: Address, rng: &mut R) -> Result<u8, OramError> {
+        self.check_bounds(key)?;
+        let (block, offset) = Self::locate(key)?;
+        let callback = |counters: &BlockValue<COUNTERS_PER_BLOCK>| {
+            let mut result = *counters;
+            for (i, counter) in result.data.iter_mut().enumerate() {
+                counter.conditional_assign(&0, i.ct_eq(&offset));
+            }
+            result
+        };
+        let counters = self.blocks.access(block, callback, rng)?;
+        Ok(Self::select(&counters, offset))
+    }
+
+    /// Adds `delta` to counter `key` (saturating), and returns its previous value.
+    fn update<R: OramRng>(
+        &mut self,
+        key: Address,
+        rng: &mut R,
+        delta: u8,
+    ) -> Result<u8, OramError> {
+        self.check_bounds(key)?;
+        let (block, offset) = Self::locate(key)?;
+        let callback = |counters: &BlockValue<COUNTERS_PER_BLOCK>| {
+            let mut result = *counters;
+            for (i, counter) in result.data.iter_mut().enumerate() {
+                let updated = counter.saturating_add(delta);
+                counter.conditional_assign(&updated, i.ct_eq(&offset));
+            }
+            result
+        };
+        let counters = self.blocks.access(block, callback, rng)?;
+        Ok(Self::select(&counters, offset))
+    }
+
+    fn locate(key: Address) -> Result<(Address, usize), OramError> {
+        let counters_per_block = Address::try_from(COUNTERS_PER_BLOCK)?;
+        Ok((
+            key / counters_per_block,
+            usize::try_from(key % counters_per_block)?,
+        ))
+    }
+
+    fn select(counters: &BlockValue<COUNTERS_PER_BLOCK>, offset: usize) -> u8 {
+        let mut result = 0;
+        for (i, counter) in counters.data.iter().enumerate() {
+            result.conditional_assign(counter, i.ct_eq(&offset));
+        }
+        result
+    }
+
+    fn check_bounds(&self, key: Address) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the key is well-formed or not.
+        if key >= self.num_counters {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: key,
+                capacity: self.num_counters,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn counters_count_and_saturate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let num_counters = 1000;
+        let mut counters = ObliviousCounterArray::new(num_counters, &mut rng).unwrap();
+        let mut mirror = vec![0u8; num_counters as usize];
+
+        for _ in 0..3000 {
+            let key = rng.gen_range(0..num_counters);
+            let delta = rng.gen_range(0..4);
+            let expected = mirror[key as usize].saturating_add(delta);
+            mirror[key as usize] = expected;
+            assert_eq!(counters.add(key, delta, &mut rng).unwrap(), expected);
+        }
+        for key in 0..num_counters {
+            assert_eq!(counters.read(key, &mut rng).unwrap(), mirror[key as usize]);
+        }
+
+        counters.add(7, 250, &mut rng).unwrap();
+        for _ in 0..10 {
+            counters.increment(7, &mut rng).unwrap();
+        }
+        assert_eq!(counters.read(7, &mut rng).unwrap(), u8::MAX);
+        assert_eq!(counters.reset(7, &mut rng).unwrap(), u8::MAX);
+        assert_eq!(counters.read(7, &mut rng).unwrap(), 0);
+        assert!(counters.increment(num_counters, &mut rng).is_err());
+    }
+}
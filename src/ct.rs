@@ -0,0 +1,186 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Constant-time helpers for composing oblivious code with this crate.
+//!
+//! These helpers are thin, documented wrappers around the traits of the [`subtle`] crate,
+//! covering the operations that applications built on ORAMs most often need:
+//! selecting and swapping values, taking minima, maxima, and clamps of integers,
+//! and reading, writing, and sorting arrays at secret indices.
+//! Their running time and memory access pattern depend only on the lengths of their arguments,
+//! never on their values or on the secret `Choice`s and indices passed to them.
+
+use crate::utils::bitonic_sort_by_keys;
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
+
+/// Returns `a` if `choice` is 0 and `b` if `choice` is 1.
+pub fn select<T: ConditionallySelectable>(a: &T, b: &T, choice: Choice) -> T {
+    T::conditional_select(a, b, choice)
+}
+
+/// Swaps `a` and `b` if `choice` is 1, and leaves them unchanged if `choice` is 0.
+pub fn swap<T: ConditionallySelectable>(a: &mut T, b: &mut T, choice: Choice) {
+    T::conditional_swap(a, b, choice)
+}
+
+/// Returns the smaller of `a` and `b`.
+pub fn min<T: ConditionallySelectable + ConstantTimeLess>(a: T, b: T) -> T {
+    T::conditional_select(&a, &b, b.ct_lt(&a))
+}
+
+/// Returns the larger of `a` and `b`.
+pub fn max<T: ConditionallySelectable + ConstantTimeGreater>(a: T, b: T) -> T {
+    T::conditional_select(&a, &b, b.ct_gt(&a))
+}
+
+/// Returns `value` restricted to the interval `[lo, hi]`. Assumes that `lo <= hi`.
+pub fn clamp<T: ConditionallySelectable + ConstantTimeGreater + ConstantTimeLess>(
+    value: T,
+    lo: T,
+    hi: T,
+) -> T {
+    min(max(value, lo), hi)
+}
+
+/// Overwrites `dst` with `src` if `choice` is 1.
+///
+/// # Panics
+///
+/// Panics if `dst` and `src` have different lengths.
+pub fn conditional_assign_slice<T: ConditionallySelectable>(
+    dst: &mut [T],
+    src: &[T],
+    choice: Choice,
+) {
+    assert_eq!(dst.len(), src.len());
+    for (d, s) in dst.iter_mut().zip(src) {
+        d.conditional_assign(s, choice);
+    }
+}
+
+/// Swaps the contents of `a` and `b` if `choice` is 1.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn conditional_swap_slice<T: ConditionallySelectable>(
+    a: &mut [T],
+    b: &mut [T],
+    choice: Choice,
+) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        T::conditional_swap(x, y, choice);
+    }
+}
+
+/// Returns `array[index]` by scanning the entire array, or `T::default()` if `index` is out of bounds.
+pub fn lookup<T: ConditionallySelectable + Default>(array: &[T], index: usize) -> T {
+    let mut result = T::default();
+    for (i, item) in array.iter().enumerate() {
+        result.conditional_assign(item, (i as u64).ct_eq(&(index as u64)));
+    }
+    result
+}
+
+/// Writes `value` to `array[index]` by scanning the entire array. Does nothing if `index` is out of bounds.
+pub fn store<T: ConditionallySelectable>(array: &mut [T], index: usize, value: &T) {
+    for (i, item) in array.iter_mut().enumerate() {
+        item.conditional_assign(value, (i as u64).ct_eq(&(index as u64)));
+    }
+}
+
+/// Sorts `items` in ascending order of `keys`, using an oblivious (bitonic) sorting network.
+///
+/// # Panics
+///
+/// Panics if `items` and `keys` have different lengths.
+pub fn sort_by_keys<
+    T: ConditionallySelectable,
+    K: Ord + ConditionallySelectable + ConstantTimeGreater + ConstantTimeLess,
+>(
+    items: &mut [T],
+    keys: &mut [K],
+) {
+    assert_eq!(items.len(), keys.len());
+    bitonic_sort_by_keys(items, keys);
+}
+
+/// Stops the processor from speculatively executing later instructions before the earlier ones complete,
+/// so that secret-dependent selections cannot be observed through speculative side channels (Spectre).
+///
+/// This emits `lfence` on x86 and x86-64, and `dsb sy; isb` on 64-bit ARM, with the `speculation-barriers` feature;
+/// otherwise, and on other architectures, it does nothing. The crate calls it in each iteration of the
+/// constant-time scans of the stash and of [`crate::linear_time_oram::LinearTimeOram`].
+#[inline(always)]
+#[allow(unsafe_code)]
+pub fn speculation_barrier() {
+    #[cfg(all(
+        feature = "speculation-barriers",
+        any(target_arch = "x86", target_arch = "x86_64")
+    ))]
+    // SAFETY: `lfence` takes no operands and only orders the instructions around it.
+    unsafe {
+        std::arch::asm!("lfence", options(nostack, preserves_flags));
+    }
+    #[cfg(all(feature = "speculation-barriers", target_arch = "aarch64"))]
+    // SAFETY: `dsb` and `isb` take no operands and only order the instructions around them.
+    unsafe {
+        std::arch::asm!("dsb sy", "isb", options(nostack, preserves_flags));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_min_max_clamp() {
+        assert_eq!(min(3u64, 5), 3);
+        assert_eq!(min(5u64, 3), 3);
+        assert_eq!(max(3u32, 5), 5);
+        assert_eq!(max(5u32, 3), 5);
+        assert_eq!(clamp(7u8, 2, 5), 5);
+        assert_eq!(clamp(1u8, 2, 5), 2);
+        assert_eq!(clamp(4u8, 2, 5), 4);
+    }
+
+    #[test]
+    fn ct_select_and_swap() {
+        assert_eq!(select(&1u16, &2, 0.into()), 1);
+        assert_eq!(select(&1u16, &2, 1.into()), 2);
+
+        let (mut a, mut b) = (1i64, 2i64);
+        swap(&mut a, &mut b, 1.into());
+        assert_eq!((a, b), (2, 1));
+
+        let (mut a, mut b) = ([1u8, 2], [3u8, 4]);
+        conditional_swap_slice(&mut a, &mut b, 1.into());
+        assert_eq!((a, b), ([3, 4], [1, 2]));
+        conditional_assign_slice(&mut a, &b, 0.into());
+        assert_eq!(a, [3, 4]);
+        conditional_assign_slice(&mut a, &b, 1.into());
+        assert_eq!(a, [1, 2]);
+    }
+
+    #[test]
+    fn ct_array_access() {
+        let mut array = [10u32, 20, 30, 40];
+        assert_eq!(lookup(&array, 2), 30);
+        assert_eq!(lookup(&array, 4), 0);
+        store(&mut array, 1, &25);
+        store(&mut array, 9, &99);
+        assert_eq!(array, [10, 25, 30, 40]);
+
+        let mut keys = [3u64, 1, 2, 0];
+        sort_by_keys(&mut array, &mut keys);
+        assert_eq!(array, [40, 25, 30, 10]);
+        assert_eq!(keys, [0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Runtime diagnostics for ORAM accesses (requires the `diagnostics` feature).
+
+use crate::{
+    bucket::Bucket, stash::PathStorage, utils::TreeIndex, Address, BucketSize, OramBlock,
+    OramError, StashSize,
+};
+use std::cell::RefCell;
+
+/// Statistics describing a single ORAM access, returned alongside the accessed value
+/// by the `access_with_stats` methods.
+///
+/// None of these statistics depend on the address accessed; they depend only on the ORAM parameters
+/// and (for `stash_occupancy`) on the random positions of the blocks in the ORAM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    /// The number of blocks in the stash of the top-level ORAM after eviction,
+    /// not counting the blocks of the path that was just evicted to.
+    pub stash_occupancy: StashSize,
+    /// The number of recursive position map ORAMs accessed. This is 0 when the position map is a linear scan.
+    pub recursion_depth: u64,
+    /// The number of physical reads (of buckets or of linear-scan entries) performed, across all recursion levels.
+    pub physical_reads: Address,
+    /// The number of physical writes (of buckets or of linear-scan entries) performed, across all recursion levels.
+    pub physical_writes: Address,
+}
+
+/// Counts of the physical reads and writes of the buckets of a Path ORAM tree, aggregated by depth.
+///
+/// The counts are taken from the bucket reads and writes actually performed on the tree by the stash,
+/// during ORAM accesses and scheduled evictions. Every path accessed is read and then written back in full,
+/// so a correct implementation performs exactly one read and one write per level for each path accessed.
+/// Buckets near the root are accessed on every path, and hence tend to be cache-resident,
+/// while each leaf-level bucket is accessed only rarely.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelStats {
+    /// The number of bucket reads at each depth, starting with the root at depth 0.
+    pub physical_reads: Vec<u64>,
+    /// The number of bucket writes at each depth, starting with the root at depth 0.
+    pub physical_writes: Vec<u64>,
+}
+
+impl LevelStats {
+    pub(crate) fn new(height: u64) -> Self {
+        let levels = height as usize + 1;
+        Self {
+            physical_reads: vec![0; levels],
+            physical_writes: vec![0; levels],
+        }
+    }
+
+    /// Clears all recorded reads and writes.
+    pub fn reset(&mut self) {
+        self.physical_reads.iter_mut().for_each(|count| *count = 0);
+        self.physical_writes.iter_mut().for_each(|count| *count = 0);
+    }
+}
+
+/// A [`PathStorage`] that counts the bucket reads and writes made through it, by depth, in a [`LevelStats`].
+pub(crate) struct CountingStorage<'a, P: ?Sized> {
+    storage: &'a mut P,
+    level_stats: RefCell<&'a mut LevelStats>,
+}
+
+impl<'a, P: ?Sized> CountingStorage<'a, P> {
+    pub(crate) fn new(storage: &'a mut P, level_stats: &'a mut LevelStats) -> Self {
+        Self {
+            storage,
+            level_stats: RefCell::new(level_stats),
+        }
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize, P: PathStorage<V, Z> + ?Sized> PathStorage<V, Z>
+    for CountingStorage<'_, P>
+{
+    fn read_bucket(&self, depth: u64, index: TreeIndex) -> Result<Bucket<V, Z>, OramError> {
+        let bucket = self.storage.read_bucket(depth, index)?;
+        self.level_stats.borrow_mut().physical_reads[usize::try_from(depth)?] += 1;
+        Ok(bucket)
+    }
+
+    fn write_bucket(
+        &mut self,
+        depth: u64,
+        index: TreeIndex,
+        bucket: &Bucket<V, Z>,
+    ) -> Result<(), OramError> {
+        self.storage.write_bucket(depth, index, bucket)?;
+        self.level_stats.get_mut().physical_writes[usize::try_from(depth)?] += 1;
+        Ok(())
+    }
+
+    fn arity(&self) -> u64 {
+        self.storage.arity()
+    }
+}
+
+/// The maximum number of bins in a [`LeafHistogram`]. Leaves are grouped into bins of consecutive leaves.
+pub const LEAF_HISTOGRAM_MAX_BINS: usize = 1024;
+
+/// A histogram of the leaves whose paths were read by ORAM accesses.
+///
+/// Leaves are assigned uniformly at random by a correct Path ORAM, whatever the workload.
+/// A histogram that fails a test of uniformity therefore indicates a bug
+/// (for example, a misconfigured parameter or a broken RNG) that weakens obliviousness.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeafHistogram {
+    counts: Vec<u64>,
+    leaves_per_bin: u64,
+}
+
+/// The result of a chi-squared test of a [`LeafHistogram`] against the uniform distribution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UniformityTest {
+    /// The chi-squared statistic.
+    pub chi_squared: f64,
+    /// The number of degrees of freedom of the test (the number of bins minus one).
+    pub degrees_of_freedom: u64,
+    /// The (approximate) probability that a uniform distribution produces a statistic at least this large.
+    /// Small values (say, below 10^{-6}) indicate that leaves are not being chosen uniformly.
+    pub p_value: f64,
+}
+
+impl LeafHistogram {
+    pub(crate) fn new(num_leaves: u64) -> Self {
+        let max_bins = LEAF_HISTOGRAM_MAX_BINS as u64;
+        let num_bins = num_leaves.clamp(1, max_bins);
+        Self {
+            counts: vec![0; num_bins as usize],
+            leaves_per_bin: (num_leaves / num_bins).max(1),
+        }
+    }
+
+    /// Records an access to the leaf with offset `leaf_offset` (from the leftmost leaf).
+    pub(crate) fn record(&mut self, leaf_offset: u64) {
+        let last_bin = self.counts.len() - 1;
+        let bin = (leaf_offset / self.leaves_per_bin) as usize;
+        self.counts[bin.min(last_bin)] += 1;
+    }
+
+    /// Returns the number of accesses recorded in each bin.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the number of consecutive leaves grouped into each bin.
+    pub fn leaves_per_bin(&self) -> u64 {
+        self.leaves_per_bin
+    }
+
+    /// Returns the total number of accesses recorded.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Clears all recorded accesses.
+    pub fn reset(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+    }
+
+    /// Performs a chi-squared test of the recorded accesses against the uniform distribution over bins.
+    /// The test is only meaningful once the expected count of each bin is reasonably large (say, at least 5).
+    pub fn uniformity_test(&self) -> UniformityTest {
+        let num_bins = self.counts.len() as f64;
+        let expected = self.total() as f64 / num_bins;
+        let chi_squared = if expected > 0.0 {
+            self.counts
+                .iter()
+                .map(|&count| (count as f64 - expected).powi(2) / expected)
+                .sum()
+        } else {
+            0.0
+        };
+        let degrees_of_freedom = self.counts.len() as u64 - 1;
+
+        UniformityTest {
+            chi_squared,
+            degrees_of_freedom,
+            p_value: chi_squared_upper_tail(chi_squared, degrees_of_freedom),
+        }
+    }
+}
+
+/// Approximates the probability that a chi-squared random variable with `k` degrees of freedom exceeds `x`,
+/// using the Wilson-Hilferty transformation to a standard normal.
+fn chi_squared_upper_tail(x: f64, k: u64) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+    let k = k as f64;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((x / k).cbrt() - (1.0 - variance)) / variance.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// The complementary error function, with absolute error below 1.2 * 10^{-7} (Numerical Recipes, `erfcc`).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erfc_known_values() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157_299_207).abs() < 1e-6);
+        assert!((erfc(-1.0) - 1.842_700_793).abs() < 1e-6);
+    }
+
+    #[test]
+    fn leaf_histogram_detects_bias() {
+        let mut uniform = LeafHistogram::new(16);
+        let mut biased = LeafHistogram::new(16);
+        for i in 0..1600 {
+            uniform.record(i % 16);
+            biased.record((i % 16) / 2);
+        }
+        assert_eq!(uniform.total(), 1600);
+        assert!(uniform.uniformity_test().p_value > 0.99);
+        assert!(biased.uniformity_test().p_value < 1e-6);
+
+        uniform.reset();
+        assert_eq!(uniform.total(), 0);
+    }
+
+    #[test]
+    fn leaf_histogram_bins_large_trees() {
+        let mut histogram = LeafHistogram::new(1 << 20);
+        assert_eq!(histogram.counts().len(), LEAF_HISTOGRAM_MAX_BINS);
+        assert_eq!(histogram.leaves_per_bin(), 1 << 10);
+        histogram.record((1 << 20) - 1);
+        assert_eq!(histogram.counts()[LEAF_HISTOGRAM_MAX_BINS - 1], 1);
+    }
+}
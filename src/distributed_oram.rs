@@ -0,0 +1,648 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A two-server distributed ORAM whose storage is secret-shared between non-colluding servers.
+
+use crate::{
+    bucket::{BlockValue, PositionBlock},
+    utils::{CompleteBinaryTreeIndex, TreeHeight, TreeIndex},
+    Address, BlockSize, BucketSize, Oram, OramBlock, OramError, OramRng, RecursionCutoff,
+    TransportError,
+};
+
+/// The number of blocks per bucket of the trees of a `DistributedOram`.
+pub const DISTRIBUTED_BUCKET_SIZE: BucketSize = 4;
+
+/// The default capacity below which a `DistributedOram` keeps its position map in client memory
+/// instead of secret-sharing it recursively.
+pub const DEFAULT_DISTRIBUTED_RECURSION_CUTOFF: RecursionCutoff = 1 << 10;
+
+/// The number of positions stored in each block of a secret-shared position map.
+const POSITIONS_PER_BLOCK: BlockSize = 8;
+
+/// The size in bytes of the metadata (tagged address and leaf) preceding the value of each stored record.
+const RECORD_HEADER_SIZE: usize = 16;
+
+/// An `OramBlock` with a fixed-size byte encoding, so that it can be secret-shared.
+pub trait ShareableBlock: OramBlock {
+    /// The size in bytes of the encoding.
+    const ENCODED_SIZE: usize;
+
+    /// Writes the encoding of `self` to `out`, which has length `ENCODED_SIZE`.
+    fn encode(&self, out: &mut [u8]);
+
+    /// Decodes a block from `bytes`, which has length `ENCODED_SIZE`.
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_shareable_block_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl ShareableBlock for $t {
+                const ENCODED_SIZE: usize = std::mem::size_of::<$t>();
+
+                fn encode(&self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Self {
+                    <$t>::from_le_bytes(bytes.try_into().expect("encoding has the right length"))
+                }
+            }
+        )*
+    };
+}
+
+impl_shareable_block_for_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl<const B: BlockSize> ShareableBlock for BlockValue<B> {
+    const ENCODED_SIZE: usize = B;
+
+    fn encode(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.data);
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        Self::new(bytes.try_into().expect("encoding has the right length"))
+    }
+}
+
+impl<const B: BlockSize> ShareableBlock for PositionBlock<B> {
+    const ENCODED_SIZE: usize = 8 * B;
+
+    fn encode(&self, out: &mut [u8]) {
+        for (chunk, position) in out.chunks_exact_mut(8).zip(self.data) {
+            chunk.copy_from_slice(&position.to_le_bytes());
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        let mut result = Self::default();
+        for (position, chunk) in result.data.iter_mut().zip(bytes.chunks_exact(8)) {
+            *position = TreeIndex::from_le_bytes(chunk.try_into().expect("chunk has length 8"));
+        }
+        result
+    }
+}
+
+/// Identifies a table of records allocated on a server by [`ShareTransport::create_table`].
+pub type TableId = u32;
+
+/// The connection from a `DistributedOram` client to one of its two storage servers.
+///
+/// A server stores tables of fixed-size byte records, each holding one secret share.
+/// Every record of a new table must read as all zeros until it is first written.
+/// Implementations typically forward these calls over the network; [`InMemoryShareServer`] stores tables locally.
+pub trait ShareTransport {
+    /// Allocates a new table of `num_records` records of `record_size` bytes each, and returns its identifier.
+    fn create_table(
+        &mut self,
+        num_records: u64,
+        record_size: usize,
+    ) -> Result<TableId, TransportError>;
+
+    /// Returns the records at `indices` of `table`, in order.
+    fn read_records(
+        &mut self,
+        table: TableId,
+        indices: &[u64],
+    ) -> Result<Vec<Vec<u8>>, TransportError>;
+
+    /// Overwrites the records at `indices` of `table` with `records`.
+    fn write_records(
+        &mut self,
+        table: TableId,
+        indices: &[u64],
+        records: &[Vec<u8>],
+    ) -> Result<(), TransportError>;
+}
+
+/// A `ShareTransport` storing its tables in local memory, for testing and for simulating deployments.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryShareServer {
+    /// The record size and contents of each table.
+    tables: Vec<(usize, Vec<u8>)>,
+}
+
+impl InMemoryShareServer {
+    /// Returns a new server with no tables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the raw contents of `table`, i.e. everything this server has learned about the stored data.
+    pub fn table_contents(&self, table: TableId) -> Option<&[u8]> {
+        self.tables
+            .get(usize::try_from(table).ok()?)
+            .map(|(_, contents)| contents.as_slice())
+    }
+
+    fn record_range(
+        &self,
+        table: TableId,
+        index: u64,
+    ) -> Result<(usize, std::ops::Range<usize>), TransportError> {
+        let table_index = usize::try_from(table)?;
+        let Some((record_size, contents)) = self.tables.get(table_index) else {
+            return Err(TransportError::StorageError {
+                reason: format!("no table with identifier {table}"),
+            });
+        };
+        let num_records = Address::try_from(contents.len() / record_size)?;
+        if index >= num_records {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: num_records,
+            }
+            .into());
+        }
+        let start = usize::try_from(index)? * record_size;
+        Ok((table_index, start..start + record_size))
+    }
+}
+
+impl ShareTransport for InMemoryShareServer {
+    fn create_table(
+        &mut self,
+        num_records: u64,
+        record_size: usize,
+    ) -> Result<TableId, TransportError> {
+        let id = TableId::try_from(self.tables.len())?;
+        let size = usize::try_from(num_records)?
+            .checked_mul(record_size)
+            .ok_or(OramError::InvalidConfigurationError {
+                parameter_name: "Table size".to_string(),
+                parameter_value: format!("{num_records} records of {record_size} bytes"),
+            })?;
+        self.tables.push((record_size, vec![0; size]));
+        Ok(id)
+    }
+
+    fn read_records(
+        &mut self,
+        table: TableId,
+        indices: &[u64],
+    ) -> Result<Vec<Vec<u8>>, TransportError> {
+        let mut result = Vec::with_capacity(indices.len());
+        for &index in indices {
+            let (table_index, range) = self.record_range(table, index)?;
+            result.push(self.tables[table_index].1[range].to_vec());
+        }
+        Ok(result)
+    }
+
+    fn write_records(
+        &mut self,
+        table: TableId,
+        indices: &[u64],
+        records: &[Vec<u8>],
+    ) -> Result<(), TransportError> {
+        for (&index, record) in indices.iter().zip(records) {
+            let (table_index, range) = self.record_range(table, index)?;
+            self.tables[table_index].1[range].copy_from_slice(record);
+        }
+        Ok(())
+    }
+}
+
+/// A block held in the client-side stash of a `DistributedTree`.
+#[derive(Clone, Copy, Debug)]
+struct StashEntry<V> {
+    address: Address,
+    leaf: TreeIndex,
+    value: V,
+}
+
+/// The position map of a `DistributedTree`. A stored position of 0 means that none has been assigned yet.
+#[derive(Debug)]
+enum PositionStore {
+    /// Positions kept in client memory.
+    Local(Vec<TreeIndex>),
+    /// Positions secret-shared in a smaller tree.
+    Recursive(Box<DistributedTree<PositionBlock<POSITIONS_PER_BLOCK>>>),
+}
+
+/// The client state of one secret-shared Path ORAM tree.
+#[derive(Debug)]
+struct DistributedTree<V> {
+    table: TableId,
+    height: TreeHeight,
+    block_capacity: Address,
+    stash: Vec<StashEntry<V>>,
+    positions: PositionStore,
+}
+
+impl<V: ShareableBlock> DistributedTree<V> {
+    const RECORD_SIZE: usize = RECORD_HEADER_SIZE + V::ENCODED_SIZE;
+
+    fn new<S: ShareTransport>(
+        block_capacity: Address,
+        servers: &mut [S; 2],
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<Self, TransportError> {
+        let height: TreeHeight = (block_capacity.next_power_of_two().max(2).ilog2() - 1).into();
+        let bucket_size = Address::try_from(DISTRIBUTED_BUCKET_SIZE)?;
+        let num_records = (2u64 << height) * bucket_size;
+
+        let [first, second] = servers;
+        let table = first.create_table(num_records, Self::RECORD_SIZE)?;
+        if second.create_table(num_records, Self::RECORD_SIZE)? != table {
+            return Err(TransportError::StorageError {
+                reason: "servers allocated different table identifiers".to_string(),
+            });
+        }
+
+        let positions = if block_capacity <= recursion_cutoff {
+            PositionStore::Local(vec![0; usize::try_from(block_capacity)?])
+        } else {
+            let position_capacity =
+                block_capacity.div_ceil(Address::try_from(POSITIONS_PER_BLOCK)?);
+            PositionStore::Recursive(Box::new(DistributedTree::new(
+                position_capacity,
+                servers,
+                recursion_cutoff,
+            )?))
+        };
+
+        Ok(Self {
+            table,
+            height,
+            block_capacity,
+            stash: Vec::new(),
+            positions,
+        })
+    }
+
+    /// Assigns `new_leaf` to `address` and returns its previous leaf, or a random leaf if it had none.
+    fn swap_position<S: ShareTransport, R: OramRng>(
+        &mut self,
+        address: Address,
+        new_leaf: TreeIndex,
+        servers: &mut [S; 2],
+        rng: &mut R,
+    ) -> Result<TreeIndex, TransportError> {
+        let old_leaf = match &mut self.positions {
+            PositionStore::Local(positions) => {
+                std::mem::replace(&mut positions[usize::try_from(address)?], new_leaf)
+            }
+            PositionStore::Recursive(tree) => {
+                let block_size = Address::try_from(POSITIONS_PER_BLOCK)?;
+                let offset = usize::try_from(address % block_size)?;
+                let block = tree.access(
+                    address / block_size,
+                    |block| {
+                        let mut result = *block;
+                        result.data[offset] = new_leaf;
+                        result
+                    },
+                    servers,
+                    rng,
+                )?;
+                block.data[offset]
+            }
+        };
+        if old_leaf == 0 {
+            Ok(TreeIndex::random_leaf(self.height, rng)?)
+        } else {
+            Ok(old_leaf)
+        }
+    }
+
+    /// Returns the indices of the records on the path to `leaf`, from the root to the leaf.
+    fn path_records(&self, leaf: TreeIndex) -> Vec<u64> {
+        let bucket_size = DISTRIBUTED_BUCKET_SIZE as u64;
+        (0..=self.height)
+            .flat_map(|depth| {
+                let bucket = leaf.ct_node_on_path(depth, self.height);
+                bucket * bucket_size..(bucket + 1) * bucket_size
+            })
+            .collect()
+    }
+
+    fn access<S: ShareTransport, R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        callback: F,
+        servers: &mut [S; 2],
+        rng: &mut R,
+    ) -> Result<V, TransportError> {
+        let new_leaf = TreeIndex::random_leaf(self.height, rng)?;
+        let leaf = self.swap_position(address, new_leaf, servers, rng)?;
+
+        // Reconstruct the path from the shares of both servers, and move its real blocks into the stash.
+        let indices = self.path_records(leaf);
+        let [first, second] = servers;
+        let first_shares = first.read_records(self.table, &indices)?;
+        let second_shares = second.read_records(self.table, &indices)?;
+        // A faulty server may return the wrong number of records, or records of the wrong size.
+        for shares in [&first_shares, &second_shares] {
+            if shares.len() != indices.len() {
+                return Err(TransportError::StorageError {
+                    reason: format!(
+                        "server returned {} records, expected {}",
+                        shares.len(),
+                        indices.len()
+                    ),
+                });
+            }
+            if let Some(record) = shares
+                .iter()
+                .find(|record| record.len() != Self::RECORD_SIZE)
+            {
+                return Err(TransportError::StorageError {
+                    reason: format!(
+                        "server returned a record of {} bytes, expected {}",
+                        record.len(),
+                        Self::RECORD_SIZE
+                    ),
+                });
+            }
+        }
+        for (a, b) in first_shares.iter().zip(&second_shares) {
+            let record: Vec<u8> = a.iter().zip(b).map(|(x, y)| x ^ y).collect();
+            let tag = u64::from_le_bytes(record[..8].try_into().expect("header has length 16"));
+            // A tag of 0 marks an empty record; otherwise, the tag is the address plus one.
+            if tag != 0 {
+                self.stash.push(StashEntry {
+                    address: tag - 1,
+                    leaf: TreeIndex::from_le_bytes(
+                        record[8..16].try_into().expect("header has length 16"),
+                    ),
+                    value: V::decode(&record[RECORD_HEADER_SIZE..]),
+                });
+            }
+        }
+
+        // Blocks that have never been written are absent from the tree and stash, and hold the default value.
+        let result = match self.stash.iter().position(|entry| entry.address == address) {
+            Some(i) => self.stash.swap_remove(i).value,
+            None => V::default(),
+        };
+        self.stash.push(StashEntry {
+            address,
+            leaf: new_leaf,
+            value: callback(&result),
+        });
+
+        // Write the path back from the leaf upward, placing each stashed block as deep as possible,
+        // under fresh random shares.
+        let mut records = vec![vec![0u8; Self::RECORD_SIZE]; indices.len()];
+        for depth in (0..=self.height).rev() {
+            let node = leaf.ct_node_on_path(depth, self.height);
+            let start = usize::try_from(depth)? * DISTRIBUTED_BUCKET_SIZE;
+            let mut filled = 0;
+            let mut i = 0;
+            while i < self.stash.len() && filled < DISTRIBUTED_BUCKET_SIZE {
+                let entry = self.stash[i];
+                if entry.leaf.ct_node_on_path(depth, self.height) == node {
+                    let record = &mut records[start + filled];
+                    record[..8].copy_from_slice(&(entry.address + 1).to_le_bytes());
+                    record[8..16].copy_from_slice(&entry.leaf.to_le_bytes());
+                    entry.value.encode(&mut record[RECORD_HEADER_SIZE..]);
+                    self.stash.swap_remove(i);
+                    filled += 1;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        let mut first_shares = Vec::with_capacity(records.len());
+        for record in records.iter_mut() {
+            let mut share = vec![0u8; Self::RECORD_SIZE];
+            rng.fill_bytes(&mut share);
+            for (byte, mask) in record.iter_mut().zip(&share) {
+                *byte ^= mask;
+            }
+            first_shares.push(share);
+        }
+        first.write_records(self.table, &indices, &first_shares)?;
+        second.write_records(self.table, &indices, &records)?;
+
+        Ok(result)
+    }
+}
+
+/// A two-server distributed ORAM (DORAM).
+///
+/// Each of the two servers, reached through a [`ShareTransport`], stores one XOR secret share
+/// of a Path ORAM tree (with buckets of [`DISTRIBUTED_BUCKET_SIZE`] blocks),
+/// and, above the recursion cutoff, one share of a recursive position map stored the same way.
+/// Every access reads one uniformly random path of each tree from both servers,
+/// and writes it back under fresh random shares.
+///
+/// ## Security
+///
+/// As long as the two servers do not collude, each server sees only uniformly random bytes
+/// and uniformly random paths, independent of the addresses and values accessed.
+/// The client keeps its stashes and, below the recursion cutoff, its position map in ordinary memory,
+/// and does not process them in constant time; the client itself is assumed to be trusted.
+#[derive(Debug)]
+pub struct DistributedOram<V: ShareableBlock, S: ShareTransport> {
+    servers: [S; 2],
+    tree: DistributedTree<V>,
+}
+
+impl<V: ShareableBlock, S: ShareTransport> DistributedOram<V, S> {
+    /// Returns a new `DistributedOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// storing its shares on `servers`.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is 0, returns an `InvalidConfigurationError`.
+    /// Returns any error returned by the servers while allocating tables.
+    pub fn new(block_capacity: Address, servers: [S; 2]) -> Result<Self, TransportError> {
+        Self::new_with_parameters(
+            block_capacity,
+            servers,
+            DEFAULT_DISTRIBUTED_RECURSION_CUTOFF,
+        )
+    }
+
+    /// Returns a new `DistributedOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// storing its shares on `servers` and keeping position maps of at most `recursion_cutoff` entries in client memory.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is 0, or `recursion_cutoff` is less than `POSITIONS_PER_BLOCK` (8),
+    /// returns an `InvalidConfigurationError`.
+    /// Returns any error returned by the servers while allocating tables.
+    pub fn new_with_parameters(
+        block_capacity: Address,
+        mut servers: [S; 2],
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<Self, TransportError> {
+        if block_capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            }
+            .into());
+        }
+        if recursion_cutoff < RecursionCutoff::try_from(POSITIONS_PER_BLOCK)? {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Recursion cutoff".to_string(),
+                parameter_value: recursion_cutoff.to_string(),
+            }
+            .into());
+        }
+
+        log::info!("DistributedOram::new(capacity = {})", block_capacity);
+
+        let tree = DistributedTree::new(block_capacity, &mut servers, recursion_cutoff)?;
+        Ok(Self { servers, tree })
+    }
+
+    /// Returns the two servers holding the shares of this ORAM.
+    pub fn servers(&self) -> &[S; 2] {
+        &self.servers
+    }
+}
+
+impl<V: ShareableBlock, S: ShareTransport> Oram for DistributedOram<V, S> {
+    type V = V;
+    type Error = TransportError;
+
+    fn block_capacity(&self) -> Result<Address, TransportError> {
+        Ok(self.tree.block_capacity)
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, TransportError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            }
+            .into());
+        }
+        self.tree.access(index, callback, &mut self.servers, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::random_workload;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn servers() -> [InMemoryShareServer; 2] {
+        [InMemoryShareServer::new(), InMemoryShareServer::new()]
+    }
+
+    #[test]
+    fn distributed_oram_random_workload() {
+        for capacity in [1, 7, 64] {
+            let mut oram = DistributedOram::<BlockValue<4>, _>::new(capacity, servers()).unwrap();
+            random_workload(&mut oram, 1000);
+        }
+    }
+
+    #[test]
+    fn distributed_oram_recursive_position_map() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DistributedOram::<u64, _>::new_with_parameters(512, servers(), 8).unwrap();
+        // 512 blocks, 64 position blocks, and 8 position blocks.
+        assert!(oram.servers()[0].table_contents(2).is_some());
+        assert!(oram.servers()[0].table_contents(3).is_none());
+
+        for i in 0..512 {
+            oram.write(i, 3 * i, &mut rng).unwrap();
+        }
+        for i in 0..512 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), 3 * i);
+        }
+    }
+
+    #[test]
+    fn distributed_oram_shares_hide_values() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DistributedOram::<BlockValue<8>, _>::new(16, servers()).unwrap();
+        let secret = BlockValue::new(*b"secret!!");
+        for i in 0..16 {
+            oram.write(i, secret, &mut rng).unwrap();
+        }
+        for server in oram.servers() {
+            let contents = server.table_contents(0).unwrap();
+            assert!(!contents.windows(8).any(|window| window == secret.data));
+        }
+    }
+
+    /// A server returning malformed records from `read_records`.
+    #[derive(Debug)]
+    struct FaultyServer {
+        server: InMemoryShareServer,
+        truncate_records: bool,
+    }
+
+    impl ShareTransport for FaultyServer {
+        fn create_table(
+            &mut self,
+            num_records: u64,
+            record_size: usize,
+        ) -> Result<TableId, TransportError> {
+            self.server.create_table(num_records, record_size)
+        }
+
+        fn read_records(
+            &mut self,
+            table: TableId,
+            indices: &[u64],
+        ) -> Result<Vec<Vec<u8>>, TransportError> {
+            let mut records = self.server.read_records(table, indices)?;
+            if self.truncate_records {
+                records[0].pop();
+            } else {
+                records.pop();
+            }
+            Ok(records)
+        }
+
+        fn write_records(
+            &mut self,
+            table: TableId,
+            indices: &[u64],
+            records: &[Vec<u8>],
+        ) -> Result<(), TransportError> {
+            self.server.write_records(table, indices, records)
+        }
+    }
+
+    #[test]
+    fn distributed_oram_rejects_malformed_records() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for truncate_records in [false, true] {
+            let servers = [
+                FaultyServer {
+                    server: InMemoryShareServer::new(),
+                    truncate_records,
+                },
+                FaultyServer {
+                    server: InMemoryShareServer::new(),
+                    truncate_records: false,
+                },
+            ];
+            let mut oram = DistributedOram::<u64, _>::new(16, servers).unwrap();
+            assert!(matches!(
+                oram.write(0, 1, &mut rng),
+                Err(TransportError::StorageError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn distributed_oram_rejects_invalid_parameters() {
+        assert!(DistributedOram::<u64, _>::new(0, servers()).is_err());
+        assert!(DistributedOram::<u64, _>::new_with_parameters(64, servers(), 4).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A front-end that pads the volume and timing of ORAM accesses with differentially private noise.
+
+use crate::{proxy::ProxyOperation, Address, Oram, OramError, OramRng};
+use rand::Rng;
+use std::time::Duration;
+
+/// The privacy parameters of a [`DpOram`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DpParameters {
+    /// The privacy loss `ε`. Smaller values add more noise.
+    pub epsilon: f64,
+    /// The probability `δ` with which the `ε` guarantee may fail.
+    pub delta: f64,
+    /// The maximum difference in the number of real accesses of a batch between neighboring workloads.
+    pub sensitivity: u64,
+    /// The duration of each unit of timing noise. If zero, no delays are injected.
+    pub delay_quantum: Duration,
+}
+
+impl DpParameters {
+    fn validate(&self) -> Result<(), OramError> {
+        if !(self.epsilon.is_finite() && self.epsilon > 0.0) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Epsilon".to_string(),
+                parameter_value: self.epsilon.to_string(),
+            });
+        }
+        if !(self.delta > 0.0 && self.delta < 1.0) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Delta".to_string(),
+                parameter_value: self.delta.to_string(),
+            });
+        }
+        if self.sensitivity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Sensitivity".to_string(),
+                parameter_value: self.sensitivity.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The results of the operations of a batch, in order.
+type BatchResults<O> = Vec<Result<<O as Oram>::V, <O as Oram>::Error>>;
+
+/// Wraps an ORAM, following every batch of real accesses with a random number of dummy accesses,
+/// and optionally a random delay, so that the number and duration of the accesses observed by the storage
+/// are `(ε, δ)`-differentially private with respect to the number of real accesses.
+///
+/// The ORAM itself already hides which addresses are accessed; `DpOram` additionally blurs how many
+/// accesses each batch (for example, each request of a higher-level protocol) makes.
+/// This is weaker than padding every batch to a fixed worst-case number of accesses at a fixed rate
+/// (as [`crate::OramProxy`] does), but much cheaper when batches are usually far smaller than the worst case.
+///
+/// ## Noise
+///
+/// The number of dummy accesses after a batch is `max(0, k + Z)`, where `Z` follows the two-sided geometric
+/// distribution `Pr[Z = z] ∝ exp(-ε |z| / s)` for sensitivity `s`, and the shift `k = ⌈s ln(1 / δ) / ε⌉`
+/// makes the truncation at 0 occur with probability at most `δ`. The expected overhead per batch is about `k`
+/// dummy accesses. The delay is an independent sample of the same distribution, in units of `delay_quantum`.
+///
+/// The noise is sampled using floating-point arithmetic, which is known to weaken DP guarantees slightly;
+/// the parameters should be chosen with some margin.
+#[derive(Debug)]
+pub struct DpOram<O: Oram> {
+    oram: O,
+    parameters: DpParameters,
+    shift: u64,
+    dummy_accesses: u64,
+}
+
+impl<O: Oram> DpOram<O> {
+    /// Wraps `oram`, padding its batches of accesses according to `parameters`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidConfigurationError` if `epsilon` is not positive, if `delta` is not in `(0, 1)`,
+    /// if `sensitivity` is 0, or if the capacity of `oram` is 0.
+    pub fn new(oram: O, parameters: DpParameters) -> Result<Self, O::Error> {
+        parameters.validate()?;
+        let capacity = oram.block_capacity()?;
+        if capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            }
+            .into());
+        }
+
+        let shift = (parameters.sensitivity as f64 * (1.0 / parameters.delta).ln()
+            / parameters.epsilon)
+            .ceil() as u64;
+        Ok(Self {
+            oram,
+            parameters,
+            shift,
+            dummy_accesses: 0,
+        })
+    }
+
+    /// Performs `operations` in order, followed by a noisy number of dummy accesses and a noisy delay,
+    /// and returns their results.
+    pub fn access_batch<R: OramRng>(
+        &mut self,
+        operations: &[ProxyOperation<O::V>],
+        rng: &mut R,
+    ) -> Result<BatchResults<O>, O::Error> {
+        let results = operations
+            .iter()
+            .map(|operation| match *operation {
+                ProxyOperation::Read(index) => self.oram.read(index, rng),
+                ProxyOperation::Write(index, value) => self.oram.write(index, value, rng),
+            })
+            .collect();
+        self.pad(rng)?;
+        Ok(results)
+    }
+
+    /// Returns the total number of dummy accesses performed so far.
+    pub fn dummy_accesses(&self) -> u64 {
+        self.dummy_accesses
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> O {
+        self.oram
+    }
+
+    fn pad<R: OramRng>(&mut self, rng: &mut R) -> Result<(), O::Error> {
+        let capacity = self.oram.block_capacity()?;
+        let num_dummies = self.sample_noise(rng);
+        for _ in 0..num_dummies {
+            self.oram.read(rng.gen_range(0..capacity), rng)?;
+        }
+        self.dummy_accesses += num_dummies;
+
+        let delay_quanta = self.sample_noise(rng);
+        if !self.parameters.delay_quantum.is_zero() {
+            let delay_quanta = u32::try_from(delay_quanta).unwrap_or(u32::MAX);
+            std::thread::sleep(self.parameters.delay_quantum.saturating_mul(delay_quanta));
+        }
+        Ok(())
+    }
+
+    /// Samples `max(0, shift + Z)`, where `Z` is a two-sided geometric random variable.
+    fn sample_noise<R: OramRng>(&self, rng: &mut R) -> u64 {
+        let alpha = (-self.parameters.epsilon / self.parameters.sensitivity as f64).exp();
+        let positive = sample_geometric(alpha, rng);
+        let negative = sample_geometric(alpha, rng);
+        self.shift.saturating_add(positive).saturating_sub(negative)
+    }
+}
+
+/// Samples `G` with `Pr[G = g] = (1 - alpha) alpha^g`.
+fn sample_geometric<R: OramRng>(alpha: f64, rng: &mut R) -> u64 {
+    // `gen` returns a value in [0, 1), so `uniform` is in (0, 1].
+    let uniform = 1.0 - rng.gen::<f64>();
+    (uniform.ln() / alpha.ln()).floor() as u64
+}
+
+impl<O: Oram> Oram for DpOram<O> {
+    type V = O::V;
+    type Error = O::Error;
+
+    fn block_capacity(&self) -> Result<Address, O::Error> {
+        self.oram.block_capacity()
+    }
+
+    /// Performs a single access as a batch of its own. See [`DpOram::access_batch`].
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, O::Error> {
+        let result = self.oram.access(index, callback, rng);
+        self.pad(rng)?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::random_workload, DefaultOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn parameters(epsilon: f64) -> DpParameters {
+        DpParameters {
+            epsilon,
+            delta: 1e-6,
+            sensitivity: 1,
+            delay_quantum: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn dp_oram_correctness_and_overhead() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        let mut dp_oram = DpOram::new(oram, parameters(1.0)).unwrap();
+        random_workload(&mut dp_oram, 200);
+
+        let results = dp_oram
+            .access_batch(
+                &[
+                    ProxyOperation::Write(3, 7),
+                    ProxyOperation::Read(3),
+                    ProxyOperation::Read(64),
+                ],
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(results[1].as_ref().unwrap(), &7);
+        assert!(results[2].is_err());
+
+        // The shift is ⌈ln(10^6)⌉ = 14, and the noise is symmetric around it.
+        let batches = 200 + 64 + 1;
+        let mean = dp_oram.dummy_accesses() as f64 / batches as f64;
+        assert!((12.0..16.0).contains(&mean), "{mean}");
+    }
+
+    #[test]
+    fn dp_oram_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for parameters in [
+            parameters(0.0),
+            parameters(f64::INFINITY),
+            DpParameters {
+                delta: 1.0,
+                ..parameters(1.0)
+            },
+            DpParameters {
+                sensitivity: 0,
+                ..parameters(1.0)
+            },
+        ] {
+            let oram = DefaultOram::<u64>::new(16, &mut rng).unwrap();
+            assert!(DpOram::new(oram, parameters).is_err());
+        }
+    }
+}
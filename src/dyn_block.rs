@@ -0,0 +1,278 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Blocks and ORAMs whose block size is chosen at runtime.
+
+use crate::{
+    bucket::BlockValue, path_oram::DefaultOram, Address, BlockSize, Oram, OramError, OramRng,
+};
+use subtle::{Choice, ConditionallySelectable};
+
+/// The size in bytes of the fixed-size chunks that `DynBlockOram` splits each block into.
+pub const DYN_BLOCK_CHUNK_SIZE: BlockSize = 64;
+
+/// A heap-allocated block of unstructured bytes whose length is chosen at runtime.
+///
+/// Unlike [`BlockValue`], `DynBlockValue` is not `Copy` and hence not an `OramBlock`;
+/// it is stored in a [`DynBlockOram`] instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynBlockValue {
+    /// The block's data payload.
+    pub data: Vec<u8>,
+}
+
+impl DynBlockValue {
+    /// Instantiates a `DynBlockValue` from a vector of bytes.
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    /// Instantiates a `DynBlockValue` consisting of `len` zero bytes.
+    pub fn zeroed(len: BlockSize) -> Self {
+        Self { data: vec![0; len] }
+    }
+
+    /// Returns the length of the block in bytes.
+    pub fn len(&self) -> BlockSize {
+        self.data.len()
+    }
+
+    /// Returns true if the block has length 0.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns `a` if `choice` is 0 and `b` if `choice` is 1, in constant time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different lengths.
+    pub fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = a.clone();
+        result.conditional_assign(b, choice);
+        result
+    }
+
+    /// Overwrites `self` with `other` if `choice` is 1, in constant time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` and `other` have different lengths.
+    pub fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+        assert_eq!(self.len(), other.len());
+        for (byte, other_byte) in self.data.iter_mut().zip(other.data.iter()) {
+            byte.conditional_assign(other_byte, choice);
+        }
+    }
+}
+
+type Chunk = BlockValue<DYN_BLOCK_CHUNK_SIZE>;
+
+/// An ORAM storing `DynBlockValue`s of a fixed length chosen at construction time.
+///
+/// Each block is split into a fixed number of [`DYN_BLOCK_CHUNK_SIZE`]-byte chunks,
+/// stored at consecutive addresses of an underlying [`DefaultOram`].
+/// Every `read` and `write` makes one access per chunk, and every `access` makes two,
+/// so the access pattern depends only on the block size.
+#[derive(Debug)]
+pub struct DynBlockOram {
+    chunks: DefaultOram<Chunk>,
+    block_size: BlockSize,
+    chunks_per_block: Address,
+}
+
+impl DynBlockOram {
+    /// Returns a new `DynBlockOram` mapping addresses `0 <= address < block_capacity`
+    /// to zeroed blocks of `block_size` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        block_capacity: Address,
+        block_size: BlockSize,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        // Rounding up to a power of two keeps the underlying capacity a power of two.
+        let chunks_per_block: Address = block_size
+            .div_ceil(DYN_BLOCK_CHUNK_SIZE)
+            .max(1)
+            .next_power_of_two()
+            .try_into()?;
+        let chunk_capacity = block_capacity.checked_mul(chunks_per_block).ok_or(
+            OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            },
+        )?;
+
+        Ok(Self {
+            chunks: DefaultOram::new(chunk_capacity, rng)?,
+            block_size,
+            chunks_per_block,
+        })
+    }
+
+    /// Returns the size in bytes of the blocks stored by this ORAM.
+    pub fn block_size(&self) -> BlockSize {
+        self.block_size
+    }
+
+    /// Returns the capacity in blocks of this ORAM.
+    pub fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(self.chunks.block_capacity()? / self.chunks_per_block)
+    }
+
+    /// Obliviously reads the value stored at `index`.
+    pub fn read<R: OramRng>(
+        &mut self,
+        index: Address,
+        rng: &mut R,
+    ) -> Result<DynBlockValue, OramError> {
+        self.check_index(index)?;
+
+        let mut result = Vec::with_capacity(self.padded_size()?);
+        for chunk_index in 0..self.chunks_per_block {
+            let chunk = self
+                .chunks
+                .read(index * self.chunks_per_block + chunk_index, rng)?;
+            result.extend_from_slice(&chunk.data);
+        }
+        result.truncate(self.block_size);
+
+        Ok(DynBlockValue::new(result))
+    }
+
+    /// Obliviously writes the value stored at `index`. Returns the value previously stored at `index`.
+    ///
+    /// # Errors
+    ///
+    /// If `new_value` does not have length `block_size`, returns an `InvalidConfigurationError`.
+    pub fn write<R: OramRng>(
+        &mut self,
+        index: Address,
+        new_value: DynBlockValue,
+        rng: &mut R,
+    ) -> Result<DynBlockValue, OramError> {
+        self.check_index(index)?;
+        self.check_length(&new_value)?;
+
+        let mut padded = new_value.data;
+        padded.resize(self.padded_size()?, 0);
+
+        let mut result = Vec::with_capacity(padded.len());
+        for (chunk_index, bytes) in
+            (0..self.chunks_per_block).zip(padded.chunks(DYN_BLOCK_CHUNK_SIZE))
+        {
+            let chunk = Chunk::new(bytes.try_into().expect("chunks are exactly chunk-sized"));
+            let previous =
+                self.chunks
+                    .write(index * self.chunks_per_block + chunk_index, chunk, rng)?;
+            result.extend_from_slice(&previous.data);
+        }
+        result.truncate(self.block_size);
+
+        Ok(DynBlockValue::new(result))
+    }
+
+    /// Performs a (oblivious) ORAM access.
+    /// Returns the value `v` previously stored at `index`, and writes `callback(v)` to `index`.
+    ///
+    /// # Errors
+    ///
+    /// If `callback` returns a value whose length is not `block_size`, returns an `InvalidConfigurationError`
+    /// and leaves the value stored at `index` unchanged.
+    pub fn access<R: OramRng, F: Fn(&DynBlockValue) -> DynBlockValue>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<DynBlockValue, OramError> {
+        let result = self.read(index, rng)?;
+        self.write(index, callback(&result), rng)?;
+        Ok(result)
+    }
+
+    fn padded_size(&self) -> Result<usize, OramError> {
+        Ok(usize::try_from(self.chunks_per_block)? * DYN_BLOCK_CHUNK_SIZE)
+    }
+
+    fn check_index(&self, index: Address) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_length(&self, value: &DynBlockValue) -> Result<(), OramError> {
+        if value.len() != self.block_size {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Block length".to_string(),
+                parameter_value: value.len().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn dyn_block_value_conditional_select() {
+        let a = DynBlockValue::new(vec![1, 2, 3]);
+        let b = DynBlockValue::new(vec![4, 5, 6]);
+        assert_eq!(DynBlockValue::conditional_select(&a, &b, 0.into()), a);
+        assert_eq!(DynBlockValue::conditional_select(&a, &b, 1.into()), b);
+    }
+
+    #[test]
+    fn dyn_block_oram_random_workload() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for block_size in [1, 64, 100] {
+            let capacity = 16;
+            let mut oram = DynBlockOram::new(capacity, block_size, &mut rng).unwrap();
+            assert_eq!(oram.block_capacity().unwrap(), capacity);
+            let mut mirror = vec![DynBlockValue::zeroed(block_size); capacity as usize];
+
+            for _ in 0..200 {
+                let index = rng.gen_range(0..capacity);
+                if rng.gen::<bool>() {
+                    assert_eq!(oram.read(index, &mut rng).unwrap(), mirror[index as usize]);
+                } else {
+                    let mut bytes = vec![0u8; block_size];
+                    rng.fill(&mut bytes[..]);
+                    let value = DynBlockValue::new(bytes);
+                    let previous = oram.write(index, value.clone(), &mut rng).unwrap();
+                    assert_eq!(previous, mirror[index as usize]);
+                    mirror[index as usize] = value;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dyn_block_oram_access_and_errors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DynBlockOram::new(8, 10, &mut rng).unwrap();
+
+        let increment =
+            |v: &DynBlockValue| DynBlockValue::new(v.data.iter().map(|b| b + 1).collect());
+        oram.access(3, increment, &mut rng).unwrap();
+        assert_eq!(oram.read(3, &mut rng).unwrap().data, vec![1; 10]);
+
+        assert!(oram.read(8, &mut rng).is_err());
+        assert!(oram.write(0, DynBlockValue::zeroed(11), &mut rng).is_err());
+    }
+}
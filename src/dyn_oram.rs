@@ -0,0 +1,145 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An object-safe interface to ORAMs.
+
+use crate::{Address, Oram, OramBlock, OramError, OramRng};
+
+/// An object-safe version of [`Oram`], implemented for every `Oram`.
+///
+/// The generic RNG and callback parameters of `Oram` prevent it from being used as a trait object.
+/// `DynOram` takes them as trait objects instead, so that ORAMs of different types storing the same
+/// block type can be held behind a `Box<dyn DynOram<V>>`, which itself implements `Oram`.
+/// ORAMs whose [`Oram::Error`] is not [`OramError`] are held behind a `Box<dyn DynOram<V, E>>`.
+pub trait DynOram<V: OramBlock, E = OramError> {
+    /// Returns the capacity in blocks of this ORAM. See [`Oram::block_capacity`].
+    fn dyn_block_capacity(&self) -> Result<Address, E>;
+
+    /// Performs a (oblivious) ORAM access. See [`Oram::access`].
+    fn dyn_access(
+        &mut self,
+        index: Address,
+        callback: &dyn Fn(&V) -> V,
+        rng: &mut dyn OramRng,
+    ) -> Result<V, E>;
+}
+
+impl<T: Oram> DynOram<T::V, T::Error> for T {
+    fn dyn_block_capacity(&self) -> Result<Address, T::Error> {
+        self.block_capacity()
+    }
+
+    fn dyn_access(
+        &mut self,
+        index: Address,
+        callback: &dyn Fn(&T::V) -> T::V,
+        mut rng: &mut dyn OramRng,
+    ) -> Result<T::V, T::Error> {
+        self.access(index, callback, &mut rng)
+    }
+}
+
+impl<'a, V: OramBlock, E: std::error::Error + From<OramError>> Oram
+    for Box<dyn DynOram<V, E> + 'a>
+{
+    type V = V;
+    type Error = E;
+
+    fn block_capacity(&self) -> Result<Address, E> {
+        self.as_ref().dyn_block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, E> {
+        self.as_mut().dyn_access(index, &callback, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bucket::BlockValue, linear_time_oram::LinearTimeOram, path_oram::PathOram,
+        test_utils::random_workload, DefaultOram,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn dyn_oram_heterogeneous_registry() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut registry: Vec<Box<dyn DynOram<BlockValue<1>>>> = vec![
+            Box::new(LinearTimeOram::new(64).unwrap()),
+            Box::new(DefaultOram::new(64, &mut rng).unwrap()),
+            Box::new(PathOram::<_, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap()),
+        ];
+
+        for oram in registry.iter_mut() {
+            assert_eq!(oram.block_capacity().unwrap(), 64);
+            random_workload(oram, 1000);
+        }
+    }
+    /// An error of a wrapper that refuses accesses to locked addresses, as an access-control layer might.
+    #[derive(Debug, thiserror::Error)]
+    enum LockingError {
+        #[error(transparent)]
+        Oram(#[from] OramError),
+        #[error("Address {index} is locked.")]
+        Locked { index: Address },
+    }
+
+    struct LockingOram {
+        oram: LinearTimeOram<u64>,
+        locked: Address,
+    }
+
+    impl Oram for LockingOram {
+        type V = u64;
+        type Error = LockingError;
+
+        fn block_capacity(&self) -> Result<Address, LockingError> {
+            Ok(self.oram.block_capacity()?)
+        }
+
+        fn access<R: OramRng, F: Fn(&u64) -> u64>(
+            &mut self,
+            index: Address,
+            callback: F,
+            rng: &mut R,
+        ) -> Result<u64, LockingError> {
+            if index == self.locked {
+                return Err(LockingError::Locked { index });
+            }
+            Ok(self.oram.access(index, callback, rng)?)
+        }
+    }
+
+    #[test]
+    fn dyn_oram_preserves_custom_errors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram: Box<dyn DynOram<u64, LockingError>> = Box::new(LockingOram {
+            oram: LinearTimeOram::new(16).unwrap(),
+            locked: 3,
+        });
+
+        assert_eq!(oram.write(2, 7, &mut rng).unwrap(), 0);
+        assert_eq!(oram.read(2, &mut rng).unwrap(), 7);
+        assert!(matches!(
+            oram.write(3, 7, &mut rng),
+            Err(LockingError::Locked { index: 3 })
+        ));
+        assert!(matches!(
+            oram.read(16, &mut rng),
+            Err(LockingError::Oram(
+                OramError::AddressOutOfBoundsError { .. }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Strategies deciding when a [`crate::PathOram`] evicts blocks from its stash into the tree.
+
+use crate::OramError;
+
+/// Decides when a [`crate::PathOram`] evicts blocks from its stash into the tree.
+///
+/// After reading the path of the target block, an access either evicts into that path,
+/// or only moves the target block into the stash (see [`EvictionStrategy::evicts_accessed_path`]).
+/// It may then perform any number of scheduled evictions, each along the next path in reverse-lexicographic order
+/// of leaves, which the ORAM tracks. To preserve obliviousness, the decisions of a strategy must depend only
+/// on the number of accesses made so far, and not on the addresses accessed or on the contents of the stash.
+///
+/// A strategy is selected with [`crate::PathOram::set_eviction_strategy`].
+/// With the `diagnostics` feature, `monitors::PhysicalAccessCountMonitor` checks that an ORAM touches exactly
+/// the paths its strategy prescribes, so new strategies can be tested against the same stash and tree code.
+pub trait EvictionStrategy: std::fmt::Debug + Send + Sync {
+    /// Returns true if every access evicts into the path it read,
+    /// and false if accesses only move the target block into the stash.
+    fn evicts_accessed_path(&self) -> bool;
+
+    /// Records an access, and returns the number of scheduled evictions to perform after it.
+    fn record_access(&mut self) -> u64;
+
+    /// Returns the number of scheduled evictions needed to complete the current period of accesses,
+    /// and starts a new period. Called before the strategy is replaced.
+    fn finish_period(&mut self) -> u64 {
+        0
+    }
+
+    /// Returns a boxed copy of this strategy.
+    fn boxed_clone(&self) -> Box<dyn EvictionStrategy>;
+}
+
+impl Clone for Box<dyn EvictionStrategy> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Every access evicts into the path it read, and no other paths are evicted.
+/// This is the eviction of the original Path ORAM, and the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathEviction;
+
+impl EvictionStrategy for PathEviction {
+    fn evicts_accessed_path(&self) -> bool {
+        true
+    }
+
+    fn record_access(&mut self) -> u64 {
+        0
+    }
+
+    fn boxed_clone(&self) -> Box<dyn EvictionStrategy> {
+        Box::new(*self)
+    }
+}
+
+/// Accesses only move the target block into the stash, and every `period`-th access is followed by a
+/// single eviction along the next path in reverse-lexicographic order (the eviction schedule of Ring ORAM).
+///
+/// This trades a larger stash (which grows by up to `period` blocks between evictions)
+/// for less computation on most accesses, since only evictions sort the stash.
+/// Unlike in Ring ORAM, accesses that do not evict still read and write back every bucket of their path,
+/// so this does not reduce the amount of data read and written per access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduledEviction {
+    period: u64,
+    accesses_since_eviction: u64,
+}
+
+impl ScheduledEviction {
+    /// Returns a strategy evicting after every `period`-th access.
+    ///
+    /// # Errors
+    ///
+    /// If `period` is 0, returns an `InvalidConfigurationError`.
+    pub fn new(period: u64) -> Result<Self, OramError> {
+        if period == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Eviction period".to_string(),
+                parameter_value: period.to_string(),
+            });
+        }
+        Ok(Self {
+            period,
+            accesses_since_eviction: 0,
+        })
+    }
+
+    /// Returns the number of accesses between evictions.
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+}
+
+impl EvictionStrategy for ScheduledEviction {
+    fn evicts_accessed_path(&self) -> bool {
+        false
+    }
+
+    fn record_access(&mut self) -> u64 {
+        self.accesses_since_eviction += 1;
+        if self.accesses_since_eviction == self.period {
+            self.accesses_since_eviction = 0;
+            return 1;
+        }
+        0
+    }
+
+    fn finish_period(&mut self) -> u64 {
+        let pending = u64::from(self.accesses_since_eviction > 0);
+        self.accesses_since_eviction = 0;
+        pending
+    }
+
+    fn boxed_clone(&self) -> Box<dyn EvictionStrategy> {
+        Box::new(*self)
+    }
+}
+
+/// Every access evicts into the path it read, and is followed by `extra_paths` scheduled evictions.
+///
+/// Evicting proactively keeps the stash smaller than [`PathEviction`] does, allowing a smaller
+/// overflow size or bucket size for the same overflow probability, at the cost of `extra_paths`
+/// additional path reads and writes per access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProactiveEviction {
+    extra_paths: u64,
+}
+
+impl ProactiveEviction {
+    /// Returns a strategy performing `extra_paths` scheduled evictions after every access.
+    pub fn new(extra_paths: u64) -> Self {
+        Self { extra_paths }
+    }
+
+    /// Returns the number of scheduled evictions performed after every access.
+    pub fn extra_paths(&self) -> u64 {
+        self.extra_paths
+    }
+}
+
+impl EvictionStrategy for ProactiveEviction {
+    fn evicts_accessed_path(&self) -> bool {
+        true
+    }
+
+    fn record_access(&mut self) -> u64 {
+        self.extra_paths
+    }
+
+    fn boxed_clone(&self) -> Box<dyn EvictionStrategy> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduled_eviction_evicts_every_period() {
+        assert!(ScheduledEviction::new(0).is_err());
+
+        let mut strategy = ScheduledEviction::new(3).unwrap();
+        let evictions: Vec<u64> = (0..7).map(|_| strategy.record_access()).collect();
+        assert_eq!(evictions, vec![0, 0, 1, 0, 0, 1, 0]);
+        assert_eq!(strategy.finish_period(), 1);
+        assert_eq!(strategy.finish_period(), 0);
+        assert_eq!(strategy.record_access(), 0);
+
+        let strategy: Box<dyn EvictionStrategy> = Box::new(ProactiveEviction::new(2));
+        assert_eq!(strategy.clone().record_access(), 2);
+        assert!(strategy.evicts_accessed_path());
+        assert_eq!(PathEviction.record_access(), 0);
+    }
+}
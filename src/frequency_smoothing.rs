@@ -0,0 +1,313 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A front-end that flattens the frequency distribution of ORAM accesses, in the style of PANCAKE.
+
+use crate::{Address, Oram, OramError, OramRng};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::collections::HashMap;
+
+/// A value written to a key whose replicas have not all been updated yet.
+#[derive(Clone, Debug)]
+struct PendingUpdate<V> {
+    value: V,
+    /// Whether each replica of the key still holds an older value.
+    stale: Vec<bool>,
+    num_stale: usize,
+}
+
+/// Wraps an ORAM, replicating frequently accessed keys and mixing real accesses with fake ones
+/// so that, if the keys are accessed with the expected frequencies, every address of the wrapped ORAM
+/// is accessed with the same frequency, in the style of [PANCAKE](https://www.usenix.org/conference/usenixsecurity20/presentation/grubbs).
+///
+/// The ORAM hides which address each access touches, but a weaker backend, or an observer of fine-grained timing,
+/// may still learn something about the address of each access. `FrequencySmoothingOram` ensures that
+/// the accessed addresses are uniformly distributed, at a far lower cost than padding every access to a fixed rate.
+///
+/// ## Replication
+///
+/// Given the expected frequencies `π` of the `n` logical keys, key `k` is stored in `R(k) = ⌈n π(k)⌉` replicas
+/// (at least 1), for a total of `M ≤ 2n` replicas, at addresses `0..M` of the wrapped ORAM.
+/// Every access is real with probability `n / M`, targeting a uniformly random replica of its key,
+/// and otherwise fake, targeting a replica drawn so that the mix of both is uniform over the `M` replicas.
+/// A logical access performs fake accesses until its real access is drawn, `M / n - 1 ≤ 1` fake accesses on average.
+///
+/// A write updates one replica. The new value is buffered by the client and written to each of the other replicas
+/// of the key the next time it is accessed, really or fakely, as in PANCAKE's update cache.
+/// The buffer and the rest of the bookkeeping are ordinary (non-oblivious) client memory.
+///
+/// The frequencies only affect performance and the flatness of the observed distribution, never correctness.
+/// The initial values of the replicas of each key must agree, which they do in a freshly initialized ORAM.
+#[derive(Debug)]
+pub struct FrequencySmoothingOram<O: Oram> {
+    oram: O,
+    /// The address of the first replica of each key, followed by the total number of replicas.
+    replica_offsets: Vec<Address>,
+    /// The probability that an access to the wrapped ORAM is real.
+    real_probability: f64,
+    /// The distribution of the keys of fake accesses, or `None` if every access is real.
+    fake_keys: Option<WeightedIndex<f64>>,
+    pending_updates: HashMap<Address, PendingUpdate<O::V>>,
+    fake_accesses: u64,
+}
+
+impl<O: Oram> FrequencySmoothingOram<O> {
+    /// Wraps `oram`, storing one logical key per entry of `frequencies`, which need not be normalized.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidConfigurationError` if `frequencies` is empty, has a negative or non-finite entry,
+    /// or sums to 0, or if `oram` has too little capacity for the replicas.
+    pub fn new(oram: O, frequencies: &[f64]) -> Result<Self, O::Error> {
+        let total: f64 = frequencies.iter().sum();
+        if frequencies.is_empty()
+            | frequencies.iter().any(|f| !(f.is_finite() && *f >= 0.0))
+            | !(total.is_finite() && total > 0.0)
+        {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Access frequencies".to_string(),
+                parameter_value: format!("{frequencies:?}"),
+            }
+            .into());
+        }
+
+        let n = frequencies.len() as f64;
+        let replica_counts: Vec<Address> = frequencies
+            .iter()
+            .map(|f| ((f / total * n).ceil() as Address).max(1))
+            .collect();
+        let mut replica_offsets = Vec::with_capacity(frequencies.len() + 1);
+        let mut num_replicas: Address = 0;
+        for count in &replica_counts {
+            replica_offsets.push(num_replicas);
+            num_replicas += count;
+        }
+        replica_offsets.push(num_replicas);
+
+        let capacity = oram.block_capacity()?;
+        if capacity < num_replicas {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            }
+            .into());
+        }
+
+        // Each replica of `k` must be accessed with probability `1 / M`. Real accesses reach it with probability
+        // `(n / M) π(k) / R(k)`, so fake accesses must reach it with probability `(1 - n π(k) / R(k)) / (M - n)`.
+        let m = num_replicas as f64;
+        let real_probability = n / m;
+        let fake_weights: Vec<f64> = frequencies
+            .iter()
+            .zip(&replica_counts)
+            .map(|(f, count)| (*count as f64 - n * f / total).max(0.0))
+            .collect();
+        let fake_keys = WeightedIndex::new(fake_weights).ok();
+
+        Ok(Self {
+            oram,
+            replica_offsets,
+            real_probability: if fake_keys.is_some() {
+                real_probability
+            } else {
+                1.0
+            },
+            fake_keys,
+            pending_updates: HashMap::new(),
+            fake_accesses: 0,
+        })
+    }
+
+    /// Returns the number of addresses of the wrapped ORAM holding replicas.
+    pub fn num_replicas(&self) -> Address {
+        self.replica_offsets[self.replica_offsets.len() - 1]
+    }
+
+    /// Returns the total number of fake accesses performed so far.
+    pub fn fake_accesses(&self) -> u64 {
+        self.fake_accesses
+    }
+
+    /// Returns the wrapped ORAM. Replicas may still hold stale values.
+    pub fn into_inner(self) -> O {
+        self.oram
+    }
+
+    /// Accesses a uniformly random replica of `key`, applying `callback` to the current value of the key,
+    /// and returns that value.
+    fn access_replica<R: OramRng, F: Fn(&O::V) -> O::V>(
+        &mut self,
+        key: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<O::V, O::Error> {
+        let first_replica = self.replica_offsets[key as usize];
+        let num_replicas = self.replica_offsets[key as usize + 1] - first_replica;
+        let replica = rng.gen_range(0..num_replicas);
+
+        let pending_value = self.pending_updates.get(&key).map(|update| update.value);
+        let stored = self.oram.access(
+            first_replica + replica,
+            |stored| callback(&pending_value.unwrap_or(*stored)),
+            rng,
+        )?;
+        let current = pending_value.unwrap_or(stored);
+        let new_value = callback(&current);
+
+        let replica = replica as usize;
+        if new_value != current {
+            if num_replicas > 1 {
+                let mut stale = vec![true; num_replicas as usize];
+                stale[replica] = false;
+                let update = PendingUpdate {
+                    value: new_value,
+                    stale,
+                    num_stale: num_replicas as usize - 1,
+                };
+                self.pending_updates.insert(key, update);
+            }
+        } else if let Some(update) = self.pending_updates.get_mut(&key) {
+            if update.stale[replica] {
+                update.stale[replica] = false;
+                update.num_stale -= 1;
+                if update.num_stale == 0 {
+                    self.pending_updates.remove(&key);
+                }
+            }
+        }
+        Ok(current)
+    }
+}
+
+impl<O: Oram> Oram for FrequencySmoothingOram<O> {
+    type V = O::V;
+    type Error = O::Error;
+
+    fn block_capacity(&self) -> Result<Address, O::Error> {
+        Ok(Address::try_from(self.replica_offsets.len() - 1).map_err(OramError::from)?)
+    }
+
+    /// Performs fake accesses until a real access is drawn, and then the real access.
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, O::Error> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            }
+            .into());
+        }
+
+        while !rng.gen_bool(self.real_probability) {
+            if let Some(fake_keys) = &self.fake_keys {
+                let key = fake_keys.sample(rng) as Address;
+                self.access_replica(key, |value| *value, rng)?;
+                self.fake_accesses += 1;
+            }
+        }
+        self.access_replica(index, callback, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{linear_time_oram::LinearTimeOram, test_utils::random_workload, DefaultOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A linear-time ORAM counting the accesses to each address.
+    #[derive(Debug)]
+    struct CountingOram {
+        oram: LinearTimeOram<u64>,
+        counts: Vec<u64>,
+    }
+
+    impl Oram for CountingOram {
+        type V = u64;
+        type Error = OramError;
+
+        fn block_capacity(&self) -> Result<Address, OramError> {
+            self.oram.block_capacity()
+        }
+
+        fn access<R: OramRng, F: Fn(&u64) -> u64>(
+            &mut self,
+            index: Address,
+            callback: F,
+            rng: &mut R,
+        ) -> Result<u64, OramError> {
+            self.counts[index as usize] += 1;
+            self.oram.access(index, callback, rng)
+        }
+    }
+
+    #[test]
+    fn frequency_smoothing_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let frequencies: Vec<f64> = (1..=64).map(|k| 1.0 / k as f64).collect();
+        let oram = DefaultOram::<u64>::new(128, &mut rng).unwrap();
+        let mut oram = FrequencySmoothingOram::new(oram, &frequencies).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 64);
+        assert!(oram.num_replicas() > 64);
+        random_workload(&mut oram, 2000);
+        assert!(oram.fake_accesses() > 0);
+    }
+
+    #[test]
+    fn frequency_smoothing_flattens_skewed_accesses() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let frequencies: Vec<f64> = (1..=16).map(|k| 1.0 / (k * k) as f64).collect();
+        let counting = CountingOram {
+            oram: LinearTimeOram::new(32).unwrap(),
+            counts: vec![0; 32],
+        };
+        let mut oram = FrequencySmoothingOram::new(counting, &frequencies).unwrap();
+        let num_replicas = oram.num_replicas() as usize;
+
+        let keys = WeightedIndex::new(&frequencies).unwrap();
+        for i in 0..20000 {
+            let key = keys.sample(&mut rng) as Address;
+            if i % 2 == 0 {
+                oram.write(key, i, &mut rng).unwrap();
+            } else {
+                oram.read(key, &mut rng).unwrap();
+            }
+        }
+
+        let counts = &oram.into_inner().counts;
+        let total: u64 = counts.iter().sum();
+        let expected = total as f64 / num_replicas as f64;
+        for count in &counts[..num_replicas] {
+            let ratio = *count as f64 / expected;
+            assert!((0.9..1.1).contains(&ratio), "{counts:?}");
+        }
+        assert!(counts[num_replicas..].iter().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn frequency_smoothing_rejects_invalid_frequencies() {
+        for frequencies in [&[][..], &[1.0, -1.0], &[0.0, 0.0], &[f64::NAN]] {
+            let oram = LinearTimeOram::<u64>::new(8).unwrap();
+            assert!(FrequencySmoothingOram::new(oram, frequencies).is_err());
+        }
+        let oram = LinearTimeOram::<u64>::new(4).unwrap();
+        assert!(FrequencySmoothingOram::new(oram, &[1.0, 0.0, 0.0, 0.0]).is_err());
+
+        // Uniform frequencies need no replication and no fake accesses.
+        let oram = LinearTimeOram::<u64>::new(4).unwrap();
+        let mut oram = FrequencySmoothingOram::new(oram, &[1.0; 4]).unwrap();
+        random_workload(&mut oram, 100);
+        assert_eq!(oram.num_replicas(), 4);
+        assert_eq!(oram.fake_accesses(), 0);
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Helpers for fuzzing [`PathOram`] with arbitrary sequences of operations, used by the targets in `fuzz/`.
+
+use crate::{path_oram::PathOram, Address, Oram, OramError, RecursionCutoff};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// An operation on a [`PathOram`] of `u64` values, decoded from fuzzer input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzOperation {
+    /// Reads `address`.
+    Read(Address),
+    /// Writes a value to `address`.
+    Write(Address, u64),
+    /// Adds a value to the value at `address`, with a single access.
+    Add(Address, u64),
+    /// Sets the eviction period (see [`PathOram::set_eviction_period`]).
+    SetEvictionPeriod(u64),
+    /// Performs scheduled evictions (see [`PathOram::flush_evictions`]).
+    FlushEvictions(u64),
+    /// Rebuilds the ORAM (see [`PathOram::rebuild`]).
+    Rebuild,
+}
+
+impl FuzzOperation {
+    /// The number of bytes of fuzzer input decoded into each operation.
+    pub const ENCODED_SIZE: usize = 4;
+
+    /// Decodes an operation from `bytes`, which must have length [`FuzzOperation::ENCODED_SIZE`].
+    /// Addresses range over `0..capacity + 2`, so that out-of-bounds accesses are exercised too.
+    pub fn decode(bytes: &[u8], capacity: Address) -> Self {
+        let address = Address::from(bytes[1]) % (capacity + 2);
+        let value = u64::from(u16::from_le_bytes([bytes[2], bytes[3]]));
+        match bytes[0] % 6 {
+            0 => Self::Read(address),
+            1 => Self::Write(address, value),
+            2 => Self::Add(address, value),
+            3 => Self::SetEvictionPeriod(value % 4 + 1),
+            4 => Self::FlushEvictions(value % 4),
+            _ => Self::Rebuild,
+        }
+    }
+}
+
+/// The parameters of the `PathOram<u64, 4, 8>` driven by [`check_path_oram_operations`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FuzzParameters {
+    /// The block capacity of the ORAM, a power of two between 2 and 256.
+    pub block_capacity: Address,
+    /// The recursion cutoff of the ORAM, either 1 (so that the position map is fully recursive)
+    /// or the block capacity (so that it is a linear scan).
+    pub recursion_cutoff: RecursionCutoff,
+}
+
+impl FuzzParameters {
+    /// The number of bytes of fuzzer input decoded into parameters.
+    pub const ENCODED_SIZE: usize = 2;
+
+    /// Decodes parameters from `bytes`, which may be shorter than [`FuzzParameters::ENCODED_SIZE`],
+    /// in which case missing bytes are taken to be zero.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let block_capacity: Address = 2 << bytes.first().map_or(0, |byte| byte % 8);
+        let recursion_cutoff = match bytes.get(1).map_or(0, |byte| byte % 2) {
+            0 => 1,
+            _ => block_capacity,
+        };
+        Self {
+            block_capacity,
+            recursion_cutoff,
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for FuzzOperation {
+    /// The block capacity of the ORAM operated on. As for [`FuzzOperation::decode`],
+    /// addresses range over `0..capacity + 2`.
+    type Parameters = Address;
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(capacity: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::arbitrary::any::<[u8; FuzzOperation::ENCODED_SIZE]>()
+            .prop_map(move |bytes| Self::decode(&bytes, capacity))
+            .boxed()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for FuzzParameters {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::arbitrary::any::<[u8; FuzzParameters::ENCODED_SIZE]>()
+            .prop_map(|bytes| Self::decode(&bytes))
+            .boxed()
+    }
+}
+
+/// Returns a strategy generating parameters together with a sequence of `num_operations` operations
+/// on an ORAM with those parameters, for use with [`check_path_oram_operations`]
+/// or with a downstream data structure built on an ORAM (requires the `proptest` feature).
+#[cfg(feature = "proptest")]
+pub fn path_oram_workloads(
+    num_operations: impl Into<proptest::collection::SizeRange>,
+) -> impl proptest::strategy::Strategy<Value = (FuzzParameters, Vec<FuzzOperation>)> {
+    use proptest::{arbitrary::any_with, strategy::Strategy};
+    let num_operations = num_operations.into();
+    proptest::arbitrary::any::<FuzzParameters>().prop_flat_map(move |parameters| {
+        (
+            proptest::strategy::Just(parameters),
+            proptest::collection::vec(
+                any_with::<FuzzOperation>(parameters.block_capacity),
+                num_operations.clone(),
+            ),
+        )
+    })
+}
+
+/// Runs the operations encoded by `data` against a `PathOram<u64, 4, 8>`, and panics if the ORAM
+/// misbehaves. The first [`FuzzParameters::ENCODED_SIZE`] bytes encode the parameters of the ORAM,
+/// and every further [`FuzzOperation::ENCODED_SIZE`] bytes encode an operation.
+/// See [`check_path_oram_operations`] for the checks performed.
+pub fn run_path_oram_operations(data: &[u8]) {
+    let (parameters, operations) = data.split_at(data.len().min(FuzzParameters::ENCODED_SIZE));
+    let parameters = FuzzParameters::decode(parameters);
+    let operations: Vec<FuzzOperation> = operations
+        .chunks_exact(FuzzOperation::ENCODED_SIZE)
+        .map(|bytes| FuzzOperation::decode(bytes, parameters.block_capacity))
+        .collect();
+    check_path_oram_operations(parameters, &operations);
+}
+
+/// Runs `operations` against a new `PathOram<u64, 4, 8>` with `parameters`, and panics if the ORAM misbehaves.
+///
+/// After each operation, checks that
+/// - accesses return the values of a plain mirror array,
+/// - exactly the out-of-bounds accesses fail, with an `AddressOutOfBoundsError`,
+/// - every address has exactly one real block (occupancy is conserved),
+/// - every block in the tree lies on the path to its position, and the position map agrees with it.
+pub fn check_path_oram_operations(parameters: FuzzParameters, operations: &[FuzzOperation]) {
+    let FuzzParameters {
+        block_capacity: capacity,
+        recursion_cutoff,
+    } = parameters;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut oram =
+        PathOram::<u64, 4, 8>::new_with_parameters(capacity, &mut rng, 40, recursion_cutoff)
+            .expect("Fuzzing parameters are valid");
+    let mut mirror = vec![0u64; usize::try_from(capacity).expect("Capacity fits in usize")];
+
+    for &operation in operations {
+        let access = match operation {
+            FuzzOperation::Read(address) => Some((address, oram.read(address, &mut rng))),
+            FuzzOperation::Write(address, value) => {
+                Some((address, oram.write(address, value, &mut rng)))
+            }
+            FuzzOperation::Add(address, value) => Some((
+                address,
+                oram.access(address, |v| v.wrapping_add(value), &mut rng),
+            )),
+            FuzzOperation::SetEvictionPeriod(period) => {
+                oram.set_eviction_period(period)
+                    .expect("Eviction periods are valid");
+                None
+            }
+            FuzzOperation::FlushEvictions(num_evictions) => {
+                oram.flush_evictions(num_evictions)
+                    .expect("Evictions succeed");
+                None
+            }
+            FuzzOperation::Rebuild => {
+                oram.rebuild(&mut rng).expect("Rebuilding succeeds");
+                None
+            }
+        };
+
+        if let Some((address, result)) = access {
+            match (
+                mirror.get_mut(usize::try_from(address).unwrap_or(usize::MAX)),
+                result,
+            ) {
+                (Some(expected), Ok(value)) => {
+                    assert_eq!(value, *expected, "{operation:?} returned the wrong value");
+                    match operation {
+                        FuzzOperation::Write(_, value) => *expected = value,
+                        FuzzOperation::Add(_, value) => *expected = expected.wrapping_add(value),
+                        _ => {}
+                    }
+                }
+                (
+                    None,
+                    Err(OramError::AddressOutOfBoundsError {
+                        attempted,
+                        capacity: reported,
+                    }),
+                ) => {
+                    assert_eq!((attempted, reported), (address, capacity), "{operation:?}");
+                }
+                (_, result) => panic!("{operation:?} returned {result:?}"),
+            }
+        }
+
+        oram.assert_invariants(&mut rng)
+            .expect("Reading the position map succeeds");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn fuzzing_random_inputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..4 {
+            let mut data = vec![0u8; 2 + 100 * FuzzOperation::ENCODED_SIZE];
+            rng.fill_bytes(&mut data);
+            run_path_oram_operations(&data);
+        }
+        run_path_oram_operations(&[]);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(16))]
+        #[test]
+        fn proptest_workloads((parameters, operations) in path_oram_workloads(0..100)) {
+            check_path_oram_operations(parameters, &operations);
+        }
+    }
+
+    #[test]
+    fn fuzzing_rejects_address_equal_to_capacity() {
+        // A capacity of 2, then a read of address 2.
+        run_path_oram_operations(&[0, 0, 0, 2, 0, 0]);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An ORAM that distinguishes never-written addresses from addresses holding default values.
+
+use crate::{
+    oram_option::OramOption, path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng,
+};
+use subtle::CtOption;
+
+/// An ORAM whose reads of never-written addresses return an oblivious "absent" indicator
+/// instead of a default `V` value, so that missing data can be distinguished from legitimate default values.
+///
+/// Values are returned as `CtOption<V>`, whose presence flag is a `Choice` and can be inspected in constant time.
+#[derive(Debug)]
+pub struct InitializedOram<V: OramBlock> {
+    oram: DefaultOram<OramOption<V>>,
+}
+
+impl<V: OramBlock> InitializedOram<V> {
+    /// Returns a new `InitializedOram` with addresses `0 <= address < block_capacity`, none of which are initialized.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(block_capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        Ok(Self {
+            oram: DefaultOram::new(block_capacity, rng)?,
+        })
+    }
+
+    /// Returns the capacity in blocks of this ORAM.
+    pub fn block_capacity(&self) -> Result<Address, OramError> {
+        self.oram.block_capacity()
+    }
+
+    /// Performs a (oblivious) ORAM access.
+    /// Returns the value `v` previously stored at `index` (absent if `index` was never written),
+    /// and writes `callback(v)` to `index`, where an absent `v` is passed to `callback` as `V::default()`.
+    pub fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<CtOption<V>, OramError> {
+        let block_callback =
+            |block: &OramOption<V>| OramOption::some(callback(&block.unwrap_or_default()));
+        Ok(self
+            .oram
+            .access(index, block_callback, rng)?
+            .into_ct_option())
+    }
+
+    /// Obliviously reads the value stored at `index`, which is absent if `index` was never written.
+    pub fn read<R: OramRng>(
+        &mut self,
+        index: Address,
+        rng: &mut R,
+    ) -> Result<CtOption<V>, OramError> {
+        Ok(self.oram.read(index, rng)?.into_ct_option())
+    }
+
+    /// Obliviously writes the value stored at `index`. Returns the value previously stored at `index`.
+    pub fn write<R: OramRng>(
+        &mut self,
+        index: Address,
+        new_value: V,
+        rng: &mut R,
+    ) -> Result<CtOption<V>, OramError> {
+        Ok(self
+            .oram
+            .write(index, OramOption::some(new_value), rng)?
+            .into_ct_option())
+    }
+
+    /// Obliviously marks `index` as never written. Returns the value previously stored at `index`.
+    pub fn remove<R: OramRng>(
+        &mut self,
+        index: Address,
+        rng: &mut R,
+    ) -> Result<CtOption<V>, OramError> {
+        Ok(self
+            .oram
+            .write(index, OramOption::none(), rng)?
+            .into_ct_option())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn initialized_oram_tracks_writes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let capacity = 64;
+        let mut oram = InitializedOram::<u64>::new(capacity, &mut rng).unwrap();
+        let mut mirror: Vec<Option<u64>> = vec![None; capacity as usize];
+
+        for _ in 0..1000 {
+            let index = rng.gen_range(0..capacity);
+            let expected = mirror[index as usize];
+            let previous = match rng.gen_range(0..3) {
+                0 => oram.read(index, &mut rng).unwrap(),
+                1 => {
+                    // Zero is a legitimate value, distinct from absence.
+                    let value = rng.gen_range(0..2);
+                    mirror[index as usize] = Some(value);
+                    oram.write(index, value, &mut rng).unwrap()
+                }
+                _ => {
+                    mirror[index as usize] = None;
+                    oram.remove(index, &mut rng).unwrap()
+                }
+            };
+            assert_eq!(Option::<u64>::from(previous), expected);
+        }
+    }
+
+    #[test]
+    fn initialized_oram_access() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = InitializedOram::<u64>::new(16, &mut rng).unwrap();
+        let increment = |v: &u64| v + 1;
+
+        assert!(bool::from(
+            oram.access(5, increment, &mut rng).unwrap().is_none()
+        ));
+        assert_eq!(
+            Option::<u64>::from(oram.access(5, increment, &mut rng).unwrap()),
+            Some(1)
+        );
+        assert_eq!(
+            Option::<u64>::from(oram.read(5, &mut rng).unwrap()),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A Path ORAM whose tree has 2, 4, or 8 children per node.
+
+use crate::{
+    bucket::{Bucket, PathOramBlock},
+    path_oram::{
+        DEFAULT_BLOCKS_PER_BUCKET, DEFAULT_POSITIONS_PER_BLOCK, DEFAULT_RECURSION_CUTOFF,
+        DEFAULT_STASH_OVERFLOW_SIZE, MAXIMUM_TREE_HEIGHT,
+    },
+    position_map::{PositionMap, RecursivePositionMap},
+    stash::{ObliviousStash, PathStorage, Stash, StashGrowth},
+    utils::{
+        random_permutation_of_0_through_n_exclusive, try_filled_vec, CompleteBinaryTreeIndex,
+        TreeHeight, TreeIndex,
+    },
+    Address, BucketSize, Oram, OramBlock, OramError, OramRng, RecursionCutoff, StashSize,
+};
+
+/// The buckets of a tree of arity `2^arity_bits`, stored level by level from the root.
+#[derive(Clone, Debug)]
+struct KaryTree<V: OramBlock, const Z: BucketSize> {
+    buckets: Vec<Bucket<V, Z>>,
+    arity_bits: u32,
+}
+
+impl<V: OramBlock, const Z: BucketSize> KaryTree<V, Z> {
+    /// Returns the offset in `buckets` of the bucket at depth `depth` whose binary heap index is `index`.
+    fn offset(&self, depth: u64, index: TreeIndex) -> Result<usize, OramError> {
+        let first_index_at_depth: TreeIndex = 1 << (depth * TreeHeight::from(self.arity_bits));
+        let buckets_above_depth = (first_index_at_depth - 1) / ((1 << self.arity_bits) - 1);
+        Ok(usize::try_from(
+            buckets_above_depth + index - first_index_at_depth,
+        )?)
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize> PathStorage<V, Z> for KaryTree<V, Z> {
+    fn read_bucket(&self, depth: u64, index: TreeIndex) -> Result<Bucket<V, Z>, OramError> {
+        Ok(self.buckets[self.offset(depth, index)?])
+    }
+
+    fn write_bucket(
+        &mut self,
+        depth: u64,
+        index: TreeIndex,
+        bucket: &Bucket<V, Z>,
+    ) -> Result<(), OramError> {
+        let offset = self.offset(depth, index)?;
+        self.buckets[offset] = *bucket;
+        Ok(())
+    }
+
+    fn arity(&self) -> u64 {
+        1 << self.arity_bits
+    }
+}
+
+/// A doubly oblivious Path ORAM whose tree has `ARITY` children per node, where `ARITY` is 2, 4, or 8.
+///
+/// A tree of higher arity is shorter: a tree with `ARITY = 2^b` has about `1/b` as many levels
+/// as the binary tree of a [`crate::PathOram`] of the same capacity, so each access reads and writes
+/// proportionally fewer buckets. This suits backends with high per-bucket latency, such as remote storage
+/// serving one bucket per round trip. In exchange, each bucket holds the blocks of `ARITY` subtrees,
+/// so the bucket size `Z` should grow with the arity to keep the stash small;
+/// `Z = 4` suffices for `ARITY = 2`, and about `2 * ARITY` is a conservative choice otherwise.
+///
+/// The tree has at least `block_capacity / 2` leaves, like that of a `PathOram`,
+/// rounded up to a power of `ARITY`. Positions are encoded as in a `PathOram`,
+/// as leaves of a binary tree in which only every `b`-th level holds buckets (see [`Stash`]),
+/// so the stash and the position map are those of a `PathOram`.
+/// Every access evicts to the path it reads.
+///
+/// ## Security
+///
+/// As for a `PathOram`, accesses are oblivious unless the stash overflows and grows.
+#[derive(Clone, Debug)]
+pub struct KaryPathOram<V: OramBlock, const ARITY: usize, const Z: BucketSize> {
+    physical_memory: KaryTree<V, Z>,
+    stash: ObliviousStash<V>,
+    position_map: RecursivePositionMap<DEFAULT_POSITIONS_PER_BLOCK, DEFAULT_BLOCKS_PER_BUCKET>,
+    /// The height of the tree, counted in levels of `ARITY`-ary nodes.
+    height: TreeHeight,
+    block_capacity: Address,
+}
+
+impl<V: OramBlock, const ARITY: usize, const Z: BucketSize> KaryPathOram<V, ARITY, Z> {
+    /// Returns a new `KaryPathOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// with the default stash overflow size and recursion cutoff.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`KaryPathOram::new_with_parameters`].
+    pub fn new<R: OramRng>(block_capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        Self::new_with_parameters(
+            block_capacity,
+            rng,
+            DEFAULT_STASH_OVERFLOW_SIZE,
+            DEFAULT_RECURSION_CUTOFF,
+        )
+    }
+
+    /// Returns a new `KaryPathOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// with a stash overflow size of `overflow_size` blocks, and a recursion cutoff of `recursion_cutoff`.
+    /// (See [`crate::PathOram`] for a description of these parameters.)
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidConfigurationError` in the following cases.
+    ///
+    /// - `ARITY` is not 2, 4, or 8.
+    /// - `block_capacity` is 0, 1, or is not a power of two.
+    /// - `Z` is 0 or 1.
+    /// - `block_capacity` is greater than `2 << MAXIMUM_TREE_HEIGHT`.
+    /// - `recursion_cutoff` is 0.
+    ///
+    /// If the memory of the ORAM cannot be allocated, returns an `AllocationError`.
+    pub fn new_with_parameters<R: OramRng>(
+        block_capacity: Address,
+        rng: &mut R,
+        overflow_size: StashSize,
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<Self, OramError> {
+        log::info!(
+            "KaryPathOram::new(capacity = {}, arity = {})",
+            block_capacity,
+            ARITY
+        );
+
+        if !matches!(ARITY, 2 | 4 | 8) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Tree arity".to_string(),
+                parameter_value: ARITY.to_string(),
+            });
+        }
+
+        if !block_capacity.is_power_of_two() | (block_capacity <= 1) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
+
+        if Z <= 1 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Bucket size Z".to_string(),
+                parameter_value: Z.to_string(),
+            });
+        }
+
+        if recursion_cutoff == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Recursion cutoff".to_string(),
+                parameter_value: recursion_cutoff.to_string(),
+            });
+        }
+
+        let binary_height: TreeHeight = (block_capacity.ilog2() - 1).into();
+        if binary_height > MAXIMUM_TREE_HEIGHT {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
+
+        let arity_bits = ARITY.ilog2();
+        let height = binary_height.div_ceil(arity_bits.into());
+        let leaf_depth = height * TreeHeight::from(arity_bits);
+        let first_leaf: TreeIndex = 1 << leaf_depth;
+        let arity = u64::try_from(ARITY)?;
+        let number_of_buckets = ((first_leaf * arity) - 1) / (arity - 1);
+
+        let path_size = u64::try_from(Z)? * (height + 1);
+        let stash = ObliviousStash::new(path_size, overflow_size, StashGrowth::default())?;
+        let mut physical_memory = KaryTree {
+            buckets: try_filled_vec(number_of_buckets, Bucket::<V, Z>::default())?,
+            arity_bits,
+        };
+        let mut position_map = RecursivePositionMap::new(
+            block_capacity,
+            rng,
+            overflow_size,
+            recursion_cutoff,
+            StashGrowth::default(),
+        )?;
+
+        // As in `PathOram`, each address is assigned to a uniformly random leaf, with at most two addresses
+        // per leaf: the `i`-th address of a random permutation goes to slot `i / leaves` of leaf `i % leaves`.
+        let leaves = first_leaf;
+        let slots_to_addresses = random_permutation_of_0_through_n_exclusive(block_capacity, rng);
+        let mut positions = vec![TreeIndex::default(); usize::try_from(block_capacity)?];
+        for (slot, address) in slots_to_addresses.iter().enumerate() {
+            let slot = u64::try_from(slot)?;
+            let position = first_leaf + slot % leaves;
+            positions[usize::try_from(*address)?] = position;
+
+            let mut bucket = physical_memory.read_bucket(height, position)?;
+            bucket.blocks[usize::try_from(slot / leaves)?] = PathOramBlock {
+                value: V::default(),
+                address: *address,
+                position,
+            };
+            physical_memory.write_bucket(height, position, &bucket)?;
+        }
+        let addresses: Vec<Address> = (0..block_capacity).collect();
+        for (addresses, positions) in addresses
+            .chunks(DEFAULT_POSITIONS_PER_BLOCK)
+            .zip(positions.chunks(DEFAULT_POSITIONS_PER_BLOCK))
+        {
+            position_map.write_positions_batch(addresses, positions, rng)?;
+        }
+
+        Ok(Self {
+            physical_memory,
+            stash,
+            position_map,
+            height,
+            block_capacity,
+        })
+    }
+
+    /// Returns the height of the tree, counted in levels of `ARITY`-ary nodes.
+    /// Every access reads and writes `tree_height() + 1` buckets.
+    pub fn tree_height(&self) -> u64 {
+        self.height
+    }
+
+    /// Returns the number of real blocks in the overflow area of the stash.
+    pub fn stash_occupancy(&self) -> StashSize {
+        self.stash.occupancy()
+    }
+}
+
+impl<V: OramBlock, const ARITY: usize, const Z: BucketSize> Oram for KaryPathOram<V, ARITY, Z> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(self.block_capacity)
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        if address >= self.block_capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: address,
+                capacity: self.block_capacity,
+            });
+        }
+
+        let leaf_depth = self.height * TreeHeight::from(self.physical_memory.arity_bits);
+        let new_position = CompleteBinaryTreeIndex::random_leaf(leaf_depth, rng)?;
+        let position = self.position_map.write(address, new_position, rng)?;
+        assert!(position.is_leaf(leaf_depth));
+
+        self.stash
+            .read_from_path(&mut self.physical_memory, position)?;
+        let result = self.stash.access(address, new_position, callback);
+        self.stash
+            .write_to_path(&mut self.physical_memory, position)?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn kary_path_oram_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for capacity in [2, 4, 64, 256] {
+            let mut oram = KaryPathOram::<BlockValue<1>, 2, 4>::new(capacity, &mut rng).unwrap();
+            random_workload(&mut oram, 500);
+            let mut oram = KaryPathOram::<BlockValue<1>, 4, 8>::new(capacity, &mut rng).unwrap();
+            random_workload(&mut oram, 500);
+            let mut oram = KaryPathOram::<BlockValue<1>, 8, 16>::new_with_parameters(
+                capacity, &mut rng, 40, 1,
+            )
+            .unwrap();
+            random_workload(&mut oram, 500);
+            assert!(oram.stash_occupancy() < 40);
+        }
+    }
+
+    #[test]
+    fn kary_path_oram_paths_shorten_with_arity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let binary = KaryPathOram::<u64, 2, 4>::new(1024, &mut rng).unwrap();
+        let quaternary = KaryPathOram::<u64, 4, 8>::new(1024, &mut rng).unwrap();
+        let octal = KaryPathOram::<u64, 8, 16>::new(1024, &mut rng).unwrap();
+        assert_eq!(binary.tree_height(), 9);
+        assert_eq!(quaternary.tree_height(), 5);
+        assert_eq!(octal.tree_height(), 3);
+        assert_eq!(octal.physical_memory.buckets.len(), 1 + 8 + 64 + 512);
+    }
+
+    #[test]
+    fn kary_path_oram_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(KaryPathOram::<u64, 3, 4>::new(64, &mut rng).is_err());
+        assert!(KaryPathOram::<u64, 16, 4>::new(64, &mut rng).is_err());
+        assert!(KaryPathOram::<u64, 4, 1>::new(64, &mut rng).is_err());
+        assert!(KaryPathOram::<u64, 4, 4>::new(48, &mut rng).is_err());
+        let mut oram = KaryPathOram::<u64, 4, 4>::new(64, &mut rng).unwrap();
+        assert!(oram.read(64, &mut rng).is_err());
+    }
+}
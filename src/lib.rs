@@ -41,9 +41,7 @@
 //! let mut oram = DefaultOram::<BlockValue<BLOCK_SIZE>>::new(DB_SIZE, &mut rng)?;
 //!
 //! // Read a database (here, an array of byte arrays) into the ORAM.
-//! for (i, bytes) in DATABASE.iter().enumerate() {
-//!     oram.write(i as Address, BlockValue::new(*bytes), &mut rng)?;
-//! }
+//! oram.load_bytes(&DATABASE.concat(), false, &mut rng)?;
 //!
 //! // Now you can safely make secret-dependent accesses to your database.
 //! let secret = 42;
@@ -55,7 +53,9 @@
 //!
 //! ORAMs can store arbitrary structs implementing `OramBlock`.
 //! We provide implementations of `OramBlock` for `u8`, `u16`, `u32`, `u64`, `i8`, `i16`, `i32`, `i64`,
-//! and `BlockValue<const B: BlockSize>`.
+//! `BlockValue<const B: BlockSize>`, `TypedBlock<T, const N: usize>` (an array of `N` values of type `T`,
+//! of which `BlockValue<B>` is the byte case), and `OramBool`, the [`oram_block!`] macro for defining
+//! simple composite records, and the [`oram_enum!`] macro for defining field-less enums.
 //!
 //! The `DefaultOram` used in the above example should have good performance in most use cases.
 //! But the underlying algorithms have several tunable parameters that impact performance.
@@ -86,27 +86,161 @@
 //! ```
 //!
 //! See [`PathOram`] for an explanation of these parameters and their possible settings.
+//!
+//! # Unsafe code
+//!
+//! Unsafe code is denied outside of three places: the `mlock` and `madvise` system calls that lock
+//! stash buffers into memory under the `mlock` feature, the speculation barriers of [`ct::speculation_barrier`]
+//! under the `speculation-barriers` feature, and the discriminant conversion generated by [`oram_enum!`].
+//!
+//! # Features
+//!
+//! No features are enabled by default.
+//!
+//! - `fuzzing`: exposes `fuzzing::run_path_oram_operations` and `fuzzing::check_path_oram_operations`,
+//!   which drive a [`PathOram`] with arbitrary operation sequences and check its invariants, for the targets in `fuzz/`.
+//! - `debug-invariants`: checks the structural invariants of every [`PathOram`] after each successful access,
+//!   panicking on a violation. Very slow.
+//! - `speculation-barriers`: inserts speculation barriers ([`ct::speculation_barrier`]) into the constant-time scans
+//!   of the stash and of [`linear_time_oram::LinearTimeOram`], hardening them against Spectre-style attacks at a significant cost in speed.
+//! - `parallel`: exposes `LinearTimeOram::par_access`, which splits the scan of the database across the threads
+//!   of a rayon pool.
+//! - `diagnostics`: exposes `access_with_stats` methods returning per-access `diagnostics::AccessStats`,
+//!   a `diagnostics::LeafHistogram` of the leaves read by accesses, and per-depth `diagnostics::LevelStats`.
+//! - `profiling`: records the duration of each phase of Path ORAM accesses, returned by `PathOram::profiler`,
+//!   and latency histograms of [`OramManager`] operations.
+//! - `research`: exposes `PathOram::inspect_path` and `PathOram::inspect_stash`, read-only inspection of the
+//!   internal state of a [`PathOram`]. **Breaks obliviousness**; never enable it in a deployment.
+//! - `test-vectors`: exposes `test_vectors::generate_transcript`, which produces known-answer transcripts
+//!   of the physical buckets touched by a deterministic workload.
+//! - `mlock`: locks the blocks and scratch space of every [`ObliviousStash`] into memory with `mlock`,
+//!   and excludes them from core dumps on Linux. Has no effect on other platforms.
+//! - `rand_core_0_9`: allows RNGs from the `rand_core` 0.9 ecosystem to be used, through `rng::RandCore09Rng`.
+//! - `serde`: implements `Serialize` and `Deserialize` for [`BlockValue`] and `PositionBlock`.
+//! - `proptest`: implements proptest's `Arbitrary` for [`TypedBlock`] and `PositionBlock`, and for the operations
+//!   and parameters of the `fuzzing` helpers. Enables `fuzzing`.
 
 #![warn(clippy::cargo, clippy::doc_markdown, missing_docs, rustdoc::all)]
+#![deny(unsafe_code)]
 
 use std::num::TryFromIntError;
 
-use rand::{CryptoRng, RngCore};
 use subtle::ConditionallySelectable;
 use thiserror::Error;
 
+pub mod access_outcome;
+pub mod allocator;
+pub mod audit;
+pub mod bitmap;
 pub(crate) mod bucket;
+pub mod channel;
+pub mod cost_model;
+pub mod counter_array;
+pub mod ct;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod distributed_oram;
+pub mod dp_oram;
+pub mod dyn_block;
+pub mod dyn_oram;
+pub mod eviction;
+pub mod frequency_smoothing;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod initialized_oram;
+pub mod kary_oram;
 pub mod linear_time_oram;
+#[cfg(all(feature = "mlock", unix))]
+#[allow(unsafe_code)]
+pub(crate) mod locked_memory;
+pub mod membership_filter;
+pub mod memory_usage;
+pub mod mirrored_oram;
+pub mod monitors;
+pub mod oblivious_bounds;
+pub mod oblivious_fs;
+pub mod oblivious_graph;
+pub mod oblivious_matrix;
+pub mod onion_oram;
+pub mod oram_manager;
+pub mod oram_option;
+pub mod partition_oram;
 pub mod path_oram;
+pub(crate) mod physical_tree;
 pub(crate) mod position_map;
+#[cfg(feature = "profiling")]
+pub mod profiler;
+pub mod proxy;
+pub mod reordering_buffer;
+pub mod replicated_oram;
+pub mod ring_buffer;
+pub mod rng;
+pub mod shared_segment;
+pub mod simulate;
+pub mod snapshot;
+pub mod sparse_array;
 pub(crate) mod stash;
+pub mod striped_oram;
 #[cfg(test)]
 mod test_utils;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+pub mod tiered_oram;
+pub mod tiny_oram;
 pub(crate) mod utils;
+pub mod verification;
+pub mod versioned_oram;
 
+pub use crate::access_outcome::AccessOutcome;
+pub use crate::allocator::BlockAllocator;
+pub use crate::bitmap::ObliviousBitmap;
 pub use crate::bucket::BlockValue;
+pub use crate::bucket::Bucket;
+pub use crate::bucket::OramBool;
+pub use crate::bucket::PathOramBlock;
+pub use crate::bucket::TypedBlock;
+pub use crate::cost_model::cost_model;
+pub use crate::counter_array::ObliviousCounterArray;
+pub use crate::distributed_oram::DistributedOram;
+pub use crate::dp_oram::DpOram;
+pub use crate::dyn_block::{DynBlockOram, DynBlockValue};
+pub use crate::dyn_oram::DynOram;
+pub use crate::eviction::{EvictionStrategy, PathEviction, ProactiveEviction, ScheduledEviction};
+pub use crate::frequency_smoothing::FrequencySmoothingOram;
+pub use crate::initialized_oram::InitializedOram;
+pub use crate::kary_oram::KaryPathOram;
+pub use crate::membership_filter::ObliviousMembershipFilter;
+pub use crate::memory_usage::MemoryUsage;
+pub use crate::mirrored_oram::MirroredOram;
+pub use crate::oblivious_bounds::ObliviousBoundsOram;
+pub use crate::oblivious_fs::{FsError, ObliviousFs};
+pub use crate::oblivious_graph::ObliviousGraph;
+pub use crate::oblivious_matrix::ObliviousMatrix;
+pub use crate::onion_oram::OnionOram;
+pub use crate::oram_manager::OramManager;
+pub use crate::oram_option::OramOption;
+pub use crate::partition_oram::PartitionOram;
 pub use crate::path_oram::DefaultOram;
 pub use crate::path_oram::PathOram;
+pub use crate::physical_tree::BucketLayout;
+pub use crate::position_map::{PositionMap, RecursivePositionMap};
+pub use crate::proxy::{OramProxy, ProxyError};
+pub use crate::reordering_buffer::ReorderingBuffer;
+pub use crate::replicated_oram::{ReplicatedOram, ReplicatedOramError};
+pub use crate::ring_buffer::ObliviousRingBuffer;
+pub use crate::rng::OramRng;
+pub use crate::shared_segment::SharedSegmentTree;
+pub use crate::simulate::PathOramSimulator;
+pub use crate::snapshot::OramSnapshot;
+pub use crate::sparse_array::ObliviousSparseArray;
+pub use crate::stash::{
+    FixedStash, InsecureVecStash, ObliviousStash, PathStorage, Stash, StashGrowth,
+};
+pub use crate::striped_oram::StripedOram;
+pub use crate::tiered_oram::TieredOram;
+pub use crate::tiny_oram::TinyOram;
+pub use crate::verification::{Inconsistency, VerificationReport};
+pub use crate::versioned_oram::VersionedOram;
 
 /// The numeric type used to specify the size of an ORAM block in bytes.
 pub type BlockSize = usize;
@@ -135,11 +269,148 @@ impl OramBlock for i16 {}
 impl OramBlock for i32 {}
 impl OramBlock for i64 {}
 
+#[doc(hidden)]
+pub use subtle;
+
+/// Defines a struct of `OramBlock` fields that is itself an `OramBlock`, for simple composite records.
+///
+/// The struct derives `Clone`, `Copy`, `Debug`, `Default`, and `PartialEq`,
+/// and implements `ConditionallySelectable` field by field.
+/// (Tuples cannot implement `OramBlock`, since `ConditionallySelectable` is a foreign trait.)
+///
+/// ```
+/// use oram::{oram_block, DefaultOram, Oram};
+/// # use oram::OramError;
+///
+/// oram_block! {
+///     /// A user record.
+///     pub struct Record {
+///         pub id: u64,
+///         pub age: u32,
+///         pub score: u32,
+///     }
+/// }
+///
+/// let mut rng = rand::rngs::OsRng;
+/// let mut oram = DefaultOram::<Record>::new(16, &mut rng)?;
+/// let record = Record { id: 7, age: 30, score: 99 };
+/// oram.write(3, record, &mut rng)?;
+/// assert_eq!(oram.read(3, &mut rng)?, record);
+/// # Ok::<(), OramError>(())
+/// ```
+#[macro_export]
+macro_rules! oram_block {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis struct $name:ident {
+            $($(#[$field_attribute:meta])* $field_visibility:vis $field:ident : $field_type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attribute])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq)]
+        $visibility struct $name {
+            $($(#[$field_attribute])* $field_visibility $field: $field_type),*
+        }
+
+        impl $crate::subtle::ConditionallySelectable for $name {
+            fn conditional_select(a: &Self, b: &Self, choice: $crate::subtle::Choice) -> Self {
+                Self {
+                    $($field: <$field_type as $crate::subtle::ConditionallySelectable>::conditional_select(
+                        &a.$field, &b.$field, choice,
+                    )),*
+                }
+            }
+        }
+
+        impl $crate::OramBlock for $name {}
+    };
+}
+
+/// Defines a field-less enum with explicit discriminants and an integer representation that is an `OramBlock`,
+/// so that small sets of states can be stored without encoding them by hand.
+///
+/// The enum is `#[repr]` of the given integer type, derives `Clone`, `Copy`, `Debug`, `PartialEq`, and `Eq`,
+/// and defaults to its first variant. It implements `ConditionallySelectable` and `ConstantTimeEq`
+/// on its discriminant, in constant time, so it can be compared and selected obliviously.
+///
+/// ```
+/// use oram::{oram_enum, DefaultOram, Oram};
+/// # use oram::OramError;
+///
+/// oram_enum! {
+///     /// The state of an account.
+///     pub enum State: u8 {
+///         Active = 0,
+///         Suspended = 1,
+///         Closed = 2,
+///     }
+/// }
+///
+/// let mut rng = rand::rngs::OsRng;
+/// let mut oram = DefaultOram::<State>::new(16, &mut rng)?;
+/// assert_eq!(oram.read(3, &mut rng)?, State::Active);
+/// oram.write(3, State::Closed, &mut rng)?;
+/// assert_eq!(oram.read(3, &mut rng)?, State::Closed);
+///
+/// use subtle::{Choice, ConditionallySelectable};
+/// let choice = Choice::from(1);
+/// assert_eq!(State::conditional_select(&State::Active, &State::Suspended, choice), State::Suspended);
+/// # Ok::<(), OramError>(())
+/// ```
+#[macro_export]
+macro_rules! oram_enum {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis enum $name:ident : $repr:ident {
+            $(#[$first_attribute:meta])* $first:ident = $first_discriminant:expr
+            $(, $(#[$variant_attribute:meta])* $variant:ident = $discriminant:expr)* $(,)?
+        }
+    ) => {
+        $(#[$attribute])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr($repr)]
+        $visibility enum $name {
+            $(#[$first_attribute])* $first = $first_discriminant,
+            $($(#[$variant_attribute])* $variant = $discriminant),*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::$first
+            }
+        }
+
+        impl $crate::subtle::ConditionallySelectable for $name {
+            fn conditional_select(a: &Self, b: &Self, choice: $crate::subtle::Choice) -> Self {
+                let discriminant = <$repr as $crate::subtle::ConditionallySelectable>::conditional_select(
+                    &(*a as $repr),
+                    &(*b as $repr),
+                    choice,
+                );
+                // SAFETY: `discriminant` is the discriminant of either `a` or `b`,
+                // and the enum has the representation of `$repr`.
+                #[allow(unsafe_code)]
+                unsafe {
+                    ::core::mem::transmute::<$repr, Self>(discriminant)
+                }
+            }
+        }
+
+        impl $crate::subtle::ConstantTimeEq for $name {
+            fn ct_eq(&self, other: &Self) -> $crate::subtle::Choice {
+                <$repr as $crate::subtle::ConstantTimeEq>::ct_eq(&(*self as $repr), &(*other as $repr))
+            }
+        }
+
+        impl $crate::OramBlock for $name {}
+    };
+}
+
 /// A list of error types which are produced during ORAM protocol execution.
 #[derive(Error, Debug)]
 pub enum OramError {
     /// Errors arising from conversions between integer types.
-    #[error("Arithmetic error encountered.")]
+    #[error("Arithmetic error encountered: {0}.")]
     IntegerConversionError(#[from] TryFromIntError),
     /// Errors arising from attempting to make an ORAM access to an invalid address.
     #[error("Attempted to access ORAM address {attempted}, which is larger than ORAM capacity {capacity}.")]
@@ -157,6 +428,50 @@ pub enum OramError {
         /// Its invalid value.
         parameter_value: String,
     },
+    /// Errors arising from attempting to allocate a block when every block is in use.
+    #[error("Attempted to allocate a block, but all {capacity} blocks are in use.")]
+    OutOfMemoryError {
+        /// The capacity of the allocator.
+        capacity: Address,
+    },
+    /// Errors arising from a stash overflowing when it cannot grow any further.
+    #[error("Stash overflow: the stash of {stash_size} blocks, with room for {overflow_capacity} blocks besides a path, is full.")]
+    StashOverflowError {
+        /// The total number of blocks the stash holds, including the blocks of the path being evicted to.
+        stash_size: StashSize,
+        /// The number of blocks the stash holds between accesses.
+        overflow_capacity: StashSize,
+    },
+    /// Errors arising from accessing an ORAM that was poisoned by an earlier stash overflow,
+    /// or by a panic during an earlier access.
+    #[error("The ORAM was poisoned by an earlier failed access and can no longer be accessed.")]
+    PoisonedError,
+    /// Errors arising from failing to allocate the memory of an ORAM.
+    #[error("Failed to allocate {requested_bytes} bytes.")]
+    AllocationError {
+        /// The number of bytes requested.
+        requested_bytes: u64,
+    },
+}
+
+/// The errors of ORAMs and channels that communicate with remote storage, such as a [`DistributedOram`].
+#[derive(Error, Debug)]
+pub enum TransportError {
+    /// Errors of the ORAM itself.
+    #[error(transparent)]
+    OramError(#[from] OramError),
+    /// Errors arising from communicating with remote storage.
+    #[error("Storage transport failed: {reason}")]
+    StorageError {
+        /// A description of the failure.
+        reason: String,
+    },
+}
+
+impl From<TryFromIntError> for TransportError {
+    fn from(error: TryFromIntError) -> Self {
+        Self::OramError(error.into())
+    }
 }
 
 /// Represents an oblivious RAM (ORAM) mapping addresses of type `Address` to values of type `V: OramBlock`.
@@ -167,39 +482,75 @@ where
     /// The type of elements stored in the ORAM.
     type V: OramBlock;
 
+    /// The type of errors returned by this ORAM. The ORAMs of this crate use [`OramError`];
+    /// wrappers layering encryption, networking, or integrity checks on an ORAM can surface their own errors,
+    /// as long as every `OramError` converts into them.
+    type Error: std::error::Error + From<OramError>;
+
     /// Returns the capacity in blocks of this ORAM.
-    fn block_capacity(&self) -> Result<Address, OramError>;
+    fn block_capacity(&self) -> Result<Address, Self::Error>;
 
     /// Performs a (oblivious) ORAM access.
     /// Returns the value `v` previously stored at `index`, and writes `callback(v)` to `index`.
     ///
     /// For updating a block in place, using `access` is expected to be about
     /// twice as fast as performing a `read` followed by a `write`.
-    fn access<R: RngCore + CryptoRng, F: Fn(&Self::V) -> Self::V>(
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
         &mut self,
         index: Address,
         callback: F,
         rng: &mut R,
-    ) -> Result<Self::V, OramError>;
+    ) -> Result<Self::V, Self::Error>;
 
     /// Obliviously reads the value stored at `index`.
-    fn read<R: RngCore + CryptoRng>(
-        &mut self,
-        index: Address,
-        rng: &mut R,
-    ) -> Result<Self::V, OramError> {
+    fn read<R: OramRng>(&mut self, index: Address, rng: &mut R) -> Result<Self::V, Self::Error> {
         let callback = |x: &Self::V| *x;
         self.access(index, callback, rng)
     }
 
     /// Obliviously writes the value stored at `index`. Returns the value previously stored at `index`.
-    fn write<R: RngCore + CryptoRng>(
+    fn write<R: OramRng>(
         &mut self,
         index: Address,
         new_value: Self::V,
         rng: &mut R,
-    ) -> Result<Self::V, OramError> {
+    ) -> Result<Self::V, Self::Error> {
         let callback = |_: &Self::V| new_value;
         self.access(index, callback, rng)
     }
+
+    /// Performs a (oblivious) ORAM access with a callback that also computes an auxiliary output.
+    /// Returns the value `v` previously stored at `index` together with `callback(v).1`,
+    /// and writes `callback(v).0` to `index`.
+    ///
+    /// This lets a computation over the secret value, such as extracting a field or comparing it to a secret,
+    /// be done in the same access that updates it, without a second access or a copy of the block.
+    /// As with `access`, `callback` may be invoked on values other than `v`, so it should not have side effects.
+    fn access_with<R: OramRng, T, F: Fn(&Self::V) -> (Self::V, T)>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<(Self::V, T), Self::Error> {
+        let previous = self.access(index, |value| callback(value).0, rng)?;
+        let (_, output) = callback(&previous);
+        Ok((previous, output))
+    }
+
+    /// Performs `num_accesses` reads of random addresses, leaving the contents of the ORAM unchanged.
+    ///
+    /// Intended to be called after construction, so that page faults, allocator growth, and cache warm-up
+    /// happen before the first real request, instead of inflating its latency.
+    /// The reads are indistinguishable from real accesses.
+    fn warm_up<R: OramRng>(&mut self, num_accesses: u64, rng: &mut R) -> Result<(), Self::Error> {
+        let capacity = self.block_capacity()?;
+        if capacity == 0 {
+            return Ok(());
+        }
+        for _ in 0..num_accesses {
+            let address = rand::Rng::gen_range(rng, 0..capacity);
+            self.read(address, rng)?;
+        }
+        Ok(())
+    }
 }
@@ -7,13 +7,21 @@
 
 //! A simple linear-time implementation of Oblivious RAM.
 
-use crate::{Address, Oram, OramBlock, OramError};
-use rand::{CryptoRng, RngCore};
+use crate::{
+    ct::speculation_barrier,
+    memory_usage::{LevelMemoryUsage, MemoryUsage},
+    utils::{apply_permutation_oblivious, try_filled_vec},
+    Address, Oram, OramBlock, OramError, OramRng,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use subtle::Choice;
 use subtle::{ConstantTimeEq, ConstantTimeLess};
 
 /// A simple ORAM that, for each access, ensures obliviousness by making a complete pass over the database,
 /// reading and writing each memory location.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LinearTimeOram<V: OramBlock> {
     /// The memory of the ORAM (public for benchmarking).
     pub physical_memory: Vec<V>,
@@ -22,18 +30,116 @@ pub struct LinearTimeOram<V: OramBlock> {
 impl<V: OramBlock> LinearTimeOram<V> {
     /// Returns a new `LinearTimeOram` mapping addresses `0 <= address < block_capacity` to default `V` values.
     pub fn new(block_capacity: Address) -> Result<Self, OramError> {
+        Self::new_with_fill_value(block_capacity, V::default())
+    }
+
+    /// Returns a new `LinearTimeOram` mapping addresses `0 <= address < block_capacity` to `fill_value`.
+    pub fn new_with_fill_value(block_capacity: Address, fill_value: V) -> Result<Self, OramError> {
         log::info!("LinearTimeOram::new(capacity = {})", block_capacity,);
 
-        let mut physical_memory = Vec::new();
-        physical_memory.resize(usize::try_from(block_capacity)?, V::default());
+        let physical_memory = try_filled_vec(block_capacity, fill_value)?;
         Ok(Self { physical_memory })
     }
+
+    /// Returns the memory used by the ORAM.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            levels: vec![LevelMemoryUsage {
+                tree_bytes: self.physical_memory.capacity() * std::mem::size_of::<V>(),
+                stash_bytes: 0,
+                scratch_bytes: 0,
+            }],
+        }
+    }
+
+    /// Obliviously moves the value stored at every address `a` to address `permutation[a]`.
+    ///
+    /// # Errors
+    ///
+    /// If `permutation` is not a permutation of `0..block_capacity`, returns an `InvalidConfigurationError`
+    /// and leaves the ORAM unchanged.
+    pub fn permute(&mut self, permutation: &[Address]) -> Result<(), OramError> {
+        apply_permutation_oblivious(&mut self.physical_memory, permutation)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<V: OramBlock + Send + Sync> LinearTimeOram<V> {
+    /// Performs an access as [`Oram::access`], splitting the pass over the database into one chunk
+    /// per thread of the current rayon pool. Each thread makes a complete pass over its chunk,
+    /// and the results of the chunks are combined with constant-time selects,
+    /// so the accesses of every thread are independent of `index`.
+    ///
+    /// This pays off for large capacities or large blocks, where a single-threaded pass is memory-bound.
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn par_access<F: Fn(&V) -> V + Sync>(
+        &mut self,
+        index: Address,
+        callback: F,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            });
+        }
+
+        let chunk_len = self
+            .physical_memory
+            .len()
+            .div_ceil(rayon::current_num_threads())
+            .max(1);
+        let (result, _) = self
+            .physical_memory
+            .par_chunks_mut(chunk_len)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                // This is a dummy value which will be overwritten in exactly one chunk.
+                let mut result = V::default();
+                let mut found = Choice::from(0);
+                let first_address = (chunk_index * chunk_len) as Address;
+                for (offset, entry) in chunk.iter_mut().enumerate() {
+                    let is_requested_index = (first_address + offset as Address).ct_eq(&index);
+                    speculation_barrier();
+                    result.conditional_assign(entry, is_requested_index);
+                    found |= is_requested_index;
+
+                    let potential_new_value = callback(entry);
+                    entry.conditional_assign(&potential_new_value, is_requested_index);
+                }
+                (result, found)
+            })
+            .reduce(
+                || (V::default(), Choice::from(0)),
+                |(a, a_found), (b, b_found)| {
+                    (V::conditional_select(&a, &b, b_found), a_found | b_found)
+                },
+            );
+        Ok(result)
+    }
+
+    /// Obliviously reads the value stored at `index`, as [`LinearTimeOram::par_access`].
+    pub fn par_read(&mut self, index: Address) -> Result<V, OramError> {
+        self.par_access(index, |x| *x)
+    }
+
+    /// Obliviously writes the value stored at `index`, as [`LinearTimeOram::par_access`].
+    /// Returns the value previously stored at `index`.
+    pub fn par_write(&mut self, index: Address, new_value: V) -> Result<V, OramError> {
+        self.par_access(index, |_| new_value)
+    }
 }
 
 impl<V: OramBlock> Oram for LinearTimeOram<V> {
     type V = V;
+    type Error = OramError;
 
-    fn access<R: RngCore + CryptoRng, F: Fn(&V) -> V>(
+    fn access<R: OramRng, F: Fn(&V) -> V>(
         &mut self,
         index: Address,
         callback: F,
@@ -56,6 +162,7 @@ impl<V: OramBlock> Oram for LinearTimeOram<V> {
             let entry = &self.physical_memory[i];
 
             let is_requested_index = (u64::try_from(i)?).ct_eq(&index);
+            speculation_barrier();
 
             result.conditional_assign(entry, is_requested_index);
 
@@ -87,4 +194,28 @@ mod tests {
         let mut oram = LinearTimeOram::<BlockValue<1>>::new(64).unwrap();
         linear_workload(&mut oram, 1000);
     }
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn linear_time_oram_parallel_scan_matches_sequential() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut parallel = LinearTimeOram::<BlockValue<64>>::new(1000).unwrap();
+        let mut sequential = parallel.clone();
+        for _ in 0..500 {
+            let index = rng.gen_range(0..1000);
+            let value = BlockValue::new([rng.gen(); 64]);
+            assert_eq!(
+                parallel.par_write(index, value).unwrap(),
+                sequential.write(index, value, &mut rng).unwrap()
+            );
+            let index = rng.gen_range(0..1000);
+            assert_eq!(
+                parallel.par_read(index).unwrap(),
+                sequential.read(index, &mut rng).unwrap()
+            );
+        }
+        assert_eq!(parallel.physical_memory, sequential.physical_memory);
+        assert!(parallel.par_read(1000).is_err());
+    }
 }
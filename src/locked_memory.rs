@@ -0,0 +1,228 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Buffers locked into memory, so that secret client state is never swapped out or written to core dumps.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// The number of live [`LockedVec`] allocations overlapping each locked page, keyed by the address of the page.
+///
+/// Memory locks apply to whole pages and do not nest, so a page shared by two allocations
+/// must stay locked until both are freed.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+fn page_size() -> usize {
+    // SAFETY: `sysconf` has no preconditions.
+    usize::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).unwrap_or(4096)
+}
+
+/// Returns the range of addresses of the pages overlapping `bytes` bytes starting at `start`.
+fn page_range(start: usize, bytes: usize) -> std::ops::Range<usize> {
+    let page_size = page_size();
+    let first_page = start & !(page_size - 1);
+    let end = (start + bytes).div_ceil(page_size) * page_size;
+    first_page..end
+}
+
+/// Locks the pages overlapping `bytes` bytes starting at `start` into memory, and excludes them from core dumps.
+fn lock(start: usize, bytes: usize) {
+    let pages = page_range(start, bytes);
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: `pages` covers memory mapped by the caller's live allocation, and neither call accesses it.
+    let result = unsafe { libc::mlock(pages.start as *const libc::c_void, pages.len()) };
+    if result != 0 {
+        log::warn!(
+            "Failed to lock {} bytes of ORAM client state into memory: {}",
+            pages.len(),
+            std::io::Error::last_os_error()
+        );
+    }
+    #[cfg(target_os = "linux")]
+    // SAFETY: As above. `MADV_DONTDUMP` does not change the contents of the pages.
+    unsafe {
+        libc::madvise(
+            pages.start as *mut libc::c_void,
+            pages.len(),
+            libc::MADV_DONTDUMP,
+        );
+    }
+
+    for page in pages.step_by(page_size()) {
+        *locked_pages.entry(page).or_insert(0) += 1;
+    }
+}
+
+/// Undoes one call to [`lock`] with the same arguments, unlocking the pages no other allocation overlaps.
+fn unlock(start: usize, bytes: usize) {
+    let pages = page_range(start, bytes);
+    let page_size = page_size();
+    let mut locked_pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    for page in pages.step_by(page_size) {
+        let Some(count) = locked_pages.get_mut(&page) else {
+            continue;
+        };
+        *count -= 1;
+        if *count == 0 {
+            locked_pages.remove(&page);
+            // SAFETY: The page is mapped, since it overlaps the caller's live allocation.
+            unsafe {
+                libc::munlock(page as *const libc::c_void, page_size);
+                #[cfg(target_os = "linux")]
+                libc::madvise(page as *mut libc::c_void, page_size, libc::MADV_DODUMP);
+            }
+        }
+    }
+}
+
+/// A vector whose allocation is locked into memory with `mlock`, and, on Linux, excluded from core dumps
+/// with `madvise(MADV_DONTDUMP)`, for as long as it is alive. Its contents are zeroed before it is freed.
+///
+/// Locking is best-effort: if it fails, for example because `RLIMIT_MEMLOCK` is exceeded,
+/// a warning is logged and the vector is used unlocked.
+pub(crate) struct LockedVec<T: Copy + Default> {
+    items: Vec<T>,
+}
+
+impl<T: Copy + Default> LockedVec<T> {
+    fn allocation(&self) -> Option<(usize, usize)> {
+        let bytes = self.items.capacity() * std::mem::size_of::<T>();
+        (bytes > 0).then_some((self.items.as_ptr() as usize, bytes))
+    }
+
+    fn lock(&self) {
+        if let Some((start, bytes)) = self.allocation() {
+            lock(start, bytes);
+        }
+    }
+
+    fn unlock(&mut self) {
+        // Zero the whole allocation, not just the initialized items, so that no secret outlives the lock.
+        let capacity = self.items.capacity();
+        self.items.resize(capacity, T::default());
+        self.items.fill(T::default());
+        std::hint::black_box(&self.items);
+        if let Some((start, bytes)) = self.allocation() {
+            unlock(start, bytes);
+        }
+    }
+
+    /// Returns an empty vector whose locked allocation can hold `capacity` items.
+    fn with_capacity(capacity: usize) -> Self {
+        Self::from(Vec::with_capacity(capacity))
+    }
+
+    /// Returns the number of items the allocation can hold.
+    pub(crate) fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+
+    /// Resizes the vector as [`Vec::resize`], moving it to a new locked allocation if it grows.
+    pub(crate) fn resize(&mut self, new_len: usize, value: T) {
+        if new_len <= self.items.capacity() {
+            self.items.resize(new_len, value);
+            return;
+        }
+        // Lock the new allocation before copying the items in, so that they never sit in unlocked memory.
+        let mut result = Self::with_capacity(new_len);
+        result.items.extend_from_slice(&self.items);
+        result.items.resize(new_len, value);
+        *self = result;
+    }
+}
+
+impl<T: Copy + Default> From<Vec<T>> for LockedVec<T> {
+    fn from(items: Vec<T>) -> Self {
+        let result = Self { items };
+        result.lock();
+        result
+    }
+}
+
+impl<T: Copy + Default> Clone for LockedVec<T> {
+    fn clone(&self) -> Self {
+        let mut result = Self::with_capacity(self.items.len());
+        result.items.extend_from_slice(&self.items);
+        result
+    }
+}
+
+impl<T: Copy + Default> Drop for LockedVec<T> {
+    fn drop(&mut self) {
+        self.unlock();
+    }
+}
+
+impl<T: Copy + Default> Deref for LockedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+impl<T: Copy + Default> DerefMut for LockedVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.items
+    }
+}
+
+impl<T: Copy + Default + std::fmt::Debug> std::fmt::Debug for LockedVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.items.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_tracked(items: &LockedVec<u64>) -> bool {
+        let (start, _) = items.allocation().unwrap();
+        let page = page_range(start, 1).start;
+        LOCKED_PAGES.lock().unwrap().contains_key(&page)
+    }
+
+    #[test]
+    fn locked_vec_tracks_its_pages() {
+        let mut items = LockedVec::from(vec![1u64; 100]);
+        assert!(is_tracked(&items));
+        let copy = items.clone();
+        assert_eq!(&copy[..], &items[..]);
+
+        items.resize(100_000, 2);
+        assert_eq!(items.len(), 100_000);
+        assert_eq!(items[99], 1);
+        assert_eq!(items[100], 2);
+        assert!(is_tracked(&items));
+        assert!(is_tracked(&copy));
+
+        items.resize(10, 0);
+        assert_eq!(items.len(), 10);
+        assert!(items.capacity() >= 100_000);
+    }
+
+    #[test]
+    fn locked_vec_copies_into_locked_allocations() {
+        let items = LockedVec::from(vec![7u64; 1000]);
+        let copy = items.clone();
+        assert!(is_tracked(&copy));
+        assert_eq!(copy.capacity(), 1000);
+
+        let mut grown = copy.clone();
+        let capacity = grown.capacity();
+        grown.resize(capacity + 1, 8);
+        // The items were copied into an allocation sized up front, which was locked before it was filled.
+        assert_eq!(grown.capacity(), capacity + 1);
+        assert!(is_tracked(&grown));
+        assert_eq!(&grown[..1000], &items[..]);
+        assert_eq!(grown[1000], 8);
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious approximate-membership filter.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramError, OramRng};
+use rand::Rng;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use subtle::Choice;
+
+const BITS_PER_WORD: u64 = u64::BITS as u64;
+
+/// A Bloom filter whose bits are stored in an ORAM, supporting `insert` and `contains`
+/// without leaking the keys inserted or queried, or the results of queries.
+///
+/// Every `insert` and `contains` makes exactly `num_hashes` ORAM accesses, and `contains`
+/// returns its result as a `Choice`. Like any Bloom filter, `contains` may return false positives
+/// but never false negatives. With `n` keys inserted into a filter of `m` bits, the false positive rate
+/// is minimized by choosing `num_hashes` close to `(m / n) * ln(2)`, in which case it is about `0.6185^(m / n)`.
+///
+/// Keys are hashed outside the ORAM, in time that depends on the length of the key,
+/// so keys of varying length (such as strings) leak their length.
+#[derive(Debug)]
+pub struct ObliviousMembershipFilter {
+    words: DefaultOram<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    /// A random salt for the hash functions, so that bit positions are not predictable from keys.
+    salt: [u64; 2],
+}
+
+impl ObliviousMembershipFilter {
+    /// Returns a new, empty filter with at least `num_bits` bits, using `num_hashes` hash functions.
+    /// The number of bits is rounded up to a power of two, and to at least 128.
+    ///
+    /// # Errors
+    ///
+    /// If `num_hashes` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(num_bits: u64, num_hashes: u32, rng: &mut R) -> Result<Self, OramError> {
+        if num_hashes == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of hash functions".to_string(),
+                parameter_value: num_hashes.to_string(),
+            });
+        }
+
+        let num_words = num_bits.div_ceil(BITS_PER_WORD).next_power_of_two().max(2);
+        Ok(Self {
+            words: DefaultOram::new(num_words, rng)?,
+            num_bits: num_words * BITS_PER_WORD,
+            num_hashes,
+            salt: rng.gen(),
+        })
+    }
+
+    /// Returns the number of bits in the filter.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Returns the number of hash functions used by the filter.
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// Obliviously inserts `key` into the filter.
+    pub fn insert<K: Hash + ?Sized, R: OramRng>(
+        &mut self,
+        key: &K,
+        rng: &mut R,
+    ) -> Result<(), OramError> {
+        for bit_index in self.bit_indices(key) {
+            let (word, bit) = Self::split(bit_index);
+            self.words.access(word, |w| w | (1 << bit), rng)?;
+        }
+        Ok(())
+    }
+
+    /// Obliviously tests whether `key` may have been inserted into the filter.
+    /// Returns 1 if `key` may have been inserted, and 0 if it definitely was not.
+    pub fn contains<K: Hash + ?Sized, R: OramRng>(
+        &mut self,
+        key: &K,
+        rng: &mut R,
+    ) -> Result<Choice, OramError> {
+        let mut result = Choice::from(1);
+        for bit_index in self.bit_indices(key) {
+            let (word, bit) = Self::split(bit_index);
+            let value = self.words.read(word, rng)?;
+            result &= Choice::from(((value >> bit) & 1) as u8);
+        }
+        Ok(result)
+    }
+
+    /// Returns the `num_hashes` bit positions of `key`, computed by double hashing.
+    fn bit_indices<K: Hash + ?Sized>(&self, key: &K) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        self.salt.hash(&mut hasher);
+        key.hash(&mut hasher);
+        let h1 = hasher.finish();
+        // Hashing a second time yields an independent-looking second hash; forcing it odd
+        // ensures that the probe sequence visits distinct positions (since `num_bits` is a power of two).
+        hasher.write_u8(0);
+        let h2 = hasher.finish() | 1;
+
+        let mask = self.num_bits - 1;
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) & mask)
+    }
+
+    fn split(bit_index: u64) -> (Address, u64) {
+        (bit_index / BITS_PER_WORD, bit_index % BITS_PER_WORD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn membership_filter_has_no_false_negatives() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut filter = ObliviousMembershipFilter::new(1024, 7, &mut rng).unwrap();
+        assert_eq!(filter.num_bits(), 1024);
+
+        for key in 0..100u64 {
+            filter.insert(&key, &mut rng).unwrap();
+        }
+        for key in 0..100u64 {
+            assert!(bool::from(filter.contains(&key, &mut rng).unwrap()));
+        }
+
+        // With 10 bits per key and 7 hash functions, the false positive rate is about 1%.
+        let false_positives = (100..1100u64)
+            .filter(|key| bool::from(filter.contains(key, &mut rng).unwrap()))
+            .count();
+        assert!(false_positives < 50, "{false_positives}");
+    }
+
+    #[test]
+    fn membership_filter_accepts_unsized_keys() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut filter = ObliviousMembershipFilter::new(100, 3, &mut rng).unwrap();
+        assert_eq!(filter.num_bits(), 128);
+
+        filter.insert("alice", &mut rng).unwrap();
+        assert!(bool::from(filter.contains("alice", &mut rng).unwrap()));
+        assert!(ObliviousMembershipFilter::new(100, 0, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Reporting of the memory footprint of ORAMs.
+
+/// The memory used by one level of a (recursive) ORAM, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelMemoryUsage {
+    /// The bytes of the bucket tree (or, for a linear-time ORAM, of its memory).
+    pub tree_bytes: usize,
+    /// The bytes of the stash.
+    pub stash_bytes: usize,
+    /// The peak bytes of scratch buffers allocated transiently during an access.
+    pub scratch_bytes: usize,
+}
+
+impl LevelMemoryUsage {
+    /// Returns the total bytes used by this level.
+    pub fn total_bytes(&self) -> usize {
+        self.tree_bytes + self.stash_bytes + self.scratch_bytes
+    }
+}
+
+/// A breakdown of the memory used by an ORAM, including every level of its recursive position map.
+///
+/// Sizes are computed from the in-memory representation (`size_of` times the allocated length),
+/// and do not include allocator overhead or the few fixed-size fields of each ORAM.
+/// Buckets shared with snapshots are counted in full.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The levels of the ORAM. The first level stores the data blocks; each subsequent level
+    /// is the position map of the previous one, and the last level is a linear-time position map.
+    pub levels: Vec<LevelMemoryUsage>,
+}
+
+impl MemoryUsage {
+    /// Returns the total bytes of the bucket trees of all levels.
+    pub fn tree_bytes(&self) -> usize {
+        self.levels.iter().map(|level| level.tree_bytes).sum()
+    }
+
+    /// Returns the total bytes of the stashes of all levels.
+    pub fn stash_bytes(&self) -> usize {
+        self.levels.iter().map(|level| level.stash_bytes).sum()
+    }
+
+    /// Returns the peak bytes of scratch buffers allocated during an access.
+    /// Levels are accessed one at a time, so this is the maximum over the levels.
+    pub fn scratch_bytes(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|level| level.scratch_bytes)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the bytes used by the position map, i.e., by all levels but the first.
+    pub fn position_map_bytes(&self) -> usize {
+        self.levels
+            .iter()
+            .skip(1)
+            .map(|level| level.tree_bytes + level.stash_bytes)
+            .sum()
+    }
+
+    /// Returns the total bytes used by the ORAM, including peak scratch space.
+    pub fn total_bytes(&self) -> usize {
+        self.tree_bytes() + self.stash_bytes() + self.scratch_bytes()
+    }
+}
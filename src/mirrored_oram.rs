@@ -0,0 +1,104 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A wrapper checking the correctness of an ORAM against a plain array.
+
+use crate::{Address, Oram, OramError, OramRng};
+
+/// Wraps an `Oram`, shadowing every operation against a plain (non-oblivious) vector
+/// and panicking if the values returned by the ORAM ever diverge from those of the vector.
+///
+/// This is intended for running integration test suites with correctness checking enabled.
+/// Since the shadow vector is accessed directly, a `MirroredOram` is **not** oblivious.
+#[derive(Debug)]
+pub struct MirroredOram<O: Oram> {
+    oram: O,
+    mirror: Vec<O::V>,
+}
+
+impl<O: Oram> MirroredOram<O> {
+    /// Wraps `oram`, initializing the shadow vector by reading every address of `oram`.
+    pub fn new<R: OramRng>(mut oram: O, rng: &mut R) -> Result<Self, O::Error> {
+        let capacity = oram.block_capacity()?;
+        let mut mirror = Vec::with_capacity(usize::try_from(capacity).map_err(OramError::from)?);
+        for address in 0..capacity {
+            mirror.push(oram.read(address, rng)?);
+        }
+        Ok(Self { oram, mirror })
+    }
+
+    /// Reads every address of the wrapped ORAM, panicking if any value diverges from the shadow vector.
+    pub fn check_all<R: OramRng>(&mut self, rng: &mut R) -> Result<(), O::Error> {
+        for address in 0..self.block_capacity()? {
+            self.read(address, rng)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> O {
+        self.oram
+    }
+}
+
+impl<O: Oram> Oram for MirroredOram<O> {
+    type V = O::V;
+    type Error = O::Error;
+
+    fn block_capacity(&self) -> Result<Address, O::Error> {
+        self.oram.block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, O::Error> {
+        let result = self.oram.access(index, &callback, rng)?;
+
+        let expected = &mut self.mirror[usize::try_from(index).map_err(OramError::from)?];
+        assert_eq!(
+            result, *expected,
+            "ORAM diverged from its mirror at address {index}"
+        );
+        *expected = callback(expected);
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bucket::BlockValue, linear_time_oram::LinearTimeOram, test_utils::random_workload,
+        DefaultOram,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn mirrored_oram_agrees_with_correct_oram() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::<BlockValue<1>>::new(64, &mut rng).unwrap();
+        let mut mirrored = MirroredOram::new(oram, &mut rng).unwrap();
+        random_workload(&mut mirrored, 1000);
+        mirrored.check_all(&mut rng).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "ORAM diverged from its mirror at address 3")]
+    fn mirrored_oram_detects_divergence() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = LinearTimeOram::<u64>::new(8).unwrap();
+        let mut mirrored = MirroredOram::new(oram, &mut rng).unwrap();
+
+        // Corrupt the wrapped ORAM behind the mirror's back.
+        mirrored.oram.physical_memory[3] = 1;
+        mirrored.check_all(&mut rng).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Wrappers that continuously check structural invariants of a [`PathOram`].
+//!
+//! Each monitor implements [`Oram`] by forwarding to the wrapped ORAM, and panics after any access
+//! that leaves the ORAM in a state violating its invariant. The checks inspect the entire tree or stash,
+//! so monitors are **not** oblivious, and are intended for test and staging environments.
+
+use crate::{path_oram::PathOram, Address, BlockSize, BucketSize, Oram, OramBlock, OramError};
+use crate::{OramRng, StashSize};
+
+/// Panics if the number of blocks in the overflow area of the stash ever exceeds a threshold after an access.
+///
+/// With suitable parameters, the stash of a Path ORAM stays small with overwhelming probability
+/// (see [`PathOram`]), so a large stash indicates a bug or a misconfiguration.
+#[derive(Debug)]
+pub struct StashSizeMonitor<V: OramBlock, const Z: BucketSize, const AB: BlockSize> {
+    oram: PathOram<V, Z, AB>,
+    max_stash_occupancy: StashSize,
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> StashSizeMonitor<V, Z, AB> {
+    /// Wraps `oram`, panicking if its stash holds more than `max_stash_occupancy` blocks after an access.
+    pub fn new(oram: PathOram<V, Z, AB>, max_stash_occupancy: StashSize) -> Self {
+        Self {
+            oram,
+            max_stash_occupancy,
+        }
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> PathOram<V, Z, AB> {
+        self.oram
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram for StashSizeMonitor<V, Z, AB> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        self.oram.block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        let result = self.oram.access(index, callback, rng);
+        let stash_occupancy = self.oram.stash_occupancy();
+        assert!(
+            stash_occupancy <= self.max_stash_occupancy,
+            "Stash holds {stash_occupancy} blocks, more than the maximum of {}",
+            self.max_stash_occupancy
+        );
+        result
+    }
+}
+
+/// Panics if the number of real (non-dummy) blocks in the tree and stash ever differs from the ORAM's capacity
+/// after an access.
+///
+/// Every address of a Path ORAM is stored in exactly one real block, so any other count indicates
+/// that a block was lost or duplicated during eviction.
+#[derive(Debug)]
+pub struct ConstantOccupancyMonitor<V: OramBlock, const Z: BucketSize, const AB: BlockSize> {
+    oram: PathOram<V, Z, AB>,
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> ConstantOccupancyMonitor<V, Z, AB> {
+    /// Wraps `oram`.
+    pub fn new(oram: PathOram<V, Z, AB>) -> Self {
+        Self { oram }
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> PathOram<V, Z, AB> {
+        self.oram
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram
+    for ConstantOccupancyMonitor<V, Z, AB>
+{
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        self.oram.block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        let result = self.oram.access(index, callback, rng)?;
+        let real_blocks = self.oram.real_block_count();
+        let capacity = self.oram.block_capacity()?;
+        assert_eq!(
+            real_blocks, capacity,
+            "ORAM holds {real_blocks} real blocks, but has capacity {capacity}"
+        );
+        Ok(result)
+    }
+}
+
+/// Panics if an access ever touches the buckets of the tree other than by reading and writing back full paths,
+/// or touches a number of paths other than the one prescribed by the eviction schedule
+/// (one path per access, plus the scheduled evictions of its [`crate::eviction::EvictionStrategy`]).
+///
+/// The check compares the bucket reads and writes actually performed on the tree, as counted by depth
+/// in [`PathOram::level_stats`], with those the schedule prescribes.
+///
+/// Requires the `diagnostics` feature.
+#[cfg(feature = "diagnostics")]
+#[derive(Debug)]
+pub struct PhysicalAccessCountMonitor<V: OramBlock, const Z: BucketSize, const AB: BlockSize> {
+    oram: PathOram<V, Z, AB>,
+}
+
+#[cfg(feature = "diagnostics")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> PhysicalAccessCountMonitor<V, Z, AB> {
+    /// Wraps `oram`.
+    pub fn new(oram: PathOram<V, Z, AB>) -> Self {
+        Self { oram }
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> PathOram<V, Z, AB> {
+        self.oram
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram
+    for PhysicalAccessCountMonitor<V, Z, AB>
+{
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        self.oram.block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        let before = self.oram.level_stats().clone();
+        let watermark_evictions = self.oram.watermark_evictions();
+        let mut expected_paths = 1 + self.oram.eviction_strategy().boxed_clone().record_access();
+        let result = self.oram.access(index, callback, rng)?;
+        expected_paths += self.oram.watermark_evictions() - watermark_evictions;
+        let after = self.oram.level_stats();
+
+        for (depth, (reads, writes)) in after
+            .physical_reads
+            .iter()
+            .zip(after.physical_writes.iter())
+            .enumerate()
+        {
+            assert_eq!(
+                reads - before.physical_reads[depth],
+                expected_paths,
+                "Unexpected number of bucket reads at depth {depth}"
+            );
+            assert_eq!(
+                writes - before.physical_writes[depth],
+                expected_paths,
+                "Unexpected number of bucket writes at depth {depth}"
+            );
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn monitors_accept_correct_oram() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for eviction_period in [1, 3] {
+            let mut oram =
+                PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            let mut monitor = ConstantOccupancyMonitor::new(oram);
+            random_workload(&mut monitor, 500);
+        }
+
+        let oram =
+            PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        let mut monitor = StashSizeMonitor::new(oram, 40);
+        random_workload(&mut monitor, 500);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn physical_access_count_monitor_accepts_correct_oram() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for eviction_period in [1, 4] {
+            let mut oram =
+                PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            let mut monitor = PhysicalAccessCountMonitor::new(oram);
+            random_workload(&mut monitor, 200);
+        }
+
+        let mut oram =
+            PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_strategy(crate::ProactiveEviction::new(2))
+            .unwrap();
+        let mut monitor = PhysicalAccessCountMonitor::new(oram);
+        random_workload(&mut monitor, 200);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    #[should_panic(expected = "Unexpected number of bucket reads")]
+    fn physical_access_count_monitor_detects_extra_paths() {
+        use crate::eviction::EvictionStrategy;
+
+        // A strategy whose copies under-report its evictions, so that the real ORAM touches
+        // one more path per access than the monitor is told to expect.
+        #[derive(Clone, Copy, Debug)]
+        struct UnderReportingEviction {
+            is_copy: bool,
+        }
+
+        impl EvictionStrategy for UnderReportingEviction {
+            fn evicts_accessed_path(&self) -> bool {
+                true
+            }
+
+            fn record_access(&mut self) -> u64 {
+                u64::from(!self.is_copy)
+            }
+
+            fn boxed_clone(&self) -> Box<dyn EvictionStrategy> {
+                Box::new(Self { is_copy: true })
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram =
+            PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_strategy(UnderReportingEviction { is_copy: false })
+            .unwrap();
+        let mut monitor = PhysicalAccessCountMonitor::new(oram);
+        random_workload(&mut monitor, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than the maximum")]
+    fn stash_size_monitor_detects_large_stash() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram =
+            PathOram::<BlockValue<1>, 2, 8>::new_with_parameters(64, &mut rng, 0, 1).unwrap();
+        let mut monitor = StashSizeMonitor::new(oram, 0);
+        random_workload(&mut monitor, 1000);
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An ORAM wrapper that handles out-of-range addresses obliviously.
+
+use crate::{Address, Oram, OramError, OramRng};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeLess};
+
+/// Wraps an ORAM so that accesses to out-of-range addresses are oblivious no-ops returning `V::default()`,
+/// instead of returning an `AddressOutOfBoundsError`.
+///
+/// Other ORAMs check addresses with a branch, which leaks whether an address is in range.
+/// This is harmless when addresses are public or always valid, but not when an address is computed
+/// from secret data and may legitimately fall out of range. `ObliviousBoundsOram` instead compares
+/// the address to the capacity in constant time, and replaces an out-of-range access with an access
+/// to address 0 that leaves its value unchanged, so the two cases are indistinguishable.
+/// The callback is evaluated in both cases.
+///
+/// [`ObliviousBoundsOram::access_with_flag`] additionally returns a constant-time flag
+/// indicating whether the address was out of range.
+#[derive(Debug)]
+pub struct ObliviousBoundsOram<O: Oram> {
+    oram: O,
+}
+
+impl<O: Oram> ObliviousBoundsOram<O> {
+    /// Wraps `oram`.
+    ///
+    /// # Errors
+    ///
+    /// If the capacity of `oram` is 0, returns an `InvalidConfigurationError`.
+    pub fn new(oram: O) -> Result<Self, O::Error> {
+        let capacity = oram.block_capacity()?;
+        if capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            }
+            .into());
+        }
+        Ok(Self { oram })
+    }
+
+    /// Performs an access as [`Oram::access`], except that if `index` is out of range,
+    /// the ORAM is left unchanged and `V::default()` is returned.
+    /// Also returns a `Choice` that is 1 if `index` was out of range, and 0 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the wrapped ORAM; out-of-range addresses are not errors.
+    pub fn access_with_flag<R: OramRng, F: Fn(&O::V) -> O::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<(O::V, Choice), O::Error> {
+        let capacity = self.oram.block_capacity()?;
+        let in_range = index.ct_lt(&capacity);
+        let effective_index = Address::conditional_select(&0, &index, in_range);
+
+        let result = self.oram.access(
+            effective_index,
+            |value| O::V::conditional_select(value, &callback(value), in_range),
+            rng,
+        )?;
+        let result = O::V::conditional_select(&O::V::default(), &result, in_range);
+        Ok((result, !in_range))
+    }
+
+    /// Returns the wrapped ORAM.
+    pub fn into_inner(self) -> O {
+        self.oram
+    }
+}
+
+impl<O: Oram> Oram for ObliviousBoundsOram<O> {
+    type V = O::V;
+    type Error = O::Error;
+
+    fn block_capacity(&self) -> Result<Address, O::Error> {
+        self.oram.block_capacity()
+    }
+
+    /// Performs an access as [`ObliviousBoundsOram::access_with_flag`], discarding the out-of-range flag.
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, O::Error> {
+        Ok(self.access_with_flag(index, callback, rng)?.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::random_workload, DefaultOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn oblivious_bounds_out_of_range_is_no_op() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::<u64>::new(16, &mut rng).unwrap();
+        let mut oram = ObliviousBoundsOram::new(oram).unwrap();
+        random_workload(&mut oram, 200);
+
+        for i in 0..16 {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+        for index in [16, 17, u64::MAX] {
+            let (value, out_of_range) = oram.access_with_flag(index, |_| 99, &mut rng).unwrap();
+            assert_eq!(value, 0);
+            assert!(bool::from(out_of_range));
+        }
+        let (value, out_of_range) = oram.access_with_flag(0, |v| *v, &mut rng).unwrap();
+        assert_eq!(value, 1);
+        assert!(!bool::from(out_of_range));
+
+        let mut oram = oram.into_inner();
+        for i in 0..16 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+}
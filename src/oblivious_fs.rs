@@ -0,0 +1,586 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious file system storing named files in ORAMs.
+
+use crate::{
+    allocator::BlockAllocator, bucket::BlockValue, path_oram::DefaultOram, Address, BlockSize,
+    Oram, OramBlock, OramError, OramRng,
+};
+use rand::Rng;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    num::TryFromIntError,
+};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use thiserror::Error;
+
+/// The errors of an [`ObliviousFs`].
+#[derive(Error, Debug)]
+pub enum FsError {
+    /// Errors of the underlying ORAMs.
+    #[error(transparent)]
+    OramError(#[from] OramError),
+    /// Errors arising from operations on files, such as opening a file that does not exist.
+    #[error("File {name}: {reason}.")]
+    FileError {
+        /// The name of the file.
+        name: String,
+        /// A description of the failure.
+        reason: String,
+    },
+}
+
+impl From<TryFromIntError> for FsError {
+    fn from(error: TryFromIntError) -> Self {
+        Self::OramError(error.into())
+    }
+}
+
+/// The number of entries in each bucket of the directory.
+const DIRECTORY_BUCKET_SIZE: usize = 8;
+
+/// A directory entry. A fingerprint of 0 marks an empty entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DirectoryEntry {
+    fingerprint: u64,
+    inode: Address,
+    size: u64,
+}
+
+impl ConditionallySelectable for DirectoryEntry {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            fingerprint: u64::conditional_select(&a.fingerprint, &b.fingerprint, choice),
+            inode: Address::conditional_select(&a.inode, &b.inode, choice),
+            size: u64::conditional_select(&a.size, &b.size, choice),
+        }
+    }
+}
+
+/// A bucket of the directory hash table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DirectoryBucket {
+    entries: [DirectoryEntry; DIRECTORY_BUCKET_SIZE],
+}
+
+impl ConditionallySelectable for DirectoryBucket {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = Self::default();
+        for i in 0..DIRECTORY_BUCKET_SIZE {
+            result.entries[i] =
+                DirectoryEntry::conditional_select(&a.entries[i], &b.entries[i], choice);
+        }
+        result
+    }
+}
+
+impl OramBlock for DirectoryBucket {}
+
+impl DirectoryBucket {
+    /// Returns the entry with the given fingerprint, and whether it was found.
+    fn find(&self, fingerprint: u64) -> (DirectoryEntry, Choice) {
+        let mut result = DirectoryEntry::default();
+        let mut found = Choice::from(0);
+        for entry in &self.entries {
+            let matches = entry.fingerprint.ct_eq(&fingerprint);
+            result.conditional_assign(entry, matches);
+            found |= matches;
+        }
+        (result, found)
+    }
+
+    fn has_free_entry(&self) -> Choice {
+        self.find(0).1
+    }
+
+    /// Applies `operation` to this bucket. `insert` must be 1 for an insertion to take place.
+    fn apply(&self, operation: DirectoryOperation, insert: Choice) -> Self {
+        let mut result = *self;
+        let mut inserted = Choice::from(0);
+        for entry in result.entries.iter_mut() {
+            let matches = entry.fingerprint.ct_eq(&operation.fingerprint());
+            match operation {
+                DirectoryOperation::Lookup(_) => {}
+                DirectoryOperation::Insert(new_entry) => {
+                    let free = entry.fingerprint.ct_eq(&0) & insert & !inserted;
+                    entry.conditional_assign(&new_entry, free);
+                    inserted |= free;
+                }
+                DirectoryOperation::Remove(_) => {
+                    entry.conditional_assign(&DirectoryEntry::default(), matches);
+                }
+                DirectoryOperation::SetSize(_, size) => {
+                    entry.size.conditional_assign(&size, matches);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// An operation on the directory, identified by the fingerprint of a file name.
+#[derive(Clone, Copy, Debug)]
+enum DirectoryOperation {
+    Lookup(u64),
+    Insert(DirectoryEntry),
+    Remove(u64),
+    SetSize(u64, u64),
+}
+
+impl DirectoryOperation {
+    fn fingerprint(&self) -> u64 {
+        match *self {
+            Self::Lookup(fingerprint)
+            | Self::Remove(fingerprint)
+            | Self::SetSize(fingerprint, _) => fingerprint,
+            Self::Insert(entry) => entry.fingerprint,
+        }
+    }
+}
+
+/// A file system of named files, each supporting oblivious reads and writes at byte offsets,
+/// for enclave applications that need private storage.
+///
+/// The directory is an oblivious hash table stored in a [`DefaultOram`]: each name is hashed (with a random salt)
+/// to a fingerprint and two candidate buckets, both of which are accessed by every directory operation.
+/// File contents are stored in a second `DefaultOram` of `C`-byte chunks, each file occupying a fixed extent
+/// of `max_file_size` bytes, allocated with a [`BlockAllocator`].
+///
+/// ## Security
+///
+/// Each operation makes a number of ORAM accesses that depends only on the kind of operation,
+/// the length of the buffer read or written, and whether the operation succeeds,
+/// and not on the file name or offset. File names are hashed outside the ORAM,
+/// in time that depends on the length of the name.
+///
+/// Two names with the same 64-bit fingerprint are treated as the same file;
+/// this happens with negligible probability for a reasonable number of files.
+#[derive(Debug)]
+pub struct ObliviousFs<const C: BlockSize> {
+    directory: DefaultOram<DirectoryBucket>,
+    chunks: DefaultOram<BlockValue<C>>,
+    inodes: BlockAllocator,
+    max_file_size: u64,
+    /// A random salt for the name hash, so that buckets are not predictable from names.
+    salt: [u64; 2],
+}
+
+impl<const C: BlockSize> ObliviousFs<C> {
+    /// Returns a new, empty file system holding up to `max_files` files of up to `max_file_size` bytes each.
+    /// Both limits are rounded up so that the underlying ORAMs have power-of-two capacities.
+    ///
+    /// # Errors
+    ///
+    /// If `C`, `max_files`, or `max_file_size` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        max_files: Address,
+        max_file_size: u64,
+        rng: &mut R,
+    ) -> Result<Self, FsError> {
+        for (parameter_name, value) in [
+            ("Chunk size", u64::try_from(C)?),
+            ("Maximum number of files", max_files),
+            ("Maximum file size", max_file_size),
+        ] {
+            if value == 0 {
+                return Err(OramError::InvalidConfigurationError {
+                    parameter_name: parameter_name.to_string(),
+                    parameter_value: value.to_string(),
+                }
+                .into());
+            }
+        }
+
+        log::info!(
+            "ObliviousFs::new(max_files = {}, max_file_size = {})",
+            max_files,
+            max_file_size
+        );
+
+        let max_files = max_files.next_power_of_two();
+        let chunks_per_file = max_file_size
+            .div_ceil(u64::try_from(C)?)
+            .next_power_of_two();
+        let num_chunks =
+            max_files
+                .checked_mul(chunks_per_file)
+                .ok_or(OramError::InvalidConfigurationError {
+                    parameter_name: "Maximum file size".to_string(),
+                    parameter_value: max_file_size.to_string(),
+                })?;
+
+        Ok(Self {
+            directory: DefaultOram::new(max_files.max(2), rng)?,
+            chunks: DefaultOram::new(num_chunks, rng)?,
+            inodes: BlockAllocator::new(max_files, rng)?,
+            max_file_size: chunks_per_file * u64::try_from(C)?,
+            salt: rng.gen(),
+        })
+    }
+
+    /// Returns the maximum number of files.
+    pub fn max_files(&self) -> Result<Address, FsError> {
+        Ok(self.inodes.block_capacity()?)
+    }
+
+    /// Returns the maximum size of a file in bytes.
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    /// Returns the number of files.
+    pub fn num_files(&self) -> Address {
+        self.inodes.allocated()
+    }
+
+    /// Creates an empty file named `name`.
+    ///
+    /// # Errors
+    ///
+    /// If a file named `name` already exists, or the directory is full, returns a `FileError`.
+    /// If `max_files` files already exist, returns an `OutOfMemoryError`.
+    pub fn create<R: OramRng>(&mut self, name: &str, rng: &mut R) -> Result<(), FsError> {
+        let fingerprint = self.fingerprint(name);
+        if self.find(name, rng)?.is_some() {
+            return Err(Self::file_error(name, "file already exists"));
+        }
+
+        let inode = self.inodes.allocate(rng)?;
+        let entry = DirectoryEntry {
+            fingerprint,
+            inode,
+            size: 0,
+        };
+        let (_, inserted) = self.directory_access(name, DirectoryOperation::Insert(entry), rng)?;
+        if !bool::from(inserted) {
+            self.inodes.free(inode, rng)?;
+            return Err(Self::file_error(name, "directory bucket is full"));
+        }
+        Ok(())
+    }
+
+    /// Removes the file named `name`.
+    ///
+    /// # Errors
+    ///
+    /// If no file named `name` exists, returns a `FileError`.
+    pub fn remove<R: OramRng>(&mut self, name: &str, rng: &mut R) -> Result<(), FsError> {
+        let fingerprint = self.fingerprint(name);
+        let (entry, found) =
+            self.directory_access(name, DirectoryOperation::Remove(fingerprint), rng)?;
+        if !bool::from(found) {
+            return Err(Self::file_error(name, "no such file"));
+        }
+        Ok(self.inodes.free(entry.inode, rng)?)
+    }
+
+    /// Returns whether a file named `name` exists.
+    pub fn exists<R: OramRng>(&mut self, name: &str, rng: &mut R) -> Result<bool, FsError> {
+        Ok(self.find(name, rng)?.is_some())
+    }
+
+    /// Returns the size in bytes of the file named `name`.
+    ///
+    /// # Errors
+    ///
+    /// If no file named `name` exists, returns a `FileError`.
+    pub fn len<R: OramRng>(&mut self, name: &str, rng: &mut R) -> Result<u64, FsError> {
+        Ok(self.open(name, rng)?.size)
+    }
+
+    /// Reads bytes of the file named `name`, starting at `offset`, into `buf`.
+    /// Returns the number of bytes read, which is less than `buf.len()` if the end of the file is reached.
+    ///
+    /// # Errors
+    ///
+    /// If no file named `name` exists, returns a `FileError`.
+    pub fn read<R: OramRng>(
+        &mut self,
+        name: &str,
+        offset: u64,
+        buf: &mut [u8],
+        rng: &mut R,
+    ) -> Result<usize, FsError> {
+        let entry = self.open(name, rng)?;
+        let available = usize::try_from(entry.size.saturating_sub(offset))
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+
+        self.for_each_chunk(
+            entry.inode,
+            offset,
+            buf.len(),
+            rng,
+            |oram, chunk, range, rng| {
+                let block = oram.read(chunk, rng)?;
+                if let Some((buf_range, block_range)) = range {
+                    let end = buf_range.end.min(available);
+                    if buf_range.start < end {
+                        let len = end - buf_range.start;
+                        buf[buf_range.start..end].copy_from_slice(
+                            &block.data[block_range.start..block_range.start + len],
+                        );
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        Ok(available)
+    }
+
+    /// Writes `data` to the file named `name`, starting at `offset`, extending the file if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If no file named `name` exists, returns a `FileError`.
+    /// If `offset` is greater than the size of the file (files cannot have holes),
+    /// or the write would extend the file beyond `max_file_size`, returns an `AddressOutOfBoundsError`.
+    pub fn write<R: OramRng>(
+        &mut self,
+        name: &str,
+        offset: u64,
+        data: &[u8],
+        rng: &mut R,
+    ) -> Result<(), FsError> {
+        let entry = self.open(name, rng)?;
+        if offset > entry.size {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: offset,
+                capacity: entry.size,
+            }
+            .into());
+        }
+        let end = offset
+            .checked_add(u64::try_from(data.len())?)
+            .filter(|end| *end <= self.max_file_size)
+            .ok_or(OramError::AddressOutOfBoundsError {
+                attempted: offset.saturating_add(u64::try_from(data.len())?),
+                capacity: self.max_file_size,
+            })?;
+
+        self.for_each_chunk(
+            entry.inode,
+            offset,
+            data.len(),
+            rng,
+            |oram, chunk, range, rng| {
+                match range {
+                    Some((data_range, block_range)) => {
+                        let bytes = &data[data_range];
+                        oram.access(
+                            chunk,
+                            |block| {
+                                let mut result = *block;
+                                result.data[block_range.clone()].copy_from_slice(bytes);
+                                result
+                            },
+                            rng,
+                        )?;
+                    }
+                    None => {
+                        oram.read(chunk, rng)?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        let fingerprint = self.fingerprint(name);
+        self.directory_access(
+            name,
+            DirectoryOperation::SetSize(fingerprint, end.max(entry.size)),
+            rng,
+        )?;
+        Ok(())
+    }
+
+    /// Calls `f` on `len.div_ceil(C) + 1` chunks of the extent of `inode`, so that the number of accesses
+    /// depends only on `len`. The chunks covering bytes `offset..offset + len` come with the range of bytes
+    /// of the buffer and of the chunk that they cover; padding chunks come with `None`.
+    fn for_each_chunk<R: OramRng>(
+        &mut self,
+        inode: Address,
+        offset: u64,
+        len: usize,
+        rng: &mut R,
+        mut f: impl FnMut(
+            &mut DefaultOram<BlockValue<C>>,
+            Address,
+            Option<(std::ops::Range<usize>, std::ops::Range<usize>)>,
+            &mut R,
+        ) -> Result<(), FsError>,
+    ) -> Result<(), FsError> {
+        let chunk_size = u64::try_from(C)?;
+        let chunks_per_file = self.max_file_size / chunk_size;
+        let extent_start = inode * chunks_per_file;
+
+        let mut position = 0;
+        for _ in 0..len.div_ceil(C) + 1 {
+            let byte = offset + u64::try_from(position)?;
+            if position < len && byte < self.max_file_size {
+                let within_chunk = usize::try_from(byte % chunk_size)?;
+                let count = (C - within_chunk).min(len - position);
+                f(
+                    &mut self.chunks,
+                    extent_start + byte / chunk_size,
+                    Some((
+                        position..position + count,
+                        within_chunk..within_chunk + count,
+                    )),
+                    rng,
+                )?;
+                position += count;
+            } else {
+                f(&mut self.chunks, extent_start, None, rng)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open<R: OramRng>(&mut self, name: &str, rng: &mut R) -> Result<DirectoryEntry, FsError> {
+        self.find(name, rng)?
+            .ok_or_else(|| Self::file_error(name, "no such file"))
+    }
+
+    fn find<R: OramRng>(
+        &mut self,
+        name: &str,
+        rng: &mut R,
+    ) -> Result<Option<DirectoryEntry>, FsError> {
+        let fingerprint = self.fingerprint(name);
+        let (entry, found) =
+            self.directory_access(name, DirectoryOperation::Lookup(fingerprint), rng)?;
+        Ok(bool::from(found).then_some(entry))
+    }
+
+    /// Applies `operation` to both candidate buckets of `name`, making exactly two directory accesses.
+    /// Returns the entry for `name` before the operation, and whether it was found;
+    /// for an insertion, returns whether the entry was inserted instead.
+    fn directory_access<R: OramRng>(
+        &mut self,
+        name: &str,
+        operation: DirectoryOperation,
+        rng: &mut R,
+    ) -> Result<(DirectoryEntry, Choice), FsError> {
+        let fingerprint = operation.fingerprint();
+        let [first, second] = self.buckets(name)?;
+
+        let old_first =
+            self.directory
+                .access(first, |bucket| bucket.apply(operation, 1.into()), rng)?;
+        // Insert into the second bucket only if the first was full.
+        let insert_second = !old_first.has_free_entry();
+        let old_second =
+            self.directory
+                .access(second, |bucket| bucket.apply(operation, insert_second), rng)?;
+
+        if let DirectoryOperation::Insert(_) = operation {
+            let inserted = !insert_second | old_second.has_free_entry();
+            return Ok((DirectoryEntry::default(), inserted));
+        }
+        let (first_entry, found_first) = old_first.find(fingerprint);
+        let (second_entry, found_second) = old_second.find(fingerprint);
+        Ok((
+            DirectoryEntry::conditional_select(&second_entry, &first_entry, found_first),
+            found_first | found_second,
+        ))
+    }
+
+    fn hash_name(&self, name: &str) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        self.salt.hash(&mut hasher);
+        name.hash(&mut hasher);
+        hasher
+    }
+
+    /// Returns the fingerprint of `name`, which is never 0.
+    fn fingerprint(&self, name: &str) -> u64 {
+        self.hash_name(name).finish().max(1)
+    }
+
+    /// Returns the two candidate directory buckets of `name`.
+    fn buckets(&self, name: &str) -> Result<[Address; 2], FsError> {
+        let mask = self.directory.block_capacity()? - 1;
+        let mut hasher = self.hash_name(name);
+        hasher.write_u8(1);
+        let first = hasher.finish() & mask;
+        hasher.write_u8(2);
+        let second = hasher.finish() & mask;
+        Ok([first, second])
+    }
+
+    fn file_error(name: &str, reason: &str) -> FsError {
+        FsError::FileError {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn fs_create_write_read_remove() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fs = ObliviousFs::<16>::new(8, 100, &mut rng).unwrap();
+        assert_eq!(fs.max_file_size(), 128);
+
+        fs.create("a.txt", &mut rng).unwrap();
+        fs.create("b.txt", &mut rng).unwrap();
+        assert!(fs.create("a.txt", &mut rng).is_err());
+        assert_eq!(fs.num_files(), 2);
+
+        let text = b"The quick brown fox jumps over the lazy dog.";
+        fs.write("a.txt", 0, text, &mut rng).unwrap();
+        fs.write("b.txt", 0, b"hello", &mut rng).unwrap();
+        fs.write("b.txt", 5, b", world", &mut rng).unwrap();
+        fs.write("a.txt", 4, b"QUICK", &mut rng).unwrap();
+        assert_eq!(fs.len("a.txt", &mut rng).unwrap(), text.len() as u64);
+        assert_eq!(fs.len("b.txt", &mut rng).unwrap(), 12);
+
+        let mut buf = [0u8; 64];
+        let n = fs.read("a.txt", 0, &mut buf, &mut rng).unwrap();
+        assert_eq!(&buf[..n], b"The QUICK brown fox jumps over the lazy dog.");
+        let n = fs.read("b.txt", 3, &mut buf[..4], &mut rng).unwrap();
+        assert_eq!(&buf[..n], b"lo, ");
+        assert_eq!(fs.read("b.txt", 20, &mut buf, &mut rng).unwrap(), 0);
+
+        fs.remove("a.txt", &mut rng).unwrap();
+        assert!(!fs.exists("a.txt", &mut rng).unwrap());
+        assert!(fs.read("a.txt", 0, &mut buf, &mut rng).is_err());
+        assert!(fs.remove("a.txt", &mut rng).is_err());
+        assert!(fs.exists("b.txt", &mut rng).unwrap());
+    }
+
+    #[test]
+    fn fs_enforces_limits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fs = ObliviousFs::<8>::new(4, 32, &mut rng).unwrap();
+        for i in 0..4 {
+            fs.create(&format!("file{i}"), &mut rng).unwrap();
+        }
+        assert!(fs.create("one too many", &mut rng).is_err());
+
+        assert!(fs.write("file0", 1, b"x", &mut rng).is_err());
+        assert!(fs.write("file0", 0, &[1; 33], &mut rng).is_err());
+        fs.write("file0", 0, &[1; 32], &mut rng).unwrap();
+        fs.write("file1", 0, &[2; 32], &mut rng).unwrap();
+
+        let mut buf = [0u8; 32];
+        assert_eq!(fs.read("file0", 0, &mut buf, &mut rng).unwrap(), 32);
+        assert_eq!(buf, [1; 32]);
+
+        assert!(ObliviousFs::<8>::new(0, 32, &mut rng).is_err());
+        assert!(ObliviousFs::<8>::new(4, 0, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious graph adjacency store.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeLess};
+
+/// The number of neighbors stored in each ORAM block of an adjacency list.
+const NEIGHBORS_PER_BLOCK: usize = 8;
+
+/// The record of a vertex: its user data and its out-degree.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct VertexRecord<V> {
+    value: V,
+    degree: u64,
+}
+
+impl<V: ConditionallySelectable> ConditionallySelectable for VertexRecord<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            value: V::conditional_select(&a.value, &b.value, choice),
+            degree: u64::conditional_select(&a.degree, &b.degree, choice),
+        }
+    }
+}
+
+impl<V: OramBlock> OramBlock for VertexRecord<V> {}
+
+/// A block of an adjacency list.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct NeighborBlock {
+    neighbors: [Address; NEIGHBORS_PER_BLOCK],
+}
+
+impl ConditionallySelectable for NeighborBlock {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = Self::default();
+        for i in 0..NEIGHBORS_PER_BLOCK {
+            result.neighbors[i] =
+                Address::conditional_select(&a.neighbors[i], &b.neighbors[i], choice);
+        }
+        result
+    }
+}
+
+impl OramBlock for NeighborBlock {}
+
+/// A directed graph on vertices `0 <= v < num_vertices`, each carrying a record of type `V`,
+/// whose structure is hidden from an adversary observing memory.
+///
+/// Vertex records (with out-degrees) are stored in one [`DefaultOram`], and adjacency lists in another,
+/// each list padded to `max_degree` entries, so that traversals do not reveal the graph's structure.
+///
+/// ## Security
+///
+/// `vertex`, `set_vertex`, and `degree` make one ORAM access; `neighbors` and `add_edge` make one access to the
+/// vertex records and `max_degree / 8` accesses (rounded up to a power of two) to the adjacency lists, regardless of the vertices
+/// involved or their degrees. Only whether `add_edge` fails (because a vertex is at `max_degree`) is revealed.
+#[derive(Debug)]
+pub struct ObliviousGraph<V: OramBlock> {
+    vertices: DefaultOram<VertexRecord<V>>,
+    adjacency: DefaultOram<NeighborBlock>,
+    num_vertices: Address,
+    max_degree: u64,
+    /// The number of adjacency blocks reserved for each vertex (a power of two).
+    blocks_per_vertex: Address,
+}
+
+impl<V: OramBlock> ObliviousGraph<V> {
+    /// The placeholder for missing neighbors in the result of [`ObliviousGraph::neighbors`].
+    pub const NO_VERTEX: Address = Address::MAX;
+
+    /// Returns a new graph with `num_vertices` vertices holding default records, no edges,
+    /// and out-degrees of at most `max_degree`.
+    ///
+    /// # Errors
+    ///
+    /// If `num_vertices` or `max_degree` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        num_vertices: Address,
+        max_degree: u64,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        if num_vertices == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of vertices".to_string(),
+                parameter_value: num_vertices.to_string(),
+            });
+        }
+        if max_degree == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Maximum degree".to_string(),
+                parameter_value: max_degree.to_string(),
+            });
+        }
+
+        log::info!(
+            "ObliviousGraph::new(num_vertices = {}, max_degree = {})",
+            num_vertices,
+            max_degree
+        );
+
+        let vertex_capacity = num_vertices.next_power_of_two();
+        let blocks_per_vertex = max_degree
+            .div_ceil(u64::try_from(NEIGHBORS_PER_BLOCK)?)
+            .next_power_of_two();
+        let adjacency_capacity = vertex_capacity.checked_mul(blocks_per_vertex).ok_or(
+            OramError::InvalidConfigurationError {
+                parameter_name: "Maximum degree".to_string(),
+                parameter_value: max_degree.to_string(),
+            },
+        )?;
+
+        Ok(Self {
+            vertices: DefaultOram::new(vertex_capacity, rng)?,
+            adjacency: DefaultOram::new(adjacency_capacity, rng)?,
+            num_vertices,
+            max_degree,
+            blocks_per_vertex,
+        })
+    }
+
+    /// Returns the number of vertices.
+    pub fn num_vertices(&self) -> Address {
+        self.num_vertices
+    }
+
+    /// Returns the maximum out-degree of a vertex.
+    pub fn max_degree(&self) -> u64 {
+        self.max_degree
+    }
+
+    /// Obliviously reads the record of `vertex`.
+    pub fn vertex<R: OramRng>(&mut self, vertex: Address, rng: &mut R) -> Result<V, OramError> {
+        self.check_bounds(vertex)?;
+        Ok(self.vertices.read(vertex, rng)?.value)
+    }
+
+    /// Obliviously overwrites the record of `vertex` with `value`, returning the previous record.
+    pub fn set_vertex<R: OramRng>(
+        &mut self,
+        vertex: Address,
+        value: V,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        self.check_bounds(vertex)?;
+        let callback = |record: &VertexRecord<V>| VertexRecord {
+            value,
+            degree: record.degree,
+        };
+        Ok(self.vertices.access(vertex, callback, rng)?.value)
+    }
+
+    /// Obliviously reads the out-degree of `vertex`.
+    pub fn degree<R: OramRng>(&mut self, vertex: Address, rng: &mut R) -> Result<u64, OramError> {
+        self.check_bounds(vertex)?;
+        Ok(self.vertices.read(vertex, rng)?.degree)
+    }
+
+    /// Obliviously reads the out-neighbors of `vertex`, in the order in which the edges were added.
+    /// The result always has `max_degree` entries, padded with [`ObliviousGraph::NO_VERTEX`].
+    pub fn neighbors<R: OramRng>(
+        &mut self,
+        vertex: Address,
+        rng: &mut R,
+    ) -> Result<Vec<Address>, OramError> {
+        self.check_bounds(vertex)?;
+        let degree = self.vertices.read(vertex, rng)?.degree;
+
+        let mut result =
+            Vec::with_capacity(usize::try_from(self.blocks_per_vertex)? * NEIGHBORS_PER_BLOCK);
+        for block in 0..self.blocks_per_vertex {
+            let neighbors = self
+                .adjacency
+                .read(vertex * self.blocks_per_vertex + block, rng)?
+                .neighbors;
+            result.extend(neighbors);
+        }
+        result.truncate(usize::try_from(self.max_degree)?);
+        for (i, neighbor) in result.iter_mut().enumerate() {
+            let missing = !(u64::try_from(i)?).ct_lt(&degree);
+            neighbor.conditional_assign(&Self::NO_VERTEX, missing);
+        }
+        Ok(result)
+    }
+
+    /// Obliviously adds an edge from `from` to `to`. For an undirected graph, add both directions.
+    ///
+    /// # Errors
+    ///
+    /// If `from` already has `max_degree` out-neighbors, returns an `OutOfMemoryError`.
+    pub fn add_edge<R: OramRng>(
+        &mut self,
+        from: Address,
+        to: Address,
+        rng: &mut R,
+    ) -> Result<(), OramError> {
+        self.check_bounds(from)?;
+        self.check_bounds(to)?;
+
+        let max_degree = self.max_degree;
+        let increment = |record: &VertexRecord<V>| {
+            let mut result = *record;
+            let has_room = record.degree.ct_lt(&max_degree);
+            result
+                .degree
+                .conditional_assign(&(record.degree + 1), has_room);
+            result
+        };
+        let degree = self.vertices.access(from, increment, rng)?.degree;
+        let full = degree.ct_eq(&max_degree);
+
+        // Access every block of the adjacency list, so that the block written does not reveal the degree.
+        let neighbors_per_block = u64::try_from(NEIGHBORS_PER_BLOCK)?;
+        for block in 0..self.blocks_per_vertex {
+            let callback = |neighbor_block: &NeighborBlock| {
+                let mut result = *neighbor_block;
+                for (i, neighbor) in result.neighbors.iter_mut().enumerate() {
+                    let slot = block * neighbors_per_block + i as u64;
+                    neighbor.conditional_assign(&to, slot.ct_eq(&degree) & !full);
+                }
+                result
+            };
+            self.adjacency
+                .access(from * self.blocks_per_vertex + block, callback, rng)?;
+        }
+
+        if bool::from(full) {
+            return Err(OramError::OutOfMemoryError {
+                capacity: max_degree,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_bounds(&self, vertex: Address) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the vertex is well-formed or not.
+        if vertex >= self.num_vertices {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: vertex,
+                capacity: self.num_vertices,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn graph_stores_records_and_edges() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut graph = ObliviousGraph::<u32>::new(10, 12, &mut rng).unwrap();
+        const NONE: Address = ObliviousGraph::<u32>::NO_VERTEX;
+
+        graph.set_vertex(3, 33, &mut rng).unwrap();
+        for to in [1, 4, 9] {
+            graph.add_edge(3, to, &mut rng).unwrap();
+        }
+        assert_eq!(graph.vertex(3, &mut rng).unwrap(), 33);
+        assert_eq!(graph.degree(3, &mut rng).unwrap(), 3);
+
+        let neighbors = graph.neighbors(3, &mut rng).unwrap();
+        assert_eq!(neighbors.len(), 12);
+        assert_eq!(&neighbors[..4], &[1, 4, 9, NONE]);
+        assert!(graph
+            .neighbors(0, &mut rng)
+            .unwrap()
+            .iter()
+            .all(|n| *n == NONE));
+
+        for to in 0..9 {
+            graph.add_edge(5, to, &mut rng).unwrap();
+        }
+        assert_eq!(graph.neighbors(5, &mut rng).unwrap()[8], 8);
+        assert!(graph.add_edge(3, 10, &mut rng).is_err());
+        assert!(graph.vertex(10, &mut rng).is_err());
+    }
+
+    #[test]
+    fn graph_rejects_edges_beyond_max_degree() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut graph = ObliviousGraph::<u8>::new(4, 2, &mut rng).unwrap();
+        graph.add_edge(0, 1, &mut rng).unwrap();
+        graph.add_edge(0, 2, &mut rng).unwrap();
+        assert!(graph.add_edge(0, 3, &mut rng).is_err());
+        assert_eq!(graph.neighbors(0, &mut rng).unwrap(), vec![1, 2]);
+        assert_eq!(graph.degree(0, &mut rng).unwrap(), 2);
+    }
+
+    #[test]
+    fn graph_breadth_first_search() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut graph = ObliviousGraph::<u64>::new(8, 3, &mut rng).unwrap();
+        // A path 0 - 1 - ... - 7.
+        for v in 0..7 {
+            graph.add_edge(v, v + 1, &mut rng).unwrap();
+            graph.add_edge(v + 1, v, &mut rng).unwrap();
+        }
+
+        let mut distances = [u64::MAX; 8];
+        distances[0] = 0;
+        let mut queue = VecDeque::from([0]);
+        while let Some(v) = queue.pop_front() {
+            let d = distances[v as usize];
+            graph.set_vertex(v, d, &mut rng).unwrap();
+            for neighbor in graph.neighbors(v, &mut rng).unwrap() {
+                if neighbor != ObliviousGraph::<u64>::NO_VERTEX
+                    && distances[neighbor as usize] == u64::MAX
+                {
+                    distances[neighbor as usize] = d + 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        for v in 0..8 {
+            assert_eq!(graph.vertex(v, &mut rng).unwrap(), v);
+        }
+    }
+}
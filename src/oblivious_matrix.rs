@@ -0,0 +1,240 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious two-dimensional array with row and column access.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The number of matrix elements stored in each ORAM block.
+const ELEMENTS_PER_BLOCK: usize = 8;
+
+/// A block of consecutive elements of a matrix row.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct MatrixBlock<T> {
+    elements: [T; ELEMENTS_PER_BLOCK],
+}
+
+impl<T: ConditionallySelectable + Default> ConditionallySelectable for MatrixBlock<T> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = Self::default();
+        for i in 0..ELEMENTS_PER_BLOCK {
+            result.elements[i] = T::conditional_select(&a.elements[i], &b.elements[i], choice);
+        }
+        result
+    }
+}
+
+impl<T: OramBlock> OramBlock for MatrixBlock<T> {}
+
+/// A `num_rows` by `num_columns` matrix of `T` values, with oblivious element, row, and column access.
+///
+/// Rows are stored in row-major order in a [`DefaultOram`], eight elements per block.
+/// Fetching a row reads the blocks of that row; fetching a column reads, from every row,
+/// the block containing that column (a strided batch of accesses).
+///
+/// ## Security
+///
+/// `get` and `set` make one ORAM access, `row` makes `num_columns / 8` (rounded up) accesses, and `column`
+/// makes `num_rows` accesses, regardless of the indices accessed. Only the dimensions of the matrix are revealed.
+#[derive(Debug)]
+pub struct ObliviousMatrix<T: OramBlock> {
+    blocks: DefaultOram<MatrixBlock<T>>,
+    num_rows: Address,
+    num_columns: Address,
+    blocks_per_row: Address,
+}
+
+impl<T: OramBlock> ObliviousMatrix<T> {
+    /// Returns a new `num_rows` by `num_columns` matrix of default `T` values.
+    ///
+    /// # Errors
+    ///
+    /// If `num_rows` or `num_columns` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        num_rows: Address,
+        num_columns: Address,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        for (parameter_name, value) in [
+            ("Number of rows", num_rows),
+            ("Number of columns", num_columns),
+        ] {
+            if value == 0 {
+                return Err(OramError::InvalidConfigurationError {
+                    parameter_name: parameter_name.to_string(),
+                    parameter_value: value.to_string(),
+                });
+            }
+        }
+
+        log::info!(
+            "ObliviousMatrix::new(num_rows = {}, num_columns = {})",
+            num_rows,
+            num_columns
+        );
+
+        let blocks_per_row = num_columns.div_ceil(Address::try_from(ELEMENTS_PER_BLOCK)?);
+        let num_blocks =
+            num_rows
+                .checked_mul(blocks_per_row)
+                .ok_or(OramError::InvalidConfigurationError {
+                    parameter_name: "Number of rows".to_string(),
+                    parameter_value: num_rows.to_string(),
+                })?;
+
+        Ok(Self {
+            blocks: DefaultOram::new(num_blocks.next_power_of_two(), rng)?,
+            num_rows,
+            num_columns,
+            blocks_per_row,
+        })
+    }
+
+    /// Returns the number of rows.
+    pub fn num_rows(&self) -> Address {
+        self.num_rows
+    }
+
+    /// Returns the number of columns.
+    pub fn num_columns(&self) -> Address {
+        self.num_columns
+    }
+
+    /// Obliviously reads the element at (`row`, `column`).
+    pub fn get<R: OramRng>(
+        &mut self,
+        row: Address,
+        column: Address,
+        rng: &mut R,
+    ) -> Result<T, OramError> {
+        self.check_bounds(row, column)?;
+        let (block, offset) = self.locate(row, column)?;
+        let block = self.blocks.read(block, rng)?;
+        Ok(Self::select_element(&block, offset))
+    }
+
+    /// Obliviously overwrites the element at (`row`, `column`) with `value`, returning the previous element.
+    pub fn set<R: OramRng>(
+        &mut self,
+        row: Address,
+        column: Address,
+        value: T,
+        rng: &mut R,
+    ) -> Result<T, OramError> {
+        self.check_bounds(row, column)?;
+        let (block, offset) = self.locate(row, column)?;
+        let callback = |block: &MatrixBlock<T>| {
+            let mut result = *block;
+            for (i, element) in result.elements.iter_mut().enumerate() {
+                element.conditional_assign(&value, i.ct_eq(&offset));
+            }
+            result
+        };
+        let block = self.blocks.access(block, callback, rng)?;
+        Ok(Self::select_element(&block, offset))
+    }
+
+    /// Obliviously reads row `row`.
+    pub fn row<R: OramRng>(&mut self, row: Address, rng: &mut R) -> Result<Vec<T>, OramError> {
+        self.check_bounds(row, 0)?;
+        let mut result = Vec::with_capacity(usize::try_from(self.num_columns)?);
+        for block in 0..self.blocks_per_row {
+            let block = self.blocks.read(row * self.blocks_per_row + block, rng)?;
+            result.extend(block.elements);
+        }
+        result.truncate(usize::try_from(self.num_columns)?);
+        Ok(result)
+    }
+
+    /// Obliviously reads column `column`, making one access to every row.
+    pub fn column<R: OramRng>(
+        &mut self,
+        column: Address,
+        rng: &mut R,
+    ) -> Result<Vec<T>, OramError> {
+        self.check_bounds(0, column)?;
+        let (first_block, offset) = self.locate(0, column)?;
+        let mut result = Vec::with_capacity(usize::try_from(self.num_rows)?);
+        for row in 0..self.num_rows {
+            let block = self
+                .blocks
+                .read(first_block + row * self.blocks_per_row, rng)?;
+            result.push(Self::select_element(&block, offset));
+        }
+        Ok(result)
+    }
+
+    /// Returns the address of the block containing (`row`, `column`) and the element's offset within it.
+    fn locate(&self, row: Address, column: Address) -> Result<(Address, usize), OramError> {
+        let elements_per_block = Address::try_from(ELEMENTS_PER_BLOCK)?;
+        Ok((
+            row * self.blocks_per_row + column / elements_per_block,
+            usize::try_from(column % elements_per_block)?,
+        ))
+    }
+
+    fn select_element(block: &MatrixBlock<T>, offset: usize) -> T {
+        let mut result = T::default();
+        for (i, element) in block.elements.iter().enumerate() {
+            result.conditional_assign(element, i.ct_eq(&offset));
+        }
+        result
+    }
+
+    fn check_bounds(&self, row: Address, column: Address) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the indices are well-formed or not.
+        if row >= self.num_rows {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: row,
+                capacity: self.num_rows,
+            });
+        }
+        if column >= self.num_columns {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: column,
+                capacity: self.num_columns,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn matrix_element_row_and_column_access() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (rows, columns) = (5, 11);
+        let mut matrix = ObliviousMatrix::<u32>::new(rows, columns, &mut rng).unwrap();
+        for i in 0..rows {
+            for j in 0..columns {
+                matrix.set(i, j, (100 * i + j) as u32, &mut rng).unwrap();
+            }
+        }
+
+        assert_eq!(matrix.get(3, 9, &mut rng).unwrap(), 309);
+        assert_eq!(matrix.set(3, 9, 0, &mut rng).unwrap(), 309);
+        assert_eq!(matrix.get(3, 9, &mut rng).unwrap(), 0);
+
+        let row = matrix.row(2, &mut rng).unwrap();
+        assert_eq!(
+            row,
+            (0..columns).map(|j| 200 + j as u32).collect::<Vec<_>>()
+        );
+        let column = matrix.column(10, &mut rng).unwrap();
+        assert_eq!(column, vec![10, 110, 210, 310, 410]);
+
+        assert!(matrix.get(5, 0, &mut rng).is_err());
+        assert!(matrix.column(11, &mut rng).is_err());
+        assert!(ObliviousMatrix::<u32>::new(0, 3, &mut rng).is_err());
+    }
+}
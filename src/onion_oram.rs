@@ -0,0 +1,381 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A tree ORAM whose reads are answered by computation on the storage server, in the style of Onion ORAM.
+
+use crate::{
+    utils::{reverse_lexicographic_leaf, CompleteBinaryTreeIndex, TreeHeight, TreeIndex},
+    Address, BucketSize, Oram, OramBlock, OramError, OramRng,
+};
+
+/// The default number of blocks per bucket of an `OnionOram`.
+pub const DEFAULT_ONION_BUCKET_SIZE: BucketSize = 8;
+
+/// The default number of accesses between evictions of an `OnionOram`.
+pub const DEFAULT_ONION_EVICTION_PERIOD: u64 = 4;
+
+/// The computation that a storage server performs on behalf of an [`OnionOram`] client.
+///
+/// To read a block, the client encrypts a one-hot selection vector over the blocks of a path,
+/// and the server computes (for example, homomorphically) the selected block, so that only a single block
+/// is returned to the client regardless of the path length or bucket size.
+/// Implementations based on additively homomorphic encryption hide the selection from the server.
+pub trait ServerComputation<V: OramBlock> {
+    /// An encrypted selection bit.
+    type EncryptedSelector;
+    /// An encrypted block, as returned by the server.
+    type EncryptedBlock;
+
+    /// (Client side.) Encrypts a selection bit.
+    fn encrypt_selector(&mut self, selected: bool) -> Self::EncryptedSelector;
+
+    /// (Server side.) Returns an encryption of the sum of the blocks whose selectors encrypt 1,
+    /// or of `V::default()` if no selector encrypts 1. At most one selector encrypts 1.
+    fn select(&self, blocks: &[V], selectors: &[Self::EncryptedSelector]) -> Self::EncryptedBlock;
+
+    /// (Client side.) Decrypts a block returned by [`ServerComputation::select`].
+    fn decrypt_block(&self, block: Self::EncryptedBlock) -> V;
+}
+
+/// A `ServerComputation` that performs the selection in the clear.
+///
+/// This reveals the selected block to the server, and is intended for testing
+/// and for deployments in which the "server" is trusted memory, such as an enclave's own heap.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlaintextComputation;
+
+impl<V: OramBlock> ServerComputation<V> for PlaintextComputation {
+    type EncryptedSelector = bool;
+    type EncryptedBlock = V;
+
+    fn encrypt_selector(&mut self, selected: bool) -> bool {
+        selected
+    }
+
+    fn select(&self, blocks: &[V], selectors: &[bool]) -> V {
+        blocks
+            .iter()
+            .zip(selectors)
+            .find(|(_, selected)| **selected)
+            .map(|(block, _)| *block)
+            .unwrap_or_default()
+    }
+
+    fn decrypt_block(&self, block: V) -> V {
+        block
+    }
+}
+
+/// A tree ORAM in the style of [Onion ORAM](https://eprint.iacr.org/2015/005.pdf),
+/// in which each read downloads a single block, computed by the server through a [`ServerComputation`].
+///
+/// As in Onion ORAM, the client keeps the metadata of the tree (which block occupies each slot)
+/// together with the position map, and every `eviction_period` accesses evicts along the next path
+/// in reverse-lexicographic order. Accessed blocks wait in a small client-side stash until they are evicted.
+/// Unlike Onion ORAM, evictions are performed by the client (downloading and re-uploading the eviction path)
+/// rather than by homomorphic computation on the server, so only the online (read) bandwidth is constant.
+///
+/// ## Security
+///
+/// The metadata, position map, and stash are kept in ordinary client memory, taking `O(N)` space,
+/// and are assumed to be hidden from the adversary. The server observes a uniformly random path per access
+/// (together with the encrypted selectors) and a deterministic eviction schedule.
+#[derive(Debug)]
+pub struct OnionOram<V: OramBlock, S: ServerComputation<V>> {
+    /// The server-side tree, stored as `bucket_size` consecutive slots per bucket. Bucket 0 is unused.
+    tree: Vec<V>,
+    /// (Client side.) The address of the block occupying each slot of the tree, if any.
+    slot_addresses: Vec<Option<Address>>,
+    /// (Client side.) The leaf assigned to each address.
+    positions: Vec<TreeIndex>,
+    /// (Client side.) Blocks waiting to be evicted, with their addresses.
+    stash: Vec<(Address, V)>,
+    server: S,
+    bucket_size: BucketSize,
+    height: TreeHeight,
+    eviction_period: u64,
+    accesses_since_eviction: u64,
+    eviction_counter: u64,
+}
+
+impl<V: OramBlock, S: ServerComputation<V>> OnionOram<V, S> {
+    /// Returns a new `OnionOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// using the default bucket size and eviction period.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is not a power of two, or is less than 2, returns an `InvalidConfigurationError`.
+    pub fn new<R: OramRng>(
+        block_capacity: Address,
+        server: S,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        Self::new_with_parameters(
+            block_capacity,
+            server,
+            rng,
+            DEFAULT_ONION_BUCKET_SIZE,
+            DEFAULT_ONION_EVICTION_PERIOD,
+        )
+    }
+
+    /// Returns a new `OnionOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// with `bucket_size` blocks per bucket and an eviction every `eviction_period` accesses.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is not a power of two, or is less than 2, or if `bucket_size` or `eviction_period`
+    /// is 0, returns an `InvalidConfigurationError`.
+    pub fn new_with_parameters<R: OramRng>(
+        block_capacity: Address,
+        server: S,
+        rng: &mut R,
+        bucket_size: BucketSize,
+        eviction_period: u64,
+    ) -> Result<Self, OramError> {
+        if !block_capacity.is_power_of_two() || block_capacity < 2 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
+        if bucket_size == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Bucket size".to_string(),
+                parameter_value: bucket_size.to_string(),
+            });
+        }
+        if eviction_period == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Eviction period".to_string(),
+                parameter_value: eviction_period.to_string(),
+            });
+        }
+
+        log::info!("OnionOram::new(capacity = {})", block_capacity);
+
+        // As in `PathOram`, the tree has `block_capacity` / 2 leaves.
+        let height: TreeHeight = (block_capacity.ilog2() - 1).into();
+        let num_slots = usize::try_from(block_capacity)? * bucket_size;
+
+        let mut positions = Vec::with_capacity(usize::try_from(block_capacity)?);
+        for _ in 0..block_capacity {
+            positions.push(TreeIndex::random_leaf(height, rng)?);
+        }
+
+        // Every block starts out in the stash with its default value, and is evicted into the tree over time.
+        let stash = (0..block_capacity)
+            .map(|address| (address, V::default()))
+            .collect();
+
+        let mut result = Self {
+            tree: vec![V::default(); num_slots],
+            slot_addresses: vec![None; num_slots],
+            positions,
+            stash,
+            server,
+            bucket_size,
+            height,
+            eviction_period,
+            accesses_since_eviction: 0,
+            eviction_counter: 0,
+        };
+
+        // One pass of evictions over every leaf places the initial blocks as deep as possible.
+        for _ in 0..(1u64 << height) {
+            result.evict()?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the number of blocks in the client-side stash.
+    pub fn stash_size(&self) -> usize {
+        self.stash.len()
+    }
+
+    /// Returns the slot indices of the path to `leaf`, from the root to the leaf.
+    fn path_slots(&self, leaf: TreeIndex) -> Result<Vec<usize>, OramError> {
+        let mut slots = Vec::with_capacity(usize::try_from(self.height + 1)? * self.bucket_size);
+        for depth in 0..=self.height {
+            let bucket = usize::try_from(leaf.ct_node_on_path(depth, self.height))?;
+            slots.extend(bucket * self.bucket_size..(bucket + 1) * self.bucket_size);
+        }
+        Ok(slots)
+    }
+
+    /// Evicts along the next path in reverse-lexicographic order:
+    /// moves the real blocks of the path into the stash, then refills the path from the stash,
+    /// placing each block as deep as its leaf allows.
+    fn evict(&mut self) -> Result<(), OramError> {
+        let leaf = reverse_lexicographic_leaf(self.eviction_counter, self.height);
+        self.eviction_counter = self.eviction_counter.wrapping_add(1);
+        self.accesses_since_eviction = 0;
+
+        let slots = self.path_slots(leaf)?;
+        for &slot in &slots {
+            if let Some(address) = self.slot_addresses[slot].take() {
+                self.stash.push((address, self.tree[slot]));
+            }
+        }
+
+        // Fill the path from the leaf upward.
+        for depth in (0..=self.height).rev() {
+            let bucket_slots =
+                &slots[usize::try_from(depth)? * self.bucket_size..][..self.bucket_size];
+            let mut free_slots = bucket_slots.iter();
+            let mut i = 0;
+            while i < self.stash.len() {
+                let (address, value) = self.stash[i];
+                let block_leaf = self.positions[usize::try_from(address)?];
+                let fits = block_leaf.ct_node_on_path(depth, self.height)
+                    == leaf.ct_node_on_path(depth, self.height);
+                match free_slots.clone().next() {
+                    Some(&slot) if fits => {
+                        free_slots.next();
+                        self.tree[slot] = value;
+                        self.slot_addresses[slot] = Some(address);
+                        self.stash.swap_remove(i);
+                    }
+                    Some(_) => i += 1,
+                    None => break,
+                }
+            }
+            // Unused slots are overwritten with default values, so that every eviction rewrites the whole path.
+            for &slot in free_slots {
+                self.tree[slot] = V::default();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<V: OramBlock, S: ServerComputation<V>> Oram for OnionOram<V, S> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(Address::try_from(self.positions.len())?)
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            });
+        }
+        let position_index = usize::try_from(index)?;
+        let leaf = self.positions[position_index];
+
+        // Ask the server for the selected block of the path. If the block is in the stash instead,
+        // every selector encrypts 0.
+        let slots = self.path_slots(leaf)?;
+        let mut blocks = Vec::with_capacity(slots.len());
+        let mut selectors = Vec::with_capacity(slots.len());
+        let mut found_slot = None;
+        for &slot in &slots {
+            let selected = self.slot_addresses[slot] == Some(index);
+            if selected {
+                found_slot = Some(slot);
+            }
+            blocks.push(self.tree[slot]);
+            selectors.push(self.server.encrypt_selector(selected));
+        }
+        let response = self.server.select(&blocks, &selectors);
+        let from_tree = self.server.decrypt_block(response);
+
+        let result = match found_slot {
+            Some(slot) => {
+                // The stale copy left in the tree is overwritten by a later eviction.
+                self.slot_addresses[slot] = None;
+                from_tree
+            }
+            None => {
+                let stash_index = self
+                    .stash
+                    .iter()
+                    .position(|(address, _)| *address == index)
+                    .expect("blocks not in the tree are in the stash");
+                self.stash.swap_remove(stash_index).1
+            }
+        };
+
+        self.positions[position_index] = TreeIndex::random_leaf(self.height, rng)?;
+        self.stash.push((index, callback(&result)));
+
+        self.accesses_since_eviction += 1;
+        if self.accesses_since_eviction == self.eviction_period {
+            self.evict()?;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A `ServerComputation` that selects by computing an inner product, as a homomorphic scheme would.
+    #[derive(Debug, Default)]
+    struct InnerProductComputation;
+
+    impl ServerComputation<u64> for InnerProductComputation {
+        type EncryptedSelector = u64;
+        type EncryptedBlock = u64;
+
+        fn encrypt_selector(&mut self, selected: bool) -> u64 {
+            selected.into()
+        }
+
+        fn select(&self, blocks: &[u64], selectors: &[u64]) -> u64 {
+            blocks.iter().zip(selectors).map(|(b, s)| b * s).sum()
+        }
+
+        fn decrypt_block(&self, block: u64) -> u64 {
+            block
+        }
+    }
+
+    #[test]
+    fn onion_oram_random_workload() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for capacity in [2, 64, 256] {
+            let mut oram =
+                OnionOram::<BlockValue<1>, _>::new(capacity, PlaintextComputation, &mut rng)
+                    .unwrap();
+            random_workload(&mut oram, 1000);
+        }
+    }
+
+    #[test]
+    fn onion_oram_with_arithmetic_selection() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = OnionOram::new(64, InnerProductComputation, &mut rng).unwrap();
+        for i in 0..64 {
+            oram.write(i, i * i, &mut rng).unwrap();
+        }
+        let mut max_stash_size = 0;
+        for _ in 0..4 {
+            for i in 0..64 {
+                assert_eq!(oram.read(i, &mut rng).unwrap(), i * i);
+                max_stash_size = max_stash_size.max(oram.stash_size());
+            }
+        }
+        assert!(max_stash_size < 32, "{max_stash_size}");
+        assert!(OnionOram::<u64, _>::new(48, PlaintextComputation, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A registry of named ORAMs sharing a single RNG and metrics sink.
+
+#[cfg(feature = "profiling")]
+use crate::profiler::{OperationTimings, OramOperation};
+use crate::{dyn_oram::DynOram, rng::BufferedRng, Address, OramBlock, OramError, OramRng};
+use std::collections::BTreeMap;
+
+/// Counts of the accesses made through an [`OramManager`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OramMetrics {
+    /// The number of accesses that succeeded.
+    pub accesses: u64,
+    /// The number of accesses that returned an error.
+    pub errors: u64,
+}
+
+/// Owns many ORAMs storing blocks of type `V`, keyed by name.
+///
+/// Services maintaining dozens of small ORAMs would otherwise keep an RNG and metrics per ORAM.
+/// The manager instead draws the randomness of every access from one [`BufferedRng`],
+/// which seeds itself from the system RNG only occasionally, and records metrics for all ORAMs in one place.
+///
+/// The names of the ORAMs, and which ORAM each access is made to, are not hidden.
+pub struct OramManager<'a, V: OramBlock, R: OramRng> {
+    orams: BTreeMap<String, Box<dyn DynOram<V> + 'a>>,
+    metrics: BTreeMap<String, OramMetrics>,
+    #[cfg(feature = "profiling")]
+    timings: BTreeMap<String, OperationTimings>,
+    rng: BufferedRng<R>,
+}
+
+impl<'a, V: OramBlock, R: OramRng> OramManager<'a, V, R> {
+    /// Returns an empty manager drawing randomness from a `BufferedRng` seeded by `rng`.
+    pub fn new(rng: R) -> Self {
+        Self {
+            orams: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            #[cfg(feature = "profiling")]
+            timings: BTreeMap::new(),
+            rng: BufferedRng::new(rng),
+        }
+    }
+
+    /// Adds `oram` under `name`, returning the ORAM previously registered under that name, if any.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        oram: Box<dyn DynOram<V> + 'a>,
+    ) -> Option<Box<dyn DynOram<V> + 'a>> {
+        let name = name.into();
+        self.metrics.insert(name.clone(), OramMetrics::default());
+        #[cfg(feature = "profiling")]
+        self.timings
+            .insert(name.clone(), OperationTimings::default());
+        self.orams.insert(name, oram)
+    }
+
+    /// Removes and returns the ORAM registered under `name`, if any.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn DynOram<V> + 'a>> {
+        self.metrics.remove(name);
+        #[cfg(feature = "profiling")]
+        self.timings.remove(name);
+        self.orams.remove(name)
+    }
+
+    /// Returns the names of the registered ORAMs, in lexicographic order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.orams.keys().map(String::as_str)
+    }
+
+    /// Returns the number of registered ORAMs.
+    pub fn len(&self) -> usize {
+        self.orams.len()
+    }
+
+    /// Returns true if no ORAM is registered.
+    pub fn is_empty(&self) -> bool {
+        self.orams.is_empty()
+    }
+
+    /// Returns the capacity in blocks of the ORAM registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// If no ORAM is registered under `name`, returns an `InvalidConfigurationError`.
+    pub fn block_capacity(&self, name: &str) -> Result<Address, OramError> {
+        self.orams
+            .get(name)
+            .ok_or_else(|| Self::unknown_name(name))?
+            .dyn_block_capacity()
+    }
+
+    /// Performs an access to `index` on the ORAM registered under `name`, using the shared RNG.
+    /// See [`crate::Oram::access`].
+    ///
+    /// # Errors
+    ///
+    /// If no ORAM is registered under `name`, returns an `InvalidConfigurationError`.
+    /// Otherwise, returns any error returned by the ORAM.
+    pub fn access<F: Fn(&V) -> V>(
+        &mut self,
+        name: &str,
+        index: Address,
+        callback: F,
+    ) -> Result<V, OramError> {
+        #[cfg(feature = "profiling")]
+        return self.timed_access(OramOperation::Access, name, index, callback);
+        #[cfg(not(feature = "profiling"))]
+        self.untimed_access(name, index, callback)
+    }
+
+    fn untimed_access<F: Fn(&V) -> V>(
+        &mut self,
+        name: &str,
+        index: Address,
+        callback: F,
+    ) -> Result<V, OramError> {
+        let oram = self
+            .orams
+            .get_mut(name)
+            .ok_or_else(|| Self::unknown_name(name))?;
+        let result = oram.dyn_access(index, &callback, &mut self.rng);
+
+        let metrics = self.metrics.entry(name.to_string()).or_default();
+        match result {
+            Ok(_) => metrics.accesses += 1,
+            Err(_) => metrics.errors += 1,
+        }
+        result
+    }
+
+    /// Reads the value stored at `index` of the ORAM registered under `name`. See [`crate::Oram::read`].
+    ///
+    /// # Errors
+    ///
+    /// See [`OramManager::access`].
+    pub fn read(&mut self, name: &str, index: Address) -> Result<V, OramError> {
+        #[cfg(feature = "profiling")]
+        return self.timed_access(OramOperation::Read, name, index, |x| *x);
+        #[cfg(not(feature = "profiling"))]
+        self.untimed_access(name, index, |x| *x)
+    }
+
+    /// Writes `new_value` at `index` of the ORAM registered under `name`, returning the previous value.
+    /// See [`crate::Oram::write`].
+    ///
+    /// # Errors
+    ///
+    /// See [`OramManager::access`].
+    pub fn write(&mut self, name: &str, index: Address, new_value: V) -> Result<V, OramError> {
+        #[cfg(feature = "profiling")]
+        return self.timed_access(OramOperation::Write, name, index, |_| new_value);
+        #[cfg(not(feature = "profiling"))]
+        self.untimed_access(name, index, |_| new_value)
+    }
+
+    /// Performs an access, recording its latency as an `operation` if it succeeds.
+    #[cfg(feature = "profiling")]
+    fn timed_access<F: Fn(&V) -> V>(
+        &mut self,
+        operation: OramOperation,
+        name: &str,
+        index: Address,
+        callback: F,
+    ) -> Result<V, OramError> {
+        let start = std::time::Instant::now();
+        let result = self.untimed_access(name, index, callback)?;
+        self.timings
+            .entry(name.to_string())
+            .or_default()
+            .record(operation, start.elapsed());
+        Ok(result)
+    }
+
+    /// Returns the latency histograms of the operations on the ORAM registered under `name`, if any
+    /// (requires the `profiling` feature). Failed operations are not recorded.
+    #[cfg(feature = "profiling")]
+    pub fn timings(&self, name: &str) -> Option<&OperationTimings> {
+        self.timings.get(name)
+    }
+
+    /// Returns the latency histograms of the operations on all registered ORAMs, merged
+    /// (requires the `profiling` feature).
+    #[cfg(feature = "profiling")]
+    pub fn total_timings(&self) -> OperationTimings {
+        self.timings
+            .values()
+            .fold(OperationTimings::default(), |mut total, timings| {
+                total.merge(timings);
+                total
+            })
+    }
+
+    /// Returns the metrics of the ORAM registered under `name`, if any.
+    pub fn metrics(&self, name: &str) -> Option<OramMetrics> {
+        self.metrics.get(name).copied()
+    }
+
+    /// Returns the metrics summed over all registered ORAMs.
+    pub fn total_metrics(&self) -> OramMetrics {
+        self.metrics
+            .values()
+            .fold(OramMetrics::default(), |total, metrics| OramMetrics {
+                accesses: total.accesses + metrics.accesses,
+                errors: total.errors + metrics.errors,
+            })
+    }
+
+    fn unknown_name(name: &str) -> OramError {
+        OramError::InvalidConfigurationError {
+            parameter_name: "ORAM name".to_string(),
+            parameter_value: name.to_string(),
+        }
+    }
+}
+
+impl<V: OramBlock, R: OramRng> std::fmt::Debug for OramManager<'_, V, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OramManager")
+            .field("names", &self.orams.keys().collect::<Vec<_>>())
+            .field("metrics", &self.metrics)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{linear_time_oram::LinearTimeOram, DefaultOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn oram_manager_routes_by_name() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut manager = OramManager::<u64, _>::new(StdRng::seed_from_u64(1));
+        manager.insert(
+            "sessions",
+            Box::new(DefaultOram::<u64>::new(2048, &mut rng).unwrap()),
+        );
+        manager.insert("flags", Box::new(LinearTimeOram::<u64>::new(16).unwrap()));
+        assert_eq!(manager.len(), 2);
+        assert_eq!(
+            manager.names().collect::<Vec<_>>(),
+            vec!["flags", "sessions"]
+        );
+        assert_eq!(manager.block_capacity("sessions").unwrap(), 2048);
+
+        for i in 0..16 {
+            manager.write("sessions", i * 100, i).unwrap();
+            manager.write("flags", i, i + 1).unwrap();
+        }
+        for i in 0..16 {
+            assert_eq!(manager.read("sessions", i * 100).unwrap(), i);
+            assert_eq!(manager.read("flags", i).unwrap(), i + 1);
+        }
+
+        assert!(manager.read("flags", 16).is_err());
+        assert!(manager.read("missing", 0).is_err());
+        assert_eq!(
+            manager.metrics("flags"),
+            Some(OramMetrics {
+                accesses: 32,
+                errors: 1
+            })
+        );
+        assert_eq!(manager.total_metrics().accesses, 64);
+
+        assert!(manager.remove("flags").is_some());
+        assert_eq!(manager.metrics("flags"), None);
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn oram_manager_records_timings() {
+        let mut manager = OramManager::<u64, _>::new(StdRng::seed_from_u64(1));
+        manager.insert("flags", Box::new(LinearTimeOram::<u64>::new(16).unwrap()));
+        for i in 0..16 {
+            manager.write("flags", i, i).unwrap();
+            manager.read("flags", i).unwrap();
+        }
+        manager.access("flags", 0, |x| x + 1).unwrap();
+        assert!(manager.read("flags", 16).is_err());
+
+        let timings = manager.timings("flags").unwrap();
+        assert_eq!(timings.histogram(OramOperation::Read).count(), 16);
+        assert_eq!(timings.histogram(OramOperation::Write).count(), 16);
+        assert_eq!(timings.histogram(OramOperation::Access).count(), 1);
+        assert_eq!(manager.total_timings(), *timings);
+        assert!(manager.timings("missing").is_none());
+    }
+}
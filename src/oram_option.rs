@@ -0,0 +1,113 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An `Option`-like `OramBlock` with a constant-time presence flag.
+
+use crate::OramBlock;
+use subtle::{Choice, ConditionallySelectable, CtOption};
+
+/// An `OramBlock` holding a value that may be absent, for ORAM slots that may be empty.
+///
+/// Unlike `Option<V>`, the presence flag is inspected in constant time, as a `Choice`,
+/// and an absent `OramOption` still stores a (default) value of the same size.
+/// The default `OramOption` is absent.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OramOption<V> {
+    value: V,
+    present: u8,
+}
+
+impl<V: OramBlock> OramOption<V> {
+    /// Returns an `OramOption` holding `value` if `is_some` is 1, and absent otherwise.
+    pub fn new(value: V, is_some: Choice) -> Self {
+        Self {
+            value: V::conditional_select(&V::default(), &value, is_some),
+            present: is_some.unwrap_u8(),
+        }
+    }
+
+    /// Returns an `OramOption` holding `value`.
+    pub fn some(value: V) -> Self {
+        Self::new(value, 1.into())
+    }
+
+    /// Returns an absent `OramOption`.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Returns 1 if a value is present, and 0 otherwise.
+    pub fn is_some(&self) -> Choice {
+        Choice::from(self.present)
+    }
+
+    /// Returns 1 if no value is present, and 0 otherwise.
+    pub fn is_none(&self) -> Choice {
+        !self.is_some()
+    }
+
+    /// Returns the value if present, and `default` otherwise, in constant time.
+    pub fn unwrap_or(&self, default: V) -> V {
+        V::conditional_select(&default, &self.value, self.is_some())
+    }
+
+    /// Returns the value if present, and `V::default()` otherwise, in constant time.
+    pub fn unwrap_or_default(&self) -> V {
+        self.unwrap_or(V::default())
+    }
+
+    /// Converts this `OramOption` into a `CtOption`.
+    pub fn into_ct_option(self) -> CtOption<V> {
+        CtOption::new(self.value, self.is_some())
+    }
+}
+
+impl<V: OramBlock> From<CtOption<V>> for OramOption<V> {
+    fn from(option: CtOption<V>) -> Self {
+        Self::new(option.unwrap_or(V::default()), option.is_some())
+    }
+}
+
+impl<V: ConditionallySelectable> ConditionallySelectable for OramOption<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            value: V::conditional_select(&a.value, &b.value, choice),
+            present: u8::conditional_select(&a.present, &b.present, choice),
+        }
+    }
+}
+
+impl<V: OramBlock> OramBlock for OramOption<V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultOram, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn oram_option_presence_and_values() {
+        let some = OramOption::some(5u32);
+        let none = OramOption::<u32>::none();
+        assert!(bool::from(some.is_some()));
+        assert!(bool::from(none.is_none()));
+        assert_eq!(some.unwrap_or(9), 5);
+        assert_eq!(none.unwrap_or(9), 9);
+        assert_eq!(OramOption::new(5u32, 0.into()), none);
+
+        let selected = OramOption::conditional_select(&none, &some, 1.into());
+        assert_eq!(selected, some);
+        assert_eq!(OramOption::from(some.into_ct_option()), some);
+        assert_eq!(OramOption::from(none.into_ct_option()), none);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<OramOption<u64>>::new(8, &mut rng).unwrap();
+        assert!(bool::from(oram.read(3, &mut rng).unwrap().is_none()));
+        oram.write(3, OramOption::some(0), &mut rng).unwrap();
+        assert!(bool::from(oram.read(3, &mut rng).unwrap().is_some()));
+    }
+}
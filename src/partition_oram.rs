@@ -0,0 +1,259 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An implementation of the partition ORAM of Stefanov, Shi, and Song.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use rand::Rng;
+
+/// The number of evictions performed after each access.
+/// The original paper shows that any rate greater than 1 keeps the eviction caches small.
+pub const DEFAULT_EVICTION_RATE: u64 = 2;
+
+/// The location of a block in a `PartitionOram`.
+#[derive(Clone, Copy, Debug)]
+enum Location {
+    /// The block is stored in the given slot of the given partition.
+    Slot { partition: usize, slot: Address },
+    /// The block is waiting in the eviction cache of the given partition.
+    Cache { partition: usize },
+}
+
+/// The partition ORAM of [Stefanov, Shi, and Song](https://arxiv.org/abs/1106.3652).
+///
+/// The `N` blocks are split across about `sqrt(N)` partitions, each a small [`DefaultOram`].
+/// Each block is assigned to a uniformly random partition, which is reassigned every time the block is accessed.
+/// Accessed blocks are placed in a client-side eviction cache for their new partition,
+/// and a background eviction process writes cached blocks back to their partitions.
+///
+/// ## Security
+///
+/// Unlike the other ORAMs in this crate, `PartitionOram` keeps a position map and eviction caches
+/// in ordinary (non-oblivious) client memory, which takes `O(N)` space.
+/// It is therefore only oblivious when this client memory is itself hidden from the adversary,
+/// for example when the partitions are stored outside an enclave but the client state is not.
+/// In return, each access costs a constant number of accesses to ORAMs of size `O(sqrt(N))`.
+///
+/// The partitions accessed are uniformly random and independent of the addresses accessed,
+/// and each eviction makes exactly one access to a random partition, whether or not a block is evicted.
+#[derive(Debug)]
+pub struct PartitionOram<V: OramBlock> {
+    partitions: Vec<DefaultOram<V>>,
+    /// The location of each block.
+    positions: Vec<Location>,
+    /// The blocks waiting to be evicted to each partition.
+    caches: Vec<Vec<(Address, V)>>,
+    /// The unoccupied slots of each partition.
+    free_slots: Vec<Vec<Address>>,
+    /// The number of evictions performed after each access.
+    eviction_rate: u64,
+}
+
+impl<V: OramBlock> PartitionOram<V> {
+    /// Returns a new `PartitionOram` mapping addresses `0 <= address < block_capacity` to default `V` values.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(block_capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        Self::new_with_parameters(block_capacity, rng, DEFAULT_EVICTION_RATE)
+    }
+
+    /// Returns a new `PartitionOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// performing `eviction_rate` evictions after each access.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` or `eviction_rate` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new_with_parameters<R: OramRng>(
+        block_capacity: Address,
+        rng: &mut R,
+        eviction_rate: u64,
+    ) -> Result<Self, OramError> {
+        if block_capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
+        if eviction_rate == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Eviction rate".to_string(),
+                parameter_value: eviction_rate.to_string(),
+            });
+        }
+
+        log::info!("PartitionOram::new(capacity = {})", block_capacity);
+
+        // Partitions have twice the average load, so that a random assignment of blocks fits with room to spare.
+        let num_partitions: Address = 1 << block_capacity.ilog2().div_ceil(2);
+        let partition_capacity = (2 * block_capacity)
+            .div_ceil(num_partitions)
+            .next_power_of_two();
+
+        let mut partitions = Vec::new();
+        for _ in 0..num_partitions {
+            partitions.push(DefaultOram::new(partition_capacity, rng)?);
+        }
+        let num_partitions = usize::try_from(num_partitions)?;
+        let mut free_slots =
+            vec![(0..partition_capacity).rev().collect::<Vec<_>>(); num_partitions];
+
+        // Every slot already holds a default value, so blocks only need to be assigned to slots.
+        let mut positions = Vec::with_capacity(usize::try_from(block_capacity)?);
+        for _ in 0..block_capacity {
+            let partition = loop {
+                let candidate = rng.gen_range(0..num_partitions);
+                if !free_slots[candidate].is_empty() {
+                    break candidate;
+                }
+            };
+            let slot = free_slots[partition]
+                .pop()
+                .expect("partition has a free slot");
+            positions.push(Location::Slot { partition, slot });
+        }
+
+        Ok(Self {
+            partitions,
+            positions,
+            caches: vec![Vec::new(); num_partitions],
+            free_slots,
+            eviction_rate,
+        })
+    }
+
+    /// Returns the number of partitions.
+    pub fn num_partitions(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// Returns the total number of blocks waiting in the eviction caches.
+    pub fn cached_blocks(&self) -> usize {
+        self.caches.iter().map(Vec::len).sum()
+    }
+
+    /// Evicts a cached block to a uniformly random partition, if that partition has a cached block
+    /// and a free slot. Otherwise, makes a dummy access to the partition.
+    fn evict<R: OramRng>(&mut self, rng: &mut R) -> Result<(), OramError> {
+        let partition = rng.gen_range(0..self.num_partitions());
+
+        if !self.caches[partition].is_empty() && !self.free_slots[partition].is_empty() {
+            let (address, value) = self.caches[partition].pop().expect("cache is nonempty");
+            let slot = self.free_slots[partition].pop().expect("a slot is free");
+            self.partitions[partition].write(slot, value, rng)?;
+            self.positions[usize::try_from(address)?] = Location::Slot { partition, slot };
+        } else {
+            self.dummy_access(partition, rng)?;
+        }
+        Ok(())
+    }
+
+    fn dummy_access<R: OramRng>(&mut self, partition: usize, rng: &mut R) -> Result<(), OramError> {
+        let oram = &mut self.partitions[partition];
+        let slot = rng.gen_range(0..oram.block_capacity()?);
+        oram.read(slot, rng)?;
+        Ok(())
+    }
+}
+
+impl<V: OramBlock> Oram for PartitionOram<V> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(Address::try_from(self.positions.len())?)
+    }
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            });
+        }
+        let position_index = usize::try_from(index)?;
+
+        // Read the block from its partition, or from the cache while reading a random partition instead.
+        let result = match self.positions[position_index] {
+            Location::Slot { partition, slot } => {
+                let value = self.partitions[partition].read(slot, rng)?;
+                self.free_slots[partition].push(slot);
+                value
+            }
+            Location::Cache { partition } => {
+                let cache = &mut self.caches[partition];
+                let cache_index = cache
+                    .iter()
+                    .position(|(address, _)| *address == index)
+                    .expect("cached blocks are present in their cache");
+                let (_, value) = cache.swap_remove(cache_index);
+                let random_partition = rng.gen_range(0..self.num_partitions());
+                self.dummy_access(random_partition, rng)?;
+                value
+            }
+        };
+
+        // Assign the block to a fresh random partition, and cache it until it is evicted there.
+        let partition = rng.gen_range(0..self.num_partitions());
+        self.caches[partition].push((index, callback(&result)));
+        self.positions[position_index] = Location::Cache { partition };
+
+        for _ in 0..self.eviction_rate {
+            self.evict(rng)?;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn partition_oram_random_workload() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for capacity in [1, 5, 64, 100] {
+            let mut oram = PartitionOram::<BlockValue<1>>::new(capacity, &mut rng).unwrap();
+            assert_eq!(oram.block_capacity().unwrap(), capacity);
+            random_workload(&mut oram, 1000);
+        }
+    }
+
+    #[test]
+    fn partition_oram_caches_stay_small() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PartitionOram::<u64>::new(256, &mut rng).unwrap();
+        assert_eq!(oram.num_partitions(), 16);
+
+        let mut max_cached = 0;
+        for i in 0..2000 {
+            oram.write(i % 256, i, &mut rng).unwrap();
+            max_cached = max_cached.max(oram.cached_blocks());
+        }
+        assert!(max_cached < 64, "{max_cached}");
+    }
+
+    #[test]
+    fn partition_oram_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(PartitionOram::<u64>::new(0, &mut rng).is_err());
+        assert!(PartitionOram::<u64>::new_with_parameters(16, &mut rng, 0).is_err());
+    }
+}
@@ -7,17 +7,53 @@
 
 //! An implementation of Path ORAM.
 
-use super::{position_map::PositionMap, stash::ObliviousStash};
+use super::{
+    position_map::{PositionMap, RecursivePositionMap},
+    stash::{ObliviousStash, PathBuckets, Stash, StashGrowth},
+};
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{AccessStats, CountingStorage, LeafHistogram, LevelStats};
+#[cfg(feature = "profiling")]
+use crate::profiler::{AccessPhase, Profiler};
 use crate::{
-    bucket::{Bucket, PathOramBlock, PositionBlock},
+    access_outcome::{AccessOutcome, NEAR_CAPACITY_PERCENT},
+    audit::{self, AuditEventKind},
+    bucket::{BlockValue, Bucket, PathOramBlock},
+    eviction::{EvictionStrategy, PathEviction, ScheduledEviction},
     linear_time_oram::LinearTimeOram,
+    memory_usage::{LevelMemoryUsage, MemoryUsage},
+    physical_tree::{BucketLayout, PhysicalTree},
+    snapshot::OramSnapshot,
     utils::{
-        invert_permutation_oblivious, random_permutation_of_0_through_n_exclusive, to_usize_vec,
-        CompleteBinaryTreeIndex, TreeHeight,
+        apply_permutation_oblivious, bitonic_sort_by_keys, check_permutation,
+        invert_permutation_oblivious, random_permutation_of_0_through_n_exclusive,
+        reverse_lexicographic_leaf, to_usize_vec, try_filled_vec, CompleteBinaryTreeIndex,
+        TreeHeight, TreeIndex,
     },
-    Address, BlockSize, BucketSize, Oram, OramBlock, OramError, RecursionCutoff, StashSize,
+    verification::{Inconsistency, VerificationReport},
+    Address, BlockSize, BucketSize, Oram, OramBlock, OramError, OramRng, RecursionCutoff,
+    StashSize,
 };
-use rand::{CryptoRng, Rng};
+use std::{marker::PhantomData, sync::Arc};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+/// Returns the tree of the `PathOram` `$oram`, for the stash to read and write paths of.
+/// With the `diagnostics` feature, the bucket reads and writes are counted in the `level_stats` of `$oram`.
+#[cfg(feature = "diagnostics")]
+macro_rules! tree_storage {
+    ($oram:ident) => {
+        &mut CountingStorage::new(
+            Arc::make_mut(&mut $oram.physical_memory),
+            &mut $oram.level_stats,
+        )
+    };
+}
+#[cfg(not(feature = "diagnostics"))]
+macro_rules! tree_storage {
+    ($oram:ident) => {
+        Arc::make_mut(&mut $oram.physical_memory)
+    };
+}
 
 /// The default cutoff size in blocks
 /// below which `PathOram` uses a linear position map instead of a recursive one.
@@ -33,33 +69,48 @@ pub const DEFAULT_POSITIONS_PER_BLOCK: BlockSize = 8;
 /// The default number of overflow blocks that the Path ORAM stash (and recursive stashes) can store.
 pub const DEFAULT_STASH_OVERFLOW_SIZE: StashSize = 40;
 
+/// The maximum height of a Path ORAM tree. The block capacity of a `PathOram` is twice the number of leaves
+/// of its tree, so capacities of up to `2 << MAXIMUM_TREE_HEIGHT` (2^48) blocks are supported.
+pub const MAXIMUM_TREE_HEIGHT: u64 = 47;
+
 const LINEAR_TIME_ORAM_CUTOFF: RecursionCutoff = 1 << 10;
 
+/// The maximum number of additional evictions triggered by the eviction watermark after a single access.
+/// See [`PathOram::set_eviction_watermark`].
+pub const MAXIMUM_WATERMARK_EVICTIONS: u64 = 16;
+
 /// A doubly oblivious Path ORAM.
 ///
 /// ## Parameters
 ///
 /// - Block type `V`: the type of elements stored by the ORAM.
 /// - Bucket size `Z`: the number of blocks per Path ORAM bucket.
-///     Must be at least 2. Typical values are 3, 4, or 5.
-///     Along with the overflow size, this value affects the probability
-///     of stash overflow (see below) and should be set with care.
+///   Must be at least 2. Typical values are 3, 4, or 5.
+///   Along with the overflow size, this value affects the probability
+///   of stash overflow (see below) and should be set with care.
 /// - Positions per block `AB`:
-///     The number of positions stored in each block of the recursive position map ORAM.
-///     Must be a power of two and must be at least 2 (otherwise the recursion will not terminate).
-///     Otherwise, can be freely tuned for performance.
-///     Larger `AB` means fewer levels of recursion but higher costs for accessing each level.
+///   The number of positions stored in each block of the recursive position map ORAM.
+///   Must be a power of two and must be at least 2 (otherwise the recursion will not terminate).
+///   Otherwise, can be freely tuned for performance.
+///   Larger `AB` means fewer levels of recursion but higher costs for accessing each level.
 /// - Recursion threshold: the maximum number of position blocks that will be stored in a recursive Path ORAM.
-///     Below this value, the position map will be a linear scanning ORAM.
-///     Can be freely tuned for performance.
-///     A larger values means fewer levels of recursion, but a more expensive base position map.
+///   Below this value, the position map will be a linear scanning ORAM.
+///   Can be freely tuned for performance.
+///   A larger values means fewer levels of recursion, but a more expensive base position map.
+///   Recursive levels of so few position blocks that scanning them all touches no more blocks
+///   than reading one of their paths are always collapsed into the base position map.
 /// - Overflow size: The number of blocks that the stash can store between ORAM accesses without overflowing.
-///     Along with the bucket size, this value affects the probability of stash overflow (see below)
-///     and should be set with care.
+///   Along with the bucket size, this value affects the probability of stash overflow (see below)
+///   and should be set with care.
+/// - Stash type `S`: the [`Stash`] implementation, [`ObliviousStash`] by default.
+///   Recursive position maps always use an `ObliviousStash`.
+/// - Position map type `M`: the [`PositionMap`] implementation, [`RecursivePositionMap`] by default.
+///   The recursion threshold is only used by the default position map.
 ///
 /// ## Security
 ///
-/// ORAM operations are guaranteed to be oblivious, *unless* the stash overflows.
+/// ORAM operations are guaranteed to be oblivious, *unless* the stash overflows
+/// or a non-oblivious stash such as [`crate::InsecureVecStash`] is used.
 /// In this case, the stash will grow, which reveals that the overflow occurred.
 /// This is a violation of obliviousness, but a mild one in several ways.
 /// The stash overflow is very likely to reset to empty after the overflow,
@@ -72,49 +123,345 @@ const LINEAR_TIME_ORAM_CUTOFF: RecursionCutoff = 1 << 10;
 /// and that setting SO = 40 is enough to reduce this probability to below 2^{-50} (Figure 3).
 /// The authors conservatively estimate that setting SO = 89 suffices for 2^{-80} overflow probability.
 /// The choice Z = 3 is also popular, although the probability of overflow is less well understood.
-#[derive(Debug)]
-pub struct PathOram<V: OramBlock, const Z: BucketSize, const AB: BlockSize> {
+///
+/// Where even this leak is unacceptable, construct the ORAM with [`StashGrowth::Never`]
+/// (see [`PathOram::new_with_stash_growth`]). An overflow then fails the access with a `StashOverflowError`
+/// and poisons the ORAM, leaving it in an inconsistent state: every later access fails with a `PoisonedError`.
+#[derive(Clone, Debug)]
+pub struct PathOram<
+    V: OramBlock,
+    const Z: BucketSize,
+    const AB: BlockSize,
+    S: Stash<V> = ObliviousStash<V>,
+    M: PositionMap = RecursivePositionMap<AB, Z>,
+> {
     /// The underlying untrusted memory that the ORAM is obliviously accessing on behalf of its client.
-    physical_memory: Vec<Bucket<V, Z>>,
+    /// It is shared (copy-on-write) between this ORAM and its snapshots.
+    physical_memory: Arc<PhysicalTree<V, Z>>,
     /// The Path ORAM stash.
-    stash: ObliviousStash<V>,
+    stash: S,
     /// The Path ORAM position map.
-    position_map: PositionMap<AB, Z>,
+    position_map: M,
     /// The height of the Path ORAM tree data structure.
     height: TreeHeight,
+    /// The number of scheduled evictions performed so far, which determines the next scheduled eviction path.
+    eviction_counter: u64,
+    /// Decides whether accesses evict to the paths they read, and when scheduled evictions are performed.
+    eviction_strategy: Box<dyn EvictionStrategy>,
+    /// The stash occupancy above which accesses perform an additional scheduled eviction, if any.
+    eviction_watermark: Option<StashSize>,
+    /// The number of additional evictions triggered by `eviction_watermark`.
+    watermark_evictions: u64,
+    /// Whether a stash overflow that could not be absorbed by growing the stash has aborted an access.
+    poisoned: bool,
+    /// The epoch of the last split of this ORAM into parts (see [`PathOram::into_parts`]).
+    epoch: u64,
+    /// The distribution of leaves read by accesses.
+    #[cfg(feature = "diagnostics")]
+    leaf_histogram: LeafHistogram,
+    /// The physical reads and writes of the tree, by depth.
+    #[cfg(feature = "diagnostics")]
+    level_stats: LevelStats,
+    /// The durations of the phases of accesses.
+    #[cfg(feature = "profiling")]
+    profiler: Profiler,
+    /// The leaf whose path was read by the most recent access.
+    #[cfg(feature = "test-vectors")]
+    last_leaf: crate::utils::TreeIndex,
+}
+
+/// The client-side state of a [`PathOram`]: its position map, stash, and eviction schedule.
+///
+/// Together with the matching [`PathOramTree`], this is everything needed to reconstruct the ORAM
+/// (see [`PathOram::into_parts`]). The client state is an in-memory value: this crate provides
+/// no encoding of it, so it cannot be persisted and reloaded to resume against a tree kept in external storage.
+///
+/// Both parts are stamped with the epoch of the split that produced them, so that
+/// [`PathOram::from_parts`] rejects a client state and a tree from different splits.
+#[derive(Clone, Debug)]
+pub struct PathOramClientState<
+    V: OramBlock,
+    const Z: BucketSize,
+    const AB: BlockSize,
+    S: Stash<V> = ObliviousStash<V>,
+    M: PositionMap = RecursivePositionMap<AB, Z>,
+> {
+    stash: S,
+    position_map: M,
+    height: TreeHeight,
+    eviction_counter: u64,
+    eviction_strategy: Box<dyn EvictionStrategy>,
+    eviction_watermark: Option<StashSize>,
+    watermark_evictions: u64,
+    poisoned: bool,
+    epoch: u64,
+    _value: PhantomData<V>,
+}
+
+/// The server-side state of a [`PathOram`]: the buckets of its tree. See [`PathOram::into_parts`].
+#[derive(Clone, Debug)]
+pub struct PathOramTree<V: OramBlock, const Z: BucketSize> {
+    buckets: Arc<PhysicalTree<V, Z>>,
+    epoch: u64,
+}
+
+impl<V: OramBlock, const Z: BucketSize> PathOramTree<V, Z> {
+    /// Returns a tree of the given buckets, including the unused bucket at index 0, stamped with `epoch`.
+    pub(crate) fn from_buckets(buckets: Vec<Bucket<V, Z>>, epoch: u64) -> Self {
+        Self {
+            buckets: Arc::new(PhysicalTree::from_buckets(buckets, BucketLayout::default())),
+            epoch,
+        }
+    }
+
+    /// Returns the epoch of the split that produced this tree. See [`PathOram::into_parts`].
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the number of buckets in the tree, including the unused bucket at index 0.
+    pub fn num_buckets(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Returns copies of the buckets at `bucket_indices`, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If any index is not a bucket of the tree, returns an `AddressOutOfBoundsError`.
+    pub fn read_path(&self, bucket_indices: &[u64]) -> Result<Vec<Bucket<V, Z>>, OramError> {
+        bucket_indices
+            .iter()
+            .map(|&index| Ok(self.buckets.bucket(self.checked_bucket_index(index)?)))
+            .collect()
+    }
+
+    /// Overwrites the buckets at `bucket_indices` with `buckets`, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If any index is not a bucket of the tree, returns an `AddressOutOfBoundsError`.
+    /// If `buckets` and `bucket_indices` have different lengths, returns an `InvalidConfigurationError`.
+    pub fn write_path(
+        &mut self,
+        bucket_indices: &[u64],
+        buckets: &[Bucket<V, Z>],
+    ) -> Result<(), OramError> {
+        if buckets.len() != bucket_indices.len() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of path buckets".to_string(),
+                parameter_value: buckets.len().to_string(),
+            });
+        }
+        for (&index, bucket) in bucket_indices.iter().zip(buckets) {
+            let index = self.checked_bucket_index(index)?;
+            Arc::make_mut(&mut self.buckets).set_bucket(index, bucket);
+        }
+        Ok(())
+    }
+
+    fn checked_bucket_index(&self, index: u64) -> Result<usize, OramError> {
+        // This operation is not constant-time, but only leaks whether the bucket index is well-formed or not.
+        let num_buckets = u64::try_from(self.buckets.len())?;
+        if index == 0 || index >= num_buckets {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: num_buckets,
+            });
+        }
+        Ok(usize::try_from(index)?)
+    }
+}
+
+/// An access started with [`PathOramClientState::begin_access`], waiting for its path to be fetched.
+#[derive(Clone, Debug)]
+pub struct PendingAccess {
+    address: Address,
+    position: TreeIndex,
+    new_position: TreeIndex,
+    bucket_indices: Vec<TreeIndex>,
+}
+
+impl PendingAccess {
+    /// Returns the indices of the buckets to fetch, from the root to the leaf.
+    ///
+    /// The path is uniformly random and independent of the accessed address.
+    pub fn bucket_indices(&self) -> &[u64] {
+        &self.bucket_indices
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOramClientState<V, Z, AB, S, M>
+{
+    /// Returns the epoch of the split that produced this client state. See [`PathOram::into_parts`].
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Starts an access to `address`, returning the path of buckets that must be fetched from the tree
+    /// before calling [`PathOramClientState::finish_access`].
+    ///
+    /// Splitting an access in two lets callers whose tree lives behind a slow backend overlap
+    /// the client-side work of one access (this function, which updates the position map)
+    /// with the fetch of another access's path. Accesses must still be finished in the order they were begun,
+    /// and the path of an access must be fetched after the path of the previous access has been written back,
+    /// since the two paths may share buckets.
+    ///
+    /// # Errors
+    ///
+    /// If `address` is out of bounds, returns an `AddressOutOfBoundsError`.
+    /// If the eviction strategy is not [`PathEviction`] (see [`PathOram::set_eviction_strategy`]),
+    /// returns an `InvalidConfigurationError`, since other strategies touch additional paths.
+    /// If the ORAM is poisoned (see [`PathOram::is_poisoned`]), returns a `PoisonedError`.
+    pub fn begin_access<R: OramRng>(
+        &mut self,
+        address: Address,
+        rng: &mut R,
+    ) -> Result<PendingAccess, OramError> {
+        if self.poisoned {
+            return Err(OramError::PoisonedError);
+        }
+
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = 2u64 << self.height;
+        if address >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: address,
+                capacity,
+            });
+        }
+
+        // Check that the next access evicts only the path it reads, without updating the strategy.
+        if !self.eviction_strategy.evicts_accessed_path()
+            | (self.eviction_strategy.boxed_clone().record_access() != 0)
+        {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Eviction strategy".to_string(),
+                parameter_value: format!("{:?}", self.eviction_strategy),
+            });
+        }
+
+        let new_position = CompleteBinaryTreeIndex::random_leaf(self.height, rng)?;
+        let position = self.position_map.write(address, new_position, rng);
+        self.poisoned |= is_poisoning(&position);
+        let position = position?;
+        assert!(position.is_leaf(self.height));
+
+        let bucket_indices = (0..=self.height)
+            .map(|depth| position.ct_node_on_path(depth, self.height))
+            .collect();
+
+        Ok(PendingAccess {
+            address,
+            position,
+            new_position,
+            bucket_indices,
+        })
+    }
+
+    /// Finishes `pending` given the buckets fetched at `pending.bucket_indices()`,
+    /// reading the target block, replacing its value by `callback` applied to it, and returning its old value.
+    ///
+    /// On success, `path` holds the updated buckets, which must be written back to the same indices
+    /// (for example with [`PathOramTree::write_path`]) before the next path is fetched.
+    ///
+    /// # Errors
+    ///
+    /// If `path` does not contain exactly one bucket per level of the tree, returns an `InvalidConfigurationError`.
+    pub fn finish_access<F: Fn(&V) -> V>(
+        &mut self,
+        pending: PendingAccess,
+        path: &mut [Bucket<V, Z>],
+        callback: F,
+    ) -> Result<V, OramError> {
+        if path.len() != pending.bucket_indices.len() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of path buckets".to_string(),
+                parameter_value: path.len().to_string(),
+            });
+        }
+
+        let mut path = PathBuckets(path);
+        self.stash.read_from_path(&mut path, pending.position)?;
+        let result = self
+            .stash
+            .access(pending.address, pending.new_position, callback);
+        let evicted = self.stash.write_to_path(&mut path, pending.position);
+        self.poisoned |= is_poisoning(&evicted);
+        evicted?;
+        let result = result?;
+        self.eviction_strategy.record_access();
+
+        Ok(result)
+    }
+}
+
+/// Returns true if `result` failed because a stash overflowed without growing, which leaves an ORAM
+/// in an inconsistent state, or because the ORAM (or its position map) was already poisoned.
+fn is_poisoning<T>(result: &Result<T, OramError>) -> bool {
+    matches!(
+        result,
+        Err(OramError::StashOverflowError { .. } | OramError::PoisonedError)
+    )
 }
 
 /// An `Oram` suitable for most use cases, with reasonable default choices of parameters.
-#[derive(Debug)]
-pub struct DefaultOram<V: OramBlock>(DefaultOramBackend<V>);
+#[derive(Clone, Debug)]
+pub struct DefaultOram<V: OramBlock> {
+    backend: DefaultOramBackend<V>,
+    /// Whether writes just beyond the current capacity grow the ORAM instead of failing.
+    auto_grow: bool,
+    /// The value stored at addresses that have never been written.
+    fill_value: V,
+}
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 enum DefaultOramBackend<V: OramBlock> {
-    Path(PathOram<V, DEFAULT_BLOCKS_PER_BUCKET, DEFAULT_POSITIONS_PER_BLOCK>),
+    Path(Box<PathOram<V, DEFAULT_BLOCKS_PER_BUCKET, DEFAULT_POSITIONS_PER_BLOCK>>),
     Linear(LinearTimeOram<V>),
 }
 
 impl<V: OramBlock> Oram for DefaultOram<V> {
     type V = V;
+    type Error = OramError;
 
     fn block_capacity(&self) -> Result<Address, OramError> {
-        match &self.0 {
+        match &self.backend {
             DefaultOramBackend::Path(p) => p.block_capacity(),
             DefaultOramBackend::Linear(l) => l.block_capacity(),
         }
     }
 
-    fn access<R: rand::RngCore + CryptoRng, F: Fn(&Self::V) -> Self::V>(
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
         &mut self,
         index: Address,
         callback: F,
         rng: &mut R,
     ) -> Result<Self::V, OramError> {
-        match &mut self.0 {
+        match &mut self.backend {
             DefaultOramBackend::Path(p) => p.access(index, callback, rng),
             DefaultOramBackend::Linear(l) => l.access(index, callback, rng),
         }
     }
+
+    fn write<R: OramRng>(
+        &mut self,
+        index: Address,
+        new_value: Self::V,
+        rng: &mut R,
+    ) -> Result<Self::V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not,
+        // which an out-of-bounds error would reveal anyway.
+        let capacity = self.block_capacity()?;
+        let grown_capacity = capacity.saturating_mul(2).max(1);
+        if self.auto_grow & (index >= capacity) & (index < grown_capacity) {
+            self.resize(grown_capacity, rng)?;
+        }
+
+        let callback = |_: &Self::V| new_value;
+        self.access(index, callback, rng)
+    }
 }
 
 impl<V: OramBlock> DefaultOram<V> {
@@ -123,30 +470,268 @@ impl<V: OramBlock> DefaultOram<V> {
     /// # Errors
     ///
     /// If `block_capacity` is not a power of two, returns an `InvalidConfigurationError`.
-    pub fn new<R: Rng + CryptoRng>(
+    pub fn new<R: OramRng>(block_capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        Self::new_with_fill_value(block_capacity, V::default(), rng)
+    }
+
+    /// Returns a new ORAM mapping addresses `0 <= address < block_capacity` to `fill_value`.
+    ///
+    /// # Errors
+    ///
+    /// If `block_capacity` is not a power of two, returns an `InvalidConfigurationError`.
+    pub fn new_with_fill_value<R: OramRng>(
         block_capacity: Address,
+        fill_value: V,
         rng: &mut R,
     ) -> Result<Self, OramError> {
+        Ok(Self {
+            backend: Self::new_backend(block_capacity, fill_value, rng)?,
+            auto_grow: false,
+            fill_value,
+        })
+    }
+
+    fn new_backend<R: OramRng>(
+        block_capacity: Address,
+        fill_value: V,
+        rng: &mut R,
+    ) -> Result<DefaultOramBackend<V>, OramError> {
         if block_capacity < LINEAR_TIME_ORAM_CUTOFF {
-            Ok(Self(DefaultOramBackend::Linear(LinearTimeOram::new(
-                block_capacity,
-            )?)))
+            Ok(DefaultOramBackend::Linear(
+                LinearTimeOram::new_with_fill_value(block_capacity, fill_value)?,
+            ))
         } else {
-            Ok(Self(DefaultOramBackend::Path(PathOram::<
-                V,
-                DEFAULT_BLOCKS_PER_BUCKET,
-                DEFAULT_POSITIONS_PER_BLOCK,
-            >::new_with_parameters(
-                block_capacity,
-                rng,
-                DEFAULT_STASH_OVERFLOW_SIZE,
-                DEFAULT_RECURSION_CUTOFF,
-            )?)))
+            Ok(DefaultOramBackend::Path(Box::new(
+                PathOram::new_with_fill_value_and_parameters(
+                    block_capacity,
+                    fill_value,
+                    rng,
+                    DEFAULT_STASH_OVERFLOW_SIZE,
+                    DEFAULT_RECURSION_CUTOFF,
+                )?,
+            )))
+        }
+    }
+
+    /// Enables or disables automatic growth (disabled by default).
+    ///
+    /// When enabled, a `write` to an address `block_capacity <= address < 2 * block_capacity`
+    /// first doubles the capacity of the ORAM using [`DefaultOram::resize`], instead of returning
+    /// an `AddressOutOfBoundsError`. This is intended for append-style workloads.
+    /// Growth reveals that the written address was out of bounds,
+    /// which is the same information revealed by the error it replaces.
+    pub fn set_auto_grow(&mut self, enabled: bool) {
+        audit::record(
+            AuditEventKind::ParameterOverride,
+            format!("DefaultOram auto-grow set to {enabled}."),
+        );
+        self.auto_grow = enabled;
+    }
+
+    /// Returns a read-only, point-in-time view of this ORAM. See [`PathOram::snapshot`].
+    pub fn snapshot(&self) -> OramSnapshot<Self> {
+        OramSnapshot::new(self.clone())
+    }
+
+    /// Changes the capacity of the ORAM to `new_capacity`, preserving the values stored at
+    /// addresses `0 <= address < min(block_capacity, new_capacity)`.
+    /// Addresses beyond the old capacity map to the fill value the ORAM was constructed with.
+    ///
+    /// Contents are migrated by reading every address of the old ORAM and writing it into a new one,
+    /// so the access pattern depends only on the old and new capacities.
+    /// The new backend is chosen exactly as in [`DefaultOram::new`], so an ORAM that grows past
+    /// the linear-time cutoff is transparently promoted to a `PathOram` (and demoted when it shrinks below it).
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`DefaultOram::new`].
+    pub fn resize<R: OramRng>(
+        &mut self,
+        new_capacity: Address,
+        rng: &mut R,
+    ) -> Result<(), OramError> {
+        let old_capacity = self.block_capacity()?;
+        log::info!(
+            "DefaultOram::resize(old_capacity = {}, new_capacity = {})",
+            old_capacity,
+            new_capacity
+        );
+
+        let mut backend = Self::new_backend(new_capacity, self.fill_value, rng)?;
+
+        for address in 0..old_capacity.min(new_capacity) {
+            let value = self.read(address, rng)?;
+            match &mut backend {
+                DefaultOramBackend::Path(p) => p.write(address, value, rng)?,
+                DefaultOramBackend::Linear(l) => l.write(address, value, rng)?,
+            };
+        }
+
+        self.backend = backend;
+        Ok(())
+    }
+
+    /// Performs `num_evictions` extra evictions to drain the stash toward empty.
+    /// See [`PathOram::flush_evictions`]. Does nothing for ORAMs below the linear-time cutoff, which have no stash.
+    pub fn flush_evictions(&mut self, num_evictions: u64) -> Result<(), OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.flush_evictions(num_evictions),
+            DefaultOramBackend::Linear(_) => Ok(()),
+        }
+    }
+
+    /// Obliviously moves the value stored at every address `a` to address `permutation[a]`.
+    /// See [`PathOram::permute`].
+    pub fn permute(&mut self, permutation: &[Address]) -> Result<(), OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.permute(permutation),
+            DefaultOramBackend::Linear(l) => l.permute(permutation),
+        }
+    }
+
+    /// Reconstructs the tree with fresh positions and drains the stash. See [`PathOram::rebuild`].
+    /// Does nothing for ORAMs below the linear-time cutoff, which have no tree.
+    pub fn rebuild<R: OramRng>(&mut self, rng: &mut R) -> Result<(), OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.rebuild(rng),
+            DefaultOramBackend::Linear(_) => Ok(()),
+        }
+    }
+
+    /// Checks the structural invariants of the ORAM. See [`PathOram::verify`].
+    /// For small ORAMs backed by a linear scan, which have no tree, returns an empty report.
+    pub fn verify<R: OramRng>(&mut self, rng: &mut R) -> Result<VerificationReport, OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.verify(rng),
+            DefaultOramBackend::Linear(_) => Ok(VerificationReport::default()),
+        }
+    }
+
+    /// Performs an access as [`Oram::access`], and additionally reports degraded conditions.
+    /// See [`PathOram::access_checked`]. For small ORAMs backed by a linear scan, no flags are ever set.
+    pub fn access_checked<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<AccessOutcome<V>, OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.access_checked(index, callback, rng),
+            DefaultOramBackend::Linear(l) => {
+                Ok(AccessOutcome::new(l.access(index, callback, rng)?))
+            }
+        }
+    }
+
+    /// Returns a breakdown of the memory used by the ORAM. See [`PathOram::memory_usage`].
+    pub fn memory_usage(&self) -> Result<MemoryUsage, OramError> {
+        match &self.backend {
+            DefaultOramBackend::Path(p) => p.memory_usage(),
+            DefaultOramBackend::Linear(l) => Ok(l.memory_usage()),
+        }
+    }
+
+    /// Compacts the ORAM and shrinks its capacity to the smallest power of two (and at least 2)
+    /// that holds every value other than the fill value, releasing the memory used by the rest.
+    ///
+    /// Live values (those other than the fill value) are relocated to addresses `0, 1, ...`,
+    /// preserving their relative order, so holes left by deletions do not pin the capacity.
+    /// Returns the remapping: the value previously stored at address `a` is now stored at `remapping[a]`.
+    /// Entries for addresses that held the fill value carry no meaning.
+    /// If no smaller capacity is possible, the values are still compacted.
+    ///
+    /// Liveness is determined by obliviously scanning the entire ORAM, and every value is then written
+    /// to its new address through the ORAM, so the only information leaked is the resulting capacity.
+    pub fn shrink_to_fit<R: OramRng>(&mut self, rng: &mut R) -> Result<Vec<Address>, OramError>
+    where
+        V: ConstantTimeEq,
+    {
+        let capacity = self.block_capacity()?;
+
+        let mut values = Vec::with_capacity(usize::try_from(capacity)?);
+        let mut live_count: Address = 0;
+        for address in 0..capacity {
+            let value = self.read(address, rng)?;
+            let is_live = !value.ct_eq(&self.fill_value);
+            live_count.conditional_assign(&(live_count + 1), is_live);
+            values.push((value, is_live));
+        }
+
+        // Live values keep their relative order at the front; the rest follow in order.
+        let mut remapping = Vec::with_capacity(values.len());
+        let mut next_live: Address = 0;
+        let mut next_dead: Address = live_count;
+        for (_, is_live) in &values {
+            remapping.push(Address::conditional_select(
+                &next_dead, &next_live, *is_live,
+            ));
+            next_live.conditional_assign(&(next_live + 1), *is_live);
+            next_dead.conditional_assign(&(next_dead + 1), !*is_live);
+        }
+        for ((value, _), &new_address) in values.into_iter().zip(&remapping) {
+            self.write(new_address, value, rng)?;
+        }
+
+        let new_capacity = live_count.next_power_of_two().max(2);
+        if new_capacity < capacity {
+            self.resize(new_capacity, rng)?;
+        }
+        Ok(remapping)
+    }
+}
+
+impl<const B: BlockSize> DefaultOram<BlockValue<B>> {
+    /// Splits `bytes` into blocks of `B` bytes and writes them to addresses `0, 1, ...` in order.
+    /// If `pad` is true, the last block is padded with zeros; otherwise,
+    /// the length of `bytes` must be a multiple of `B`.
+    ///
+    /// The access pattern reveals only the number of blocks written.
+    ///
+    /// # Errors
+    ///
+    /// If `B` is zero, or if `pad` is false and the length of `bytes` is not a multiple of `B`,
+    /// returns an `InvalidConfigurationError`. If `bytes` does not fit in the ORAM,
+    /// returns an `AddressOutOfBoundsError` without writing anything.
+    pub fn load_bytes<R: OramRng>(
+        &mut self,
+        bytes: &[u8],
+        pad: bool,
+        rng: &mut R,
+    ) -> Result<(), OramError> {
+        if B == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Block size".to_string(),
+                parameter_value: B.to_string(),
+            });
+        }
+        if !pad && (bytes.len() % B != 0) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Byte length".to_string(),
+                parameter_value: bytes.len().to_string(),
+            });
+        }
+
+        let num_blocks = Address::try_from(bytes.len().div_ceil(B))?;
+        let capacity = self.block_capacity()?;
+        if num_blocks > capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: num_blocks - 1,
+                capacity,
+            });
+        }
+
+        for (address, chunk) in (0..).zip(bytes.chunks(B)) {
+            let mut block = BlockValue::<B>::default();
+            block.data[..chunk.len()].copy_from_slice(chunk);
+            self.write(address, block, rng)?;
         }
+        Ok(())
     }
 }
 
-impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> PathOram<V, Z, AB> {
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOram<V, Z, AB, S, M>
+{
     /// Returns a new `PathOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
     /// with a stash overflow size of `overflow_size` blocks, and a recursion cutoff of `recursion_cutoff`.
     /// (See [`PathOram`]) for a description of these parameters).
@@ -158,14 +743,64 @@ impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> PathOram<V, Z, AB>
     /// - `block_capacity` is 0, 1, or is not a power of two.
     /// - `AB` is 0, 1, or is not a power of two.
     /// - `Z` is 0 or 1.
+    /// - `block_capacity` is greater than `2 << MAXIMUM_TREE_HEIGHT`.
     /// - `recursion_cutoff` is 0.
     ///
     /// If `block_capacity` is not a power of two, returns an `InvalidConfigurationError`.
-    pub fn new_with_parameters<R: Rng + CryptoRng>(
+    /// If the memory of the ORAM cannot be allocated, returns an `AllocationError`.
+    pub fn new_with_parameters<R: OramRng>(
+        block_capacity: Address,
+        rng: &mut R,
+        overflow_size: StashSize,
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<Self, OramError> {
+        Self::new_with_fill_value_and_parameters(
+            block_capacity,
+            V::default(),
+            rng,
+            overflow_size,
+            recursion_cutoff,
+        )
+    }
+
+    /// Returns a new `PathOram` mapping addresses `0 <= address < block_capacity` to `fill_value`,
+    /// with a stash overflow size of `overflow_size` blocks, and a recursion cutoff of `recursion_cutoff`.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`PathOram::new_with_parameters`].
+    pub fn new_with_fill_value_and_parameters<R: OramRng>(
+        block_capacity: Address,
+        fill_value: V,
+        rng: &mut R,
+        overflow_size: StashSize,
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<Self, OramError> {
+        Self::new_with_stash_growth(
+            block_capacity,
+            fill_value,
+            rng,
+            overflow_size,
+            recursion_cutoff,
+            StashGrowth::default(),
+        )
+    }
+
+    /// Returns a new `PathOram` mapping addresses `0 <= address < block_capacity` to `fill_value`,
+    /// with a stash overflow size of `overflow_size` blocks, a recursion cutoff of `recursion_cutoff`,
+    /// and stashes (including those of the recursive position map ORAMs) that grow on overflow according to `stash_growth`.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`PathOram::new_with_parameters`],
+    /// and returns an `InvalidConfigurationError` if `stash_growth` has an increment of 0.
+    pub fn new_with_stash_growth<R: OramRng>(
         block_capacity: Address,
+        fill_value: V,
         rng: &mut R,
         overflow_size: StashSize,
         recursion_cutoff: RecursionCutoff,
+        stash_growth: StashGrowth,
     ) -> Result<Self, OramError> {
         log::info!("PathOram::new(capacity = {})", block_capacity,);
 
@@ -193,32 +828,117 @@ impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> PathOram<V, Z, AB>
         let number_of_nodes = block_capacity;
 
         let height: u64 = (block_capacity.ilog2() - 1).into();
+        if height > MAXIMUM_TREE_HEIGHT {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
 
         let path_size = u64::try_from(Z)? * (height + 1);
-        let stash = ObliviousStash::new(path_size, overflow_size)?;
+        let stash = S::new(path_size, overflow_size, stash_growth)?;
 
         // physical_memory holds `block_capacity` buckets, each storing up to Z blocks.
         // The number of leaves is `block_capacity` / 2, which the original Path ORAM paper's experiments
         // found was sufficient to keep the stash size small with high probability.
-        let mut physical_memory = Vec::new();
-        physical_memory.resize(usize::try_from(number_of_nodes)?, Bucket::<V, Z>::default());
+        let mut physical_memory = try_filled_vec(number_of_nodes, Bucket::<V, Z>::default())?;
 
-        // The rest of this function initializes the logical memory to contain default values at every address.
+        // The rest of this function initializes the logical memory to contain `fill_value` at every address.
         // This is done by (1) initializing the position map with fresh random leaf identifiers,
-        // and (2) writing blocks to the physical memory with the appropriate positions, and default values.
-        let mut position_map =
-            PositionMap::new(block_capacity, rng, overflow_size, recursion_cutoff)?;
+        // and (2) writing blocks to the physical memory with the appropriate positions, and fill values.
+        let mut position_map = M::new(
+            block_capacity,
+            rng,
+            overflow_size,
+            recursion_cutoff,
+            stash_growth,
+        )?;
+
+        let (slot_indices_to_addresses, _) =
+            Self::assign_leaves(block_capacity, height, &mut position_map, rng)?;
+        Self::fill_leaves(
+            &mut physical_memory,
+            height,
+            &slot_indices_to_addresses,
+            |_| fill_value,
+        )?;
+
+        Ok(Self {
+            physical_memory: Arc::new(PhysicalTree::from_buckets(
+                physical_memory,
+                BucketLayout::default(),
+            )),
+            stash,
+            position_map,
+            height,
+            eviction_counter: 0,
+            eviction_strategy: Box::new(PathEviction),
+            eviction_watermark: None,
+            watermark_evictions: 0,
+            poisoned: false,
+            epoch: 0,
+            #[cfg(feature = "diagnostics")]
+            leaf_histogram: LeafHistogram::new(1 << height),
+            #[cfg(feature = "diagnostics")]
+            level_stats: LevelStats::new(height),
+            #[cfg(feature = "profiling")]
+            profiler: Profiler::default(),
+            #[cfg(feature = "test-vectors")]
+            last_leaf: 0,
+        })
+    }
 
+    /// Assigns every address to one of the two leading slots of a uniformly random leaf bucket,
+    /// such that each leaf bucket receives exactly two addresses, and writes the resulting positions
+    /// to `position_map`. Returns the address assigned to each slot, in order of leaves,
+    /// and the slot assigned to each address.
+    fn assign_leaves<R: OramRng>(
+        block_capacity: Address,
+        height: TreeHeight,
+        position_map: &mut M,
+        rng: &mut R,
+    ) -> Result<(Vec<usize>, Vec<u64>), OramError> {
         let slot_indices_to_addresses =
             random_permutation_of_0_through_n_exclusive(block_capacity, rng);
         let addresses_to_slot_indices = invert_permutation_oblivious(&slot_indices_to_addresses)?;
         let slot_indices_to_addresses = to_usize_vec(slot_indices_to_addresses)?;
-        let mut addresses_to_slot_indices = to_usize_vec(addresses_to_slot_indices)?;
+        let mut padded_addresses_to_slot_indices = to_usize_vec(addresses_to_slot_indices.clone())?;
 
         let first_leaf_index: usize = 2u64.pow(height.try_into()?).try_into()?;
-        let last_leaf_index = (2 * first_leaf_index) - 1;
+        // The address block size might not divide the block capacity.
+        // If it doesn't, we will have one block that contains dummy values.
+        let ab_address: Address = AB.try_into()?;
+        let mut num_blocks = block_capacity / ab_address;
+        if block_capacity % ab_address > 0 {
+            num_blocks += 1;
+            padded_addresses_to_slot_indices.resize((block_capacity + ab_address).try_into()?, 0);
+        }
 
-        // Iterate over leaves, writing 2 blocks into each leaf bucket with random(ly permuted) addresses and default values.
+        for block_index in 0..num_blocks {
+            let first_address = block_index * ab_address;
+            let addresses = Vec::from_iter(first_address..first_address + ab_address);
+            let mut positions = Vec::with_capacity(AB);
+            for address in &addresses {
+                let slot_index = padded_addresses_to_slot_indices[usize::try_from(*address)?];
+                positions.push((first_leaf_index + slot_index / 2).try_into()?);
+            }
+            position_map.write_positions_batch(&addresses, &positions, rng)?;
+        }
+
+        Ok((slot_indices_to_addresses, addresses_to_slot_indices))
+    }
+
+    /// Writes a block to each of the two leading slots of every leaf bucket of `physical_memory`,
+    /// with the address given by `slot_indices_to_addresses` and the value `slot_value(slot_index)`.
+    fn fill_leaves<F: Fn(usize) -> V>(
+        physical_memory: &mut [Bucket<V, Z>],
+        height: TreeHeight,
+        slot_indices_to_addresses: &[usize],
+        slot_value: F,
+    ) -> Result<(), OramError> {
+        let first_leaf_index: usize = 2u64.pow(height.try_into()?).try_into()?;
+        let last_leaf_index = (2 * first_leaf_index) - 1;
+        // Iterate over leaves, writing 2 blocks into each leaf bucket with random(ly permuted) addresses and fill values.
         let addresses_per_leaf = 2;
         for (leaf_index, tree_bucket) in physical_memory
             .iter_mut()
@@ -229,59 +949,469 @@ impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> PathOram<V, Z, AB>
             for slot_index in 0..addresses_per_leaf {
                 let address_index = (leaf_index - first_leaf_index) * 2 + slot_index;
                 tree_bucket.blocks[slot_index] = PathOramBlock::<V> {
-                    value: V::default(),
+                    value: slot_value(address_index),
                     address: slot_indices_to_addresses[address_index].try_into()?,
                     position: leaf_index.try_into()?,
                 };
             }
         }
 
-        // The address block size might not divide the block capacity.
-        // If it doesn't, we will have one block that contains dummy values.
-        let ab_address: Address = AB.try_into()?;
-        let mut num_blocks = block_capacity / ab_address;
-        if block_capacity % ab_address > 0 {
-            num_blocks += 1;
-            addresses_to_slot_indices.resize((block_capacity + ab_address).try_into()?, 0);
-        }
+        Ok(())
+    }
 
-        for block_index in 0..num_blocks {
-            let mut data = [0; AB];
-            for i in 0..AB {
-                let offset: usize = (block_index * ab_address).try_into()?;
-                data[i] =
-                    (first_leaf_index + addresses_to_slot_indices[offset + i] / 2).try_into()?;
-            }
-            let block = PositionBlock::<AB> { data };
-            position_map.write_position_block(block_index * ab_address, block, rng)?;
-        }
+    /// Returns a read-only, point-in-time view of this ORAM, which can be cloned to give each reader
+    /// its own stash and position map.
+    ///
+    /// The tree is shared copy-on-write between this ORAM and its snapshots, so taking a snapshot
+    /// only copies the stash and the position map. The first subsequent access to either this ORAM
+    /// or a snapshot copies the tree (the cost of a single linear scan); later accesses run at full speed.
+    pub fn snapshot(&self) -> OramSnapshot<Self> {
+        OramSnapshot::new(self.clone())
+    }
 
-        Ok(Self {
-            physical_memory,
-            stash,
-            position_map,
-            height,
+    /// Splits this ORAM into its client-side state (position map, stash, and eviction schedule) and its tree,
+    /// so that they can be held separately (for example, to serve split-phase accesses with
+    /// [`PathOramClientState::begin_access`] against a tree held elsewhere) and later recombined
+    /// with [`PathOram::from_parts`].
+    ///
+    /// Each split starts a new epoch, with which both parts are stamped: the epoch of an ORAM
+    /// reconstructed from parts is incremented again when it is next split.
+    /// This detects, for example, a clone of the client state from an earlier split being combined
+    /// with the current tree, or the tree of one ORAM being combined with the client state of another
+    /// that has been split a different number of times.
+    pub fn into_parts(self) -> (PathOramClientState<V, Z, AB, S, M>, PathOramTree<V, Z>) {
+        let epoch = self.epoch.wrapping_add(1);
+        let client_state = PathOramClientState {
+            stash: self.stash,
+            position_map: self.position_map,
+            height: self.height,
+            eviction_counter: self.eviction_counter,
+            eviction_strategy: self.eviction_strategy,
+            eviction_watermark: self.eviction_watermark,
+            watermark_evictions: self.watermark_evictions,
+            poisoned: self.poisoned,
+            epoch,
+            _value: PhantomData,
+        };
+        let tree = PathOramTree {
+            buckets: self.physical_memory,
+            epoch,
+        };
+        (client_state, tree)
+    }
+
+    /// Reconstructs an ORAM from the parts returned by [`PathOram::into_parts`].
+    ///
+    /// The client state must be the one split off together with `tree`: combining a client state
+    /// with an older or newer version of the tree returns incorrect values. In particular,
+    /// this function cannot detect a tree that was rolled back by an untrusted storage provider.
+    ///
+    /// # Errors
+    ///
+    /// If the number of buckets in `tree` does not match the height recorded in `client_state`,
+    /// or the two parts have different epochs, returns an `InvalidConfigurationError`.
+    pub fn from_parts(
+        client_state: PathOramClientState<V, Z, AB, S, M>,
+        tree: PathOramTree<V, Z>,
+    ) -> Result<Self, OramError> {
+        let expected_buckets = 2u64.checked_shl(u32::try_from(client_state.height)?);
+        if expected_buckets != Some(u64::try_from(tree.num_buckets())?) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of tree buckets".to_string(),
+                parameter_value: tree.num_buckets().to_string(),
+            });
+        }
+
+        if tree.epoch != client_state.epoch {
+            audit::record(
+                AuditEventKind::IntegrityFailure,
+                format!(
+                    "PathOram tree epoch {} does not match client state epoch {}.",
+                    tree.epoch, client_state.epoch
+                ),
+            );
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Tree epoch".to_string(),
+                parameter_value: tree.epoch.to_string(),
+            });
+        }
+
+        Ok(Self {
+            physical_memory: tree.buckets,
+            stash: client_state.stash,
+            position_map: client_state.position_map,
+            height: client_state.height,
+            eviction_counter: client_state.eviction_counter,
+            eviction_strategy: client_state.eviction_strategy,
+            eviction_watermark: client_state.eviction_watermark,
+            watermark_evictions: client_state.watermark_evictions,
+            poisoned: client_state.poisoned,
+            epoch: client_state.epoch,
+            #[cfg(feature = "diagnostics")]
+            leaf_histogram: LeafHistogram::new(1 << client_state.height),
+            #[cfg(feature = "diagnostics")]
+            level_stats: LevelStats::new(client_state.height),
+            #[cfg(feature = "profiling")]
+            profiler: Profiler::default(),
+            #[cfg(feature = "test-vectors")]
+            last_leaf: 0,
         })
     }
 
-    #[cfg(test)]
-    pub(crate) fn stash_occupancy(&self) -> StashSize {
-        self.stash.occupancy()
+    /// Sets the number of accesses `A` between evictions (1 by default).
+    ///
+    /// By default, every access evicts blocks from the stash back into the path it just read,
+    /// which requires an oblivious sort of the stash. When `eviction_period` is greater than 1,
+    /// accesses instead only remove the target block from the path it lies on and move it into the stash,
+    /// and every `eviction_period`-th access additionally performs an eviction along the next path
+    /// in a deterministic schedule (that of Ring ORAM). This trades a larger stash (which grows by up to `A` blocks
+    /// between evictions) for skipping the oblivious sort on most accesses.
+    /// The eviction schedule depends only on the number of accesses, so obliviousness is preserved.
+    ///
+    /// This does not reduce the amount of data read and written: to hide which bucket held the target block,
+    /// an access that does not evict still reads and writes back every bucket of its path,
+    /// and the scheduled evictions read and write additional paths.
+    ///
+    /// This is shorthand for [`PathOram::set_eviction_strategy`] with [`PathEviction`] if `eviction_period` is 1,
+    /// and with [`ScheduledEviction`] otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If `eviction_period` is 0, returns an `InvalidConfigurationError`.
+    pub fn set_eviction_period(&mut self, eviction_period: u64) -> Result<(), OramError> {
+        if eviction_period == 1 {
+            self.set_eviction_strategy(PathEviction)
+        } else {
+            self.set_eviction_strategy(ScheduledEviction::new(eviction_period)?)
+        }
     }
-}
 
-impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram for PathOram<V, Z, AB> {
-    type V = V;
+    /// Sets the strategy deciding when blocks are evicted from the stash (by default, [`PathEviction`]).
+    /// Any scheduled evictions owed by the current strategy are performed first.
+    ///
+    /// This setting applies to the top-level ORAM only; recursive position map ORAMs evict on every access.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error encountered while completing the current strategy's evictions.
+    pub fn set_eviction_strategy<E: EvictionStrategy + 'static>(
+        &mut self,
+        eviction_strategy: E,
+    ) -> Result<(), OramError> {
+        // Finish the current period, and discard the path slots,
+        // which the non-evicting access path assumes are empty.
+        for _ in 0..self.eviction_strategy.finish_period() {
+            self.evict_scheduled_path()?;
+        }
+        self.stash.clear_path_slots()?;
+
+        audit::record(
+            AuditEventKind::ParameterOverride,
+            format!("PathOram eviction strategy set to {eviction_strategy:?}."),
+        );
+        self.eviction_strategy = Box::new(eviction_strategy);
+        Ok(())
+    }
+
+    /// Sets the stash occupancy above which every access is followed by an additional scheduled eviction
+    /// (by default, `None`, so that no additional evictions are performed).
+    ///
+    /// Under adversarially skewed workloads, or with a [`ScheduledEviction`] strategy, the stash can grow
+    /// well beyond its expected size. With a watermark set, accesses keep evicting additional paths
+    /// (in the same reverse-lexicographic order as scheduled evictions) until the occupancy drops
+    /// to the watermark, bounding the probability that the stash overflows. To bound the cost of a single access,
+    /// at most [`MAXIMUM_WATERMARK_EVICTIONS`] additional paths are evicted after each access;
+    /// any remaining excess is evicted by the following accesses.
+    ///
+    /// ## Security
+    ///
+    /// The occupancy check is not constant-time: the number of paths touched by an access
+    /// reveals whether the stash occupancy exceeded the watermark, a leak similar to that of a stash overflow.
+    /// Set the watermark well above the expected occupancy, so that it is exceeded only rarely.
+    /// The watermark applies to [`Oram::access`], and not to [`PathOramClientState::finish_access`].
+    pub fn set_eviction_watermark(&mut self, eviction_watermark: Option<StashSize>) {
+        audit::record(
+            AuditEventKind::ParameterOverride,
+            format!("PathOram eviction watermark set to {eviction_watermark:?}."),
+        );
+        self.eviction_watermark = eviction_watermark;
+    }
+
+    /// Returns the stash occupancy above which accesses perform an additional eviction.
+    /// See [`PathOram::set_eviction_watermark`].
+    pub fn eviction_watermark(&self) -> Option<StashSize> {
+        self.eviction_watermark
+    }
+
+    /// Returns the number of additional evictions triggered by the eviction watermark so far.
+    /// See [`PathOram::set_eviction_watermark`].
+    pub fn watermark_evictions(&self) -> u64 {
+        self.watermark_evictions
+    }
+
+    /// Evicts blocks from the stash into the next path in the eviction schedule.
+    fn evict_scheduled_path(&mut self) -> Result<(), OramError> {
+        let position = reverse_lexicographic_leaf(self.eviction_counter, self.height);
+        self.eviction_counter = self.eviction_counter.wrapping_add(1);
+
+        #[cfg(feature = "profiling")]
+        let start = std::time::Instant::now();
+        self.stash.read_from_path(tree_storage!(self), position)?;
+        #[cfg(feature = "profiling")]
+        let start = self.record_phase(AccessPhase::PathRead, start);
+        self.stash.write_to_path(tree_storage!(self), position)?;
+        #[cfg(feature = "profiling")]
+        self.record_path_write(start);
+        self.stash.clear_path_slots()
+    }
+
+    /// Performs `num_evictions` extra evictions, draining the stash (and the stashes of the recursive
+    /// position map ORAMs) toward empty. Intended to be called at quiescent points,
+    /// such as the end of a batch of requests, to minimize stash occupancy entering the next batch.
+    ///
+    /// Evictions follow a deterministic schedule of paths (reverse-lexicographic order of leaves),
+    /// which visits every bucket of the tree once every `block_capacity / 2` evictions,
+    /// so the access pattern depends only on `num_evictions` and the number of previous scheduled evictions.
+    pub fn flush_evictions(&mut self, num_evictions: u64) -> Result<(), OramError> {
+        if self.poisoned {
+            return Err(OramError::PoisonedError);
+        }
+        let result = (0..num_evictions)
+            .try_for_each(|_| self.evict_scheduled_path())
+            .and_then(|_| self.position_map.flush_evictions(num_evictions));
+        self.poisoned |= is_poisoning(&result);
+        result
+    }
+
+    /// Returns true if a stash overflow (in this ORAM or its position map) aborted an access,
+    /// so that every later access fails. See [`StashGrowth::Never`].
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Obliviously sorts every block of the tree and the stash by address, so that the first `block_capacity`
+    /// blocks hold addresses `0..block_capacity` in order, followed by dummy blocks (which have the largest address).
+    /// Returns the sorted blocks, and the slots they were taken from, for [`PathOram::restore_blocks`].
+    fn blocks_by_address(&mut self) -> Result<(Vec<PathOramBlock<V>>, Vec<u64>), OramError> {
+        self.stash.clear_path_slots()?;
+        let tree = &self.physical_memory;
+        let mut blocks = Vec::with_capacity(tree.len() * Z + self.stash.blocks_mut().len());
+        for i in 0..tree.len() {
+            blocks.extend_from_slice(&tree.bucket(i).blocks);
+        }
+        blocks.extend_from_slice(self.stash.blocks_mut());
+
+        let mut slots: Vec<u64> = (0..u64::try_from(blocks.len())?).collect();
+        let mut addresses: Vec<Address> = blocks.iter().map(|block| block.address).collect();
+        bitonic_sort_by_keys(&mut slots, &mut addresses.clone());
+        bitonic_sort_by_keys(&mut blocks, &mut addresses);
+        Ok((blocks, slots))
+    }
+
+    /// Obliviously returns `blocks`, as returned by [`PathOram::blocks_by_address`], to their `slots`
+    /// in the tree and the stash.
+    fn restore_blocks(
+        &mut self,
+        mut blocks: Vec<PathOramBlock<V>>,
+        mut slots: Vec<u64>,
+    ) -> Result<(), OramError> {
+        bitonic_sort_by_keys(&mut blocks, &mut slots);
+        let tree = Arc::make_mut(&mut self.physical_memory);
+        for (i, bucket_blocks) in blocks.chunks_exact(Z).take(tree.len()).enumerate() {
+            let mut bucket = Bucket::<V, Z>::default();
+            bucket.blocks.copy_from_slice(bucket_blocks);
+            tree.set_bucket(i, &bucket);
+        }
+        let stash_blocks = self.stash.blocks_mut();
+        let num_tree_blocks = blocks.len() - stash_blocks.len();
+        stash_blocks.copy_from_slice(&blocks[num_tree_blocks..]);
+        Ok(())
+    }
+
+    /// Converts this ORAM into an ORAM with the same contents but different parameters: bucket size `Z2`,
+    /// position block size `AB2`, stash overflow size `overflow_size`, recursion cutoff `recursion_cutoff`,
+    /// and possibly a different stash and position map. The result can be annotated with its type, as in
+    /// `let migrated: PathOram<V, 2, 16> = oram.migrate_parameters(rng, overflow_size, recursion_cutoff)?`.
+    ///
+    /// This lets deployments re-tune an ORAM (for example, one reconstructed with [`PathOram::from_parts`])
+    /// without exporting its contents. The values are never exposed: the blocks of both ORAMs are obliviously sorted
+    /// by address and the values are copied across in order, with `O(N log^2 N)` work for capacity `N`.
+    /// The access pattern depends only on the capacity and the parameters.
+    /// The new ORAM uses the default eviction strategy and no eviction watermark.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`PathOram::new_with_parameters`] for the new parameters.
+    pub fn migrate_parameters<
+        const Z2: BucketSize,
+        const AB2: BlockSize,
+        S2: Stash<V>,
+        M2: PositionMap,
+        R: OramRng,
+    >(
+        mut self,
+        rng: &mut R,
+        overflow_size: StashSize,
+        recursion_cutoff: RecursionCutoff,
+    ) -> Result<PathOram<V, Z2, AB2, S2, M2>, OramError> {
+        let block_capacity = self.block_capacity()?;
+        log::info!(
+            "PathOram::migrate_parameters(capacity = {}, Z = {} -> {}, AB = {} -> {})",
+            block_capacity,
+            Z,
+            Z2,
+            AB,
+            AB2
+        );
+
+        let mut migrated = PathOram::<V, Z2, AB2, S2, M2>::new_with_parameters(
+            block_capacity,
+            rng,
+            overflow_size,
+            recursion_cutoff,
+        )?;
+        let (blocks, _) = self.blocks_by_address()?;
+        drop(self);
+        let (mut migrated_blocks, slots) = migrated.blocks_by_address()?;
+        let num_blocks = usize::try_from(block_capacity)?;
+        for (migrated_block, block) in migrated_blocks[..num_blocks].iter_mut().zip(&blocks) {
+            migrated_block.value = block.value;
+        }
+        drop(blocks);
+        migrated.restore_blocks(migrated_blocks, slots)?;
+        Ok(migrated)
+    }
+
+    /// Obliviously moves the value stored at every address `a` to address `permutation[a]`.
+    ///
+    /// The blocks of the tree and the stash are obliviously sorted by address, their values are obliviously sorted
+    /// by destination, and the blocks are sorted back into place, with `O(N log^2 N)` work
+    /// for an ORAM of capacity `N` with `Z` blocks per bucket. Neither the memory access pattern
+    /// nor the positions of the blocks depend on the permutation.
+    ///
+    /// # Errors
+    ///
+    /// If `permutation` is not a permutation of `0..block_capacity`, returns an `InvalidConfigurationError`
+    /// and leaves the ORAM unchanged.
+    pub fn permute(&mut self, permutation: &[Address]) -> Result<(), OramError> {
+        check_permutation(permutation, self.block_capacity()?)?;
+
+        let (mut blocks, slots) = self.blocks_by_address()?;
+        let mut values: Vec<V> = blocks[..permutation.len()]
+            .iter()
+            .map(|block| block.value)
+            .collect();
+        apply_permutation_oblivious(&mut values, permutation)?;
+        for (block, value) in blocks.iter_mut().zip(&values) {
+            block.value = *value;
+        }
+        self.restore_blocks(blocks, slots)?;
+        Ok(())
+    }
+
+    /// Reconstructs the tree from scratch: every address is assigned a fresh position in a freshly permuted
+    /// layout, exactly as in a newly constructed ORAM, and the stash is drained.
+    ///
+    /// The values never leave the ORAM. The blocks of the tree and the stash are obliviously sorted by address,
+    /// and their values are obliviously sorted into their new slots, with `O(N log^2 N)` work
+    /// for an ORAM of capacity `N`, plus one position map write per address block.
+    /// Neither the memory access pattern nor the new layout depends on the contents of the ORAM.
+    /// This is useful after repairing integrity failures, after changing parameters such as the eviction strategy,
+    /// or periodically over long uptimes, to return the stash to its expected size.
+    /// Any scheduled eviction period in progress is discarded. The stashes of the recursive position map ORAMs
+    /// are not drained.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the position map.
+    pub fn rebuild<R: OramRng>(&mut self, rng: &mut R) -> Result<(), OramError> {
+        let block_capacity = self.block_capacity()?;
+        log::info!("PathOram::rebuild(capacity = {})", block_capacity);
+
+        // Gather the values of every address, in order of addresses.
+        let (blocks, _) = self.blocks_by_address()?;
+        let mut values: Vec<V> = blocks[..usize::try_from(block_capacity)?]
+            .iter()
+            .map(|block| block.value)
+            .collect();
+        drop(blocks);
+
+        // Lay the values out as in `new_with_stash_growth`.
+        let (slot_indices_to_addresses, addresses_to_slot_indices) =
+            Self::assign_leaves(block_capacity, self.height, &mut self.position_map, rng)?;
+        apply_permutation_oblivious(&mut values, &addresses_to_slot_indices)?;
+        let mut physical_memory = try_filled_vec(block_capacity, Bucket::<V, Z>::default())?;
+        Self::fill_leaves(
+            &mut physical_memory,
+            self.height,
+            &slot_indices_to_addresses,
+            |slot_index| values[slot_index],
+        )?;
+
+        self.physical_memory = Arc::new(PhysicalTree::from_buckets(
+            physical_memory,
+            self.physical_memory.layout(),
+        ));
+        self.stash.clear();
+        self.eviction_strategy.finish_period();
+        Ok(())
+    }
 
-    // REVIEW NOTE: This function has not been modified.
-    fn access<R: Rng + CryptoRng, F: Fn(&V) -> V>(
+    /// Returns a breakdown of the memory used by the ORAM and every level of its recursive position map,
+    /// for capacity planning in memory-constrained environments.
+    pub fn memory_usage(&self) -> Result<MemoryUsage, OramError> {
+        let mut levels = vec![LevelMemoryUsage {
+            tree_bytes: self.physical_memory.memory_bytes(),
+            stash_bytes: self.stash.memory_bytes(),
+            scratch_bytes: self.stash.scratch_bytes(self.height)?,
+        }];
+        levels.extend(self.position_map.memory_usage()?);
+        Ok(MemoryUsage { levels })
+    }
+
+    /// Sets the layout of the tree in memory, rearranging the existing tree if the layout changes.
+    /// See [`BucketLayout`]. The layout does not affect the sequence of buckets accessed.
+    pub fn set_bucket_layout(&mut self, layout: BucketLayout) {
+        if layout != self.physical_memory.layout() {
+            self.physical_memory = Arc::new(PhysicalTree::from_buckets(
+                self.physical_memory.to_buckets(),
+                layout,
+            ));
+        }
+    }
+
+    /// Performs an access as [`Oram::access`], and additionally reports whether the stash grew during the access
+    /// or is close to growing. See [`AccessOutcome`].
+    ///
+    /// Computing the flags scans the stash in non-constant time, revealing its occupancy through timing.
+    pub fn access_checked<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<AccessOutcome<V>, OramError> {
+        let stash_capacity = self.total_stash_capacity();
+        let value = self.access(address, callback, rng)?;
+
+        let overflow_capacity = self.stash.overflow_capacity();
+        Ok(AccessOutcome {
+            value,
+            stash_grew: self.total_stash_capacity() > stash_capacity,
+            near_capacity: self.stash.occupancy() * 100
+                >= overflow_capacity * NEAR_CAPACITY_PERCENT,
+            integrity_repaired: false,
+        })
+    }
+
+    /// Performs an access as [`Oram::access`], without checking or updating whether this ORAM is poisoned.
+    fn access_unpoisoned<R: OramRng, F: Fn(&V) -> V>(
         &mut self,
         address: Address,
         callback: F,
         rng: &mut R,
     ) -> Result<V, OramError> {
         // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
-        if address > self.block_capacity()? {
+        if address >= self.block_capacity()? {
             return Err(OramError::AddressOutOfBoundsError {
                 attempted: address,
                 capacity: self.block_capacity()?,
@@ -291,22 +1421,432 @@ impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram for PathOram<V
         // Get the position of the target block (with address `address`),
         // and update that block's position map entry to a fresh random position
         let new_position = CompleteBinaryTreeIndex::random_leaf(self.height, rng)?;
+        #[cfg(feature = "profiling")]
+        let start = std::time::Instant::now();
         let position = self.position_map.write(address, new_position, rng)?;
+        #[cfg(feature = "profiling")]
+        let start = self.record_phase(AccessPhase::PositionMap, start);
 
         assert!(position.is_leaf(self.height));
 
-        self.stash
-            .read_from_path(&mut self.physical_memory, position)?;
+        #[cfg(feature = "diagnostics")]
+        self.leaf_histogram.record(position - (1 << self.height));
+        #[cfg(feature = "test-vectors")]
+        {
+            self.last_leaf = position;
+        }
 
-        // Scan the stash for the target block, read its value into `result`,
-        // and overwrite its position (and possibly its value).
-        let result = self.stash.access(address, new_position, callback);
+        let result = if self.eviction_strategy.evicts_accessed_path() {
+            self.stash.read_from_path(tree_storage!(self), position)?;
+            #[cfg(feature = "profiling")]
+            let start = self.record_phase(AccessPhase::PathRead, start);
 
-        // Evict blocks from the stash into the path that was just read,
-        // replacing them with dummy blocks.
-        self.stash
-            .write_to_path(&mut self.physical_memory, position)?;
+            // Scan the stash for the target block, read its value into `result`,
+            // and overwrite its position (and possibly its value).
+            let result = self.stash.access(address, new_position, callback);
+            #[cfg(feature = "profiling")]
+            let start = self.record_phase(AccessPhase::StashScan, start);
 
+            // Evict blocks from the stash into the path that was just read,
+            // replacing them with dummy blocks.
+            self.stash.write_to_path(tree_storage!(self), position)?;
+            #[cfg(feature = "profiling")]
+            self.record_path_write(start);
+            result
+        } else {
+            let result = self.stash.access_without_eviction(
+                tree_storage!(self),
+                position,
+                address,
+                new_position,
+                callback,
+            );
+            #[cfg(feature = "profiling")]
+            self.record_phase(AccessPhase::StashScan, start);
+            result
+        };
+        // Scheduled and watermark evictions only follow accesses that succeeded.
+        let result = result?;
+
+        for _ in 0..self.eviction_strategy.record_access() {
+            self.evict_scheduled_path()?;
+        }
+
+        // This operation is not constant-time, but only leaks whether the stash occupancy exceeded the watermark.
+        if let Some(eviction_watermark) = self.eviction_watermark {
+            for _ in 0..MAXIMUM_WATERMARK_EVICTIONS {
+                if self.stash.occupancy() <= eviction_watermark {
+                    break;
+                }
+                self.evict_scheduled_path()?;
+                self.watermark_evictions += 1;
+            }
+        }
+
+        #[cfg(feature = "debug-invariants")]
+        self.assert_invariants(rng)?;
+
+        Ok(result)
+    }
+
+    /// Returns the total overflow capacity of the stashes of this ORAM and of its recursive position map ORAMs.
+    pub(crate) fn total_stash_capacity(&self) -> StashSize {
+        self.stash.overflow_capacity() + self.position_map.stash_capacity()
+    }
+
+    /// Returns true if the addresses and positions of the blocks in the tree are stored in 32 bits each,
+    /// rather than 64. This is selected automatically for the array-of-structs layout
+    /// when the block capacity is below `u32::MAX`.
+    pub fn has_packed_metadata(&self) -> bool {
+        self.physical_memory.is_packed()
+    }
+
+    /// Returns the layout of the tree in memory. See [`PathOram::set_bucket_layout`].
+    pub fn bucket_layout(&self) -> BucketLayout {
+        self.physical_memory.layout()
+    }
+
+    /// Returns the eviction strategy. See [`PathOram::set_eviction_strategy`].
+    pub fn eviction_strategy(&self) -> &dyn EvictionStrategy {
+        self.eviction_strategy.as_ref()
+    }
+
+    pub(crate) fn stash_occupancy(&self) -> StashSize {
+        self.stash.occupancy()
+    }
+
+    /// Returns the number of real (non-dummy) blocks in the tree and the overflow area of the stash.
+    /// (Between accesses, the path slots of the stash hold only stale copies of blocks written to the tree.)
+    pub(crate) fn real_block_count(&self) -> Address {
+        self.physical_memory.real_block_count() as Address + self.stash.occupancy()
+    }
+
+    /// Checks that every address has exactly one real block, in the tree or the stash,
+    /// that each block in the tree lies on the path to its position, and that the position map agrees with the blocks,
+    /// and returns a report of every violation found. This is useful after crashes, after reconstructing an ORAM
+    /// from parts held by untrusted storage, or when storage faults are suspected.
+    ///
+    /// Every bucket of the tree and every entry of the stash is read once, in a fixed order,
+    /// and the position map is read once for every address, in order of addresses,
+    /// so the accesses to the tree and the position map do not depend on the stored data.
+    /// The bookkeeping of the check itself is not constant-time, and the report describes the stored data.
+    /// The recursive position map ORAMs are accessed normally, but not themselves verified.
+    /// If any violation is found, an integrity failure is recorded in the audit log.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the position map.
+    pub fn verify<R: OramRng>(&mut self, rng: &mut R) -> Result<VerificationReport, OramError> {
+        let capacity = self.block_capacity()?;
+        let mut report = VerificationReport::default();
+        let mut positions = vec![None; usize::try_from(capacity)?];
+        let height = self.height;
+        let mut record = |block: &PathOramBlock<V>, report: &mut VerificationReport| {
+            if !block.position.is_leaf(height) {
+                report.inconsistencies.push(Inconsistency::InvalidPosition {
+                    address: block.address,
+                    position: block.position,
+                });
+            }
+            match usize::try_from(block.address)
+                .ok()
+                .and_then(|address| positions.get_mut(address))
+            {
+                None => report
+                    .inconsistencies
+                    .push(Inconsistency::AddressOutOfRange {
+                        address: block.address,
+                    }),
+                Some(Some(_)) => report.inconsistencies.push(Inconsistency::DuplicateBlock {
+                    address: block.address,
+                }),
+                Some(entry) => *entry = Some(block.position),
+            }
+        };
+
+        for index in 1..self.physical_memory.len() {
+            let depth = u64::from(index.ilog2());
+            let bucket = TreeIndex::try_from(index)?;
+            for block in self.physical_memory.bucket(index).blocks.iter() {
+                if block.is_dummy() {
+                    continue;
+                }
+                report.tree_blocks += 1;
+                record(block, &mut report);
+                if block.position.checked_shr(u32::try_from(height - depth)?) != Some(bucket) {
+                    report.inconsistencies.push(Inconsistency::MisplacedBlock {
+                        address: block.address,
+                        position: block.position,
+                        bucket,
+                    });
+                }
+            }
+            report.buckets_checked += 1;
+        }
+        let mut stash = self.stash.clone();
+        stash.clear_path_slots()?;
+        for block in stash.blocks_mut().iter() {
+            if !block.is_dummy() {
+                report.stash_blocks += 1;
+                record(block, &mut report);
+            }
+            report.stash_entries_checked += 1;
+        }
+
+        for (address, position) in (0..capacity).zip(positions) {
+            let position_map_position = self.position_map.read(address, rng)?;
+            match position {
+                None => report
+                    .inconsistencies
+                    .push(Inconsistency::MissingBlock { address }),
+                Some(block_position) if block_position != position_map_position => report
+                    .inconsistencies
+                    .push(Inconsistency::PositionMismatch {
+                        address,
+                        block_position,
+                        position_map_position,
+                    }),
+                Some(_) => {}
+            }
+        }
+
+        if !report.is_consistent() {
+            audit::record(
+                AuditEventKind::IntegrityFailure,
+                format!(
+                    "PathOram verification found {} inconsistencies; the first is: {}.",
+                    report.inconsistencies.len(),
+                    report.inconsistencies[0]
+                ),
+            );
+        }
+        Ok(report)
+    }
+
+    /// Panics unless [`PathOram::verify`] finds no violation.
+    #[cfg(any(test, feature = "fuzzing", feature = "debug-invariants"))]
+    pub(crate) fn assert_invariants<R: OramRng>(&mut self, rng: &mut R) -> Result<(), OramError> {
+        let report = self.verify(rng)?;
+        if let Some(inconsistency) = report.inconsistencies.first() {
+            panic!("{inconsistency}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "test-vectors")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOram<V, Z, AB, S, M>
+{
+    /// Returns the leaf whose path was read (and evicted to) by the most recent access.
+    pub(crate) fn last_leaf(&self) -> crate::utils::TreeIndex {
+        self.last_leaf
+    }
+
+    /// Returns the buckets of the tree, indexed by `TreeIndex`. Bucket 0 is unused.
+    pub(crate) fn buckets(&self) -> Vec<Bucket<V, Z>> {
+        self.physical_memory.to_buckets()
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOram<V, Z, AB, S, M>
+{
+    /// Performs an access exactly like [`Oram::access`], additionally returning statistics about the access.
+    pub fn access_with_stats<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<(V, AccessStats), OramError> {
+        let value = self.access(address, callback, rng)?;
+        let stats = AccessStats {
+            stash_occupancy: self.stash.occupancy(),
+            ..self.access_cost_stats()?
+        };
+        Ok((value, stats))
+    }
+
+    /// Returns the distribution of the leaves whose paths were read by accesses to this ORAM
+    /// (not including scheduled evictions), for checking that leaves are chosen uniformly.
+    pub fn leaf_histogram(&self) -> &LeafHistogram {
+        &self.leaf_histogram
+    }
+
+    /// Clears the distribution returned by [`PathOram::leaf_histogram`].
+    pub fn reset_leaf_histogram(&mut self) {
+        self.leaf_histogram.reset();
+    }
+
+    /// Returns the physical reads and writes of the tree of this ORAM, by depth.
+    /// Accesses to the trees of recursive position map ORAMs are not included.
+    pub fn level_stats(&self) -> &LevelStats {
+        &self.level_stats
+    }
+
+    /// Clears the counts returned by [`PathOram::level_stats`].
+    pub fn reset_level_stats(&mut self) {
+        self.level_stats.reset();
+    }
+
+    /// Returns the recursion depth and physical operation counts of a single access,
+    /// which do not depend on the address accessed.
+    pub(crate) fn access_cost_stats(&self) -> Result<AccessStats, OramError> {
+        let mut stats = self.position_map.access_stats()?;
+        stats.physical_reads += self.height + 1;
+        stats.physical_writes += self.height + 1;
+        Ok(stats)
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOram<V, Z, AB, S, M>
+{
+    /// Returns the durations of the phases of the accesses to this ORAM (and of its scheduled evictions).
+    /// The phases of accesses to recursive position map ORAMs are included in [`AccessPhase::PositionMap`].
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    /// Clears the durations returned by [`PathOram::profiler`].
+    pub fn reset_profiler(&mut self) {
+        self.profiler.reset();
+    }
+
+    /// Records that `phase` lasted from `start` until now, and returns the current time.
+    fn record_phase(
+        &mut self,
+        phase: AccessPhase,
+        start: std::time::Instant,
+    ) -> std::time::Instant {
+        let now = std::time::Instant::now();
+        self.profiler.record(phase, now - start);
+        now
+    }
+
+    /// Records the sort and write phases of a path write that began at `start`.
+    fn record_path_write(&mut self, start: std::time::Instant) {
+        let sort_duration = self.stash.last_sort_duration();
+        self.profiler.record(AccessPhase::Sort, sort_duration);
+        self.profiler.record(
+            AccessPhase::PathWrite,
+            start.elapsed().saturating_sub(sort_duration),
+        );
+    }
+}
+
+/// Read-only inspection of the internal state of a [`PathOram`], for research (requires the `research` feature).
+///
+/// **These methods break obliviousness.** They reveal the positions of blocks and the contents of the stash,
+/// which an ORAM exists to hide. They are intended only for instrumenting block movement in experiments,
+/// and must never be used in a deployment handling real secrets.
+#[cfg(feature = "research")]
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap>
+    PathOram<V, Z, AB, S, M>
+{
+    /// Returns the height of the tree. Leaves are numbered `2^height <= leaf < 2^(height + 1)`.
+    pub fn tree_height(&self) -> u64 {
+        self.height
+    }
+
+    /// Returns copies of the buckets on the path from the root to `leaf`, root first.
+    /// **Not oblivious**; see the `research` feature.
+    ///
+    /// # Errors
+    ///
+    /// If `leaf` is not a leaf of the tree, returns an `InvalidConfigurationError`.
+    pub fn inspect_path(&self, leaf: u64) -> Result<Vec<Bucket<V, Z>>, OramError> {
+        if (leaf < (1 << self.height)) | (leaf >= (2 << self.height)) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Leaf".to_string(),
+                parameter_value: leaf.to_string(),
+            });
+        }
+        (0..=self.height)
+            .map(|depth| {
+                let index = usize::try_from(leaf.ct_node_on_path(depth, self.height))?;
+                Ok(self.physical_memory.bucket(index))
+            })
+            .collect()
+    }
+
+    /// Returns copies of the real blocks in the stash, not including copies of the last path written.
+    /// **Not oblivious**; see the `research` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the stash.
+    pub fn inspect_stash(&self) -> Result<Vec<PathOramBlock<V>>, OramError> {
+        let mut stash = self.stash.clone();
+        stash.clear_path_slots()?;
+        Ok(stash
+            .blocks_mut()
+            .iter()
+            .filter(|block| !block.is_dummy())
+            .copied()
+            .collect())
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl<V: OramBlock> DefaultOram<V> {
+    /// Performs an access exactly like [`Oram::access`], additionally returning statistics about the access.
+    pub fn access_with_stats<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<(V, AccessStats), OramError> {
+        match &mut self.backend {
+            DefaultOramBackend::Path(p) => p.access_with_stats(index, callback, rng),
+            DefaultOramBackend::Linear(l) => {
+                let value = l.access(index, callback, rng)?;
+                let capacity = l.block_capacity()?;
+                let stats = AccessStats {
+                    physical_reads: capacity,
+                    physical_writes: capacity,
+                    ..Default::default()
+                };
+                Ok((value, stats))
+            }
+        }
+    }
+
+    /// Returns the distribution of leaves read by accesses (see [`PathOram::leaf_histogram`]),
+    /// or `None` for ORAMs below the linear-time cutoff, which have no tree.
+    pub fn leaf_histogram(&self) -> Option<&LeafHistogram> {
+        match &self.backend {
+            DefaultOramBackend::Path(p) => Some(p.leaf_histogram()),
+            DefaultOramBackend::Linear(_) => None,
+        }
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize, S: Stash<V>, M: PositionMap> Oram
+    for PathOram<V, Z, AB, S, M>
+{
+    type V = V;
+    type Error = OramError;
+
+    /// Performs a (oblivious) ORAM access. See [`Oram::access`].
+    ///
+    /// Each access draws a fresh random leaf from `rng` at every level of recursion of the position map,
+    /// and `PathOram` does not buffer randomness itself. With a system RNG such as `OsRng`, each draw
+    /// is a system call; to amortize them, wrap the RNG in a [`BufferedRng`](crate::rng::BufferedRng)
+    /// and pass that to every access.
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        if self.poisoned {
+            return Err(OramError::PoisonedError);
+        }
+        let result = self.access_unpoisoned(address, callback, rng);
+        self.poisoned |= is_poisoning(&result);
         result
     }
 
@@ -318,6 +1858,8 @@ impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram for PathOram<V
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::eviction::ProactiveEviction;
+    use crate::stash::InsecureVecStash;
 
     use crate::{bucket::*, test_utils::*};
 
@@ -350,10 +1892,11 @@ mod tests {
 
     // Sanity checks on the `DefaultOram` convenience wrapper.
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn default_oram_linear_correctness() {
         let mut rng = StdRng::seed_from_u64(0);
         let mut oram = DefaultOram::<BlockValue<1>>::new(64, &mut rng).unwrap();
-        match oram.0 {
+        match oram.backend {
             DefaultOramBackend::Linear(_) => {}
             DefaultOramBackend::Path(_) => assert!(false),
         }
@@ -363,10 +1906,11 @@ mod tests {
     // This test is #[ignore]'d because it takes about 1 second to run.
     #[test]
     #[ignore]
+    #[allow(clippy::assertions_on_constants)]
     fn default_oram_path_correctness() {
         let mut rng = StdRng::seed_from_u64(0);
         let mut oram = DefaultOram::<BlockValue<1>>::new(2048, &mut rng).unwrap();
-        match oram.0 {
+        match oram.backend {
             DefaultOramBackend::Linear(_) => {
                 assert!(false)
             }
@@ -374,4 +1918,917 @@ mod tests {
         }
         random_workload(&mut oram, 1000);
     }
+
+    #[test]
+    fn default_oram_resize_preserves_contents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+
+        oram.resize(128, &mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 128);
+        for i in 0..128 {
+            let expected = if i < 64 { i + 1 } else { 0 };
+            assert_eq!(oram.read(i, &mut rng).unwrap(), expected);
+        }
+
+        oram.resize(32, &mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 32);
+        for i in 0..32 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn default_oram_auto_grow() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(16, &mut rng).unwrap();
+
+        // Auto-grow is disabled by default.
+        assert!(oram.write(16, 1, &mut rng).is_err());
+
+        oram.set_auto_grow(true);
+        for i in 0..100 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        assert_eq!(oram.block_capacity().unwrap(), 128);
+        for i in 0..100 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i);
+        }
+
+        // Reads and far out-of-bounds writes still fail.
+        assert!(oram.read(128, &mut rng).is_err());
+        assert!(oram.write(256, 1, &mut rng).is_err());
+        assert_eq!(oram.block_capacity().unwrap(), 128);
+    }
+
+    #[test]
+    fn default_oram_shrink_to_fit() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(256, &mut rng).unwrap();
+        for i in 0..200 {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+
+        // Simulate bulk deletions.
+        for i in 20..200 {
+            oram.write(i, 0, &mut rng).unwrap();
+        }
+
+        oram.shrink_to_fit(&mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 32);
+        for i in 0..32 {
+            let expected = if i < 20 { i + 1 } else { 0 };
+            assert_eq!(oram.read(i, &mut rng).unwrap(), expected);
+        }
+
+        // Shrinking again is a no-op.
+        oram.shrink_to_fit(&mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 32);
+    }
+
+    #[test]
+    fn default_oram_shrink_to_fit_compacts_holes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(256, &mut rng).unwrap();
+        let live_addresses = [3, 50, 51, 130, 255];
+        for &address in &live_addresses {
+            oram.write(address, address + 1, &mut rng).unwrap();
+        }
+
+        let remapping = oram.shrink_to_fit(&mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 8);
+        for (new_address, &address) in (0..).zip(&live_addresses) {
+            assert_eq!(remapping[address as usize], new_address);
+            assert_eq!(oram.read(new_address, &mut rng).unwrap(), address + 1);
+        }
+        for new_address in 5..8 {
+            assert_eq!(oram.read(new_address, &mut rng).unwrap(), 0);
+        }
+    }
+
+    // This test is #[ignore]'d because it takes about 1 second to run.
+    #[test]
+    #[ignore]
+    fn default_oram_resize_promotes_and_demotes_backend() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let capacity = LINEAR_TIME_ORAM_CUTOFF / 2;
+        let mut oram = DefaultOram::<u64>::new(capacity, &mut rng).unwrap();
+        for i in 0..capacity {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+
+        oram.resize(2 * capacity, &mut rng).unwrap();
+        assert!(matches!(oram.backend, DefaultOramBackend::Path(_)));
+        for i in 0..capacity {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+
+        oram.resize(capacity, &mut rng).unwrap();
+        assert!(matches!(oram.backend, DefaultOramBackend::Linear(_)));
+        for i in 0..capacity {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn path_oram_fill_value() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let fill_value = BlockValue::new([0xAB; 4]);
+        let mut oram = PathOram::<BlockValue<4>, 4, 8>::new_with_fill_value_and_parameters(
+            64, fill_value, &mut rng, 40, 1,
+        )
+        .unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), fill_value);
+        }
+    }
+
+    #[test]
+    fn default_oram_fill_value() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sentinel = u64::MAX;
+        let mut oram = DefaultOram::<u64>::new_with_fill_value(64, sentinel, &mut rng).unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), sentinel);
+        }
+
+        oram.write(3, 0, &mut rng).unwrap();
+        oram.resize(128, &mut rng).unwrap();
+        assert_eq!(oram.read(3, &mut rng).unwrap(), 0);
+        assert_eq!(oram.read(100, &mut rng).unwrap(), sentinel);
+
+        let remapping = oram.shrink_to_fit(&mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 2);
+        assert_eq!(oram.read(remapping[3], &mut rng).unwrap(), 0);
+    }
+
+    #[test]
+    fn path_oram_access_with_auxiliary_output() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram =
+            PathOram::<BlockValue<4>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.write(9, BlockValue::new([1, 2, 3, 4]), &mut rng)
+            .unwrap();
+
+        let secret = BlockValue::new([1, 2, 3, 4]);
+        let (previous, matched) = oram
+            .access_with(
+                9,
+                |value| (BlockValue::new([0, 0, 0, value[3]]), value.ct_eq(&secret)),
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(previous, secret);
+        assert!(bool::from(matched));
+        assert_eq!(oram.read(9, &mut rng).unwrap().data, [0, 0, 0, 4]);
+        assert!(oram
+            .access_with(64, |value| (*value, ()), &mut rng)
+            .is_err());
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn path_oram_access_with_stats() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Capacity 64 with 2 positions per block and a recursion cutoff of 4 gives a recursive position map
+        // of capacity 32. Its own position map level of 16 blocks, no more than the 4 * 4 blocks of one of its paths,
+        // is collapsed into a linear base map of capacity 16.
+        let mut oram =
+            PathOram::<BlockValue<1>, 4, 2>::new_with_parameters(64, &mut rng, 40, 4).unwrap();
+        let (_, stats) = oram
+            .access_with_stats(3, |_| BlockValue::new([1]), &mut rng)
+            .unwrap();
+        assert_eq!(stats.recursion_depth, 1);
+        // Tree heights 5 and 4 give 6 + 5 buckets per pass, plus 16 linear entries.
+        assert_eq!(stats.physical_reads, 27);
+        assert_eq!(stats.physical_writes, 27);
+        assert!(stats.stash_occupancy <= 40);
+
+        let (value, _) = oram.access_with_stats(3, |v| *v, &mut rng).unwrap();
+        assert_eq!(value, BlockValue::new([1]));
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn default_oram_access_with_stats() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        let (_, stats) = oram.access_with_stats(3, |_| 1, &mut rng).unwrap();
+        assert_eq!(stats.recursion_depth, 0);
+        assert_eq!(stats.physical_reads, 64);
+        assert_eq!(stats.physical_writes, 64);
+    }
+
+    #[test]
+    fn path_oram_flush_evictions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // A zero overflow size forces blocks to accumulate in the (grown) stash.
+        let mut oram = PathOram::<u64, 3, 2>::new_with_parameters(64, &mut rng, 0, 1).unwrap();
+        for _ in 0..10 {
+            for i in 0..64 {
+                oram.write(i, i + 1, &mut rng).unwrap();
+            }
+        }
+
+        let occupancy_before = oram.stash_occupancy();
+        oram.flush_evictions(32).unwrap();
+        assert!(oram.stash_occupancy() <= occupancy_before);
+        assert_eq!(oram.eviction_counter, 32);
+
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn path_oram_eviction_period() {
+        for eviction_period in [2, 3, 8] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut oram =
+                PathOram::<BlockValue<1>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            random_workload(&mut oram, 1000);
+        }
+    }
+
+    #[test]
+    fn path_oram_eviction_strategies() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_strategy(ProactiveEviction::new(2))
+            .unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        assert_eq!(oram.eviction_counter, 128);
+
+        oram.set_eviction_strategy(ScheduledEviction::new(4).unwrap())
+            .unwrap();
+        for i in 0..62 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i);
+        }
+        assert_eq!(oram.eviction_counter, 143);
+        // Replacing the strategy completes the current period.
+        oram.set_eviction_strategy(PathEviction).unwrap();
+        assert_eq!(oram.eviction_counter, 144);
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i);
+        }
+        assert_eq!(oram.eviction_counter, 144);
+    }
+
+    #[test]
+    fn path_oram_failed_access_skips_evictions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram =
+            PathOram::<u64, 4, 8>::new_with_stash_growth(64, 0, &mut rng, 0, 1, StashGrowth::Never)
+                .unwrap();
+        oram.set_eviction_strategy(ScheduledEviction::new(1).unwrap())
+            .unwrap();
+        oram.set_eviction_watermark(Some(0));
+
+        // With no overflow space, moving the target block into the stash fails.
+        assert!(matches!(
+            oram.write(0, 1, &mut rng),
+            Err(OramError::StashOverflowError { .. })
+        ));
+        assert_eq!(oram.eviction_counter, 0);
+        assert_eq!(oram.watermark_evictions(), 0);
+    }
+
+    #[test]
+    fn path_oram_rejects_address_equal_to_capacity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        assert!(matches!(
+            oram.read(64, &mut rng),
+            Err(OramError::AddressOutOfBoundsError {
+                attempted: 64,
+                capacity: 64
+            })
+        ));
+        random_workload(&mut oram, 200);
+        oram.assert_invariants(&mut rng).unwrap();
+    }
+
+    #[test]
+    fn path_oram_permute() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(3).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 100, &mut rng).unwrap();
+        }
+
+        let permutation: Vec<Address> = (0..64).map(|i| (i * 5 + 7) % 64).collect();
+        assert!(oram.permute(&permutation[..63]).is_err());
+        let mut not_a_permutation = permutation.clone();
+        not_a_permutation[0] = not_a_permutation[1];
+        assert!(oram.permute(&not_a_permutation).is_err());
+
+        oram.permute(&permutation).unwrap();
+        for (i, destination) in permutation.iter().enumerate() {
+            assert_eq!(oram.read(*destination, &mut rng).unwrap(), i as u64 + 100);
+        }
+        oram.set_eviction_period(1).unwrap();
+        oram.permute(&permutation).unwrap();
+        for (i, destination) in permutation.iter().enumerate() {
+            let twice = permutation[*destination as usize];
+            assert_eq!(oram.read(twice, &mut rng).unwrap(), i as u64 + 100);
+        }
+
+        let mut small = DefaultOram::<u64>::new(4, &mut rng).unwrap();
+        for i in 0..4 {
+            small.write(i, i + 1, &mut rng).unwrap();
+        }
+        small.permute(&[3, 0, 1, 2]).unwrap();
+        for (i, expected) in [2, 3, 4, 1].into_iter().enumerate() {
+            assert_eq!(small.read(i as u64, &mut rng).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn path_oram_rebuild() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(8).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 100, &mut rng).unwrap();
+        }
+        assert!(oram.stash.occupancy() > 0);
+
+        let tree_before = oram.physical_memory.to_buckets();
+        oram.rebuild(&mut rng).unwrap();
+        assert_eq!(oram.stash.occupancy(), 0);
+        assert_ne!(oram.physical_memory.to_buckets(), tree_before);
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 100);
+        }
+
+        let mut insecure =
+            PathOram::<u64, 4, 8, InsecureVecStash<u64>>::new_with_parameters(64, &mut rng, 40, 1)
+                .unwrap();
+        for i in 0..64 {
+            insecure.write(i, i + 1, &mut rng).unwrap();
+        }
+        insecure.rebuild(&mut rng).unwrap();
+        assert_eq!(insecure.stash.occupancy(), 0);
+        for i in 0..64 {
+            assert_eq!(insecure.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn path_oram_eviction_watermark() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(32).unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        assert!(oram.stash.occupancy() > 8);
+        assert_eq!(oram.watermark_evictions(), 0);
+
+        oram.set_eviction_watermark(Some(8));
+        assert_eq!(oram.eviction_watermark(), Some(8));
+        for _ in 0..4 {
+            for i in 0..64 {
+                assert_eq!(oram.read(i, &mut rng).unwrap(), i);
+                assert!(oram.stash.occupancy() <= 8 + 32);
+            }
+        }
+        assert!(oram.watermark_evictions() > 0);
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn path_oram_research_inspection() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(16).unwrap();
+        for i in 0..8 {
+            oram.write(i, i + 100, &mut rng).unwrap();
+        }
+
+        let height = oram.tree_height();
+        assert!(oram.inspect_path(0).is_err());
+        assert!(oram.inspect_path(2 << height).is_err());
+
+        // Every block is either in the stash or on the path to its position.
+        let stash = oram.inspect_stash().unwrap();
+        assert_eq!(stash.len(), 8);
+        for block in &stash {
+            assert_eq!(block.value, block.address + 100);
+        }
+        for leaf in (1 << height)..(2 << height) {
+            let path = oram.inspect_path(leaf).unwrap();
+            assert_eq!(path.len() as u64, height + 1);
+            for bucket in path {
+                for block in bucket.blocks.iter().filter(|block| !block.is_dummy()) {
+                    assert!(block.position.is_leaf(height));
+                }
+            }
+        }
+        let leaf_blocks = oram.inspect_path(1 << height).unwrap()[height as usize].blocks;
+        for block in leaf_blocks.iter().filter(|block| !block.is_dummy()) {
+            assert_eq!(block.position, 1 << height);
+        }
+    }
+
+    #[test]
+    fn path_oram_eviction_watermark_evicts_until_below() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(64).unwrap();
+        for i in 0..63 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        assert!(oram.stash.occupancy() > 8);
+
+        // A single access evicts as many paths as needed to get back to the watermark.
+        oram.set_eviction_watermark(Some(8));
+        assert_eq!(oram.read(0, &mut rng).unwrap(), 0);
+        assert!(oram.stash.occupancy() <= 8);
+        assert!(oram.watermark_evictions() > 1);
+
+        // The number of additional evictions per access is bounded.
+        oram.set_eviction_watermark(Some(0));
+        for i in 0..4 {
+            let watermark_evictions = oram.watermark_evictions();
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i);
+            assert!(
+                oram.watermark_evictions() - watermark_evictions <= MAXIMUM_WATERMARK_EVICTIONS
+            );
+        }
+    }
+
+    #[test]
+    fn path_oram_migrate_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(256, &mut rng, 40, 1).unwrap();
+        oram.set_eviction_period(4).unwrap();
+        for i in 0..256 {
+            oram.write(i, i + 100, &mut rng).unwrap();
+        }
+
+        let mut migrated: PathOram<u64, 2, 4> = oram.migrate_parameters(&mut rng, 10, 16).unwrap();
+        assert_eq!(migrated.block_capacity().unwrap(), 256);
+        for i in 0..256 {
+            assert_eq!(migrated.read(i, &mut rng).unwrap(), i + 100);
+        }
+
+        let (client_state, tree) = migrated.into_parts();
+        let mut migrated: PathOram<u64, 6, 16, InsecureVecStash<u64>> =
+            PathOram::from_parts(client_state, tree)
+                .unwrap()
+                .migrate_parameters(&mut rng, 0, 1)
+                .unwrap();
+        for i in 0..256 {
+            assert_eq!(migrated.read(i, &mut rng).unwrap(), i + 100);
+        }
+
+        let invalid: Result<PathOram<u64, 1, 8>, _> = migrated.migrate_parameters(&mut rng, 40, 1);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn path_oram_change_eviction_period() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        assert!(oram.set_eviction_period(0).is_err());
+
+        for (round, eviction_period) in [1, 3, 1, 2].into_iter().enumerate() {
+            oram.set_eviction_period(eviction_period).unwrap();
+            let round = round as u64;
+            for i in 0..64 {
+                if round > 0 {
+                    assert_eq!(oram.read(i, &mut rng).unwrap(), i + round - 1);
+                }
+                oram.write(i, i + round, &mut rng).unwrap();
+            }
+        }
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn path_oram_leaf_histogram_is_uniform() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        // A maximally skewed workload.
+        for _ in 0..3200 {
+            oram.read(0, &mut rng).unwrap();
+        }
+
+        let histogram = oram.leaf_histogram();
+        assert_eq!(histogram.counts().len(), 32);
+        assert_eq!(histogram.total(), 3200);
+        assert!(histogram.uniformity_test().p_value > 1e-6);
+
+        oram.reset_leaf_histogram();
+        assert_eq!(oram.leaf_histogram().total(), 0);
+    }
+
+    #[test]
+    fn path_oram_from_parts() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+
+        let (client_state, tree) = oram.into_parts();
+        assert_eq!(tree.num_buckets(), 64);
+        let mut oram = PathOram::from_parts(client_state.clone(), tree).unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+
+        let (_, wrong_tree) = PathOram::<u64, 4, 8>::new_with_parameters(128, &mut rng, 40, 1)
+            .unwrap()
+            .into_parts();
+        assert!(PathOram::from_parts(client_state.clone(), wrong_tree).is_err());
+
+        // Parts from different splits are rejected.
+        assert_eq!(client_state.epoch(), 1);
+        let (new_client_state, new_tree) = oram.into_parts();
+        assert_eq!(new_tree.epoch(), 2);
+        assert!(PathOram::from_parts(client_state, new_tree.clone()).is_err());
+        let mut oram = PathOram::from_parts(new_client_state, new_tree).unwrap();
+        assert_eq!(oram.read(1, &mut rng).unwrap(), 2);
+    }
+
+    #[test]
+    fn path_oram_split_phase_access() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        let (mut client_state, mut tree) = oram.into_parts();
+        let mut mirror = vec![0u64; 64];
+
+        // Begin the next access while the current one is in flight.
+        let mut pending = client_state.begin_access(0, &mut rng).unwrap();
+        for i in 1..=200u64 {
+            let address = i % 64;
+            let next = client_state.begin_access(address, &mut rng).unwrap();
+            assert_eq!(next.bucket_indices().len(), 6);
+
+            let mut path = tree.read_path(pending.bucket_indices()).unwrap();
+            let indices = pending.bucket_indices().to_vec();
+            let previous_address = (i - 1) % 64;
+            let old_value = client_state
+                .finish_access(pending, &mut path, |_| i)
+                .unwrap();
+            assert_eq!(old_value, mirror[previous_address as usize]);
+            mirror[previous_address as usize] = i;
+            tree.write_path(&indices, &path).unwrap();
+
+            pending = next;
+        }
+        let mut path = tree.read_path(pending.bucket_indices()).unwrap();
+        let indices = pending.bucket_indices().to_vec();
+        client_state
+            .finish_access(pending, &mut path, |v| *v)
+            .unwrap();
+        tree.write_path(&indices, &path).unwrap();
+
+        let mut oram = PathOram::from_parts(client_state.clone(), tree).unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), mirror[i as usize]);
+        }
+
+        let pending = client_state.begin_access(0, &mut rng).unwrap();
+        assert!(client_state.begin_access(64, &mut rng).is_err());
+        assert!(client_state
+            .finish_access(pending, &mut [], |v| *v)
+            .is_err());
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn path_oram_level_stats() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..10 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        let stats = oram.level_stats();
+        assert_eq!(stats.physical_reads, vec![10; 6]);
+        assert_eq!(stats.physical_writes, vec![10; 6]);
+
+        // With an eviction period of 2, every other access is followed by an eviction.
+        oram.reset_level_stats();
+        oram.set_eviction_period(2).unwrap();
+        for i in 0..10 {
+            oram.read(i, &mut rng).unwrap();
+        }
+        assert_eq!(oram.level_stats().physical_reads, vec![15; 6]);
+        assert_eq!(oram.level_stats().physical_writes, vec![15; 6]);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn path_oram_profiler_records_every_phase() {
+        use crate::profiler::AccessPhase;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..10 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        for phase in AccessPhase::ALL {
+            assert_eq!(oram.profiler().summary(phase).unwrap().count, 10);
+        }
+
+        // Scheduled evictions read, sort, and write paths.
+        oram.set_eviction_period(2).unwrap();
+        oram.reset_profiler();
+        for i in 0..10 {
+            oram.read(i, &mut rng).unwrap();
+        }
+        let profiler = oram.profiler();
+        assert_eq!(profiler.summary(AccessPhase::StashScan).unwrap().count, 10);
+        assert_eq!(profiler.summary(AccessPhase::Sort).unwrap().count, 5);
+        assert_eq!(profiler.summary(AccessPhase::PathRead).unwrap().count, 5);
+    }
+
+    #[test]
+    fn path_oram_access_checked_reports_stash_growth() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 2, 8>::new_with_parameters(64, &mut rng, 0, 1).unwrap();
+        let mut mirror = vec![0; 64];
+        let mut grew = 0;
+        for i in 0..500u64 {
+            let address = i * 7 % 64;
+            let outcome = oram.access_checked(address, |_| i, &mut rng).unwrap();
+            assert_eq!(outcome.value, mirror[address as usize]);
+            mirror[address as usize] = i;
+            grew += u64::from(outcome.stash_grew);
+        }
+        // With an overflow size of 0 and Z = 2, the stash must grow.
+        assert!(grew > 0);
+
+        let mut oram = DefaultOram::<u64>::new(16, &mut rng).unwrap();
+        let outcome = oram.access_checked(3, |_| 1, &mut rng).unwrap();
+        assert_eq!(outcome, AccessOutcome::new(0));
+        assert!(!outcome.is_degraded());
+    }
+
+    #[test]
+    fn path_oram_large_capacities() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let too_large = 4 << MAXIMUM_TREE_HEIGHT;
+        assert!(matches!(
+            PathOram::<u64, 4, 8>::new_with_parameters(too_large, &mut rng, 40, 1),
+            Err(OramError::InvalidConfigurationError { .. })
+        ));
+
+        // The largest supported capacity is accepted, but its tree does not fit in memory.
+        let largest = 2 << MAXIMUM_TREE_HEIGHT;
+        assert!(matches!(
+            PathOram::<u64, 4, 8>::new_with_parameters(largest, &mut rng, 40, 1),
+            Err(OramError::AllocationError { .. })
+        ));
+    }
+
+    #[test]
+    fn path_oram_memory_usage() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = PathOram::<u64, 4, 8>::new_with_parameters(1024, &mut rng, 40, 1).unwrap();
+        let usage = oram.memory_usage().unwrap();
+
+        // 1024 blocks, a recursive position map of 128 blocks, and a linear base position map.
+        assert_eq!(usage.levels.len(), 3);
+        assert_eq!(
+            usage.levels[0].tree_bytes,
+            1024 * 4 * (std::mem::size_of::<u64>() + 8)
+        );
+        assert!(usage.levels[0].stash_bytes > 0);
+        assert_eq!(usage.levels[2].stash_bytes, 0);
+        assert_eq!(
+            usage.total_bytes(),
+            usage.tree_bytes() + usage.stash_bytes() + usage.scratch_bytes()
+        );
+        assert_eq!(
+            usage.position_map_bytes(),
+            usage.total_bytes()
+                - usage.levels[0].tree_bytes
+                - usage.levels[0].stash_bytes
+                - usage.scratch_bytes()
+        );
+
+        let linear = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        assert_eq!(linear.memory_usage().unwrap().total_bytes(), 64 * 8);
+    }
+
+    #[test]
+    fn path_oram_stash_growth_strategies() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_stash_growth(
+            64,
+            0,
+            &mut rng,
+            0,
+            1,
+            StashGrowth::Doubling,
+        )
+        .unwrap();
+        random_workload(&mut oram, 1000);
+
+        // With no overflow space and a cap at the path size, the first overflow fails.
+        let path_size = 4 * 6;
+        let mut oram = PathOram::<u64, 4, 8>::new_with_stash_growth(
+            64,
+            0,
+            &mut rng,
+            0,
+            1,
+            StashGrowth::Capped {
+                increment: 1,
+                max_size: path_size,
+            },
+        )
+        .unwrap();
+        let overflowed = (0..1000).any(|i| {
+            matches!(
+                oram.write(i % 64, i, &mut rng),
+                Err(OramError::StashOverflowError {
+                    stash_size: 24,
+                    overflow_capacity: 0
+                })
+            )
+        });
+        assert!(overflowed);
+
+        assert!(PathOram::<u64, 4, 8>::new_with_stash_growth(
+            64,
+            0,
+            &mut rng,
+            40,
+            1,
+            StashGrowth::Increment(0),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn path_oram_strict_stash_poisons_on_overflow() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram =
+            PathOram::<u64, 4, 8>::new_with_stash_growth(64, 0, &mut rng, 0, 1, StashGrowth::Never)
+                .unwrap();
+        let stash_bytes = oram.memory_usage().unwrap().stash_bytes();
+
+        let overflow = (0..1000)
+            .map(|i| oram.write(i % 64, i, &mut rng))
+            .find(Result::is_err)
+            .unwrap();
+        assert!(matches!(
+            overflow,
+            Err(OramError::StashOverflowError {
+                overflow_capacity: 0,
+                ..
+            })
+        ));
+        assert!(oram.is_poisoned());
+        assert_eq!(oram.memory_usage().unwrap().stash_bytes(), stash_bytes);
+
+        assert!(matches!(
+            oram.read(0, &mut rng),
+            Err(OramError::PoisonedError)
+        ));
+        assert!(matches!(
+            oram.flush_evictions(1),
+            Err(OramError::PoisonedError)
+        ));
+        let (mut client_state, _) = oram.into_parts();
+        assert!(matches!(
+            client_state.begin_access(0, &mut rng),
+            Err(OramError::PoisonedError)
+        ));
+    }
+
+    #[test]
+    fn path_oram_verify_reports_corruption() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+        let report = oram.verify(&mut rng).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.buckets_checked, 63);
+        assert_eq!(report.tree_blocks + report.stash_blocks, 64);
+
+        // Move a real block from a leaf to its sibling, which is not on the path to its position.
+        let leaf = (32..64)
+            .find(|&i| {
+                oram.physical_memory
+                    .bucket(i)
+                    .blocks
+                    .iter()
+                    .any(|b| !b.is_dummy())
+            })
+            .unwrap();
+        let mut bucket = oram.physical_memory.bucket(leaf);
+        let slot = bucket.blocks.iter().position(|b| !b.is_dummy()).unwrap();
+        let block = bucket.blocks[slot];
+        bucket.blocks[slot] = PathOramBlock::dummy();
+        Arc::make_mut(&mut oram.physical_memory).set_bucket(leaf, &bucket);
+        let mut sibling = oram.physical_memory.bucket(leaf ^ 1);
+        let free_slot = sibling.blocks.iter().position(|b| b.is_dummy()).unwrap();
+        sibling.blocks[free_slot] = block;
+        Arc::make_mut(&mut oram.physical_memory).set_bucket(leaf ^ 1, &sibling);
+
+        let report = oram.verify(&mut rng).unwrap();
+        assert_eq!(
+            report.inconsistencies,
+            vec![Inconsistency::MisplacedBlock {
+                address: block.address,
+                position: block.position,
+                bucket: (leaf ^ 1) as TreeIndex,
+            }]
+        );
+
+        // Drop the block altogether.
+        sibling.blocks[free_slot] = PathOramBlock::dummy();
+        Arc::make_mut(&mut oram.physical_memory).set_bucket(leaf ^ 1, &sibling);
+        let report = oram.verify(&mut rng).unwrap();
+        assert_eq!(
+            report.inconsistencies,
+            vec![Inconsistency::MissingBlock {
+                address: block.address
+            }]
+        );
+    }
+
+    #[test]
+    fn default_oram_load_bytes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bytes: Vec<u8> = (0..30).collect();
+        let mut oram = DefaultOram::<BlockValue<4>>::new(8, &mut rng).unwrap();
+
+        assert!(oram.load_bytes(&bytes, false, &mut rng).is_err());
+        oram.load_bytes(&bytes, true, &mut rng).unwrap();
+        assert_eq!(oram.read(0, &mut rng).unwrap().data, [0, 1, 2, 3]);
+        assert_eq!(oram.read(7, &mut rng).unwrap().data, [28, 29, 0, 0]);
+
+        oram.load_bytes(&bytes[..8], false, &mut rng).unwrap();
+        assert_eq!(oram.read(1, &mut rng).unwrap().data, [4, 5, 6, 7]);
+
+        let too_long = vec![1u8; 33];
+        assert!(oram.load_bytes(&too_long, true, &mut rng).is_err());
+        assert_eq!(oram.read(2, &mut rng).unwrap().data, [8, 9, 10, 11]);
+
+        let mut empty_blocks = DefaultOram::<BlockValue<0>>::new(8, &mut rng).unwrap();
+        for pad in [false, true] {
+            assert!(matches!(
+                empty_blocks.load_bytes(&bytes, pad, &mut rng),
+                Err(OramError::InvalidConfigurationError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn warm_up_preserves_contents() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i * 3, &mut rng).unwrap();
+        }
+        oram.warm_up(500, &mut rng).unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i * 3);
+        }
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    #[should_panic(expected = "is missing")]
+    fn debug_invariants_detect_corruption() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+
+        // Drop a real block from a leaf bucket, so that the next access to any other address panics.
+        let leaf = (32..64)
+            .find(|&i| {
+                oram.physical_memory
+                    .bucket(i)
+                    .blocks
+                    .iter()
+                    .any(|b| !b.is_dummy())
+            })
+            .unwrap();
+        let mut bucket = oram.physical_memory.bucket(leaf);
+        let slot = bucket.blocks.iter().position(|b| !b.is_dummy()).unwrap();
+        let address = bucket.blocks[slot].address;
+        bucket.blocks[slot] = PathOramBlock::dummy();
+        Arc::make_mut(&mut oram.physical_memory).set_bucket(leaf, &bucket);
+
+        oram.read((address + 1) % 64, &mut rng).unwrap();
+    }
 }
@@ -0,0 +1,327 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! The physical memory layouts of the tree of a Path ORAM.
+
+use crate::{
+    bucket::{Bucket, PathOramBlock},
+    stash::PathStorage,
+    utils::TreeIndex,
+    Address, BucketSize, OramBlock, OramError,
+};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+/// The layout of the buckets of a Path ORAM tree in memory. See [`crate::PathOram::set_bucket_layout`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BucketLayout {
+    /// Each block is stored with its metadata (address and position), and each bucket is stored contiguously.
+    #[default]
+    ArrayOfStructs,
+    /// The addresses, the positions, and the values of all blocks of the tree are stored in three separate arrays.
+    ///
+    /// Scans that only inspect metadata then touch far fewer cache lines when values are large,
+    /// at the cost of gathering each bucket from three places when a path is read or written.
+    StructOfArrays,
+}
+
+/// A `PathOramBlock` whose address and position are stored in 32 bits each.
+///
+/// The position is stored as an offset from the first leaf. The dummy address is stored as `u32::MAX`,
+/// so real addresses must be smaller than `u32::MAX`.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PackedBlock<V> {
+    value: V,
+    address: u32,
+    leaf_offset: u32,
+}
+
+impl<V: OramBlock> PackedBlock<V> {
+    const DUMMY_ADDRESS: u32 = u32::MAX;
+
+    fn pack(block: &PathOramBlock<V>, first_leaf: TreeIndex) -> Self {
+        let is_dummy = block.ct_is_dummy();
+        // Real addresses and leaf offsets fit in 32 bits, so the truncations are lossless.
+        let address =
+            u32::conditional_select(&(block.address as u32), &Self::DUMMY_ADDRESS, is_dummy);
+        let leaf_offset = u32::conditional_select(
+            &(block.position.wrapping_sub(first_leaf) as u32),
+            &0,
+            is_dummy,
+        );
+        Self {
+            value: block.value,
+            address,
+            leaf_offset,
+        }
+    }
+
+    fn unpack(&self, first_leaf: TreeIndex) -> PathOramBlock<V> {
+        let is_dummy = self.address.ct_eq(&Self::DUMMY_ADDRESS);
+        let dummy = PathOramBlock::<V>::dummy();
+        PathOramBlock {
+            value: self.value,
+            address: Address::conditional_select(&self.address.into(), &dummy.address, is_dummy),
+            position: TreeIndex::conditional_select(
+                &(first_leaf + TreeIndex::from(self.leaf_offset)),
+                &dummy.position,
+                is_dummy,
+            ),
+        }
+    }
+}
+
+/// The buckets of a Path ORAM tree, indexed by `TreeIndex`, in either `BucketLayout`.
+///
+/// In the array-of-structs layout, block metadata is packed into 32-bit fields whenever
+/// the number of buckets (which equals the block capacity) is below `u32::MAX`,
+/// reducing the per-block overhead from 16 to 8 bytes. The struct-of-arrays layout always stores 64-bit metadata.
+#[derive(Clone, Debug)]
+pub(crate) enum PhysicalTree<V: OramBlock, const Z: BucketSize> {
+    ArrayOfStructs(Vec<Bucket<V, Z>>),
+    PackedArrayOfStructs(Vec<[PackedBlock<V>; Z]>),
+    StructOfArrays {
+        addresses: Vec<Address>,
+        positions: Vec<TreeIndex>,
+        values: Vec<V>,
+    },
+}
+
+impl<V: OramBlock, const Z: BucketSize> PhysicalTree<V, Z> {
+    /// Returns a tree holding `buckets`, stored in `layout`.
+    pub fn from_buckets(buckets: Vec<Bucket<V, Z>>, layout: BucketLayout) -> Self {
+        match layout {
+            BucketLayout::ArrayOfStructs if buckets.len() < u32::MAX as usize => {
+                let first_leaf = Self::first_leaf(buckets.len());
+                Self::PackedArrayOfStructs(
+                    buckets
+                        .iter()
+                        .map(|bucket| {
+                            bucket
+                                .blocks
+                                .map(|block| PackedBlock::pack(&block, first_leaf))
+                        })
+                        .collect(),
+                )
+            }
+            BucketLayout::ArrayOfStructs => Self::ArrayOfStructs(buckets),
+            BucketLayout::StructOfArrays => {
+                let blocks = buckets.iter().flat_map(|bucket| bucket.blocks.iter());
+                Self::StructOfArrays {
+                    addresses: blocks.clone().map(|block| block.address).collect(),
+                    positions: blocks.clone().map(|block| block.position).collect(),
+                    values: blocks.map(|block| block.value).collect(),
+                }
+            }
+        }
+    }
+
+    /// Returns the buckets of the tree.
+    pub fn to_buckets(&self) -> Vec<Bucket<V, Z>> {
+        match self {
+            Self::ArrayOfStructs(buckets) => buckets.clone(),
+            _ => (0..self.len()).map(|i| self.bucket(i)).collect(),
+        }
+    }
+
+    pub fn layout(&self) -> BucketLayout {
+        match self {
+            Self::ArrayOfStructs(_) | Self::PackedArrayOfStructs(_) => BucketLayout::ArrayOfStructs,
+            Self::StructOfArrays { .. } => BucketLayout::StructOfArrays,
+        }
+    }
+
+    /// Returns the number of buckets in the tree, including the unused bucket at index 0.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::ArrayOfStructs(buckets) => buckets.len(),
+            Self::PackedArrayOfStructs(buckets) => buckets.len(),
+            Self::StructOfArrays { addresses, .. } => addresses.len() / Z,
+        }
+    }
+
+    pub fn bucket(&self, index: usize) -> Bucket<V, Z> {
+        match self {
+            Self::ArrayOfStructs(buckets) => buckets[index],
+            Self::PackedArrayOfStructs(buckets) => {
+                let first_leaf = Self::first_leaf(buckets.len());
+                Bucket {
+                    blocks: buckets[index].map(|block| block.unpack(first_leaf)),
+                }
+            }
+            Self::StructOfArrays {
+                addresses,
+                positions,
+                values,
+            } => {
+                let mut bucket = Bucket::default();
+                for (slot, block) in bucket.blocks.iter_mut().enumerate() {
+                    let i = index * Z + slot;
+                    *block = PathOramBlock {
+                        value: values[i],
+                        address: addresses[i],
+                        position: positions[i],
+                    };
+                }
+                bucket
+            }
+        }
+    }
+
+    pub fn set_bucket(&mut self, index: usize, bucket: &Bucket<V, Z>) {
+        match self {
+            Self::ArrayOfStructs(buckets) => buckets[index] = *bucket,
+            Self::PackedArrayOfStructs(buckets) => {
+                let first_leaf = Self::first_leaf(buckets.len());
+                buckets[index] = bucket
+                    .blocks
+                    .map(|block| PackedBlock::pack(&block, first_leaf));
+            }
+            Self::StructOfArrays {
+                addresses,
+                positions,
+                values,
+            } => {
+                for (slot, block) in bucket.blocks.iter().enumerate() {
+                    let i = index * Z + slot;
+                    values[i] = block.value;
+                    addresses[i] = block.address;
+                    positions[i] = block.position;
+                }
+            }
+        }
+    }
+
+    /// Returns true if block metadata is stored in 32-bit fields.
+    pub fn is_packed(&self) -> bool {
+        matches!(self, Self::PackedArrayOfStructs(_))
+    }
+
+    /// Returns the index of the first leaf of a tree with `num_buckets` buckets (including the unused bucket 0).
+    fn first_leaf(num_buckets: usize) -> TreeIndex {
+        (num_buckets / 2) as TreeIndex
+    }
+
+    /// Returns the number of real (non-dummy) blocks in the tree. This is not constant-time.
+    pub fn real_block_count(&self) -> usize {
+        match self {
+            Self::ArrayOfStructs(buckets) => buckets
+                .iter()
+                .flat_map(|bucket| bucket.blocks.iter())
+                .filter(|block| !block.is_dummy())
+                .count(),
+            Self::PackedArrayOfStructs(buckets) => buckets
+                .iter()
+                .flatten()
+                .filter(|block| block.address != PackedBlock::<V>::DUMMY_ADDRESS)
+                .count(),
+            Self::StructOfArrays { addresses, .. } => addresses
+                .iter()
+                .filter(|&&address| address != PathOramBlock::<V>::DUMMY_ADDRESS)
+                .count(),
+        }
+    }
+
+    /// Returns the number of bytes allocated for the tree.
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Self::ArrayOfStructs(buckets) => {
+                buckets.capacity() * std::mem::size_of::<Bucket<V, Z>>()
+            }
+            Self::PackedArrayOfStructs(buckets) => {
+                buckets.capacity() * std::mem::size_of::<[PackedBlock<V>; Z]>()
+            }
+            Self::StructOfArrays {
+                addresses,
+                positions,
+                values,
+            } => {
+                addresses.capacity() * std::mem::size_of::<Address>()
+                    + positions.capacity() * std::mem::size_of::<TreeIndex>()
+                    + values.capacity() * std::mem::size_of::<V>()
+            }
+        }
+    }
+}
+
+impl<V: OramBlock, const Z: BucketSize> PathStorage<V, Z> for PhysicalTree<V, Z> {
+    fn read_bucket(&self, _depth: u64, index: TreeIndex) -> Result<Bucket<V, Z>, OramError> {
+        Ok(self.bucket(usize::try_from(index)?))
+    }
+
+    fn write_bucket(
+        &mut self,
+        _depth: u64,
+        index: TreeIndex,
+        bucket: &Bucket<V, Z>,
+    ) -> Result<(), OramError> {
+        self.set_bucket(usize::try_from(index)?, bucket);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, path_oram::PathOram, test_utils::random_workload, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn struct_of_arrays_layout_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for eviction_period in [1, 3] {
+            let mut oram =
+                PathOram::<BlockValue<64>, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            oram.set_bucket_layout(BucketLayout::StructOfArrays);
+            assert_eq!(oram.bucket_layout(), BucketLayout::StructOfArrays);
+            random_workload(&mut oram, 500);
+            assert_eq!(oram.real_block_count(), 64);
+        }
+
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 1, &mut rng).unwrap();
+        }
+        oram.set_bucket_layout(BucketLayout::StructOfArrays);
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+        assert_eq!(
+            oram.memory_usage().unwrap().levels[0].tree_bytes,
+            64 * 4 * 3 * std::mem::size_of::<u64>()
+        );
+        oram.set_bucket_layout(BucketLayout::ArrayOfStructs);
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn packed_metadata_is_selected_for_small_capacities() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        assert!(oram.has_packed_metadata());
+        assert_eq!(
+            oram.memory_usage().unwrap().levels[0].tree_bytes,
+            64 * 4 * (std::mem::size_of::<u64>() + 8)
+        );
+        random_workload(&mut oram, 500);
+        assert_eq!(oram.real_block_count(), 64);
+
+        let buckets = vec![Bucket::<u64, 4>::default(); 8];
+        let mut tree = PhysicalTree::from_buckets(buckets, BucketLayout::ArrayOfStructs);
+        let mut bucket = Bucket::<u64, 4>::default();
+        bucket.blocks[1] = PathOramBlock {
+            value: 9,
+            address: 5,
+            position: 6,
+        };
+        tree.set_bucket(3, &bucket);
+        assert!(tree.bucket(3) == bucket);
+        assert_eq!(tree.real_block_count(), 1);
+    }
+}
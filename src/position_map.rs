@@ -5,27 +5,113 @@
 // License, Version 2.0 found in the LICENSE-APACHE file in the root directory
 // of this source tree. You may select, at your option, one of the above-listed licenses.
 
-//! A recursive Path ORAM position map data structure.
+//! A trait representing a Path ORAM position map, and the default recursive position map.
 
 use super::path_oram::PathOram;
 use crate::bucket::PositionBlock;
-use crate::StashSize;
+use crate::memory_usage::LevelMemoryUsage;
 use crate::{
     linear_time_oram::LinearTimeOram, utils::TreeIndex, Address, BlockSize, BucketSize, Oram,
+    OramRng,
 };
+use crate::{stash::StashGrowth, StashSize};
 use crate::{OramError, RecursionCutoff};
-use rand::{CryptoRng, RngCore};
-use subtle::{ConditionallySelectable, ConstantTimeEq};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// A Path ORAM position map, which maps each ORAM address to the leaf of the tree on whose path its block resides.
+///
+/// [`PathOram`] is generic over its position map, so that alternative maps (such as plain in-memory maps,
+/// PRF-based maps, or externally hosted maps) can reuse its tree and stash. [`RecursivePositionMap`] is the default.
+/// The position map must be oblivious for the `PathOram` using it to be oblivious.
+pub trait PositionMap: Oram<V = TreeIndex, Error = OramError> + Clone + std::fmt::Debug {
+    /// Returns a position map for `number_of_addresses` addresses, with every position initially 0.
+    /// The remaining parameters are those of the `PathOram` using the map, which a map may use for its own storage.
+    fn new<R: OramRng>(
+        number_of_addresses: Address,
+        rng: &mut R,
+        overflow_size: StashSize,
+        recursion_cutoff: RecursionCutoff,
+        stash_growth: StashGrowth,
+    ) -> Result<Self, OramError>;
+
+    /// Writes `new_positions[i]` to the entry of `addresses[i]`, for every `i`, and returns the previous positions.
+    /// If an address appears more than once, its last new position is written.
+    /// `PathOram` uses this to initialize the map, with batches of consecutive addresses.
+    ///
+    /// The default implementation writes each address with a separate access,
+    /// ignoring addresses that are out of range.
+    ///
+    /// # Errors
+    ///
+    /// If `addresses` and `new_positions` differ in length, returns an `InvalidConfigurationError`.
+    fn write_positions_batch<R: OramRng>(
+        &mut self,
+        addresses: &[Address],
+        new_positions: &[TreeIndex],
+        rng: &mut R,
+    ) -> Result<Vec<TreeIndex>, OramError> {
+        // This check is not constant-time, but only leaks whether the batch is well-formed or not.
+        if addresses.len() != new_positions.len() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of new positions".to_string(),
+                parameter_value: new_positions.len().to_string(),
+            });
+        }
+
+        let capacity = self.block_capacity()?;
+        let mut previous: Vec<TreeIndex> = Vec::with_capacity(addresses.len());
+        for (i, (address, new_position)) in addresses.iter().zip(new_positions).enumerate() {
+            let mut position = TreeIndex::default();
+            if *address < capacity {
+                position = self.write(*address, *new_position, rng)?;
+            }
+            if let Some(first) = addresses[..i].iter().position(|other| other == address) {
+                position = previous[first];
+            }
+            previous.push(position);
+        }
+        Ok(previous)
+    }
+
+    /// Returns the memory used by each level of the map, as reported by [`crate::PathOram::memory_usage`].
+    fn memory_usage(&self) -> Result<Vec<LevelMemoryUsage>, OramError>;
+
+    /// Returns the total overflow capacity of any stashes used by the map.
+    fn stash_capacity(&self) -> StashSize {
+        0
+    }
+
+    /// Performs `num_evictions` evictions of any stashes used by the map, without accessing any addresses.
+    fn flush_evictions(&mut self, _num_evictions: u64) -> Result<(), OramError> {
+        Ok(())
+    }
+
+    /// Returns the recursion depth and physical operation counts of a single access.
+    #[cfg(feature = "diagnostics")]
+    fn access_stats(&self) -> Result<crate::diagnostics::AccessStats, OramError> {
+        Ok(crate::diagnostics::AccessStats::default())
+    }
+}
+
+/// Returns whether a recursive position map level of `block_capacity` position blocks,
+/// in a tree with `blocks_per_bucket` blocks per bucket, is so small that scanning all of its blocks
+/// touches no more blocks than reading one of its paths. Such levels are collapsed into the linear base map,
+/// which saves the fixed cost of their stash and of the levels below them.
+pub(crate) fn collapses_into_base(block_capacity: Address, blocks_per_bucket: BucketSize) -> bool {
+    let path_length = Address::from(block_capacity.max(1).ilog2());
+    let path_blocks = path_length.saturating_mul(Address::try_from(blocks_per_bucket).unwrap_or(0));
+    block_capacity <= path_blocks
+}
 
 /// A recursive Path ORAM position map data structure. `AB` is the number of addresses stored in each ORAM block.
-#[derive(Debug)]
-pub enum PositionMap<const AB: BlockSize, const Z: BucketSize> {
+#[derive(Clone, Debug)]
+pub enum RecursivePositionMap<const AB: BlockSize, const Z: BucketSize> {
     /// A simple, linear-time `AddressOram`.
     Base(LinearTimeOram<PositionBlock<AB>>),
     /// A recursive `AddressOram` whose position map is also an `AddressOram`.
     Recursive(Box<PathOram<PositionBlock<AB>, Z, AB>>),
 }
-impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
+impl<const AB: BlockSize, const Z: BucketSize> RecursivePositionMap<AB, Z> {
     fn address_of_block(address: Address) -> Address {
         let block_address_bits = AB.ilog2();
         address >> block_address_bits
@@ -38,38 +124,127 @@ impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
     }
 }
 
-impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
-    pub fn write_position_block<R: RngCore + CryptoRng>(
+impl<const AB: BlockSize, const Z: BucketSize> PositionMap for RecursivePositionMap<AB, Z> {
+    /// Obliviously writes `new_positions[i]` to the entry of `addresses[i]`, for every `i`,
+    /// with a single access to the position block containing them, and returns the previous positions.
+    /// If an address appears more than once, its last new position is written.
+    ///
+    /// # Errors
+    ///
+    /// If `addresses` is empty, its entries do not all reside in the same position block,
+    /// or `addresses` and `new_positions` differ in length, returns an `InvalidConfigurationError`.
+    fn write_positions_batch<R: OramRng>(
         &mut self,
-        address: Address,
-        position_block: PositionBlock<AB>,
+        addresses: &[Address],
+        new_positions: &[TreeIndex],
         rng: &mut R,
-    ) -> Result<(), OramError> {
-        let address_of_block = PositionMap::<AB, Z>::address_of_block(address);
+    ) -> Result<Vec<TreeIndex>, OramError> {
+        // These checks are not constant-time, but only leak whether the batch is well-formed or not.
+        if addresses.len() != new_positions.len() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of new positions".to_string(),
+                parameter_value: new_positions.len().to_string(),
+            });
+        }
+        let address_of_block = match addresses.first() {
+            Some(address) => RecursivePositionMap::<AB, Z>::address_of_block(*address),
+            None => {
+                return Err(OramError::InvalidConfigurationError {
+                    parameter_name: "Number of addresses".to_string(),
+                    parameter_value: "0".to_string(),
+                })
+            }
+        };
+        if let Some(address) = addresses.iter().find(|address| {
+            RecursivePositionMap::<AB, Z>::address_of_block(**address) != address_of_block
+        }) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Batched address".to_string(),
+                parameter_value: address.to_string(),
+            });
+        }
 
-        match self {
-            PositionMap::Base(linear_oram) => {
-                linear_oram.write(address_of_block, position_block, rng)?;
+        let mut offsets = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            offsets.push(RecursivePositionMap::<AB, Z>::address_within_block(
+                *address,
+            )?);
+        }
+
+        // Compute the updated entries once, since the callback may be invoked many times by the stash scan.
+        let mut is_updated = [Choice::from(0); AB];
+        let mut updated_positions = [TreeIndex::default(); AB];
+        for i in 0..AB {
+            for (offset, new_position) in offsets.iter().zip(new_positions) {
+                let matches = i.ct_eq(offset);
+                updated_positions[i].conditional_assign(new_position, matches);
+                is_updated[i] |= matches;
+            }
+        }
+
+        let block_callback = |block: &PositionBlock<AB>| {
+            let mut result: PositionBlock<AB> = *block;
+            for (i, position) in result.data.iter_mut().enumerate() {
+                position.conditional_assign(&updated_positions[i], is_updated[i]);
             }
+            result
+        };
 
-            PositionMap::Recursive(block_oram) => {
-                block_oram.write(address_of_block, position_block, rng)?;
+        let block = match self {
+            RecursivePositionMap::Base(linear_oram) => {
+                linear_oram.access(address_of_block, block_callback, rng)?
             }
+            RecursivePositionMap::Recursive(block_oram) => {
+                block_oram.access(address_of_block, block_callback, rng)?
+            }
+        };
+
+        Ok(offsets
+            .iter()
+            .map(|offset| {
+                let mut result = TreeIndex::default();
+                for (i, position) in block.data.iter().enumerate() {
+                    result.conditional_assign(position, i.ct_eq(offset));
+                }
+                result
+            })
+            .collect())
+    }
+
+    fn memory_usage(&self) -> Result<Vec<LevelMemoryUsage>, OramError> {
+        match self {
+            RecursivePositionMap::Base(linear_oram) => Ok(linear_oram.memory_usage().levels),
+            RecursivePositionMap::Recursive(block_oram) => Ok(block_oram.memory_usage()?.levels),
         }
+    }
 
-        Ok(())
+    /// Returns the total overflow capacity of the stashes of the recursive position map ORAMs.
+    fn stash_capacity(&self) -> StashSize {
+        match self {
+            RecursivePositionMap::Base(_) => 0,
+            RecursivePositionMap::Recursive(block_oram) => block_oram.total_stash_capacity(),
+        }
     }
-}
 
-impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
-    pub fn new<R: CryptoRng + RngCore>(
+    fn flush_evictions(&mut self, num_evictions: u64) -> Result<(), OramError> {
+        match self {
+            // A linear-time ORAM has no stash to flush.
+            RecursivePositionMap::Base(_) => Ok(()),
+            RecursivePositionMap::Recursive(block_oram) => {
+                block_oram.flush_evictions(num_evictions)
+            }
+        }
+    }
+
+    fn new<R: OramRng>(
         number_of_addresses: Address,
         rng: &mut R,
         overflow_size: StashSize,
         recursion_cutoff: RecursionCutoff,
+        stash_growth: StashGrowth,
     ) -> Result<Self, OramError> {
         log::info!(
-            "PositionMap::new(number_of_addresses = {})",
+            "RecursivePositionMap::new(number_of_addresses = {})",
             number_of_addresses
         );
 
@@ -81,7 +256,9 @@ impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
         }
 
         let ab_address: Address = AB.try_into()?;
-        if number_of_addresses / ab_address <= recursion_cutoff {
+        if (number_of_addresses / ab_address <= recursion_cutoff)
+            | collapses_into_base(number_of_addresses / ab_address, Z)
+        {
             let mut block_capacity = number_of_addresses / ab_address;
             if number_of_addresses % ab_address > 0 {
                 block_capacity += 1;
@@ -89,37 +266,59 @@ impl<const AB: BlockSize, const Z: BucketSize> PositionMap<AB, Z> {
             Ok(Self::Base(LinearTimeOram::new(block_capacity)?))
         } else {
             let block_capacity = number_of_addresses / ab_address;
-            Ok(Self::Recursive(Box::new(PathOram::new_with_parameters(
+            Ok(Self::Recursive(Box::new(PathOram::new_with_stash_growth(
                 block_capacity,
+                PositionBlock::default(),
                 rng,
                 overflow_size,
                 recursion_cutoff,
+                stash_growth,
             )?)))
         }
     }
+
+    #[cfg(feature = "diagnostics")]
+    fn access_stats(&self) -> Result<crate::diagnostics::AccessStats, OramError> {
+        match self {
+            RecursivePositionMap::Base(linear_oram) => {
+                let capacity = linear_oram.block_capacity()?;
+                Ok(crate::diagnostics::AccessStats {
+                    physical_reads: capacity,
+                    physical_writes: capacity,
+                    ..Default::default()
+                })
+            }
+            RecursivePositionMap::Recursive(block_oram) => {
+                let mut stats = block_oram.access_cost_stats()?;
+                stats.recursion_depth += 1;
+                Ok(stats)
+            }
+        }
+    }
 }
 
-impl<const AB: BlockSize, const Z: BucketSize> Oram for PositionMap<AB, Z> {
+impl<const AB: BlockSize, const Z: BucketSize> Oram for RecursivePositionMap<AB, Z> {
     type V = TreeIndex;
+    type Error = OramError;
 
     fn block_capacity(&self) -> Result<Address, OramError> {
         match self {
-            PositionMap::Base(linear_oram) => linear_oram.block_capacity(),
-            PositionMap::Recursive(block_oram) => {
+            RecursivePositionMap::Base(linear_oram) => linear_oram.block_capacity(),
+            RecursivePositionMap::Recursive(block_oram) => {
                 let ab_address: Address = AB.try_into()?;
                 Ok(block_oram.block_capacity()? * ab_address)
             }
         }
     }
 
-    fn access<R: RngCore + CryptoRng, F: Fn(&TreeIndex) -> TreeIndex>(
+    fn access<R: OramRng, F: Fn(&TreeIndex) -> TreeIndex>(
         &mut self,
         address: Address,
         callback: F,
         rng: &mut R,
     ) -> Result<TreeIndex, OramError> {
-        let address_of_block = PositionMap::<AB, Z>::address_of_block(address);
-        let address_within_block = PositionMap::<AB, Z>::address_within_block(address)?;
+        let address_of_block = RecursivePositionMap::<AB, Z>::address_of_block(address);
+        let address_within_block = RecursivePositionMap::<AB, Z>::address_within_block(address)?;
 
         let block_callback = |block: &PositionBlock<AB>| {
             let mut result: PositionBlock<AB> = *block;
@@ -133,7 +332,7 @@ impl<const AB: BlockSize, const Z: BucketSize> Oram for PositionMap<AB, Z> {
 
         match self {
             // Base case: index into a linear-time ORAM.
-            PositionMap::Base(linear_oram) => {
+            RecursivePositionMap::Base(linear_oram) => {
                 let block = linear_oram.access(address_of_block, block_callback, rng)?;
                 Ok(block.data[address_within_block])
             }
@@ -142,7 +341,7 @@ impl<const AB: BlockSize, const Z: BucketSize> Oram for PositionMap<AB, Z> {
             // (1) split the address into an ORAM address (`address_of_block`) and an offset within the block (`address_within_block`)
             // (2) Recursively access the block at `address_of_block`, using a callback which updates only the address of interest in that block.
             // (3) Return the address of interest from the block.
-            PositionMap::Recursive(block_oram) => {
+            RecursivePositionMap::Recursive(block_oram) => {
                 let block = block_oram.access(address_of_block, block_callback, rng)?;
 
                 let mut result = u64::default();
@@ -156,3 +355,137 @@ impl<const AB: BlockSize, const Z: BucketSize> Oram for PositionMap<AB, Z> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bucket::BlockValue, path_oram::PathOram, stash::ObliviousStash, test_utils::random_workload,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A non-recursive position map, accessed by a linear scan.
+    #[derive(Clone, Debug)]
+    struct LinearScanPositionMap {
+        positions: Vec<TreeIndex>,
+    }
+
+    impl Oram for LinearScanPositionMap {
+        type V = TreeIndex;
+        type Error = OramError;
+
+        fn block_capacity(&self) -> Result<Address, OramError> {
+            Ok(self.positions.len().try_into()?)
+        }
+
+        fn access<R: OramRng, F: Fn(&TreeIndex) -> TreeIndex>(
+            &mut self,
+            address: Address,
+            callback: F,
+            _rng: &mut R,
+        ) -> Result<TreeIndex, OramError> {
+            let mut result = TreeIndex::default();
+            for (i, position) in self.positions.iter_mut().enumerate() {
+                let matches = (i as Address).ct_eq(&address);
+                result.conditional_assign(position, matches);
+                position.conditional_assign(&callback(position), matches);
+            }
+            Ok(result)
+        }
+    }
+
+    impl PositionMap for LinearScanPositionMap {
+        fn new<R: OramRng>(
+            number_of_addresses: Address,
+            _rng: &mut R,
+            _overflow_size: StashSize,
+            _recursion_cutoff: RecursionCutoff,
+            _stash_growth: StashGrowth,
+        ) -> Result<Self, OramError> {
+            Ok(Self {
+                positions: vec![0; number_of_addresses.try_into()?],
+            })
+        }
+
+        fn memory_usage(&self) -> Result<Vec<LevelMemoryUsage>, OramError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn path_oram_with_custom_position_map() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<
+            BlockValue<1>,
+            4,
+            8,
+            ObliviousStash<_>,
+            LinearScanPositionMap,
+        >::new_with_parameters(64, &mut rng, 40, 1)
+        .unwrap();
+        random_workload(&mut oram, 500);
+
+        let mut position_map =
+            LinearScanPositionMap::new(4, &mut rng, 0, 1, Default::default()).unwrap();
+        let previous = position_map
+            .write_positions_batch(&[1, 2, 1, 4], &[5, 6, 7, 8], &mut rng)
+            .unwrap();
+        assert_eq!(previous, vec![0, 0, 0, 0]);
+        assert_eq!(position_map.positions, vec![0, 7, 6, 0]);
+    }
+
+    #[test]
+    fn recursive_position_map_collapses_small_levels() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // 128 addresses fill 16 position blocks, no more than the 4 * 4 blocks of a path of a 16-block tree.
+        let position_map =
+            RecursivePositionMap::<8, 4>::new(128, &mut rng, 40, 1, Default::default()).unwrap();
+        assert!(matches!(position_map, RecursivePositionMap::Base(_)));
+        // 256 addresses fill 32 position blocks, more than the 4 * 5 blocks of a path of a 32-block tree.
+        let position_map =
+            RecursivePositionMap::<8, 4>::new(256, &mut rng, 40, 1, Default::default()).unwrap();
+        assert!(matches!(position_map, RecursivePositionMap::Recursive(_)));
+        assert!(!collapses_into_base(32, 6));
+        assert!(collapses_into_base(32, 7));
+    }
+
+    #[test]
+    fn write_positions_batch_updates_one_block() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for recursion_cutoff in [1, 1 << 10] {
+            let mut position_map = RecursivePositionMap::<8, 4>::new(
+                256,
+                &mut rng,
+                40,
+                recursion_cutoff,
+                Default::default(),
+            )
+            .unwrap();
+            for address in 16..24 {
+                position_map
+                    .write(address, address + 100, &mut rng)
+                    .unwrap();
+            }
+
+            let previous = position_map
+                .write_positions_batch(&[17, 20, 17], &[1, 2, 3], &mut rng)
+                .unwrap();
+            assert_eq!(previous, vec![117, 120, 117]);
+            for address in 16..24 {
+                let expected = match address {
+                    17 => 3,
+                    20 => 2,
+                    _ => address + 100,
+                };
+                assert_eq!(position_map.read(address, &mut rng).unwrap(), expected);
+            }
+
+            assert!(position_map
+                .write_positions_batch(&[17, 24], &[1, 2], &mut rng)
+                .is_err());
+            assert!(position_map
+                .write_positions_batch(&[], &[], &mut rng)
+                .is_err());
+        }
+    }
+}
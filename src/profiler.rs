@@ -0,0 +1,378 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A profiler recording the duration of each phase of Path ORAM accesses, and latency histograms
+//! of ORAM operations (requires the `profiling` feature).
+
+use std::time::Duration;
+
+/// The number of most recent samples kept for each phase.
+pub const MAX_SAMPLES_PER_PHASE: usize = 1 << 16;
+
+/// A phase of a Path ORAM access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessPhase {
+    /// Reading and updating the position of the target block in the position map,
+    /// including any accesses to recursive position map ORAMs.
+    PositionMap,
+    /// Reading the blocks of a path into the stash.
+    PathRead,
+    /// Scanning the stash for the target block. When accesses do not evict into the path they read
+    /// (see [`crate::EvictionStrategy`]), this also includes reading the path.
+    StashScan,
+    /// Obliviously sorting the stash before writing a path.
+    Sort,
+    /// Writing a path back to the tree, not including the sort.
+    PathWrite,
+}
+
+impl AccessPhase {
+    /// Every phase, in the order in which they occur during an access.
+    pub const ALL: [AccessPhase; 5] = [
+        AccessPhase::PositionMap,
+        AccessPhase::PathRead,
+        AccessPhase::StashScan,
+        AccessPhase::Sort,
+        AccessPhase::PathWrite,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A summary of the recorded durations of a phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhaseSummary {
+    /// The total number of times the phase was recorded, including samples no longer kept.
+    pub count: u64,
+    /// The mean of the kept samples.
+    pub mean: Duration,
+    /// The median of the kept samples.
+    pub p50: Duration,
+    /// The 90th percentile of the kept samples.
+    pub p90: Duration,
+    /// The 99th percentile of the kept samples.
+    pub p99: Duration,
+    /// The maximum of the kept samples.
+    pub max: Duration,
+}
+
+/// The samples kept for a single phase.
+#[derive(Clone, Debug, Default)]
+struct PhaseSamples {
+    samples: Vec<Duration>,
+    count: u64,
+}
+
+/// Records the durations of the phases of accesses to a [`crate::PathOram`]
+/// (see [`crate::PathOram::profiler`]), so that performance regressions can be localized.
+///
+/// The [`MAX_SAMPLES_PER_PHASE`] most recent samples of each phase are kept.
+/// Durations depend on the hardware and on stash overflows, but not otherwise on the addresses accessed;
+/// still, they should not be revealed to untrusted parties, and are omitted from the `Debug` output.
+#[derive(Clone)]
+pub struct Profiler {
+    phases: Vec<PhaseSamples>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            phases: vec![PhaseSamples::default(); AccessPhase::ALL.len()],
+        }
+    }
+}
+
+impl std::fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts: Vec<u64> = self.phases.iter().map(|phase| phase.count).collect();
+        f.debug_struct("Profiler")
+            .field("counts", &counts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Profiler {
+    /// Records that `phase` took `duration`.
+    pub(crate) fn record(&mut self, phase: AccessPhase, duration: Duration) {
+        let phase = &mut self.phases[phase.index()];
+        if phase.samples.len() < MAX_SAMPLES_PER_PHASE {
+            phase.samples.push(duration);
+        } else {
+            phase.samples[(phase.count % MAX_SAMPLES_PER_PHASE as u64) as usize] = duration;
+        }
+        phase.count += 1;
+    }
+
+    /// Returns a summary of the durations of `phase`, or `None` if it has not been recorded.
+    pub fn summary(&self, phase: AccessPhase) -> Option<PhaseSummary> {
+        let phase = &self.phases[phase.index()];
+        let mut samples = phase.samples.clone();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+        let total: Duration = samples.iter().sum();
+        Some(PhaseSummary {
+            count: phase.count,
+            mean: total / u32::try_from(samples.len()).unwrap_or(u32::MAX),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples[samples.len() - 1],
+        })
+    }
+
+    /// Returns the summaries of every recorded phase, in the order of [`AccessPhase::ALL`].
+    pub fn summaries(&self) -> Vec<(AccessPhase, PhaseSummary)> {
+        AccessPhase::ALL
+            .iter()
+            .filter_map(|phase| Some((*phase, self.summary(*phase)?)))
+            .collect()
+    }
+
+    /// Discards all recorded samples.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The number of buckets of a [`LatencyHistogram`] below which every nanosecond count has its own bucket.
+const LINEAR_BUCKETS: u64 = 16;
+
+/// The number of buckets into which each power of two above [`LINEAR_BUCKETS`] nanoseconds is divided.
+const SUB_BUCKETS_PER_POWER: u64 = 8;
+
+/// The total number of buckets of a [`LatencyHistogram`], covering every `u64` number of nanoseconds.
+const NUM_LATENCY_BUCKETS: usize = 16 + 60 * 8;
+
+/// A histogram of latencies with logarithmically sized buckets, in the style of HDR histograms.
+///
+/// Latencies are recorded in nanoseconds, with a relative error of at most 12.5%,
+/// in constant memory and constant time per sample, so that histograms can be kept indefinitely.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    total_nanos: u128,
+    max: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; NUM_LATENCY_BUCKETS],
+            count: 0,
+            total_nanos: 0,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl std::fmt::Debug for LatencyHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LatencyHistogram")
+            .field("count", &self.count)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LatencyHistogram {
+    /// Records a sample of `latency`.
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[Self::bucket(nanos)] += 1;
+        self.count += 1;
+        self.total_nanos += u128::from(nanos);
+        self.max = self.max.max(latency);
+    }
+
+    /// Returns the number of samples recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of the samples recorded, or zero if there are none.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let mean = self.total_nanos / u128::from(self.count);
+        Duration::from_nanos(u64::try_from(mean).unwrap_or(u64::MAX))
+    }
+
+    /// Returns the maximum of the samples recorded, or zero if there are none.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns an upper bound (within 12.5%) on the `percentile`-th percentile of the samples recorded,
+    /// for `0 <= percentile <= 100`, or zero if there are none.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank.max(1) {
+                return Duration::from_nanos(Self::bucket_upper_bound(bucket)).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Adds the samples of `other` to this histogram.
+    pub fn merge(&mut self, other: &Self) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.total_nanos += other.total_nanos;
+        self.max = self.max.max(other.max);
+    }
+
+    /// Discards all recorded samples.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the index of the bucket containing `nanos`.
+    fn bucket(nanos: u64) -> usize {
+        if nanos < LINEAR_BUCKETS {
+            return nanos as usize;
+        }
+        let exponent = u64::from(63 - nanos.leading_zeros());
+        let sub_bucket = (nanos >> (exponent - 3)) & (SUB_BUCKETS_PER_POWER - 1);
+        (LINEAR_BUCKETS + (exponent - 4) * SUB_BUCKETS_PER_POWER + sub_bucket) as usize
+    }
+
+    /// Returns the largest number of nanoseconds in bucket `bucket`.
+    fn bucket_upper_bound(bucket: usize) -> u64 {
+        let bucket = bucket as u64;
+        if bucket < LINEAR_BUCKETS {
+            return bucket;
+        }
+        let exponent = (bucket - LINEAR_BUCKETS) / SUB_BUCKETS_PER_POWER + 4;
+        let sub_bucket = (bucket - LINEAR_BUCKETS) % SUB_BUCKETS_PER_POWER;
+        let width = 1u64 << (exponent - 3);
+        ((SUB_BUCKETS_PER_POWER + sub_bucket) * width).saturating_add(width - 1)
+    }
+}
+
+/// An operation on an ORAM, for which latencies are recorded separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OramOperation {
+    /// A read ([`crate::Oram::read`]).
+    Read,
+    /// A write ([`crate::Oram::write`]).
+    Write,
+    /// A read-modify-write access ([`crate::Oram::access`]).
+    Access,
+}
+
+/// Latency histograms of the read, write, and access operations on an ORAM.
+/// Recorded by [`crate::OramManager`]; see [`crate::OramManager::timings`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationTimings {
+    read: LatencyHistogram,
+    write: LatencyHistogram,
+    access: LatencyHistogram,
+}
+
+impl OperationTimings {
+    /// Returns the histogram of the latencies of `operation`.
+    pub fn histogram(&self, operation: OramOperation) -> &LatencyHistogram {
+        match operation {
+            OramOperation::Read => &self.read,
+            OramOperation::Write => &self.write,
+            OramOperation::Access => &self.access,
+        }
+    }
+
+    /// Records that an `operation` took `latency`.
+    pub(crate) fn record(&mut self, operation: OramOperation, latency: Duration) {
+        match operation {
+            OramOperation::Read => self.read.record(latency),
+            OramOperation::Write => self.write.record(latency),
+            OramOperation::Access => self.access.record(latency),
+        }
+    }
+
+    /// Adds the samples of `other` to these histograms.
+    pub fn merge(&mut self, other: &Self) {
+        self.read.merge(&other.read);
+        self.write.merge(&other.write);
+        self.access.merge(&other.access);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiler_summarizes_samples() {
+        let mut profiler = Profiler::default();
+        assert_eq!(profiler.summary(AccessPhase::Sort), None);
+
+        for micros in 1..=100 {
+            profiler.record(AccessPhase::Sort, Duration::from_micros(micros));
+        }
+        let summary = profiler.summary(AccessPhase::Sort).unwrap();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.mean, Duration::from_nanos(50_500));
+        assert_eq!(summary.p50, Duration::from_micros(50));
+        assert_eq!(summary.p90, Duration::from_micros(90));
+        assert_eq!(summary.p99, Duration::from_micros(99));
+        assert_eq!(summary.max, Duration::from_micros(100));
+        assert_eq!(profiler.summaries().len(), 1);
+
+        profiler.reset();
+        assert_eq!(profiler.summary(AccessPhase::Sort), None);
+    }
+
+    #[test]
+    fn latency_histogram_bounds_percentiles() {
+        for nanos in [0, 1, 15, 16, 17, 100, 12_345, 1 << 40, u64::MAX] {
+            let bucket = LatencyHistogram::bucket(nanos);
+            assert!(bucket < NUM_LATENCY_BUCKETS);
+            let upper_bound = LatencyHistogram::bucket_upper_bound(bucket);
+            assert!(upper_bound >= nanos);
+            assert!(upper_bound - nanos <= nanos / 8);
+            if bucket > 0 {
+                assert!(LatencyHistogram::bucket_upper_bound(bucket - 1) < nanos);
+            }
+        }
+
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(50.0), Duration::ZERO);
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.mean(), Duration::from_nanos(500_500));
+        assert_eq!(histogram.max(), Duration::from_micros(1000));
+        for (percentile, expected) in [(50.0, 500), (90.0, 900), (99.0, 990), (100.0, 1000)] {
+            let estimate = histogram.percentile(percentile).as_nanos() as f64;
+            let expected = expected as f64 * 1000.0;
+            assert!((expected..=expected * 1.125).contains(&estimate));
+        }
+
+        let mut merged = LatencyHistogram::default();
+        merged.merge(&histogram);
+        merged.merge(&histogram);
+        assert_eq!(merged.count(), 2000);
+        assert_eq!(merged.percentile(50.0), histogram.percentile(50.0));
+        merged.reset();
+        assert_eq!(merged, LatencyHistogram::default());
+    }
+}
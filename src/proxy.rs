@@ -0,0 +1,294 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A proxy serving concurrent asynchronous requests to a single ORAM on a fixed schedule.
+
+use crate::{Address, Oram, OramError, OramRng};
+use rand::Rng;
+use std::{
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// An operation submitted to an [`OramProxy`].
+#[derive(Clone, Copy, Debug)]
+pub enum ProxyOperation<V> {
+    /// Reads the value at an address.
+    Read(Address),
+    /// Writes a value to an address, returning the previous value.
+    Write(Address, V),
+}
+
+struct ProxyRequest<V, E> {
+    operation: ProxyOperation<V>,
+    responder: Sender<Result<V, E>>,
+}
+
+/// The errors of requests submitted to an [`OramProxy`].
+#[derive(Error, Debug)]
+pub enum ProxyError<E> {
+    /// Errors of the underlying ORAM.
+    #[error(transparent)]
+    OramError(E),
+    /// Errors arising from the proxy worker stopping before serving a request.
+    #[error("The proxy worker stopped before serving the request.")]
+    WorkerStoppedError,
+}
+
+/// A handle to the response to a request submitted to an [`OramProxy`].
+#[derive(Debug)]
+pub struct ProxyResponse<V, E = OramError> {
+    receiver: Receiver<Result<V, E>>,
+}
+
+impl<V, E> ProxyResponse<V, E> {
+    /// Blocks until the request has been served, and returns its result.
+    pub fn wait(self) -> Result<V, ProxyError<E>> {
+        match self.receiver.recv() {
+            Ok(result) => result.map_err(ProxyError::OramError),
+            Err(_) => Err(ProxyError::WorkerStoppedError),
+        }
+    }
+
+    /// Returns the result of the request if it has been served, and `None` otherwise.
+    pub fn try_wait(&self) -> Option<Result<V, ProxyError<E>>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result.map_err(ProxyError::OramError)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(ProxyError::WorkerStoppedError)),
+        }
+    }
+}
+
+/// A proxy, in the spirit of [TaoStore](https://ieeexplore.ieee.org/document/7546532), that lets many clients
+/// (for example, the connections of a service terminating in one enclave) submit overlapping requests
+/// to a single ORAM without waiting for one another.
+///
+/// Requests are queued and served by a worker thread in rounds, one round every `period`.
+/// Each round performs exactly `batch_size` ORAM accesses, padding the queued requests with dummy reads
+/// of random addresses, and releases all of the round's responses together once the round is complete.
+///
+/// ## Consistency
+///
+/// Requests are served in the order in which they were submitted, so the proxy is sequentially consistent
+/// (indeed linearizable): a read returns the value of the latest write submitted before it.
+///
+/// ## Security
+///
+/// The number and timing of ORAM accesses, and of released responses, depend only on the schedule and
+/// not on the requests, their addresses, or on whether they depend on one another
+/// (for example, a read of an address with a pending write is served like any other request).
+/// When more than `batch_size` requests are pending, the excess waits for a later round,
+/// so the response latency reveals the load; `batch_size` should be sized to the peak load.
+/// The proxy does not hide the time it takes to perform the round's accesses, which should itself be constant.
+#[derive(Debug)]
+pub struct OramProxy<O: Oram> {
+    sender: Option<Sender<ProxyRequest<O::V, O::Error>>>,
+    worker: Option<JoinHandle<O>>,
+}
+
+impl<O> OramProxy<O>
+where
+    O: Oram + Send + 'static,
+    O::V: Send + 'static,
+    O::Error: Send + 'static,
+{
+    /// Starts a proxy serving requests to `oram` on a worker thread,
+    /// performing `batch_size` accesses every `period` using randomness from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// If `batch_size` or the capacity of `oram` is 0, returns an `InvalidConfigurationError`.
+    pub fn new<R: OramRng + Send + 'static>(
+        oram: O,
+        rng: R,
+        batch_size: usize,
+        period: Duration,
+    ) -> Result<Self, O::Error> {
+        if batch_size == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Batch size".to_string(),
+                parameter_value: batch_size.to_string(),
+            }
+            .into());
+        }
+        let capacity = oram.block_capacity()?;
+        if capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            }
+            .into());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let worker =
+            std::thread::spawn(move || Self::serve(oram, rng, receiver, batch_size, period));
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    /// Submits `operation` without waiting for it to be served.
+    pub fn submit(&self, operation: ProxyOperation<O::V>) -> ProxyResponse<O::V, O::Error> {
+        let (responder, receiver) = mpsc::channel();
+        if let Some(sender) = &self.sender {
+            // If the worker has stopped, the response handle reports it.
+            let _ = sender.send(ProxyRequest {
+                operation,
+                responder,
+            });
+        }
+        ProxyResponse { receiver }
+    }
+
+    /// Reads the value at `index`, blocking until the request has been served.
+    pub fn read(&self, index: Address) -> Result<O::V, ProxyError<O::Error>> {
+        self.submit(ProxyOperation::Read(index)).wait()
+    }
+
+    /// Writes `value` to `index`, blocking until the request has been served. Returns the previous value.
+    pub fn write(&self, index: Address, value: O::V) -> Result<O::V, ProxyError<O::Error>> {
+        self.submit(ProxyOperation::Write(index, value)).wait()
+    }
+
+    /// Serves all pending requests, stops the worker thread, and returns the underlying ORAM.
+    pub fn shutdown(mut self) -> O {
+        self.stop()
+            .expect("the worker runs until the proxy stops it")
+    }
+
+    fn stop(&mut self) -> Option<O> {
+        // Dropping the sender tells the worker to stop once the queue is empty.
+        self.sender = None;
+        self.worker
+            .take()
+            .map(|worker| worker.join().expect("the proxy worker panicked"))
+    }
+
+    fn serve<R: OramRng>(
+        mut oram: O,
+        mut rng: R,
+        receiver: Receiver<ProxyRequest<O::V, O::Error>>,
+        batch_size: usize,
+        period: Duration,
+    ) -> O {
+        let mut next_round = Instant::now() + period;
+        loop {
+            if let Some(wait) = next_round.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+            next_round += period;
+
+            let mut batch = Vec::with_capacity(batch_size);
+            let mut disconnected = false;
+            while batch.len() < batch_size {
+                match receiver.try_recv() {
+                    Ok(request) => batch.push(request),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        disconnected = true;
+                        break;
+                    }
+                }
+            }
+            if disconnected && batch.is_empty() {
+                return oram;
+            }
+
+            let mut responses = Vec::with_capacity(batch.len());
+            for request in batch {
+                let result = match request.operation {
+                    ProxyOperation::Read(index) => oram.read(index, &mut rng),
+                    ProxyOperation::Write(index, value) => oram.write(index, value, &mut rng),
+                };
+                responses.push((request.responder, result));
+            }
+            for _ in responses.len()..batch_size {
+                // The capacity was checked to be positive when the proxy was created.
+                if let Ok(capacity) = oram.block_capacity() {
+                    let _ = oram.read(rng.gen_range(0..capacity), &mut rng);
+                }
+            }
+
+            for (responder, result) in responses {
+                // The client may have dropped its response handle.
+                let _ = responder.send(result);
+            }
+        }
+    }
+}
+
+impl<O: Oram> Drop for OramProxy<O> {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            // A panic on the worker thread has already been reported to the clients as a stopped worker.
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultOram;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn proxy(capacity: Address, batch_size: usize) -> OramProxy<DefaultOram<u64>> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::new(capacity, &mut rng).unwrap();
+        OramProxy::new(oram, rng, batch_size, Duration::from_millis(1)).unwrap()
+    }
+
+    #[test]
+    fn proxy_serves_concurrent_clients() {
+        let proxy = proxy(64, 8);
+        std::thread::scope(|scope| {
+            for client in 0..4u64 {
+                let proxy = &proxy;
+                scope.spawn(move || {
+                    for i in 0..16 {
+                        let index = 16 * client + i;
+                        proxy.write(index, index + 1).unwrap();
+                        assert_eq!(proxy.read(index).unwrap(), index + 1);
+                    }
+                });
+            }
+        });
+
+        let mut oram = proxy.shutdown();
+        let mut rng = StdRng::seed_from_u64(1);
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 1);
+        }
+    }
+
+    #[test]
+    fn proxy_serves_requests_in_submission_order() {
+        let proxy = proxy(16, 4);
+        let responses: Vec<_> = (1..=10)
+            .map(|value| proxy.submit(ProxyOperation::Write(3, value)))
+            .collect();
+        let read = proxy.submit(ProxyOperation::Read(3));
+        for (previous, response) in responses.into_iter().enumerate() {
+            assert_eq!(response.wait().unwrap(), previous as u64);
+        }
+        assert_eq!(read.wait().unwrap(), 10);
+        assert!(proxy.read(16).is_err());
+    }
+
+    #[test]
+    fn proxy_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::<u64>::new(16, &mut rng).unwrap();
+        assert!(OramProxy::new(oram, rng, 0, Duration::from_millis(1)).is_err());
+    }
+}
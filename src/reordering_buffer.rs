@@ -0,0 +1,256 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A buffer that delays and obliviously permutes requests before issuing them to an ORAM.
+
+use crate::{proxy::ProxyOperation, utils::bitonic_sort_by_keys, Address, Oram, OramError};
+use crate::{OramBlock, OramRng};
+use rand::Rng;
+use std::collections::HashMap;
+use subtle::{Choice, ConditionallySelectable};
+
+/// Identifies a request submitted to a [`ReorderingBuffer`].
+pub type Ticket = u64;
+
+/// A request waiting in a [`ReorderingBuffer`], stored so that it can be permuted obliviously.
+#[derive(Clone, Copy, Default)]
+struct BufferedRequest<V: OramBlock> {
+    ticket: Ticket,
+    index: Address,
+    value: V,
+    is_write: u8,
+}
+
+impl<V: OramBlock> ConditionallySelectable for BufferedRequest<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            ticket: Ticket::conditional_select(&a.ticket, &b.ticket, choice),
+            index: Address::conditional_select(&a.index, &b.index, choice),
+            value: V::conditional_select(&a.value, &b.value, choice),
+            is_write: u8::conditional_select(&a.is_write, &b.is_write, choice),
+        }
+    }
+}
+
+impl<V: OramBlock> std::fmt::Debug for BufferedRequest<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The contents of a request are secret.
+        f.debug_struct("BufferedRequest").finish_non_exhaustive()
+    }
+}
+
+/// Holds incoming requests until `window_size` of them have arrived, then issues them to the ORAM
+/// in a uniformly random order.
+///
+/// An ORAM hides which addresses are accessed, but the host still observes when each access happens,
+/// which it can correlate with application events (such as the arrival of a network packet).
+/// Delaying requests to the end of a window and permuting them within it breaks this fine-grained correlation:
+/// the host only learns that a window was processed.
+///
+/// The permutation is applied with an oblivious sorting network on random keys, and the results
+/// are returned to ticket order the same way, so the memory access pattern of the buffer itself
+/// does not reveal the permutation. Partial windows flushed with [`ReorderingBuffer::flush`]
+/// are padded with dummy reads, so every window performs exactly `window_size` accesses.
+///
+/// ## Consistency
+///
+/// Requests within a window are executed in an arbitrary order, so a read returns either the value
+/// before or after a write to the same address submitted in the same window.
+/// Dependent requests must be submitted in different windows.
+#[derive(Debug)]
+pub struct ReorderingBuffer<O: Oram> {
+    oram: O,
+    window_size: usize,
+    pending: Vec<BufferedRequest<O::V>>,
+    next_ticket: Ticket,
+    results: HashMap<Ticket, O::V>,
+}
+
+impl<O: Oram> ReorderingBuffer<O> {
+    /// Wraps `oram`, issuing requests in windows of `window_size`.
+    ///
+    /// # Errors
+    ///
+    /// If `window_size` or the capacity of `oram` is 0, returns an `InvalidConfigurationError`.
+    pub fn new(oram: O, window_size: usize) -> Result<Self, O::Error> {
+        if window_size == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Window size".to_string(),
+                parameter_value: window_size.to_string(),
+            }
+            .into());
+        }
+        let capacity = oram.block_capacity()?;
+        if capacity == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            }
+            .into());
+        }
+
+        Ok(Self {
+            oram,
+            window_size,
+            pending: Vec::with_capacity(window_size),
+            next_ticket: 0,
+            results: HashMap::new(),
+        })
+    }
+
+    /// Submits `operation`, returning a ticket with which to retrieve its result.
+    /// If this completes a window, the window is issued to the ORAM.
+    ///
+    /// # Errors
+    ///
+    /// If the address of `operation` is out of bounds, returns an `AddressOutOfBoundsError`.
+    /// Otherwise, returns any error returned by the ORAM while issuing a window.
+    pub fn submit<R: OramRng>(
+        &mut self,
+        operation: ProxyOperation<O::V>,
+        rng: &mut R,
+    ) -> Result<Ticket, O::Error> {
+        let (index, value, is_write) = match operation {
+            ProxyOperation::Read(index) => (index, O::V::default(), 0),
+            ProxyOperation::Write(index, value) => (index, value, 1),
+        };
+
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.oram.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            }
+            .into());
+        }
+
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.pending.push(BufferedRequest {
+            ticket,
+            index,
+            value,
+            is_write,
+        });
+        if self.pending.len() == self.window_size {
+            self.issue_window(rng)?;
+        }
+        Ok(ticket)
+    }
+
+    /// Issues the pending requests, padded with dummy reads to a full window. Does nothing if none are pending.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the ORAM.
+    pub fn flush<R: OramRng>(&mut self, rng: &mut R) -> Result<(), O::Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.issue_window(rng)
+    }
+
+    /// Returns the result of the request with the given ticket (the value read, or the value overwritten),
+    /// if its window has been issued and the result has not already been taken.
+    pub fn take_result(&mut self, ticket: Ticket) -> Option<O::V> {
+        self.results.remove(&ticket)
+    }
+
+    /// Returns the number of requests waiting for their window to be issued.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Flushes the pending requests and returns the wrapped ORAM.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the ORAM.
+    pub fn into_inner<R: OramRng>(mut self, rng: &mut R) -> Result<O, O::Error> {
+        self.flush(rng)?;
+        Ok(self.oram)
+    }
+
+    fn issue_window<R: OramRng>(&mut self, rng: &mut R) -> Result<(), O::Error> {
+        let capacity = self.oram.block_capacity()?;
+        let num_real = self.pending.len();
+        let mut window = std::mem::take(&mut self.pending);
+        while window.len() < self.window_size {
+            window.push(BufferedRequest {
+                ticket: Ticket::MAX,
+                index: rng.gen_range(0..capacity),
+                value: O::V::default(),
+                is_write: 0,
+            });
+        }
+
+        let mut random_keys: Vec<u64> = (0..window.len()).map(|_| rng.gen()).collect();
+        bitonic_sort_by_keys(&mut window, &mut random_keys);
+
+        let mut results = Vec::with_capacity(window.len());
+        let mut tickets = Vec::with_capacity(window.len());
+        for request in &window {
+            let is_write = Choice::from(request.is_write);
+            let result = self.oram.access(
+                request.index,
+                |old| O::V::conditional_select(old, &request.value, is_write),
+                rng,
+            )?;
+            results.push(result);
+            tickets.push(request.ticket);
+        }
+
+        // Dummy requests have the largest ticket, so they are sorted to the end.
+        bitonic_sort_by_keys(&mut results, &mut tickets);
+        for (ticket, result) in tickets.into_iter().zip(results).take(num_real) {
+            self.results.insert(ticket, result);
+        }
+        self.pending = Vec::with_capacity(self.window_size);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultOram;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn reordering_buffer_returns_results_by_ticket() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        let mut buffer = ReorderingBuffer::new(oram, 8).unwrap();
+
+        let writes: Vec<_> = (0..20)
+            .map(|i| {
+                buffer
+                    .submit(ProxyOperation::Write(i, i + 100), &mut rng)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(buffer.num_pending(), 4);
+        assert_eq!(buffer.take_result(writes[19]), None);
+        buffer.flush(&mut rng).unwrap();
+        assert_eq!(buffer.num_pending(), 0);
+        for ticket in writes {
+            assert_eq!(buffer.take_result(ticket), Some(0));
+        }
+
+        let reads: Vec<_> = (0..20)
+            .map(|i| buffer.submit(ProxyOperation::Read(i), &mut rng).unwrap())
+            .collect();
+        buffer.flush(&mut rng).unwrap();
+        for (i, ticket) in reads.into_iter().enumerate() {
+            assert_eq!(buffer.take_result(ticket), Some(i as u64 + 100));
+        }
+
+        assert!(buffer.submit(ProxyOperation::Read(64), &mut rng).is_err());
+        assert!(ReorderingBuffer::new(buffer.into_inner(&mut rng).unwrap(), 0).is_err());
+    }
+}
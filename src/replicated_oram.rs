@@ -0,0 +1,441 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A pair of ORAM replicas updated in lockstep, tolerating the failure of one of them.
+
+use crate::{
+    access_outcome::AccessOutcome,
+    audit::{self, AuditEventKind},
+    Address, Oram, OramError, OramRng,
+};
+use thiserror::Error;
+
+/// One of the two replicas of a [`ReplicatedOram`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Replica {
+    /// The first replica.
+    Primary,
+    /// The second replica.
+    Secondary,
+}
+
+/// What a [`ReplicatedOram`] does when its replicas return different values for the same access.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MismatchPolicy {
+    /// Return a [`ReplicatedOramError::ReplicaMismatchError`], leaving both replicas as they are.
+    #[default]
+    Fail,
+    /// Return the value of the primary replica, and repair the secondary replica with an extra access.
+    TrustPrimary,
+    /// Return the value of the secondary replica, and repair the primary replica with an extra access.
+    TrustSecondary,
+}
+
+/// The errors of a [`ReplicatedOram`] whose replicas return errors of type `E`.
+#[derive(Error, Debug)]
+pub enum ReplicatedOramError<E> {
+    /// Errors of the replicas, or of the configuration of the wrapper.
+    #[error(transparent)]
+    OramError(E),
+    /// Errors arising from the replicas disagreeing on the value of an address.
+    #[error("The replicas disagree on the value of ORAM address {index}.")]
+    ReplicaMismatchError {
+        /// The address whose values differ.
+        index: Address,
+    },
+}
+
+impl<E: From<OramError>> From<OramError> for ReplicatedOramError<E> {
+    fn from(error: OramError) -> Self {
+        Self::OramError(error.into())
+    }
+}
+
+/// Wraps two ORAMs of the same capacity, possibly on different storage backends, applying every access to both,
+/// so that the data survives the failure of either backend.
+///
+/// While both replicas are healthy, each access is applied to the primary replica and then to the secondary replica,
+/// with the same address and callback, so each backend observes the access pattern of a single ORAM.
+/// If one replica returns an error, it is marked as failed and the access is served by the other replica,
+/// which alone serves every subsequent access until [`ReplicatedOram::resync`] restores the failed replica.
+/// If the replicas return different values, the [`MismatchPolicy`] decides which one (if any) to trust,
+/// and the mismatch is recorded in the audit log as an integrity failure.
+///
+/// ## Security
+///
+/// Failures and mismatches are visible to the backends: a failed replica stops receiving accesses,
+/// and repairing a mismatch makes an extra access to the repaired replica.
+/// Both replicas must start with the same contents, which they do if they are freshly initialized
+/// with the same fill value.
+#[derive(Debug)]
+pub struct ReplicatedOram<A: Oram, B: Oram<V = A::V>> {
+    primary: A,
+    secondary: B,
+    policy: MismatchPolicy,
+    failed: Option<Replica>,
+    mismatches: u64,
+}
+
+impl<A: Oram, B: Oram<V = A::V>> ReplicatedOram<A, B>
+where
+    B::Error: Into<A::Error>,
+{
+    /// Wraps `primary` and `secondary`, which must hold the same contents,
+    /// resolving mismatches according to `policy`.
+    ///
+    /// # Errors
+    ///
+    /// If the replicas have different capacities, returns an `InvalidConfigurationError`.
+    pub fn new(
+        primary: A,
+        secondary: B,
+        policy: MismatchPolicy,
+    ) -> Result<Self, ReplicatedOramError<A::Error>> {
+        let capacity = primary
+            .block_capacity()
+            .map_err(ReplicatedOramError::OramError)?;
+        let secondary_capacity = secondary.block_capacity().map_err(Self::secondary_error)?;
+        if capacity != secondary_capacity {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Secondary replica capacity".to_string(),
+                parameter_value: secondary_capacity.to_string(),
+            }
+            .into());
+        }
+        Ok(Self {
+            primary,
+            secondary,
+            policy,
+            failed: None,
+            mismatches: 0,
+        })
+    }
+
+    /// Returns the replica that has failed, if any.
+    pub fn failed_replica(&self) -> Option<Replica> {
+        self.failed
+    }
+
+    /// Returns the number of accesses for which the replicas returned different values.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches
+    }
+
+    /// Copies every address from `source` to the other replica, and marks both replicas as healthy.
+    /// Call this after replacing or repairing the backend of a failed replica,
+    /// or after a [`ReplicatedOramError::ReplicaMismatchError`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by either replica, in which case the failed replica, if any, remains failed.
+    pub fn resync<R: OramRng>(
+        &mut self,
+        source: Replica,
+        rng: &mut R,
+    ) -> Result<(), ReplicatedOramError<A::Error>> {
+        log::info!("ReplicatedOram::resync(source = {:?})", source);
+
+        for index in 0..self.block_capacity()? {
+            match source {
+                Replica::Primary => {
+                    let value = self
+                        .primary
+                        .read(index, rng)
+                        .map_err(ReplicatedOramError::OramError)?;
+                    self.secondary
+                        .write(index, value, rng)
+                        .map_err(Self::secondary_error)?;
+                }
+                Replica::Secondary => {
+                    let value = self
+                        .secondary
+                        .read(index, rng)
+                        .map_err(Self::secondary_error)?;
+                    self.primary
+                        .write(index, value, rng)
+                        .map_err(ReplicatedOramError::OramError)?;
+                }
+            }
+        }
+        self.failed = None;
+        Ok(())
+    }
+
+    /// Performs an access as [`Oram::access`], and additionally reports whether it recovered
+    /// from a failed or corrupted replica. [`AccessOutcome::integrity_repaired`] is set if a replica
+    /// failed during the access, or if the replicas disagreed and the [`MismatchPolicy`] repaired one of them.
+    pub fn access_checked<R: OramRng, F: Fn(&A::V) -> A::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<AccessOutcome<A::V>, ReplicatedOramError<A::Error>> {
+        let mismatches = self.mismatches;
+        let failed = self.failed;
+        let mut outcome = AccessOutcome::new(self.access(index, callback, rng)?);
+        outcome.integrity_repaired = self.mismatches > mismatches || self.failed != failed;
+        Ok(outcome)
+    }
+
+    /// Returns the wrapped replicas.
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.secondary)
+    }
+
+    /// Resolves a mismatch between the values `primary_value` and `secondary_value` returned for `index`.
+    fn arbitrate<R: OramRng, F: Fn(&A::V) -> A::V>(
+        &mut self,
+        index: Address,
+        primary_value: A::V,
+        secondary_value: A::V,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<A::V, ReplicatedOramError<A::Error>> {
+        self.mismatches += 1;
+        audit::record(
+            AuditEventKind::IntegrityFailure,
+            format!("ReplicatedOram replicas disagree on address {index}."),
+        );
+
+        match self.policy {
+            MismatchPolicy::Fail => Err(ReplicatedOramError::ReplicaMismatchError { index }),
+            MismatchPolicy::TrustPrimary => {
+                self.secondary
+                    .write(index, callback(&primary_value), rng)
+                    .map_err(Self::secondary_error)?;
+                Ok(primary_value)
+            }
+            MismatchPolicy::TrustSecondary => {
+                self.primary
+                    .write(index, callback(&secondary_value), rng)
+                    .map_err(ReplicatedOramError::OramError)?;
+                Ok(secondary_value)
+            }
+        }
+    }
+
+    fn fail_over(&mut self, replica: Replica, error: impl std::fmt::Display) {
+        log::warn!("ReplicatedOram {replica:?} replica failed, failing over: {error}");
+        self.failed = Some(replica);
+    }
+
+    fn secondary_error(error: B::Error) -> ReplicatedOramError<A::Error> {
+        ReplicatedOramError::OramError(error.into())
+    }
+}
+
+impl<A: Oram, B: Oram<V = A::V>> Oram for ReplicatedOram<A, B>
+where
+    B::Error: Into<A::Error>,
+{
+    type V = A::V;
+    type Error = ReplicatedOramError<A::Error>;
+
+    fn block_capacity(&self) -> Result<Address, Self::Error> {
+        match self.failed {
+            Some(Replica::Primary) => self
+                .secondary
+                .block_capacity()
+                .map_err(Self::secondary_error),
+            _ => self
+                .primary
+                .block_capacity()
+                .map_err(ReplicatedOramError::OramError),
+        }
+    }
+
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, Self::Error> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        // Checking it here ensures that errors returned by the replicas below are failures of the replicas.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            }
+            .into());
+        }
+
+        match self.failed {
+            Some(Replica::Primary) => {
+                return self
+                    .secondary
+                    .access(index, callback, rng)
+                    .map_err(Self::secondary_error)
+            }
+            Some(Replica::Secondary) => {
+                return self
+                    .primary
+                    .access(index, callback, rng)
+                    .map_err(ReplicatedOramError::OramError)
+            }
+            None => {}
+        }
+
+        let primary_result = self.primary.access(index, &callback, rng);
+        let secondary_result = self.secondary.access(index, &callback, rng);
+        match (primary_result, secondary_result) {
+            (Ok(primary_value), Ok(secondary_value)) => {
+                if primary_value == secondary_value {
+                    Ok(primary_value)
+                } else {
+                    self.arbitrate(index, primary_value, secondary_value, callback, rng)
+                }
+            }
+            (Ok(primary_value), Err(error)) => {
+                self.fail_over(Replica::Secondary, error);
+                Ok(primary_value)
+            }
+            (Err(error), Ok(secondary_value)) => {
+                self.fail_over(Replica::Primary, error);
+                Ok(secondary_value)
+            }
+            (Err(error), Err(_)) => Err(ReplicatedOramError::OramError(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{linear_time_oram::LinearTimeOram, test_utils::random_workload, DefaultOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A linear-time ORAM that fails every access once `broken` is set,
+    /// and otherwise adds `offset` to every value it returns.
+    #[derive(Debug)]
+    struct FaultyOram {
+        oram: LinearTimeOram<u64>,
+        broken: bool,
+        offset: u64,
+    }
+
+    impl FaultyOram {
+        fn new(capacity: Address) -> Self {
+            Self {
+                oram: LinearTimeOram::new(capacity).unwrap(),
+                broken: false,
+                offset: 0,
+            }
+        }
+    }
+
+    impl Oram for FaultyOram {
+        type V = u64;
+        type Error = OramError;
+
+        fn block_capacity(&self) -> Result<Address, OramError> {
+            self.oram.block_capacity()
+        }
+
+        fn access<R: OramRng, F: Fn(&u64) -> u64>(
+            &mut self,
+            index: Address,
+            callback: F,
+            rng: &mut R,
+        ) -> Result<u64, OramError> {
+            if self.broken {
+                return Err(OramError::PoisonedError);
+            }
+            let value = self.oram.access(index, callback, rng)?;
+            Ok(value + self.offset)
+        }
+    }
+
+    #[test]
+    fn replicated_oram_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let primary = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        let secondary = FaultyOram::new(64);
+        let mut oram = ReplicatedOram::new(primary, secondary, MismatchPolicy::Fail).unwrap();
+        random_workload(&mut oram, 500);
+        assert_eq!(oram.failed_replica(), None);
+        assert_eq!(oram.mismatches(), 0);
+
+        let secondary = FaultyOram::new(32);
+        let primary = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        assert!(ReplicatedOram::new(primary, secondary, MismatchPolicy::Fail).is_err());
+    }
+
+    #[test]
+    fn replicated_oram_fails_over_and_resyncs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = ReplicatedOram::new(
+            FaultyOram::new(16),
+            FaultyOram::new(16),
+            MismatchPolicy::Fail,
+        )
+        .unwrap();
+        oram.write(1, 10, &mut rng).unwrap();
+
+        oram.primary.broken = true;
+        assert_eq!(oram.write(1, 11, &mut rng).unwrap(), 10);
+        assert_eq!(oram.failed_replica(), Some(Replica::Primary));
+        assert_eq!(oram.write(2, 20, &mut rng).unwrap(), 0);
+        assert!(oram.read(16, &mut rng).is_err());
+
+        oram.primary.broken = false;
+        oram.resync(Replica::Secondary, &mut rng).unwrap();
+        assert_eq!(oram.failed_replica(), None);
+        let (mut primary, _) = oram.into_inner();
+        assert_eq!(primary.read(1, &mut rng).unwrap(), 11);
+        assert_eq!(primary.read(2, &mut rng).unwrap(), 20);
+
+        let mut oram = ReplicatedOram::new(
+            FaultyOram::new(16),
+            FaultyOram::new(16),
+            MismatchPolicy::Fail,
+        )
+        .unwrap();
+        oram.secondary.broken = true;
+        let outcome = oram.access_checked(1, |_| 11, &mut rng).unwrap();
+        assert!(outcome.integrity_repaired);
+        let outcome = oram.access_checked(1, |_| 12, &mut rng).unwrap();
+        assert_eq!(outcome.value, 11);
+        assert!(!outcome.integrity_repaired);
+    }
+
+    #[test]
+    fn replicated_oram_arbitrates_mismatches() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut secondary = FaultyOram::new(16);
+        secondary.offset = 100;
+        let mut oram =
+            ReplicatedOram::new(FaultyOram::new(16), secondary, MismatchPolicy::Fail).unwrap();
+        assert!(matches!(
+            oram.read(3, &mut rng),
+            Err(ReplicatedOramError::ReplicaMismatchError { index: 3 })
+        ));
+
+        oram.policy = MismatchPolicy::TrustPrimary;
+        assert_eq!(oram.write(3, 7, &mut rng).unwrap(), 0);
+        oram.secondary.offset = 0;
+        assert_eq!(oram.read(3, &mut rng).unwrap(), 7);
+        assert_eq!(oram.mismatches(), 2);
+
+        let outcome = oram.access_checked(3, |value| *value, &mut rng).unwrap();
+        assert_eq!(outcome.value, 7);
+        assert!(!outcome.is_degraded());
+        oram.secondary.offset = 100;
+        let outcome = oram.access_checked(3, |value| *value, &mut rng).unwrap();
+        assert_eq!(outcome.value, 7);
+        assert!(outcome.integrity_repaired);
+        oram.secondary.offset = 0;
+
+        oram.secondary.offset = 100;
+        oram.policy = MismatchPolicy::TrustSecondary;
+        assert_eq!(oram.read(3, &mut rng).unwrap(), 107);
+        oram.secondary.offset = 0;
+        assert_eq!(oram.primary.read(3, &mut rng).unwrap(), 107);
+        assert_eq!(oram.failed_replica(), None);
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious fixed-capacity ring buffer.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// A fixed-capacity FIFO queue of `V` values stored in a [`DefaultOram`],
+/// for example to pass secret-dependent work items between enclave threads (behind a `Mutex`).
+///
+/// ## Security
+///
+/// `push_back` and `pop_front` each make exactly one ORAM access and run in constant time,
+/// whether or not they succeed. The head and tail positions, and hence the number of queued values,
+/// are never revealed: success is reported as a `Choice`, and failed operations access a position
+/// of the buffer like successful ones do, without modifying it.
+#[derive(Debug)]
+pub struct ObliviousRingBuffer<V: OramBlock> {
+    slots: DefaultOram<V>,
+    /// The position of the first value in the queue.
+    head: Address,
+    /// The number of values in the queue.
+    len: Address,
+}
+
+impl<V: OramBlock> ObliviousRingBuffer<V> {
+    /// Returns a new, empty ring buffer holding up to `capacity` values.
+    ///
+    /// # Errors
+    ///
+    /// If `capacity` is not a power of two, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(capacity: Address, rng: &mut R) -> Result<Self, OramError> {
+        if !capacity.is_power_of_two() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Ring buffer capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            });
+        }
+        Ok(Self {
+            slots: DefaultOram::new(capacity, rng)?,
+            head: 0,
+            len: 0,
+        })
+    }
+
+    /// Returns the maximum number of values in the buffer.
+    pub fn capacity(&self) -> Result<Address, OramError> {
+        self.slots.block_capacity()
+    }
+
+    /// Returns the number of values in the buffer. The result is secret, and should be handled in constant time.
+    pub fn len(&self) -> Address {
+        self.len
+    }
+
+    /// Returns whether the buffer is empty. Like [`ObliviousRingBuffer::len`], the result is secret.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Obliviously appends `value` to the back of the buffer.
+    /// Returns 1 if the value was appended, and 0 if the buffer was full.
+    pub fn push_back<R: OramRng>(&mut self, value: V, rng: &mut R) -> Result<Choice, OramError> {
+        let capacity = self.capacity()?;
+        let has_room = !self.len.ct_eq(&capacity);
+        let tail = (self.head + self.len) & (capacity - 1);
+
+        self.slots.access(
+            tail,
+            |old| V::conditional_select(old, &value, has_room),
+            rng,
+        )?;
+        self.len
+            .conditional_assign(&(self.len.wrapping_add(1)), has_room);
+        Ok(has_room)
+    }
+
+    /// Obliviously removes the value at the front of the buffer.
+    /// Returns the value and 1, or `V::default()` and 0 if the buffer was empty.
+    pub fn pop_front<R: OramRng>(&mut self, rng: &mut R) -> Result<(V, Choice), OramError> {
+        let capacity = self.capacity()?;
+        let nonempty = !self.len.ct_eq(&0);
+
+        let value = self.slots.read(self.head, rng)?;
+        self.head
+            .conditional_assign(&((self.head + 1) & (capacity - 1)), nonempty);
+        self.len
+            .conditional_assign(&(self.len.wrapping_sub(1)), nonempty);
+        Ok((
+            V::conditional_select(&V::default(), &value, nonempty),
+            nonempty,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn ring_buffer_matches_vec_deque() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut buffer = ObliviousRingBuffer::<u64>::new(8, &mut rng).unwrap();
+        let mut mirror = VecDeque::new();
+
+        for i in 0..500 {
+            if rng.gen_bool(0.5) {
+                let pushed = buffer.push_back(i, &mut rng).unwrap();
+                assert_eq!(bool::from(pushed), mirror.len() < 8);
+                if mirror.len() < 8 {
+                    mirror.push_back(i);
+                }
+            } else {
+                let (value, popped) = buffer.pop_front(&mut rng).unwrap();
+                match mirror.pop_front() {
+                    Some(expected) => {
+                        assert!(bool::from(popped));
+                        assert_eq!(value, expected);
+                    }
+                    None => {
+                        assert!(!bool::from(popped));
+                        assert_eq!(value, 0);
+                    }
+                }
+            }
+            assert_eq!(buffer.len(), mirror.len() as u64);
+        }
+    }
+
+    #[test]
+    fn ring_buffer_rejects_invalid_capacity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(ObliviousRingBuffer::<u64>::new(6, &mut rng).is_err());
+        assert!(ObliviousRingBuffer::<u64>::new(0, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! The random number generators accepted by ORAMs.
+
+use crate::audit::{self, AuditEventKind};
+use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// A cryptographically secure random number generator, as required by ORAM operations.
+///
+/// `OramRng` is implemented for every RNG implementing `RngCore` and `CryptoRng` from `rand` 0.8.
+/// With the `rand_core_0_9` feature, RNGs from the `rand_core` 0.9 ecosystem (such as those of `rand` 0.9)
+/// can be used by wrapping them in a `RandCore09Rng`.
+/// (A second blanket implementation for `rand_core` 0.9 RNGs is not possible,
+/// since a type may implement the traits of both versions.)
+///
+/// `OramRng` is object-safe, and `&mut dyn OramRng` itself implements `OramRng`, so RNG trait objects can be used.
+pub trait OramRng: RngCore + CryptoRng {}
+
+impl<R: RngCore + CryptoRng + ?Sized> OramRng for R {}
+
+/// Adapts an RNG implementing `rand_core` 0.9's `CryptoRng` into an [`OramRng`].
+///
+/// ```
+/// use oram::{rng::RandCore09Rng, DefaultOram, Oram};
+/// # use oram::OramError;
+/// # use rand_core_0_9 as rand_core;
+/// # struct Counter(u64);
+/// # impl rand_core::RngCore for Counter {
+/// #     fn next_u32(&mut self) -> u32 { self.next_u64() as u32 }
+/// #     fn next_u64(&mut self) -> u64 { self.0 += 1; self.0 }
+/// #     fn fill_bytes(&mut self, dst: &mut [u8]) { rand_core::impls::fill_bytes_via_next(self, dst) }
+/// # }
+/// # impl rand_core::CryptoRng for Counter {}
+/// # let new_rng = Counter(0);
+/// // `new_rng` implements `rand_core::CryptoRng` from rand_core 0.9.
+/// let mut rng = RandCore09Rng(new_rng);
+/// let mut oram = DefaultOram::<u64>::new(64, &mut rng)?;
+/// oram.write(3, 42, &mut rng)?;
+/// # Ok::<(), OramError>(())
+/// ```
+#[cfg(feature = "rand_core_0_9")]
+#[derive(Clone, Debug)]
+pub struct RandCore09Rng<R>(pub R);
+
+#[cfg(feature = "rand_core_0_9")]
+impl<R: rand_core_0_9::RngCore> RngCore for RandCore09Rng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand_core_0_9")]
+impl<R: rand_core_0_9::CryptoRng> CryptoRng for RandCore09Rng<R> {}
+
+/// The default number of bytes a [`BufferedRng`] generates between reseedings.
+pub const DEFAULT_RESEED_INTERVAL: u64 = 1 << 20;
+
+/// A fast CSPRNG (`rand`'s `StdRng`) seeded from, and periodically reseeded from, an inner [`OramRng`].
+///
+/// Each ORAM access draws fresh leaves at every level of recursion. With a system RNG such as `OsRng`,
+/// each of those draws is a system call; wrapping the system RNG in a `BufferedRng` amortizes them
+/// to one system call every `reseed_interval` bytes. The ORAMs of this crate draw directly from the RNG
+/// passed to each access, so the wrapping is up to the caller.
+///
+/// Every reseeding is recorded in the [audit log](crate::audit) as an [`AuditEventKind::RngReseed`] event.
+///
+/// ```
+/// use oram::{rng::BufferedRng, DefaultOram, Oram};
+/// # use oram::OramError;
+///
+/// let mut rng = BufferedRng::new(rand::rngs::OsRng);
+/// let mut oram = DefaultOram::<u64>::new(64, &mut rng)?;
+/// oram.write(3, 42, &mut rng)?;
+/// # Ok::<(), OramError>(())
+/// ```
+#[derive(Debug)]
+pub struct BufferedRng<R: OramRng> {
+    inner: R,
+    rng: StdRng,
+    reseed_interval: u64,
+    bytes_until_reseed: u64,
+}
+
+impl<R: OramRng> BufferedRng<R> {
+    /// Returns a `BufferedRng` reseeded from `inner` every [`DEFAULT_RESEED_INTERVAL`] bytes.
+    pub fn new(inner: R) -> Self {
+        Self::new_with_reseed_interval(inner, DEFAULT_RESEED_INTERVAL)
+    }
+
+    /// Returns a `BufferedRng` reseeded from `inner` every `reseed_interval` bytes (and at least once per draw).
+    pub fn new_with_reseed_interval(mut inner: R, reseed_interval: u64) -> Self {
+        let rng = Self::seed_from(&mut inner);
+        Self {
+            inner,
+            rng,
+            reseed_interval,
+            bytes_until_reseed: reseed_interval,
+        }
+    }
+
+    /// Returns the inner RNG.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn seed_from(inner: &mut R) -> StdRng {
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        inner.fill_bytes(&mut seed);
+        StdRng::from_seed(seed)
+    }
+
+    /// Reseeds if fewer than `num_bytes` bytes remain before the next scheduled reseeding.
+    fn prepare(&mut self, num_bytes: usize) {
+        let num_bytes = u64::try_from(num_bytes).unwrap_or(u64::MAX);
+        if self.bytes_until_reseed < num_bytes {
+            self.rng = Self::seed_from(&mut self.inner);
+            self.bytes_until_reseed = self.reseed_interval;
+            audit::record(
+                AuditEventKind::RngReseed,
+                format!(
+                    "BufferedRng reseeded after its reseed interval of {} bytes.",
+                    self.reseed_interval
+                ),
+            );
+        }
+        self.bytes_until_reseed = self.bytes_until_reseed.saturating_sub(num_bytes);
+    }
+}
+
+impl<R: OramRng> RngCore for BufferedRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.prepare(4);
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.prepare(8);
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.prepare(dest.len());
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<R: OramRng> CryptoRng for BufferedRng<R> {}
+
+/// A deterministic random number generator for reproducible ORAM runs.
+///
+/// All randomness used by this crate's ORAMs is drawn from the RNG passed by the caller,
+/// so an ORAM driven by a `DeterministicRng` evolves identically (the same tree, positions, and stash)
+/// across runs and platforms, given the same seed, parameters, and sequence of operations.
+/// The generator's output itself is fixed, independent of the versions of this crate and of `rand`.
+/// This supports debugging, differential testing against other implementations, and reproducible experiments.
+///
+/// The generator is the `ChaCha20` stream cipher (20 rounds), keyed by the 32-byte seed, with stream (nonce) 0
+/// and starting at block 0. Each 64-byte keystream block is consumed as sixteen little-endian 32-bit words,
+/// exactly as by `rand_chacha::ChaCha20Rng`.
+///
+/// **Warning**: a `DeterministicRng` is only as secret as its seed. A fixed or guessable seed
+/// makes the access pattern predictable, so use it only for testing and research.
+#[derive(Clone, Debug)]
+pub struct DeterministicRng(ChaCha20Rng);
+
+impl DeterministicRng {
+    /// Returns a `DeterministicRng` keyed by `seed`.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self(ChaCha20Rng::from_seed(seed))
+    }
+
+    /// Returns a `DeterministicRng` keyed by the little-endian bytes of `seed`, followed by zeros.
+    pub fn from_u64_seed(seed: u64) -> Self {
+        let mut key = [0u8; 32];
+        key[..8].copy_from_slice(&seed.to_le_bytes());
+        Self::from_seed(key)
+    }
+}
+
+impl RngCore for DeterministicRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for DeterministicRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::random_workload, DefaultOram, Oram};
+
+    #[test]
+    fn buffered_rng_reseeds_from_inner() {
+        let mut inner = StdRng::seed_from_u64(0);
+        let mut rng = BufferedRng::new_with_reseed_interval(&mut inner, 16);
+
+        // The first 16 bytes do not touch the inner RNG; the next byte reseeds it.
+        rng.next_u64();
+        rng.next_u64();
+        let mut expected = StdRng::seed_from_u64(0);
+        expected.fill_bytes(&mut [0; 32]);
+        assert_eq!(rng.inner.clone().next_u64(), expected.clone().next_u64());
+        rng.next_u32();
+        expected.fill_bytes(&mut [0; 32]);
+        assert_eq!(rng.into_inner().next_u64(), expected.next_u64());
+
+        let mut oram = DefaultOram::<u64>::new(64, &mut BufferedRng::new(inner)).unwrap();
+        random_workload(&mut oram, 100);
+    }
+
+    #[test]
+    fn buffered_rng_records_reseeds() {
+        let mut rng = BufferedRng::new_with_reseed_interval(StdRng::seed_from_u64(0), 8);
+        // The audit log is shared by all tests running in this process, so only check that the count increases.
+        let before = audit::count(AuditEventKind::RngReseed);
+        // The first 8 bytes do not reseed; the next draw does.
+        rng.next_u64();
+        rng.next_u64();
+        assert!(audit::count(AuditEventKind::RngReseed) > before);
+        assert!(audit::events()
+            .iter()
+            .any(|event| event.kind == AuditEventKind::RngReseed
+                && event.detail == "BufferedRng reseeded after its reseed interval of 8 bytes."));
+    }
+
+    #[test]
+    fn deterministic_rng_matches_chacha20_keystream() {
+        // The first bytes of the ChaCha20 keystream for the all-zero key and nonce.
+        let mut bytes = [0u8; 8];
+        DeterministicRng::from_seed([0; 32]).fill_bytes(&mut bytes);
+        assert_eq!(bytes, [0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90]);
+    }
+
+    #[test]
+    fn deterministic_rng_reproduces_oram_state() {
+        let run = || {
+            let mut rng = DeterministicRng::from_u64_seed(7);
+            let mut oram =
+                crate::PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+            for i in 0..100 {
+                oram.write(i % 64, i, &mut rng).unwrap();
+            }
+            format!("{oram:?}")
+        };
+        assert_eq!(run(), run());
+    }
+}
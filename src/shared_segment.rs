@@ -0,0 +1,242 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A Path ORAM tree stored in a caller-provided memory segment, such as untrusted host memory shared with an enclave.
+
+use crate::{
+    bucket::{Bucket, PathOramBlock},
+    channel::FrameSealer,
+    distributed_oram::ShareableBlock,
+    path_oram::PathOramTree,
+    BucketSize, OramError, TransportError,
+};
+use std::marker::PhantomData;
+
+/// The size in bytes of the address and position preceding the value of each encoded block.
+const BLOCK_HEADER_SIZE: usize = 16;
+
+/// The buckets of a [`crate::PathOram`] tree, each sealed with a [`FrameSealer`] into a fixed-size record
+/// of a caller-provided memory segment.
+///
+/// In SGX-style designs, the tree can live in untrusted host memory mapped into the enclave, rather than
+/// on the (small) enclave heap. Split an ORAM with [`crate::PathOram::into_parts`], move its tree into a segment
+/// with [`SharedSegmentTree::new`], and serve accesses with [`crate::path_oram::PathOramClientState::begin_access`]
+/// and [`crate::path_oram::PathOramClientState::finish_access`], reading and writing the buckets of each path
+/// with [`SharedSegmentTree::read_path`] and [`SharedSegmentTree::write_path`].
+///
+/// Every record of the segment has the same size, and every bucket written is resealed,
+/// so the host observes only which buckets are accessed, as with any Path ORAM tree.
+/// The confidentiality and integrity of the contents depend entirely on the sealer.
+#[derive(Debug)]
+pub struct SharedSegmentTree<'a, V: ShareableBlock, const Z: BucketSize, F: FrameSealer> {
+    segment: &'a mut [u8],
+    num_buckets: usize,
+    epoch: u64,
+    sealer: F,
+    _value: PhantomData<V>,
+}
+
+impl<'a, V: ShareableBlock, const Z: BucketSize, F: FrameSealer> SharedSegmentTree<'a, V, Z, F> {
+    /// Returns the size in bytes of an unsealed bucket.
+    pub fn bucket_size() -> usize {
+        Z * (BLOCK_HEADER_SIZE + V::ENCODED_SIZE)
+    }
+
+    /// Returns the number of bytes of segment needed to store a tree of `num_buckets` buckets
+    /// (as returned by [`PathOramTree::num_buckets`]) sealed with `sealer`, or `None` on overflow.
+    pub fn required_size(num_buckets: usize, sealer: &F) -> Option<usize> {
+        // Bucket 0 is unused, and not stored.
+        num_buckets
+            .saturating_sub(1)
+            .checked_mul(sealer.sealed_len(Self::bucket_size()))
+    }
+
+    /// Seals the buckets of `tree` into `segment`, which is used from then on to store them.
+    ///
+    /// # Errors
+    ///
+    /// If `segment` is smaller than [`SharedSegmentTree::required_size`], returns an `InvalidConfigurationError`.
+    pub fn new(
+        segment: &'a mut [u8],
+        tree: &PathOramTree<V, Z>,
+        sealer: F,
+    ) -> Result<Self, TransportError> {
+        let num_buckets = tree.num_buckets();
+        let required_size = Self::required_size(num_buckets, &sealer);
+        if required_size.map_or(true, |required_size| segment.len() < required_size) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Segment size".to_string(),
+                parameter_value: segment.len().to_string(),
+            }
+            .into());
+        }
+
+        let mut result = Self {
+            segment,
+            num_buckets,
+            epoch: tree.epoch(),
+            sealer,
+            _value: PhantomData,
+        };
+        let bucket_indices = Vec::from_iter(1..u64::try_from(num_buckets)?);
+        let buckets = tree.read_path(&bucket_indices)?;
+        result.write_path(&bucket_indices, &buckets)?;
+        Ok(result)
+    }
+
+    /// Returns the number of buckets in the tree, including the unused bucket at index 0.
+    pub fn num_buckets(&self) -> usize {
+        self.num_buckets
+    }
+
+    /// Returns the buckets at `bucket_indices`, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If any index is not a bucket of the tree, returns an `AddressOutOfBoundsError`.
+    /// If a record fails to open, returns the error of the sealer.
+    pub fn read_path(
+        &mut self,
+        bucket_indices: &[u64],
+    ) -> Result<Vec<Bucket<V, Z>>, TransportError> {
+        let mut result = Vec::with_capacity(bucket_indices.len());
+        for &index in bucket_indices {
+            let range = self.record_range(index)?;
+            let bytes = self.sealer.open(&self.segment[range])?;
+            if bytes.len() != Self::bucket_size() {
+                return Err(TransportError::StorageError {
+                    reason: format!("bucket {index} has length {}", bytes.len()),
+                });
+            }
+            result.push(Self::decode_bucket(&bytes));
+        }
+        Ok(result)
+    }
+
+    /// Seals `buckets` into the records of `bucket_indices`, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If any index is not a bucket of the tree, returns an `AddressOutOfBoundsError`.
+    /// If `buckets` and `bucket_indices` have different lengths, returns an `InvalidConfigurationError`.
+    pub fn write_path(
+        &mut self,
+        bucket_indices: &[u64],
+        buckets: &[Bucket<V, Z>],
+    ) -> Result<(), TransportError> {
+        if buckets.len() != bucket_indices.len() {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of path buckets".to_string(),
+                parameter_value: buckets.len().to_string(),
+            }
+            .into());
+        }
+        let mut bytes = vec![0; Self::bucket_size()];
+        for (&index, bucket) in bucket_indices.iter().zip(buckets) {
+            let range = self.record_range(index)?;
+            Self::encode_bucket(bucket, &mut bytes);
+            let sealed = self.sealer.seal(&bytes);
+            if sealed.len() != range.len() {
+                return Err(TransportError::StorageError {
+                    reason: format!("sealed bucket {index} has length {}", sealed.len()),
+                });
+            }
+            self.segment[range].copy_from_slice(&sealed);
+        }
+        Ok(())
+    }
+
+    /// Opens every bucket of the segment, returning a tree that can be recombined with its client state
+    /// by [`crate::PathOram::from_parts`]. The tree has the epoch of the tree the segment was created from.
+    ///
+    /// # Errors
+    ///
+    /// If a record fails to open, returns the error of the sealer.
+    pub fn to_tree(&mut self) -> Result<PathOramTree<V, Z>, TransportError> {
+        let bucket_indices = Vec::from_iter(1..u64::try_from(self.num_buckets)?);
+        let mut buckets = vec![Bucket::default()];
+        buckets.extend(self.read_path(&bucket_indices)?);
+        Ok(PathOramTree::from_buckets(buckets, self.epoch))
+    }
+
+    fn record_range(&self, index: u64) -> Result<std::ops::Range<usize>, TransportError> {
+        // This operation is not constant-time, but only leaks whether the bucket index is well-formed or not.
+        let num_buckets = u64::try_from(self.num_buckets)?;
+        if index == 0 || index >= num_buckets {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: num_buckets,
+            }
+            .into());
+        }
+        let record_size = self.sealer.sealed_len(Self::bucket_size());
+        let start = usize::try_from(index - 1)? * record_size;
+        Ok(start..start + record_size)
+    }
+
+    fn encode_bucket(bucket: &Bucket<V, Z>, out: &mut [u8]) {
+        let block_size = BLOCK_HEADER_SIZE + V::ENCODED_SIZE;
+        for (block, record) in bucket.blocks.iter().zip(out.chunks_exact_mut(block_size)) {
+            record[..8].copy_from_slice(&block.address.to_le_bytes());
+            record[8..16].copy_from_slice(&block.position.to_le_bytes());
+            block.value.encode(&mut record[BLOCK_HEADER_SIZE..]);
+        }
+    }
+
+    fn decode_bucket(bytes: &[u8]) -> Bucket<V, Z> {
+        let block_size = BLOCK_HEADER_SIZE + V::ENCODED_SIZE;
+        let mut bucket = Bucket::default();
+        for (block, record) in bucket.blocks.iter_mut().zip(bytes.chunks_exact(block_size)) {
+            *block = PathOramBlock {
+                value: V::decode(&record[BLOCK_HEADER_SIZE..]),
+                address: u64::from_le_bytes(record[..8].try_into().expect("slice has length 8")),
+                position: u64::from_le_bytes(record[8..16].try_into().expect("slice has length 8")),
+            };
+        }
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel::PlaintextSealer, Oram, PathOram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn shared_segment_tree_serves_split_accesses() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i + 100, &mut rng).unwrap();
+        }
+        let (mut client_state, tree) = oram.into_parts();
+
+        let size =
+            SharedSegmentTree::<u64, 4, _>::required_size(tree.num_buckets(), &PlaintextSealer);
+        let mut segment = vec![0u8; size.unwrap()];
+        assert!(SharedSegmentTree::new(&mut segment[1..], &tree, PlaintextSealer).is_err());
+        let mut shared = SharedSegmentTree::new(&mut segment, &tree, PlaintextSealer).unwrap();
+        assert!(shared.read_path(&[0]).is_err());
+
+        for i in 0..64 {
+            let pending = client_state.begin_access(i, &mut rng).unwrap();
+            let mut path = shared.read_path(pending.bucket_indices()).unwrap();
+            let bucket_indices = pending.bucket_indices().to_vec();
+            let old = client_state
+                .finish_access(pending, &mut path, |v| v + 1)
+                .unwrap();
+            assert_eq!(old, i + 100);
+            shared.write_path(&bucket_indices, &path).unwrap();
+        }
+
+        let mut oram = PathOram::from_parts(client_state, shared.to_tree().unwrap()).unwrap();
+        for i in 0..64 {
+            assert_eq!(oram.read(i, &mut rng).unwrap(), i + 101);
+        }
+    }
+}
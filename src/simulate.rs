@@ -0,0 +1,469 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Simulation of Path ORAM workloads without allocating the ORAM's memory.
+
+use crate::{
+    path_oram::{
+        DEFAULT_BLOCKS_PER_BUCKET, DEFAULT_POSITIONS_PER_BLOCK, DEFAULT_RECURSION_CUTOFF,
+        DEFAULT_STASH_OVERFLOW_SIZE, MAXIMUM_TREE_HEIGHT,
+    },
+    position_map::collapses_into_base,
+    utils::{
+        random_permutation_of_0_through_n_exclusive, try_filled_vec, CompleteBinaryTreeIndex,
+        TreeHeight, TreeIndex,
+    },
+    Address, BlockSize, BucketSize, OramError, OramRng, RecursionCutoff, StashSize,
+};
+use std::mem::size_of;
+
+/// The parameters of a simulated [`crate::PathOram`].
+/// The defaults match those of [`crate::DefaultOram`] (for capacities above the linear-time cutoff).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulationParameters {
+    /// The number of blocks stored by the ORAM. Must be a power of two greater than 1.
+    pub block_capacity: Address,
+    /// The size of each stored value in bytes.
+    pub block_size: BlockSize,
+    /// The number of blocks per bucket ("Z").
+    pub blocks_per_bucket: BucketSize,
+    /// The number of positions stored per position map block ("AB").
+    pub positions_per_block: BlockSize,
+    /// The number of position blocks below which the position map is a linear scan.
+    pub recursion_cutoff: RecursionCutoff,
+    /// The number of blocks the stash of each level holds between accesses without overflowing.
+    pub overflow_size: StashSize,
+}
+
+impl Default for SimulationParameters {
+    fn default() -> Self {
+        Self {
+            block_capacity: 1 << 16,
+            block_size: 64,
+            blocks_per_bucket: DEFAULT_BLOCKS_PER_BUCKET,
+            positions_per_block: DEFAULT_POSITIONS_PER_BLOCK,
+            recursion_cutoff: DEFAULT_RECURSION_CUTOFF,
+            overflow_size: DEFAULT_STASH_OVERFLOW_SIZE,
+        }
+    }
+}
+
+/// The physical traffic of one level of a simulated ORAM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelTraffic {
+    /// The number of buckets (or, for the linear-scan base of the position map, entries) read.
+    pub physical_reads: u64,
+    /// The number of buckets (or entries) written.
+    pub physical_writes: u64,
+    /// The number of bytes read, including block metadata.
+    pub bytes_read: u64,
+    /// The number of bytes written, including block metadata.
+    pub bytes_written: u64,
+    /// The largest number of blocks held in the stash of this level between accesses.
+    pub max_stash_occupancy: StashSize,
+    /// The number of accesses after which the stash of this level held more than `overflow_size` blocks.
+    pub stash_overflows: u64,
+}
+
+/// The results of a simulation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationReport {
+    /// The number of logical accesses simulated.
+    pub logical_accesses: u64,
+    /// The traffic of each level. The first level stores the data blocks; each subsequent level
+    /// is the position map of the previous one, and the last level is a linear-scan position map.
+    pub levels: Vec<LevelTraffic>,
+    /// The occupancy of the stash of the first level after each logical access.
+    pub stash_occupancy_trace: Vec<StashSize>,
+}
+
+impl SimulationReport {
+    /// Returns the total number of physical reads, across all levels.
+    pub fn physical_reads(&self) -> u64 {
+        self.levels.iter().map(|level| level.physical_reads).sum()
+    }
+
+    /// Returns the total number of physical writes, across all levels.
+    pub fn physical_writes(&self) -> u64 {
+        self.levels.iter().map(|level| level.physical_writes).sum()
+    }
+
+    /// Returns the total number of bytes read and written, across all levels.
+    pub fn total_bytes(&self) -> u64 {
+        self.levels
+            .iter()
+            .map(|level| level.bytes_read + level.bytes_written)
+            .sum()
+    }
+
+    /// Returns the bytes read and written by the position map, i.e., by all levels but the first.
+    pub fn recursion_bytes(&self) -> u64 {
+        self.levels
+            .iter()
+            .skip(1)
+            .map(|level| level.bytes_read + level.bytes_written)
+            .sum()
+    }
+}
+
+/// The state of one simulated Path ORAM tree: the position and location of every block, but no values.
+#[derive(Clone, Debug)]
+struct SimulatedTree {
+    height: TreeHeight,
+    blocks_per_bucket: usize,
+    bucket_bytes: u64,
+    /// The position of each block, indexed by address.
+    positions: Vec<TreeIndex>,
+    /// The address held by each slot of each bucket, or `EMPTY_SLOT`. Bucket 0 is unused.
+    slots: Vec<Address>,
+    /// The addresses of the blocks in the stash.
+    stash: Vec<Address>,
+}
+
+const EMPTY_SLOT: Address = Address::MAX;
+
+impl SimulatedTree {
+    /// Places every block in one of the two leading slots of a uniformly random leaf bucket,
+    /// as [`crate::PathOram`] does on initialization.
+    fn new<R: OramRng>(
+        block_capacity: Address,
+        blocks_per_bucket: usize,
+        value_bytes: u64,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        let height: TreeHeight = (block_capacity.ilog2() - 1).into();
+        if height > MAXIMUM_TREE_HEIGHT {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: block_capacity.to_string(),
+            });
+        }
+        let first_leaf: TreeIndex = 1 << height;
+
+        let mut positions = try_filled_vec(block_capacity, 0)?;
+        let mut slots = try_filled_vec(
+            block_capacity * u64::try_from(blocks_per_bucket)?,
+            EMPTY_SLOT,
+        )?;
+        let slot_indices_to_addresses =
+            random_permutation_of_0_through_n_exclusive(block_capacity, rng);
+        for (slot_index, address) in slot_indices_to_addresses.into_iter().enumerate() {
+            let leaf = first_leaf + u64::try_from(slot_index / 2)?;
+            positions[usize::try_from(address)?] = leaf;
+            slots[usize::try_from(leaf)? * blocks_per_bucket + slot_index % 2] = address;
+        }
+
+        let metadata_bytes = u64::try_from(2 * size_of::<Address>())?;
+        Ok(Self {
+            height,
+            blocks_per_bucket,
+            bucket_bytes: u64::try_from(blocks_per_bucket)? * (value_bytes + metadata_bytes),
+            positions,
+            slots,
+            stash: Vec::new(),
+        })
+    }
+
+    /// Simulates an access to `address`: reads the path to its position into the stash,
+    /// remaps it to a fresh random leaf, and greedily evicts the stash back into the path.
+    fn access<R: OramRng>(
+        &mut self,
+        address: Address,
+        rng: &mut R,
+        traffic: &mut LevelTraffic,
+    ) -> Result<(), OramError> {
+        let position = self.positions[usize::try_from(address)?];
+        self.positions[usize::try_from(address)?] = TreeIndex::random_leaf(self.height, rng)?;
+
+        for depth in 0..=self.height {
+            let bucket = usize::try_from(position >> (self.height - depth))?;
+            let bucket_slots =
+                &mut self.slots[bucket * self.blocks_per_bucket..][..self.blocks_per_bucket];
+            for slot in bucket_slots.iter_mut() {
+                if *slot != EMPTY_SLOT {
+                    self.stash.push(*slot);
+                    *slot = EMPTY_SLOT;
+                }
+            }
+        }
+
+        // Fill the path from the leaf up, placing each block as deep as its position allows.
+        for depth in (0..=self.height).rev() {
+            let bucket = position >> (self.height - depth);
+            let first_slot = usize::try_from(bucket)? * self.blocks_per_bucket;
+            let mut filled = 0;
+            let mut index = 0;
+            while index < self.stash.len() && filled < self.blocks_per_bucket {
+                let block = self.stash[index];
+                if self.positions[usize::try_from(block)?] >> (self.height - depth) == bucket {
+                    self.slots[first_slot + filled] = self.stash.swap_remove(index);
+                    filled += 1;
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        let path_length = self.height + 1;
+        traffic.physical_reads += path_length;
+        traffic.physical_writes += path_length;
+        traffic.bytes_read += path_length * self.bucket_bytes;
+        traffic.bytes_written += path_length * self.bucket_bytes;
+        Ok(())
+    }
+}
+
+/// Replays logical workloads against the logic of a [`crate::PathOram`] with a recursive position map,
+/// tracking only the location of each block, never its value.
+///
+/// Each level needs `8 * (Z + 1)` bytes per block, regardless of the block size,
+/// so that deployments can be sized before any memory is committed to them.
+/// The simulation assumes the default eviction strategy, which evicts to the path of each access.
+#[derive(Clone, Debug)]
+pub struct PathOramSimulator {
+    parameters: SimulationParameters,
+    /// The data tree followed by the recursive position map trees.
+    trees: Vec<SimulatedTree>,
+    /// The number of entries of the linear-scan base of the position map.
+    base_entries: u64,
+    base_entry_bytes: u64,
+    report: SimulationReport,
+}
+
+impl PathOramSimulator {
+    /// Returns a simulator of a freshly initialized ORAM with the given parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidConfigurationError` under the same conditions as [`crate::PathOram::new_with_parameters`].
+    pub fn new<R: OramRng>(
+        parameters: SimulationParameters,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        let capacity = parameters.block_capacity;
+        if !capacity.is_power_of_two() | (capacity <= 1) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "ORAM capacity".to_string(),
+                parameter_value: capacity.to_string(),
+            });
+        }
+        if parameters.blocks_per_bucket <= 1 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Bucket size Z".to_string(),
+                parameter_value: parameters.blocks_per_bucket.to_string(),
+            });
+        }
+        if (parameters.positions_per_block < 2) | !parameters.positions_per_block.is_power_of_two()
+        {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Position block size AB".to_string(),
+                parameter_value: parameters.positions_per_block.to_string(),
+            });
+        }
+        if parameters.recursion_cutoff == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Recursion cutoff".to_string(),
+                parameter_value: parameters.recursion_cutoff.to_string(),
+            });
+        }
+
+        let ab = Address::try_from(parameters.positions_per_block)?;
+        let position_block_bytes = ab * u64::try_from(size_of::<TreeIndex>())?;
+        let mut trees = vec![SimulatedTree::new(
+            capacity,
+            parameters.blocks_per_bucket,
+            u64::try_from(parameters.block_size)?,
+            rng,
+        )?];
+        let mut capacity = capacity;
+        while (capacity / ab > parameters.recursion_cutoff)
+            & !collapses_into_base(capacity / ab, parameters.blocks_per_bucket)
+        {
+            capacity /= ab;
+            trees.push(SimulatedTree::new(
+                capacity,
+                parameters.blocks_per_bucket,
+                position_block_bytes,
+                rng,
+            )?);
+        }
+
+        Ok(Self {
+            parameters,
+            base_entries: capacity.div_ceil(ab),
+            base_entry_bytes: position_block_bytes,
+            report: SimulationReport {
+                levels: vec![LevelTraffic::default(); trees.len() + 1],
+                ..Default::default()
+            },
+            trees,
+        })
+    }
+
+    /// Returns the parameters of the simulated ORAM.
+    pub fn parameters(&self) -> &SimulationParameters {
+        &self.parameters
+    }
+
+    /// Simulates a logical access to `address`, including the accesses to the position map it entails.
+    ///
+    /// # Errors
+    ///
+    /// If `address` is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn access<R: OramRng>(&mut self, address: Address, rng: &mut R) -> Result<(), OramError> {
+        if address >= self.parameters.block_capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: address,
+                capacity: self.parameters.block_capacity,
+            });
+        }
+
+        let ab = Address::try_from(self.parameters.positions_per_block)?;
+        let base = self.report.levels.len() - 1;
+        let base_traffic = &mut self.report.levels[base];
+        base_traffic.physical_reads += self.base_entries;
+        base_traffic.physical_writes += self.base_entries;
+        base_traffic.bytes_read += self.base_entries * self.base_entry_bytes;
+        base_traffic.bytes_written += self.base_entries * self.base_entry_bytes;
+
+        // The position map is accessed before the level it maps, so simulate the deepest tree first.
+        for level in (0..self.trees.len()).rev() {
+            let level_address = address / ab.pow(u32::try_from(level)?);
+            let traffic = &mut self.report.levels[level];
+            let tree = &mut self.trees[level];
+            tree.access(level_address, rng, traffic)?;
+
+            let occupancy = u64::try_from(tree.stash.len())?;
+            traffic.max_stash_occupancy = traffic.max_stash_occupancy.max(occupancy);
+            if occupancy > self.parameters.overflow_size {
+                traffic.stash_overflows += 1;
+            }
+        }
+
+        self.report.logical_accesses += 1;
+        let occupancy = u64::try_from(self.trees[0].stash.len())?;
+        self.report.stash_occupancy_trace.push(occupancy);
+        Ok(())
+    }
+
+    /// Simulates the logical accesses to each address of `workload` in order,
+    /// and returns the report of all accesses simulated so far.
+    ///
+    /// # Errors
+    ///
+    /// If an address is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn run<R: OramRng, I: IntoIterator<Item = Address>>(
+        &mut self,
+        workload: I,
+        rng: &mut R,
+    ) -> Result<&SimulationReport, OramError> {
+        for address in workload {
+            self.access(address, rng)?;
+        }
+        Ok(&self.report)
+    }
+
+    /// Returns the report of all accesses simulated so far.
+    pub fn report(&self) -> &SimulationReport {
+        &self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn simulator_counts_path_and_recursion_traffic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parameters = SimulationParameters {
+            block_capacity: 1 << 12,
+            recursion_cutoff: 8,
+            ..Default::default()
+        };
+        let mut simulator = PathOramSimulator::new(parameters, &mut rng).unwrap();
+        let workload: Vec<Address> = (0..1000).map(|_| rng.gen_range(0..1 << 12)).collect();
+        let report = simulator.run(workload, &mut rng).unwrap().clone();
+
+        // Trees of 2^12, 2^9 and 2^6 blocks, then a linear scan of 8 entries.
+        assert_eq!(report.logical_accesses, 1000);
+        assert_eq!(report.levels.len(), 4);
+        let reads: Vec<u64> = report.levels.iter().map(|l| l.physical_reads).collect();
+        assert_eq!(reads, vec![12000, 9000, 6000, 8000]);
+        assert_eq!(report.physical_writes(), report.physical_reads());
+        assert_eq!(report.levels[0].bytes_read, 12000 * 4 * (64 + 16));
+        assert_eq!(report.levels[3].bytes_written, 8000 * 64);
+        assert_eq!(
+            report.total_bytes() - report.recursion_bytes(),
+            2 * report.levels[0].bytes_read
+        );
+
+        assert_eq!(report.stash_occupancy_trace.len(), 1000);
+        let max = *report.stash_occupancy_trace.iter().max().unwrap();
+        assert_eq!(report.levels[0].max_stash_occupancy, max);
+        assert!(max < DEFAULT_STASH_OVERFLOW_SIZE);
+        assert_eq!(report.levels[0].stash_overflows, 0);
+    }
+
+    #[test]
+    fn simulator_conserves_blocks() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let parameters = SimulationParameters {
+            block_capacity: 64,
+            blocks_per_bucket: 2,
+            recursion_cutoff: 1,
+            overflow_size: 0,
+            ..Default::default()
+        };
+        let mut simulator = PathOramSimulator::new(parameters, &mut rng).unwrap();
+        simulator.run((0..2000).map(|i| i % 64), &mut rng).unwrap();
+
+        for tree in &simulator.trees {
+            let mut addresses: Vec<Address> = tree
+                .slots
+                .iter()
+                .copied()
+                .filter(|slot| *slot != EMPTY_SLOT)
+                .chain(tree.stash.iter().copied())
+                .collect();
+            addresses.sort_unstable();
+            assert!(addresses
+                .iter()
+                .copied()
+                .eq(0..tree.positions.len() as Address));
+        }
+        // With Z = 2 and no overflow allowance, the stash must overflow at some point.
+        assert!(simulator.report().levels[0].stash_overflows > 0);
+        assert!(simulator.access(64, &mut rng).is_err());
+    }
+
+    #[test]
+    fn simulator_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for parameters in [
+            SimulationParameters {
+                block_capacity: 100,
+                ..Default::default()
+            },
+            SimulationParameters {
+                blocks_per_bucket: 1,
+                ..Default::default()
+            },
+            SimulationParameters {
+                positions_per_block: 3,
+                ..Default::default()
+            },
+            SimulationParameters {
+                recursion_cutoff: 0,
+                ..Default::default()
+            },
+        ] {
+            assert!(PathOramSimulator::new(parameters, &mut rng).is_err());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Read-only, point-in-time views of ORAMs.
+
+use crate::{Address, Oram, OramRng};
+
+/// A read-only, point-in-time view of an ORAM, created by
+/// [`PathOram::snapshot`](crate::PathOram::snapshot) or [`DefaultOram::snapshot`](crate::DefaultOram::snapshot).
+///
+/// Writes to the original ORAM after the snapshot was taken are not visible through the snapshot.
+/// Since every ORAM read updates the client state (stash and position map) of the ORAM it reads,
+/// reading requires `&mut self`. To read from several threads concurrently, give each thread its own clone.
+#[derive(Clone, Debug)]
+pub struct OramSnapshot<O: Oram> {
+    oram: O,
+}
+
+impl<O: Oram> OramSnapshot<O> {
+    pub(crate) fn new(oram: O) -> Self {
+        Self { oram }
+    }
+
+    /// Returns the capacity in blocks of the snapshotted ORAM.
+    pub fn block_capacity(&self) -> Result<Address, O::Error> {
+        self.oram.block_capacity()
+    }
+
+    /// Obliviously reads the value stored at `index` at the time the snapshot was taken.
+    pub fn read<R: OramRng>(&mut self, index: Address, rng: &mut R) -> Result<O::V, O::Error> {
+        self.oram.read(index, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{path_oram::PathOram, DefaultOram, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn snapshot_is_isolated_from_later_writes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = PathOram::<u64, 4, 8>::new_with_parameters(64, &mut rng, 40, 1).unwrap();
+        for i in 0..64 {
+            oram.write(i, i, &mut rng).unwrap();
+        }
+
+        let mut snapshot = oram.snapshot();
+        for i in 0..64 {
+            oram.write(i, 100 + i, &mut rng).unwrap();
+        }
+
+        for i in 0..64 {
+            assert_eq!(snapshot.read(i, &mut rng).unwrap(), i);
+            assert_eq!(oram.read(i, &mut rng).unwrap(), 100 + i);
+        }
+    }
+
+    #[test]
+    fn snapshot_clones_read_concurrently() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = DefaultOram::<u64>::new(64, &mut rng).unwrap();
+        for i in 0..64 {
+            oram.write(i, 2 * i, &mut rng).unwrap();
+        }
+        let snapshot = oram.snapshot();
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let mut reader = snapshot.clone();
+                scope.spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(thread);
+                    for i in 0..64 {
+                        assert_eq!(reader.read(i, &mut rng).unwrap(), 2 * i);
+                    }
+                });
+            }
+            for i in 0..64 {
+                oram.write(i, 0, &mut rng).unwrap();
+            }
+        });
+    }
+}
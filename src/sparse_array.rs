@@ -0,0 +1,299 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An oblivious array storing only its non-default entries.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use rand::Rng;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The number of entries in each bucket of the table.
+const SPARSE_BUCKET_SIZE: usize = 8;
+
+/// The number of table entries allocated per non-default entry, keeping the table sparse enough
+/// that a bucket overflows only with small probability.
+const TABLE_ENTRIES_PER_ENTRY: u64 = 4;
+
+/// An entry of the table. A key of 0 marks an empty entry; otherwise, the key is the array index plus 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SparseEntry<V> {
+    key: Address,
+    value: V,
+}
+
+impl<V: ConditionallySelectable> ConditionallySelectable for SparseEntry<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            key: Address::conditional_select(&a.key, &b.key, choice),
+            value: V::conditional_select(&a.value, &b.value, choice),
+        }
+    }
+}
+
+/// A bucket of the table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SparseBucket<V> {
+    entries: [SparseEntry<V>; SPARSE_BUCKET_SIZE],
+}
+
+impl<V: ConditionallySelectable> ConditionallySelectable for SparseBucket<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = *a;
+        for i in 0..SPARSE_BUCKET_SIZE {
+            result.entries[i] =
+                SparseEntry::conditional_select(&a.entries[i], &b.entries[i], choice);
+        }
+        result
+    }
+}
+
+impl<V: OramBlock> OramBlock for SparseBucket<V> {}
+
+impl<V: OramBlock> SparseBucket<V> {
+    /// Returns the value with the given key, and whether it was found.
+    fn find(&self, key: Address) -> (V, Choice) {
+        let mut result = V::default();
+        let mut found = Choice::from(0);
+        for entry in &self.entries {
+            let matches = entry.key.ct_eq(&key);
+            result.conditional_assign(&entry.value, matches);
+            found |= matches;
+        }
+        (result, found)
+    }
+
+    fn has_free_entry(&self) -> Choice {
+        self.find(0).1
+    }
+
+    /// Sets the value of the entry with the given key, if any, removing the entry if `remove` is 1.
+    /// If `insert` is 1, instead inserts a new entry into the first free slot.
+    fn update(&self, key: Address, value: &V, remove: Choice, insert: Choice) -> Self {
+        let mut result = *self;
+        let mut inserted = Choice::from(0);
+        for entry in result.entries.iter_mut() {
+            let matches = entry.key.ct_eq(&key);
+            entry.value.conditional_assign(value, matches);
+            entry.conditional_assign(&SparseEntry::default(), matches & remove);
+
+            let free = entry.key.ct_eq(&0) & insert & !inserted;
+            entry.conditional_assign(&SparseEntry { key, value: *value }, free);
+            inserted |= free;
+        }
+        result
+    }
+}
+
+/// An array of `len` values, of which at most `max_entries` differ from `V::default()` at any time,
+/// using memory proportional to `max_entries` rather than `len`.
+///
+/// This suits huge, mostly-empty logical arrays, for which the tree of a [`DefaultOram`] of capacity `len`
+/// would waste memory. The non-default entries are stored in an oblivious hash table in a `DefaultOram`:
+/// each index is hashed (with a random salt) to two candidate buckets, and every access reads
+/// and then writes both. Writing `V::default()` to an index removes its entry, freeing space.
+///
+/// ## Security
+///
+/// Every access makes exactly four accesses to the underlying ORAM, whatever the index and
+/// whether its entry is present, updated, inserted, or removed. The only exception is an access that would
+/// insert an entry into a full array: it fails after two accesses, which reveals that the array is full.
+#[derive(Debug)]
+pub struct ObliviousSparseArray<V: OramBlock> {
+    table: DefaultOram<SparseBucket<V>>,
+    len: Address,
+    max_entries: Address,
+    num_entries: Address,
+    /// A random salt for the index hash, so that buckets are not predictable from indices.
+    salt: [u64; 2],
+}
+
+impl<V: OramBlock> ObliviousSparseArray<V> {
+    /// Returns a new array of `len` default values, of which up to `max_entries` can later be non-default.
+    ///
+    /// # Errors
+    ///
+    /// If `len` or `max_entries` is 0, or `len` is `Address::MAX`, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        len: Address,
+        max_entries: Address,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        if (len == 0) | (len == Address::MAX) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Array length".to_string(),
+                parameter_value: len.to_string(),
+            });
+        }
+        let num_buckets = max_entries
+            .checked_mul(TABLE_ENTRIES_PER_ENTRY)
+            .map(|entries| entries.div_ceil(u64::try_from(SPARSE_BUCKET_SIZE).unwrap_or(1)))
+            .and_then(Address::checked_next_power_of_two)
+            .filter(|_| max_entries > 0)
+            .ok_or(OramError::InvalidConfigurationError {
+                parameter_name: "Maximum number of entries".to_string(),
+                parameter_value: max_entries.to_string(),
+            })?;
+
+        log::info!(
+            "ObliviousSparseArray::new(len = {}, max_entries = {})",
+            len,
+            max_entries
+        );
+
+        Ok(Self {
+            table: DefaultOram::new(num_buckets.max(2), rng)?,
+            len,
+            max_entries,
+            num_entries: 0,
+            salt: rng.gen(),
+        })
+    }
+
+    /// Returns the maximum number of non-default values.
+    pub fn max_entries(&self) -> Address {
+        self.max_entries
+    }
+
+    /// Returns the number of non-default values.
+    pub fn num_entries(&self) -> Address {
+        self.num_entries
+    }
+
+    /// Returns the two candidate buckets of `index`.
+    fn buckets(&self, index: Address) -> Result<[Address; 2], OramError> {
+        let mask = self.table.block_capacity()? - 1;
+        let mut hasher = DefaultHasher::new();
+        self.salt.hash(&mut hasher);
+        index.hash(&mut hasher);
+        hasher.write_u8(1);
+        let first = hasher.finish() & mask;
+        hasher.write_u8(2);
+        let second = hasher.finish() & mask;
+        Ok([first, second])
+    }
+}
+
+impl<V: OramBlock + ConstantTimeEq> Oram for ObliviousSparseArray<V> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(self.len)
+    }
+
+    /// Performs an access, returning `V::default()` for indices without an entry.
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of bounds, returns an `AddressOutOfBoundsError`.
+    /// If the access would insert an entry while `max_entries` entries are present,
+    /// or both candidate buckets of `index` are full, returns an `OutOfMemoryError`.
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        if index >= self.len {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: self.len,
+            });
+        }
+
+        let key = index + 1;
+        let [first, second] = self.buckets(index)?;
+        let old_first = self.table.read(first, rng)?;
+        let old_second = self.table.read(second, rng)?;
+
+        let (first_value, found_first) = old_first.find(key);
+        let (second_value, found_second) = old_second.find(key);
+        let found = found_first | found_second;
+        let result = V::conditional_select(&second_value, &first_value, found_first);
+
+        let new_value = callback(&result);
+        let remove = new_value.ct_eq(&V::default());
+        let insert = !found & !remove;
+        let insert_first = insert & old_first.has_free_entry();
+        let insert_second = insert & !insert_first & old_second.has_free_entry();
+
+        // This operation is not constant-time, but only leaks whether the array is full or not.
+        let full =
+            insert & (self.num_entries.ct_eq(&self.max_entries) | !(insert_first | insert_second));
+        if bool::from(full) {
+            return Err(OramError::OutOfMemoryError {
+                capacity: self.max_entries,
+            });
+        }
+
+        // The two candidate buckets may coincide, so each update applies to the current bucket.
+        self.table.access(
+            first,
+            |bucket| bucket.update(key, &new_value, remove, insert_first),
+            rng,
+        )?;
+        self.table.access(
+            second,
+            |bucket| bucket.update(key, &new_value, remove, insert_second),
+            rng,
+        )?;
+
+        self.num_entries += u64::from(insert.unwrap_u8());
+        self.num_entries -= u64::from((found & remove).unwrap_u8());
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn sparse_array_stores_non_default_entries() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let len = 1 << 40;
+        let mut array = ObliviousSparseArray::<u64>::new(len, 64, &mut rng).unwrap();
+        assert_eq!(array.block_capacity().unwrap(), len);
+
+        let indices: Vec<Address> = (0..64).map(|i| i * 0x0001_2345_6789 % len).collect();
+        for (i, index) in indices.iter().enumerate() {
+            array.write(*index, i as u64 + 1, &mut rng).unwrap();
+        }
+        assert_eq!(array.num_entries(), 64);
+        assert!(array.write(len - 1, 7, &mut rng).is_err());
+        assert!(array.read(len, &mut rng).is_err());
+
+        for (i, index) in indices.iter().enumerate() {
+            assert_eq!(array.read(*index, &mut rng).unwrap(), i as u64 + 1);
+            assert_eq!(array.read(*index + 1, &mut rng).unwrap(), 0);
+        }
+
+        // Writing the default value removes an entry, making room for another.
+        array.write(indices[0], 0, &mut rng).unwrap();
+        assert_eq!(array.num_entries(), 63);
+        array.write(len - 1, 7, &mut rng).unwrap();
+        assert_eq!(array.read(len - 1, &mut rng).unwrap(), 7);
+        assert_eq!(array.read(indices[0], &mut rng).unwrap(), 0);
+        assert_eq!(array.num_entries(), 64);
+    }
+
+    #[test]
+    fn sparse_array_rejects_invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(ObliviousSparseArray::<u64>::new(0, 8, &mut rng).is_err());
+        assert!(ObliviousSparseArray::<u64>::new(Address::MAX, 8, &mut rng).is_err());
+        assert!(ObliviousSparseArray::<u64>::new(16, 0, &mut rng).is_err());
+        assert!(ObliviousSparseArray::<u64>::new(16, Address::MAX, &mut rng).is_err());
+    }
+}
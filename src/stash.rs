@@ -8,207 +8,1127 @@
 //! A trait representing a Path ORAM stash.
 
 use crate::{
+    audit::{self, AuditEventKind},
     bucket::{Bucket, PathOramBlock},
-    utils::{bitonic_sort_by_keys, CompleteBinaryTreeIndex, TreeIndex},
+    ct::speculation_barrier,
+    path_oram::MAXIMUM_TREE_HEIGHT,
+    utils::{bitonic_sort_by_keys, CompleteBinaryTreeIndex, TreeHeight, TreeIndex},
     Address, BucketSize, OramBlock, OramError, StashSize,
 };
 
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
-const STASH_GROWTH_INCREMENT: usize = 10;
+/// The number of blocks by which the stash grows on overflow, by default.
+const DEFAULT_STASH_GROWTH_INCREMENT: usize = 10;
 
-#[derive(Debug)]
+/// How a Path ORAM stash grows when it overflows.
+///
+/// Growing the stash reveals that an overflow occurred (see [`crate::PathOram`]),
+/// so the strategy trades off the number of such events against the memory held by the stash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StashGrowth {
+    /// Grow by a fixed number of blocks on each overflow.
+    Increment(usize),
+    /// Double the size of the stash on each overflow.
+    Doubling,
+    /// Grow by `increment` blocks on each overflow, up to a total of `max_size` blocks.
+    /// An overflow of a stash of `max_size` blocks returns a `StashOverflowError`.
+    Capped {
+        /// The number of blocks to grow by.
+        increment: usize,
+        /// The maximum size of the stash, in blocks.
+        max_size: usize,
+    },
+    /// Never grow. An overflow returns a `StashOverflowError` and poisons the ORAM (see [`crate::PathOram`]),
+    /// so that the overflow is never revealed by a change in the size of the stash.
+    Never,
+}
+
+impl Default for StashGrowth {
+    fn default() -> Self {
+        Self::Increment(DEFAULT_STASH_GROWTH_INCREMENT)
+    }
+}
+
+impl StashGrowth {
+    /// Returns an `InvalidConfigurationError` if the strategy could fail to grow the stash
+    /// before any maximum size is reached.
+    pub(crate) fn validate(&self) -> Result<(), OramError> {
+        match self {
+            Self::Increment(0) | Self::Capped { increment: 0, .. } => {
+                Err(OramError::InvalidConfigurationError {
+                    parameter_name: "Stash growth increment".to_string(),
+                    parameter_value: "0".to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the number of blocks by which to grow a stash of `size` blocks,
+    /// or `None` if it has reached its maximum size.
+    fn increment(&self, size: usize) -> Option<usize> {
+        match *self {
+            Self::Increment(increment) => Some(increment),
+            Self::Doubling => Some(size.max(1)),
+            Self::Capped {
+                increment,
+                max_size,
+            } => (size < max_size).then(|| increment.min(max_size - size)),
+            Self::Never => None,
+        }
+    }
+}
+
+/// The buckets read and written by a [`Stash`] when accessing a path.
+pub trait PathStorage<V: OramBlock, const Z: BucketSize> {
+    /// Returns the bucket at depth `depth` of the path, whose index in the tree is `index`.
+    fn read_bucket(&self, depth: u64, index: TreeIndex) -> Result<Bucket<V, Z>, OramError>;
+
+    /// Overwrites the bucket at depth `depth` of the path, whose index in the tree is `index`.
+    fn write_bucket(
+        &mut self,
+        depth: u64,
+        index: TreeIndex,
+        bucket: &Bucket<V, Z>,
+    ) -> Result<(), OramError>;
+
+    /// Returns the number of children of each internal node of the tree, a power of two.
+    /// The default is 2, for a binary tree.
+    fn arity(&self) -> u64 {
+        2
+    }
+}
+
+/// Returns the height of the tree stored in `physical_memory`, of which `position` is a leaf,
+/// and the base-2 logarithm of the arity of the tree.
+fn path_shape<V: OramBlock, const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+    physical_memory: &P,
+    position: TreeIndex,
+) -> (TreeHeight, u32) {
+    let arity_bits = physical_memory.arity().ilog2();
+    (
+        position.ct_depth() / TreeHeight::from(arity_bits),
+        arity_bits,
+    )
+}
+
+/// The buckets of a single path, ordered from the root to the leaf.
+pub(crate) struct PathBuckets<'a, V: OramBlock, const Z: BucketSize>(
+    pub(crate) &'a mut [Bucket<V, Z>],
+);
+
+impl<V: OramBlock, const Z: BucketSize> PathStorage<V, Z> for PathBuckets<'_, V, Z> {
+    fn read_bucket(&self, depth: u64, _index: TreeIndex) -> Result<Bucket<V, Z>, OramError> {
+        Ok(self.0[usize::try_from(depth)?])
+    }
+
+    fn write_bucket(
+        &mut self,
+        depth: u64,
+        _index: TreeIndex,
+        bucket: &Bucket<V, Z>,
+    ) -> Result<(), OramError> {
+        self.0[usize::try_from(depth)?] = *bucket;
+        Ok(())
+    }
+}
+
+/// A Path ORAM stash, together with the logic moving blocks between the stash and a path of the tree.
+///
+/// [`crate::PathOram`] is generic over its stash, so that alternative stash layouts and eviction strategies
+/// can be plugged in. [`ObliviousStash`] is the default, and the only implementation in this crate
+/// whose accesses are oblivious; [`InsecureVecStash`] is a fast, non-oblivious baseline for benchmarking.
+///
+/// Positions are leaves of the tree, numbered as in a binary heap: the root is 1, and the children of `i`
+/// are `2i` and `2i + 1`. The bucket at depth `d` of the path to a leaf `position` at depth `height`
+/// is therefore `position >> (height - d)`. In a tree of arity `2^b` (see [`PathStorage::arity`]),
+/// only every `b`-th level of this binary tree holds buckets: leaves are at binary depth `b * height`,
+/// and the bucket at depth `d` is `position >> (b * (height - d))`.
+pub trait Stash<V: OramBlock>: Clone + std::fmt::Debug + Sized {
+    /// Returns an empty stash for paths of `path_size` blocks, with room for `overflow_size` further blocks,
+    /// which grows according to `growth` when it overflows.
+    fn new(
+        path_size: StashSize,
+        overflow_size: StashSize,
+        growth: StashGrowth,
+    ) -> Result<Self, OramError>;
+
+    /// Reads the blocks of the path to the leaf `position` into the stash.
+    fn read_from_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError>;
+
+    /// Returns the value of the block with address `address`, replacing it with `value_callback` applied to it,
+    /// and moving the block to `new_position`. The block must be in the stash.
+    fn access<F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError>;
+
+    /// Evicts blocks from the stash into the path to the leaf `position`, overwriting every bucket of the path.
+    /// The path must have been read by `read_from_path` since it was last written.
+    fn write_to_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError>;
+
+    /// Accesses the block with address `address` without evicting.
+    /// The target block is removed from the path to `position` or from the stash,
+    /// updated with `new_position` and `value_callback`, and inserted into the stash.
+    /// An oblivious implementation reads and writes back every bucket of the path,
+    /// so that the bucket the target block was removed from is not revealed.
+    /// Used by eviction strategies that do not evict the accessed path (see [`crate::EvictionStrategy`]).
+    fn access_without_eviction<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized, F: Fn(&V) -> V>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError>;
+
+    /// Discards any copies of blocks that `write_to_path` left in the stash after writing them to the tree.
+    fn clear_path_slots(&mut self) -> Result<(), OramError>;
+
+    /// Returns the blocks held by the stash, some of which may be dummies, after `clear_path_slots`
+    /// has been called. Used by operations on the whole ORAM, such as [`crate::PathOram::permute`],
+    /// which may change the values of the blocks, but not their addresses or positions.
+    fn blocks_mut(&mut self) -> &mut [PathOramBlock<V>];
+
+    /// Removes every block from the stash. Used by [`crate::PathOram::rebuild`],
+    /// after the blocks have been moved back into the tree.
+    fn clear(&mut self) {
+        for block in self.blocks_mut() {
+            *block = PathOramBlock::dummy();
+        }
+    }
+
+    /// Returns the number of real blocks held by the stash (outside of any copies of the last path written).
+    /// This need not be constant-time.
+    fn occupancy(&self) -> StashSize;
+
+    /// Returns the number of blocks the stash can hold, outside of the last path, before it grows.
+    fn overflow_capacity(&self) -> StashSize;
+
+    /// Returns the number of bytes allocated for the stash blocks.
+    fn memory_bytes(&self) -> usize;
+
+    /// Returns the number of bytes of scratch space allocated by `write_to_path` for a tree of height `height`.
+    fn scratch_bytes(&self, height: u64) -> Result<usize, OramError>;
+
+    /// Returns the time spent sorting by the last call to `write_to_path`, or zero if the stash does not sort.
+    #[cfg(feature = "profiling")]
+    fn last_sort_duration(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
+/// The buffers of an [`ObliviousStash`], which are locked into memory under the `mlock` feature.
+#[cfg(all(feature = "mlock", unix))]
+type StashBuffer<T> = crate::locked_memory::LockedVec<T>;
+#[cfg(not(all(feature = "mlock", unix)))]
+type StashBuffer<T> = Vec<T>;
+
+#[derive(Clone)]
 /// A fixed-size, obliviously accessed Path ORAM stash data structure implemented using oblivious sorting.
+/// This is the default [`Stash`] of a [`crate::PathOram`].
+///
+/// With the `mlock` feature, on Unix, the blocks of the stash and the scratch space of `write_to_path`
+/// are locked into memory, and on Linux excluded from core dumps, so that they are never written to disk.
+/// This includes the stashes of the recursive position map ORAMs, which hold positions.
 pub struct ObliviousStash<V: OramBlock> {
-    blocks: Vec<PathOramBlock<V>>,
+    blocks: StashBuffer<PathOramBlock<V>>,
     path_size: StashSize,
+    growth: StashGrowth,
+    /// Scratch space of `write_to_path`, kept between calls to avoid reallocating it on every eviction.
+    level_assignments: StashBuffer<TreeIndex>,
+    level_counts: StashBuffer<u64>,
+    /// The time spent sorting by the last call to `write_to_path`.
+    #[cfg(feature = "profiling")]
+    last_sort_duration: std::time::Duration,
+}
+
+impl<V: OramBlock> std::fmt::Debug for ObliviousStash<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The scratch space and the sort duration are omitted, so that the output is deterministic.
+        f.debug_struct("ObliviousStash")
+            .field("blocks", &self.blocks)
+            .field("path_size", &self.path_size)
+            .field("growth", &self.growth)
+            .finish()
+    }
 }
 
 impl<V: OramBlock> ObliviousStash<V> {
     fn len(&self) -> usize {
         self.blocks.len()
     }
+
+    /// Grows the stash according to its growth strategy, returning the number of blocks added.
+    fn grow(&mut self) -> Result<usize, OramError> {
+        let Some(increment) = self.growth.increment(self.blocks.len()) else {
+            let stash_size = StashSize::try_from(self.blocks.len())?;
+            return Err(OramError::StashOverflowError {
+                stash_size,
+                overflow_capacity: stash_size - self.path_size,
+            });
+        };
+        self.blocks
+            .resize(self.blocks.len() + increment, PathOramBlock::<V>::dummy());
+        Ok(increment)
+    }
 }
 
-impl<V: OramBlock> ObliviousStash<V> {
-    pub fn new(path_size: StashSize, overflow_size: StashSize) -> Result<Self, OramError> {
+impl<V: OramBlock> Stash<V> for ObliviousStash<V> {
+    fn new(
+        path_size: StashSize,
+        overflow_size: StashSize,
+        growth: StashGrowth,
+    ) -> Result<Self, OramError> {
+        growth.validate()?;
         let num_stash_blocks: usize = (path_size + overflow_size).try_into()?;
 
         Ok(Self {
-            blocks: vec![PathOramBlock::<V>::dummy(); num_stash_blocks],
+            blocks: StashBuffer::from(vec![PathOramBlock::<V>::dummy(); num_stash_blocks]),
             path_size,
+            growth,
+            level_assignments: StashBuffer::from(Vec::new()),
+            level_counts: StashBuffer::from(Vec::new()),
+            #[cfg(feature = "profiling")]
+            last_sort_duration: std::time::Duration::ZERO,
         })
     }
 
-    pub fn write_to_path<const Z: BucketSize>(
+    fn write_to_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
         &mut self,
-        physical_memory: &mut [Bucket<V, Z>],
+        physical_memory: &mut P,
         position: TreeIndex,
     ) -> Result<(), OramError> {
-        let height = position.ct_depth();
-        let mut level_assignments = vec![TreeIndex::MAX; self.len()];
-        let mut level_counts = vec![0; usize::try_from(height)? + 1];
-
-        for (i, block) in self.blocks.iter().enumerate() {
-            // If `block` is a dummy, the rest of this loop iteration will be a no-op, and the values don't matter.
-            let block_is_dummy = block.ct_is_dummy();
-
-            // Set up valid but meaningless input to the computation in case `block` is a dummy.
-            let an_arbitrary_leaf: TreeIndex = 1 << height;
-            let block_position =
-                TreeIndex::conditional_select(&block.position, &an_arbitrary_leaf, block_is_dummy);
-
-            // Assign the block to a bucket or to the overflow.
-            let mut assigned = Choice::from(0);
-            // Obliviously scan through the buckets from leaf to root,
-            // assigning the block to the first empty bucket satisfying the invariant.
-            for (level, count) in level_counts.iter_mut().enumerate().rev() {
-                let level_bucket_full: Choice = count.ct_eq(&(u64::try_from(Z)?));
-
-                let level_u64 = u64::try_from(level)?;
-                let level_satisfies_invariant = block_position
-                    .ct_node_on_path(level_u64, height)
-                    .ct_eq(&position.ct_node_on_path(level_u64, height));
-
-                let should_assign = level_satisfies_invariant
-                    & (!level_bucket_full)
-                    & (!block_is_dummy)
-                    & (!assigned);
-                assigned |= should_assign;
-
-                let level_count_incremented = *count + 1;
-                count.conditional_assign(&level_count_incremented, should_assign);
-                level_assignments[i].conditional_assign(&level_u64, should_assign);
-            }
-            // If the block was not able to be assigned to any bucket, assign it to the overflow.
-            level_assignments[i].conditional_assign(&(TreeIndex::MAX - 1), !assigned);
-        }
+        let (height, arity_bits) = path_shape(physical_memory, position);
+        self.level_assignments.resize(self.len(), TreeIndex::MAX);
+        self.level_assignments.fill(TreeIndex::MAX);
+        self.level_counts.resize(usize::try_from(height)? + 1, 0);
+        self.level_counts.fill(0);
+
+        assign_real_blocks::<V, Z>(
+            &self.blocks,
+            position,
+            arity_bits,
+            &mut self.level_assignments,
+            &mut self.level_counts,
+        )?;
 
         // Assign dummy blocks to the remaining non-full buckets until all buckets are full.
-        let mut exists_unfilled_levels: Choice = 1.into();
-        let mut first_unassigned_block_index: usize = 0;
-        // Unless the stash overflows, this loop will execute exactly once, and the inner `if` will not execute.
-        // If the stash overflows, this loop will execute twice and the inner `if` will execute.
+        // Unless the stash overflows, this loop will execute exactly once, and its body will not execute.
+        // If the stash overflows, the body will execute once.
         // This difference in control flow will leak the fact that the stash has overflowed.
         // This is a violation of obliviousness, but the alternative is simply to fail.
         // If the stash is set large enough when the ORAM is initialized,
         // stash overflow will occur only with negligible probability.
-        while exists_unfilled_levels.into() {
-            for (i, block) in self
-                .blocks
-                .iter()
-                .enumerate()
-                .skip(first_unassigned_block_index)
-            {
-                let block_free = block.ct_is_dummy();
-
-                let mut assigned: Choice = 0.into();
-                for (level, count) in level_counts.iter_mut().enumerate() {
-                    let full = count.ct_eq(&(u64::try_from(Z)?));
-                    let no_op = assigned | full | !block_free;
+        let mut first_unassigned_block_index: usize = 0;
+        while assign_dummy_blocks::<V, Z>(
+            &self.blocks,
+            first_unassigned_block_index,
+            &mut self.level_assignments,
+            &mut self.level_counts,
+        )?
+        .into()
+        {
+            first_unassigned_block_index = self.blocks.len();
 
-                    level_assignments[i].conditional_assign(&(u64::try_from(level))?, !no_op);
-                    count.conditional_assign(&(*count + 1), !no_op);
-                    assigned |= !no_op;
-                }
-            }
+            let increment = self.grow()?;
+            self.level_assignments
+                .resize(self.level_assignments.len() + increment, TreeIndex::MAX);
 
-            exists_unfilled_levels = 0.into();
-            for count in level_counts.iter() {
-                let full = count.ct_eq(&(u64::try_from(Z)?));
-                exists_unfilled_levels |= !full;
-            }
+            log::warn!(
+                "Stash overflow occurred. Stash resized to {} blocks.",
+                self.blocks.len()
+            );
+            audit::record(
+                AuditEventKind::StashOverflow,
+                format!("Stash resized to {} blocks.", self.blocks.len()),
+            );
+        }
 
-            if exists_unfilled_levels.into() {
-                first_unassigned_block_index = self.blocks.len();
-
-                self.blocks.resize(
-                    self.blocks.len() + STASH_GROWTH_INCREMENT,
-                    PathOramBlock::<V>::dummy(),
-                );
-                level_assignments.resize(
-                    level_assignments.len() + STASH_GROWTH_INCREMENT,
-                    TreeIndex::MAX,
-                );
-
-                log::warn!(
-                    "Stash overflow occurred. Stash resized to {} blocks.",
-                    self.blocks.len()
-                );
-            }
+        #[cfg(feature = "profiling")]
+        let sort_start = std::time::Instant::now();
+        bitonic_sort_by_keys(&mut self.blocks, &mut self.level_assignments);
+        #[cfg(feature = "profiling")]
+        {
+            self.last_sort_duration = sort_start.elapsed();
         }
 
-        bitonic_sort_by_keys(&mut self.blocks, &mut level_assignments);
+        write_sorted_blocks(&self.blocks, physical_memory, position)
+    }
 
-        // Write the first Z * height blocks into slots in the tree
-        for depth in 0..=height {
-            let bucket_to_write =
-                &mut physical_memory[usize::try_from(position.ct_node_on_path(depth, height))?];
-            for slot_number in 0..Z {
-                let stash_index = (usize::try_from(depth)?) * Z + slot_number;
+    fn access<F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError> {
+        Ok(access_blocks(
+            &mut self.blocks,
+            address,
+            new_position,
+            value_callback,
+        ))
+    }
 
-                bucket_to_write.blocks[slot_number] = self.blocks[stash_index];
-            }
+    /// Accesses the block with address `address` without evicting.
+    /// The target block is obliviously removed from the path to `position` or from the stash,
+    /// updated with `new_position` and `value_callback`, and inserted into a free stash slot.
+    ///
+    /// Assumes that the path slots of the stash (the first `path_size` blocks) are all dummies,
+    /// and preserves this invariant.
+    fn access_without_eviction<
+        const Z: BucketSize,
+        P: PathStorage<V, Z> + ?Sized,
+        F: Fn(&V) -> V,
+    >(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError> {
+        let (result, uninserted) = move_block_to_overflow(
+            &mut self.blocks,
+            self.path_size.try_into()?,
+            physical_memory,
+            position,
+            address,
+            new_position,
+            value_callback,
+        )?;
+
+        // As in `write_to_path`, growing the stash leaks the fact that the stash has overflowed.
+        if let Some(target) = uninserted {
+            let first_new_slot = self.blocks.len();
+            self.grow()?;
+            self.blocks[first_new_slot] = target;
+
+            log::warn!(
+                "Stash overflow occurred. Stash resized to {} blocks.",
+                self.blocks.len()
+            );
+            audit::record(
+                AuditEventKind::StashOverflow,
+                format!("Stash resized to {} blocks.", self.blocks.len()),
+            );
         }
 
+        Ok(result)
+    }
+
+    /// Replaces the path slots of the stash (the first `path_size` blocks) with dummy blocks.
+    /// After `write_to_path`, these slots hold copies of blocks that were written to the tree.
+    fn clear_path_slots(&mut self) -> Result<(), OramError> {
+        let path_size: usize = self.path_size.try_into()?;
+        for block in self.blocks.iter_mut().take(path_size) {
+            *block = PathOramBlock::dummy();
+        }
         Ok(())
     }
 
-    pub fn access<F: Fn(&V) -> V>(
+    fn blocks_mut(&mut self) -> &mut [PathOramBlock<V>] {
+        &mut self.blocks
+    }
+
+    fn occupancy(&self) -> StashSize {
+        count_overflow_blocks(&self.blocks, self.path_size)
+    }
+
+    fn read_from_path<const Z: crate::BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError> {
+        read_path_blocks(&mut self.blocks, self.path_size, physical_memory, position)
+    }
+
+    /// Returns the number of blocks the overflow area of the stash can hold before it grows.
+    fn overflow_capacity(&self) -> StashSize {
+        self.blocks.len() as StashSize - self.path_size
+    }
+
+    /// Returns the number of bytes allocated for the stash blocks.
+    fn memory_bytes(&self) -> usize {
+        self.blocks.capacity() * std::mem::size_of::<PathOramBlock<V>>()
+    }
+
+    /// Returns the number of bytes of scratch space used by `write_to_path` for a tree of height `height`,
+    /// which the stash keeps allocated between calls.
+    fn scratch_bytes(&self, height: u64) -> Result<usize, OramError> {
+        Ok(self.len() * std::mem::size_of::<TreeIndex>()
+            + (usize::try_from(height)? + 1) * std::mem::size_of::<u64>())
+    }
+
+    #[cfg(feature = "profiling")]
+    fn last_sort_duration(&self) -> std::time::Duration {
+        self.last_sort_duration
+    }
+}
+
+/// The largest number of levels of any path, which bounds the scratch space of a [`FixedStash`].
+const MAXIMUM_PATH_LEVELS: usize = MAXIMUM_TREE_HEIGHT as usize + 1;
+
+/// An obliviously accessed Path ORAM stash of exactly `CAPACITY` blocks, which never allocates after construction.
+///
+/// `FixedStash` implements the same oblivious sorting as [`ObliviousStash`], but holds its blocks,
+/// and the scratch space used by `write_to_path`, in arrays sized at compile time.
+/// It suits environments that forbid dynamic allocation after startup, such as some embedded TEEs.
+/// Since the position map ORAMs of a [`crate::PathOram`] use an [`ObliviousStash`],
+/// an ORAM serving accesses without allocating must also have a recursion cutoff of at least its block capacity,
+/// so that its position map is a linear scan.
+///
+/// Rather than growing, a `FixedStash` fails with a `StashOverflowError` when it overflows,
+/// which leaves the ORAM in an inconsistent state and poisons it, as with [`StashGrowth::Never`].
+/// As with [`ObliviousStash`], this happens only with negligible probability
+/// if `CAPACITY` leaves enough room for the overflow.
+#[derive(Clone, Debug)]
+pub struct FixedStash<V: OramBlock, const CAPACITY: usize> {
+    blocks: [PathOramBlock<V>; CAPACITY],
+    path_size: StashSize,
+    level_assignments: [TreeIndex; CAPACITY],
+    level_counts: [u64; MAXIMUM_PATH_LEVELS],
+}
+
+impl<V: OramBlock, const CAPACITY: usize> FixedStash<V, CAPACITY> {
+    fn overflow_error(&self) -> OramError {
+        log::warn!("Stash overflow occurred. Stash of {CAPACITY} blocks is full.");
+        audit::record(
+            AuditEventKind::StashOverflow,
+            format!("Stash of {CAPACITY} blocks is full."),
+        );
+        OramError::StashOverflowError {
+            stash_size: CAPACITY as StashSize,
+            overflow_capacity: self.overflow_capacity(),
+        }
+    }
+}
+
+impl<V: OramBlock, const CAPACITY: usize> Stash<V> for FixedStash<V, CAPACITY> {
+    /// Returns an empty stash of `CAPACITY` blocks. `growth` is validated, but otherwise ignored.
+    ///
+    /// # Errors
+    ///
+    /// If `path_size + overflow_size` is greater than `CAPACITY`, returns an `InvalidConfigurationError`.
+    fn new(
+        path_size: StashSize,
+        overflow_size: StashSize,
+        growth: StashGrowth,
+    ) -> Result<Self, OramError> {
+        growth.validate()?;
+        let num_stash_blocks = path_size.checked_add(overflow_size);
+        if num_stash_blocks.map_or(true, |num_stash_blocks| {
+            num_stash_blocks > StashSize::try_from(CAPACITY).unwrap_or(StashSize::MAX)
+        }) {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Stash size".to_string(),
+                parameter_value: format!("{path_size} + {overflow_size}"),
+            });
+        }
+
+        Ok(Self {
+            blocks: [PathOramBlock::<V>::dummy(); CAPACITY],
+            path_size,
+            level_assignments: [TreeIndex::MAX; CAPACITY],
+            level_counts: [0; MAXIMUM_PATH_LEVELS],
+        })
+    }
+
+    fn read_from_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError> {
+        read_path_blocks(&mut self.blocks, self.path_size, physical_memory, position)
+    }
+
+    fn access<F: Fn(&V) -> V>(
         &mut self,
         address: Address,
         new_position: TreeIndex,
         value_callback: F,
     ) -> Result<V, OramError> {
-        let mut result: V = V::default();
-
-        for block in &mut self.blocks {
-            let is_requested_index = block.address.ct_eq(&address);
+        Ok(access_blocks(
+            &mut self.blocks,
+            address,
+            new_position,
+            value_callback,
+        ))
+    }
 
-            // Read current value of target block into `result`.
-            result.conditional_assign(&block.value, is_requested_index);
+    fn write_to_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError> {
+        let (height, arity_bits) = path_shape(physical_memory, position);
+        let num_levels = usize::try_from(height)? + 1;
+        let level_counts = &mut self.level_counts[..num_levels];
+        self.level_assignments.fill(TreeIndex::MAX);
+        level_counts.fill(0);
 
-            // Write new position into target block.
-            block
-                .position
-                .conditional_assign(&new_position, is_requested_index);
+        assign_real_blocks::<V, Z>(
+            &self.blocks,
+            position,
+            arity_bits,
+            &mut self.level_assignments,
+            level_counts,
+        )?;
+        // This operation is not constant-time, but only leaks whether the stash has overflowed.
+        if assign_dummy_blocks::<V, Z>(&self.blocks, 0, &mut self.level_assignments, level_counts)?
+            .into()
+        {
+            return Err(self.overflow_error());
+        }
 
-            // If a write, write new value into target block.
-            let value_to_write = value_callback(&result);
+        bitonic_sort_by_keys(&mut self.blocks, &mut self.level_assignments);
+        write_sorted_blocks(&self.blocks, physical_memory, position)
+    }
 
-            block
-                .value
-                .conditional_assign(&value_to_write, is_requested_index);
+    fn access_without_eviction<
+        const Z: BucketSize,
+        P: PathStorage<V, Z> + ?Sized,
+        F: Fn(&V) -> V,
+    >(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError> {
+        let (result, uninserted) = move_block_to_overflow(
+            &mut self.blocks,
+            self.path_size.try_into()?,
+            physical_memory,
+            position,
+            address,
+            new_position,
+            value_callback,
+        )?;
+        if uninserted.is_some() {
+            return Err(self.overflow_error());
         }
         Ok(result)
     }
 
-    #[cfg(test)]
-    pub fn occupancy(&self) -> StashSize {
-        let mut result = 0;
-        for i in self.path_size.try_into().unwrap()..(self.blocks.len()) {
-            if !self.blocks[i].is_dummy() {
-                result += 1;
+    fn clear_path_slots(&mut self) -> Result<(), OramError> {
+        let path_size: usize = self.path_size.try_into()?;
+        for block in self.blocks.iter_mut().take(path_size) {
+            *block = PathOramBlock::dummy();
+        }
+        Ok(())
+    }
+
+    fn blocks_mut(&mut self) -> &mut [PathOramBlock<V>] {
+        &mut self.blocks
+    }
+
+    fn occupancy(&self) -> StashSize {
+        count_overflow_blocks(&self.blocks, self.path_size)
+    }
+
+    fn overflow_capacity(&self) -> StashSize {
+        CAPACITY as StashSize - self.path_size
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<[PathOramBlock<V>; CAPACITY]>()
+    }
+
+    /// Returns the number of bytes of scratch space used by `write_to_path`,
+    /// which is held by the stash rather than allocated on each call.
+    fn scratch_bytes(&self, _height: u64) -> Result<usize, OramError> {
+        Ok(std::mem::size_of::<[TreeIndex; CAPACITY]>()
+            + std::mem::size_of::<[u64; MAXIMUM_PATH_LEVELS]>())
+    }
+}
+
+/// A non-oblivious stash holding its real blocks in a `Vec`, evicting them greedily.
+///
+/// **Warning**: the time taken by, and the memory accessed by, every operation of this stash depend on
+/// the stash contents and on the accessed address, so an ORAM using it is **not** oblivious.
+/// It is intended only as a baseline for benchmarking the cost of obliviousness, and for testing.
+#[derive(Clone, Debug)]
+pub struct InsecureVecStash<V: OramBlock> {
+    blocks: Vec<PathOramBlock<V>>,
+}
+
+impl<V: OramBlock> InsecureVecStash<V> {
+    /// Removes and returns the block with address `address` from the stash, if present.
+    fn remove(&mut self, address: Address) -> Option<PathOramBlock<V>> {
+        let index = self
+            .blocks
+            .iter()
+            .position(|block| block.address == address)?;
+        Some(self.blocks.swap_remove(index))
+    }
+}
+
+impl<V: OramBlock> Stash<V> for InsecureVecStash<V> {
+    fn new(
+        path_size: StashSize,
+        overflow_size: StashSize,
+        growth: StashGrowth,
+    ) -> Result<Self, OramError> {
+        growth.validate()?;
+        Ok(Self {
+            blocks: Vec::with_capacity((path_size + overflow_size).try_into()?),
+        })
+    }
+
+    fn read_from_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+    ) -> Result<(), OramError> {
+        let (height, arity_bits) = path_shape(physical_memory, position);
+        for depth in 0..=height {
+            let bucket_index = position.ct_node_on_kary_path(depth, height, arity_bits);
+            let bucket = physical_memory.read_bucket(depth, bucket_index)?;
+            self.blocks
+                .extend(bucket.blocks.iter().filter(|block| !block.is_dummy()));
+        }
+        Ok(())
+    }
+
+    fn access<F: Fn(&V) -> V>(
+        &mut self,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError> {
+        match self
+            .blocks
+            .iter_mut()
+            .find(|block| block.address == address)
+        {
+            Some(block) => {
+                let result = block.value;
+                block.value = value_callback(&result);
+                block.position = new_position;
+                Ok(result)
             }
+            None => Ok(V::default()),
         }
-        result
     }
 
-    pub fn read_from_path<const Z: crate::BucketSize>(
+    fn write_to_path<const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
         &mut self,
-        physical_memory: &mut [Bucket<V, Z>],
+        physical_memory: &mut P,
         position: TreeIndex,
     ) -> Result<(), OramError> {
-        let height = position.ct_depth();
+        let (height, arity_bits) = path_shape(physical_memory, position);
+        // Fill the path from the leaf up, so that blocks are evicted as deep as possible.
+        for depth in (0..=height).rev() {
+            let bucket_index = position.ct_node_on_kary_path(depth, height, arity_bits);
+            let mut bucket = Bucket::<V, Z>::default();
+            let mut slot = 0;
+            let mut i = 0;
+            while (slot < Z) & (i < self.blocks.len()) {
+                if self.blocks[i]
+                    .position
+                    .ct_node_on_kary_path(depth, height, arity_bits)
+                    == bucket_index
+                {
+                    bucket.blocks[slot] = self.blocks.swap_remove(i);
+                    slot += 1;
+                } else {
+                    i += 1;
+                }
+            }
+            physical_memory.write_bucket(depth, bucket_index, &bucket)?;
+        }
+        Ok(())
+    }
 
-        for i in (0..(self.path_size / u64::try_from(Z)?)).rev() {
-            let bucket_index = position.ct_node_on_path(i, height);
-            let bucket = physical_memory[usize::try_from(bucket_index)?];
-            for slot_index in 0..Z {
-                self.blocks[Z * (usize::try_from(i)?) + slot_index] = bucket.blocks[slot_index];
+    fn access_without_eviction<
+        const Z: BucketSize,
+        P: PathStorage<V, Z> + ?Sized,
+        F: Fn(&V) -> V,
+    >(
+        &mut self,
+        physical_memory: &mut P,
+        position: TreeIndex,
+        address: Address,
+        new_position: TreeIndex,
+        value_callback: F,
+    ) -> Result<V, OramError> {
+        let mut target = self.remove(address);
+        let (height, arity_bits) = path_shape(physical_memory, position);
+        for depth in 0..=height {
+            if target.is_some() {
+                break;
+            }
+            let bucket_index = position.ct_node_on_kary_path(depth, height, arity_bits);
+            let mut bucket = physical_memory.read_bucket(depth, bucket_index)?;
+            if let Some(block) = bucket
+                .blocks
+                .iter_mut()
+                .find(|block| !block.is_dummy() & (block.address == address))
+            {
+                target = Some(std::mem::replace(block, PathOramBlock::dummy()));
+                physical_memory.write_bucket(depth, bucket_index, &bucket)?;
             }
         }
 
+        let result = target.map_or_else(V::default, |block| block.value);
+        self.blocks.push(PathOramBlock {
+            value: value_callback(&result),
+            address,
+            position: new_position,
+        });
+        Ok(result)
+    }
+
+    fn clear_path_slots(&mut self) -> Result<(), OramError> {
+        // `write_to_path` removes the blocks it evicts.
         Ok(())
     }
+
+    fn blocks_mut(&mut self) -> &mut [PathOramBlock<V>] {
+        &mut self.blocks
+    }
+
+    fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    fn occupancy(&self) -> StashSize {
+        self.blocks.len() as StashSize
+    }
+
+    fn overflow_capacity(&self) -> StashSize {
+        self.blocks.capacity() as StashSize
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.blocks.capacity() * std::mem::size_of::<PathOramBlock<V>>()
+    }
+
+    fn scratch_bytes(&self, _height: u64) -> Result<usize, OramError> {
+        Ok(0)
+    }
+}
+
+/// Assigns every real block of `blocks` to the deepest non-full level of the path to `position`
+/// in a tree of arity `2^arity_bits` that it may reside at, or to the overflow if there is none,
+/// recording the levels in `level_assignments` and the number of blocks assigned to each level in `level_counts`.
+fn assign_real_blocks<V: OramBlock, const Z: BucketSize>(
+    blocks: &[PathOramBlock<V>],
+    position: TreeIndex,
+    arity_bits: u32,
+    level_assignments: &mut [TreeIndex],
+    level_counts: &mut [u64],
+) -> Result<(), OramError> {
+    let binary_height = position.ct_depth();
+    let height = binary_height / TreeHeight::from(arity_bits);
+    for (i, block) in blocks.iter().enumerate() {
+        // If `block` is a dummy, the rest of this loop iteration will be a no-op, and the values don't matter.
+        let block_is_dummy = block.ct_is_dummy();
+        speculation_barrier();
+
+        // Set up valid but meaningless input to the computation in case `block` is a dummy.
+        let an_arbitrary_leaf: TreeIndex = 1 << binary_height;
+        let block_position =
+            TreeIndex::conditional_select(&block.position, &an_arbitrary_leaf, block_is_dummy);
+
+        // Assign the block to a bucket or to the overflow.
+        let mut assigned = Choice::from(0);
+        // Obliviously scan through the buckets from leaf to root,
+        // assigning the block to the first empty bucket satisfying the invariant.
+        for (level, count) in level_counts.iter_mut().enumerate().rev() {
+            let level_bucket_full: Choice = count.ct_eq(&(u64::try_from(Z)?));
+
+            let level_u64 = u64::try_from(level)?;
+            let level_satisfies_invariant = block_position
+                .ct_node_on_kary_path(level_u64, height, arity_bits)
+                .ct_eq(&position.ct_node_on_kary_path(level_u64, height, arity_bits));
+
+            let should_assign =
+                level_satisfies_invariant & (!level_bucket_full) & (!block_is_dummy) & (!assigned);
+            assigned |= should_assign;
+
+            let level_count_incremented = *count + 1;
+            count.conditional_assign(&level_count_incremented, should_assign);
+            level_assignments[i].conditional_assign(&level_u64, should_assign);
+        }
+        // If the block was not able to be assigned to any bucket, assign it to the overflow.
+        level_assignments[i].conditional_assign(&(TreeIndex::MAX - 1), !assigned);
+    }
+    Ok(())
+}
+
+/// Assigns the dummy blocks of `blocks`, from index `first_index` on, to the non-full levels
+/// recorded in `level_counts`, returning whether any level remains non-full.
+fn assign_dummy_blocks<V: OramBlock, const Z: BucketSize>(
+    blocks: &[PathOramBlock<V>],
+    first_index: usize,
+    level_assignments: &mut [TreeIndex],
+    level_counts: &mut [u64],
+) -> Result<Choice, OramError> {
+    for (i, block) in blocks.iter().enumerate().skip(first_index) {
+        let block_free = block.ct_is_dummy();
+        speculation_barrier();
+
+        let mut assigned: Choice = 0.into();
+        for (level, count) in level_counts.iter_mut().enumerate() {
+            let full = count.ct_eq(&(u64::try_from(Z)?));
+            let no_op = assigned | full | !block_free;
+
+            level_assignments[i].conditional_assign(&(u64::try_from(level))?, !no_op);
+            count.conditional_assign(&(*count + 1), !no_op);
+            assigned |= !no_op;
+        }
+    }
+
+    let mut exists_unfilled_levels: Choice = 0.into();
+    for count in level_counts.iter() {
+        let full = count.ct_eq(&(u64::try_from(Z)?));
+        exists_unfilled_levels |= !full;
+    }
+    Ok(exists_unfilled_levels)
+}
+
+/// Writes the first `Z * (height + 1)` blocks of `blocks`, sorted by level, into the path to `position`.
+fn write_sorted_blocks<V: OramBlock, const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+    blocks: &[PathOramBlock<V>],
+    physical_memory: &mut P,
+    position: TreeIndex,
+) -> Result<(), OramError> {
+    let (height, arity_bits) = path_shape(physical_memory, position);
+    for depth in 0..=height {
+        let mut bucket_to_write = Bucket::<V, Z>::default();
+        for slot_number in 0..Z {
+            let stash_index = (usize::try_from(depth)?) * Z + slot_number;
+
+            bucket_to_write.blocks[slot_number] = blocks[stash_index];
+        }
+        physical_memory.write_bucket(
+            depth,
+            position.ct_node_on_kary_path(depth, height, arity_bits),
+            &bucket_to_write,
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads the blocks of the path to `position` into the path slots of `blocks`.
+fn read_path_blocks<V: OramBlock, const Z: BucketSize, P: PathStorage<V, Z> + ?Sized>(
+    blocks: &mut [PathOramBlock<V>],
+    path_size: StashSize,
+    physical_memory: &mut P,
+    position: TreeIndex,
+) -> Result<(), OramError> {
+    let (height, arity_bits) = path_shape(physical_memory, position);
+
+    for i in (0..(path_size / u64::try_from(Z)?)).rev() {
+        let bucket_index = position.ct_node_on_kary_path(i, height, arity_bits);
+        let bucket = physical_memory.read_bucket(i, bucket_index)?;
+        for slot_index in 0..Z {
+            blocks[Z * (usize::try_from(i)?) + slot_index] = bucket.blocks[slot_index];
+        }
+    }
+
+    Ok(())
+}
+
+/// Obliviously scans `blocks` for the block with address `address`, returning its value,
+/// replacing it with `value_callback` applied to it, and moving the block to `new_position`.
+fn access_blocks<V: OramBlock, F: Fn(&V) -> V>(
+    blocks: &mut [PathOramBlock<V>],
+    address: Address,
+    new_position: TreeIndex,
+    value_callback: F,
+) -> V {
+    let mut result: V = V::default();
+
+    for block in blocks {
+        let is_requested_index = block.address.ct_eq(&address);
+        speculation_barrier();
+
+        // Read current value of target block into `result`.
+        result.conditional_assign(&block.value, is_requested_index);
+
+        // Write new position into target block.
+        block
+            .position
+            .conditional_assign(&new_position, is_requested_index);
+
+        // If a write, write new value into target block.
+        let value_to_write = value_callback(&result);
+
+        block
+            .value
+            .conditional_assign(&value_to_write, is_requested_index);
+    }
+    result
+}
+
+/// Obliviously removes the block with address `address` from the path to `position` and from the overflow
+/// slots of `blocks` (those after the first `path_size`), updates it with `new_position` and `value_callback`,
+/// and inserts it into the first free overflow slot. Every bucket of the path is read and written back. Returns the previous value of the block,
+/// and the updated block if no overflow slot was free.
+fn move_block_to_overflow<
+    V: OramBlock,
+    const Z: BucketSize,
+    P: PathStorage<V, Z> + ?Sized,
+    F: Fn(&V) -> V,
+>(
+    blocks: &mut [PathOramBlock<V>],
+    path_size: usize,
+    physical_memory: &mut P,
+    position: TreeIndex,
+    address: Address,
+    new_position: TreeIndex,
+    value_callback: F,
+) -> Result<(V, Option<PathOramBlock<V>>), OramError> {
+    let (height, arity_bits) = path_shape(physical_memory, position);
+    let mut target = PathOramBlock::<V>::dummy();
+
+    // Scan the path and the stash for the target block, replacing it with a dummy block.
+    for depth in 0..=height {
+        let bucket_index = position.ct_node_on_kary_path(depth, height, arity_bits);
+        let mut bucket = physical_memory.read_bucket(depth, bucket_index)?;
+        for block in bucket.blocks.iter_mut() {
+            let is_target = block.address.ct_eq(&address) & !block.ct_is_dummy();
+            speculation_barrier();
+            target.conditional_assign(block, is_target);
+            block.conditional_assign(&PathOramBlock::dummy(), is_target);
+        }
+        physical_memory.write_bucket(depth, bucket_index, &bucket)?;
+    }
+    for block in blocks.iter_mut().skip(path_size) {
+        let is_target = block.address.ct_eq(&address) & !block.ct_is_dummy();
+        speculation_barrier();
+        target.conditional_assign(block, is_target);
+        block.conditional_assign(&PathOramBlock::dummy(), is_target);
+    }
+
+    let result = target.value;
+    target.value = value_callback(&result);
+    target.address = address;
+    target.position = new_position;
+
+    // Insert the target block into the first free overflow slot.
+    let mut inserted = Choice::from(0);
+    for block in blocks.iter_mut().skip(path_size) {
+        let should_insert = block.ct_is_dummy() & !inserted;
+        speculation_barrier();
+        block.conditional_assign(&target, should_insert);
+        inserted |= should_insert;
+    }
+
+    // This operation is not constant-time, but only leaks whether the stash has overflowed.
+    if bool::from(inserted) {
+        Ok((result, None))
+    } else {
+        Ok((result, Some(target)))
+    }
+}
+
+/// Returns the number of real blocks in the overflow slots of `blocks` (those after the first `path_size`).
+fn count_overflow_blocks<V: OramBlock>(
+    blocks: &[PathOramBlock<V>],
+    path_size: StashSize,
+) -> StashSize {
+    let mut result = 0;
+    for block in blocks.iter().skip(path_size.try_into().unwrap()) {
+        if !block.is_dummy() {
+            result += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, path_oram::PathOram, test_utils::random_workload, Oram};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn insecure_vec_stash_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for eviction_period in [1, 3] {
+            let mut oram =
+                PathOram::<BlockValue<1>, 4, 8, InsecureVecStash<_>>::new_with_parameters(
+                    64, &mut rng, 40, 1,
+                )
+                .unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            random_workload(&mut oram, 500);
+            assert_eq!(oram.real_block_count(), 64);
+        }
+    }
+
+    #[test]
+    fn oblivious_stash_reuses_scratch_space() {
+        let mut stash = ObliviousStash::<u64>::new(12, 8, StashGrowth::Doubling).unwrap();
+        let mut buckets = [Bucket::<u64, 4>::default(); 3];
+        stash
+            .write_to_path(&mut PathBuckets(&mut buckets), 4)
+            .unwrap();
+        let level_assignments = stash.level_assignments.as_ptr();
+        let level_counts = stash.level_counts.as_ptr();
+
+        for position in 4..8 {
+            stash
+                .write_to_path(&mut PathBuckets(&mut buckets), position)
+                .unwrap();
+            assert_eq!(stash.level_assignments.as_ptr(), level_assignments);
+            assert_eq!(stash.level_counts.as_ptr(), level_counts);
+        }
+        assert_eq!(stash.level_assignments.len(), 20);
+        assert_eq!(stash.level_counts.len(), 3);
+    }
+
+    #[test]
+    fn fixed_stash_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(
+            PathOram::<BlockValue<1>, 4, 8, FixedStash<_, 32>>::new_with_parameters(
+                64, &mut rng, 40, 64
+            )
+            .is_err()
+        );
+
+        for eviction_period in [1, 3] {
+            let mut oram = PathOram::<BlockValue<1>, 4, 8, FixedStash<_, 64>>::new_with_parameters(
+                64, &mut rng, 40, 64,
+            )
+            .unwrap();
+            oram.set_eviction_period(eviction_period).unwrap();
+            random_workload(&mut oram, 500);
+            assert_eq!(oram.real_block_count(), 64);
+        }
+    }
+
+    #[test]
+    fn fixed_stash_fails_on_overflow() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram =
+            PathOram::<u64, 2, 8, FixedStash<_, 14>>::new_with_parameters(64, &mut rng, 2, 64)
+                .unwrap();
+        oram.set_eviction_period(64).unwrap();
+        let result = (0..64).try_for_each(|i| oram.write(i, i, &mut rng).map(|_| ()));
+        assert!(matches!(
+            result,
+            Err(OramError::StashOverflowError {
+                stash_size: 14,
+                overflow_capacity: 2
+            })
+        ));
+    }
 }
@@ -0,0 +1,220 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An ORAM whose address space is split across independently locked shards.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use std::sync::{Mutex, MutexGuard};
+
+/// An ORAM whose address space is split across `num_shards` independent [`DefaultOram`] shards,
+/// each behind its own lock, so that accesses to different shards can proceed concurrently from many threads.
+///
+/// Address `a` is stored at address `a / num_shards` of shard `a % num_shards`.
+///
+/// ## Security
+///
+/// Each access is oblivious *within* its shard, but the shard accessed is revealed
+/// (through timing, lock contention, and the memory locations touched).
+/// A `StripedOram` is therefore only oblivious when the shard of each accessed address
+/// (that is, the address modulo `num_shards`) is not secret.
+/// If it is secret, callers should first map addresses through a secret pseudorandom permutation,
+/// so that the revealed shards are uniformly distributed and independent of the logical addresses;
+/// even then, repeated accesses to the same address reveal that they hit the same shard.
+#[derive(Debug)]
+pub struct StripedOram<V: OramBlock> {
+    shards: Vec<Mutex<DefaultOram<V>>>,
+    /// The capacity in blocks of each shard, stored here so that bounds checks do not need to take a lock.
+    shard_capacity: Address,
+}
+
+impl<V: OramBlock> StripedOram<V> {
+    /// Returns a new `StripedOram` mapping addresses `0 <= address < block_capacity` to default `V` values,
+    /// split across `num_shards` shards.
+    ///
+    /// # Errors
+    ///
+    /// If `num_shards` is not a power of two, or is greater than `block_capacity / 2`,
+    /// returns an `InvalidConfigurationError`. Otherwise, fails under the same conditions as [`DefaultOram::new`].
+    pub fn new<R: OramRng>(
+        block_capacity: Address,
+        num_shards: Address,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        if !num_shards.is_power_of_two() || num_shards > block_capacity / 2 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Number of shards".to_string(),
+                parameter_value: num_shards.to_string(),
+            });
+        }
+
+        let shard_capacity = block_capacity / num_shards;
+        let shards = (0..num_shards)
+            .map(|_| Ok(Mutex::new(DefaultOram::new(shard_capacity, rng)?)))
+            .collect::<Result<_, OramError>>()?;
+        Ok(Self {
+            shards,
+            shard_capacity,
+        })
+    }
+
+    /// Returns the number of shards.
+    pub fn num_shards(&self) -> Address {
+        self.shards.len() as Address
+    }
+
+    /// Returns the shard storing `index`. This is the information revealed by an access to `index`.
+    pub fn shard_of(&self, index: Address) -> Address {
+        index % self.num_shards()
+    }
+
+    /// Returns the capacity in blocks of this ORAM.
+    pub fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(self.shard_capacity * self.num_shards())
+    }
+
+    /// Performs a (oblivious within its shard) ORAM access, locking only the shard storing `index`.
+    /// Returns the value `v` previously stored at `index`, and writes `callback(v)` to `index`.
+    ///
+    /// # Errors
+    ///
+    /// If a thread panicked while accessing the shard storing `index`, that shard may have been left mid-access,
+    /// and every later access to it returns a `PoisonedError`. Otherwise, fails under the same conditions
+    /// as [`DefaultOram::access`](Oram::access).
+    pub fn access<R: OramRng, F: Fn(&V) -> V>(
+        &self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            });
+        }
+
+        let shard = usize::try_from(self.shard_of(index))?;
+        self.lock_shard(shard)?
+            .access(index / self.num_shards(), callback, rng)
+    }
+
+    /// Obliviously (within its shard) reads the value stored at `index`.
+    pub fn read<R: OramRng>(&self, index: Address, rng: &mut R) -> Result<V, OramError> {
+        self.access(index, |v| *v, rng)
+    }
+
+    /// Obliviously (within its shard) writes the value stored at `index`.
+    /// Returns the value previously stored at `index`.
+    pub fn write<R: OramRng>(
+        &self,
+        index: Address,
+        new_value: V,
+        rng: &mut R,
+    ) -> Result<V, OramError> {
+        self.access(index, |_| new_value, rng)
+    }
+
+    fn lock_shard(&self, shard: usize) -> Result<MutexGuard<'_, DefaultOram<V>>, OramError> {
+        // A shard whose lock is poisoned may have been left mid-access, so it cannot safely be used again.
+        self.shards[shard]
+            .lock()
+            .map_err(|_| OramError::PoisonedError)
+    }
+}
+
+impl<V: OramBlock> Oram for StripedOram<V> {
+    type V = V;
+    type Error = OramError;
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        StripedOram::block_capacity(self)
+    }
+
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, OramError> {
+        StripedOram::access(self, index, callback, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn striped_oram_random_workload() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = StripedOram::<BlockValue<1>>::new(64, 4, &mut rng).unwrap();
+        assert_eq!(oram.block_capacity().unwrap(), 64);
+        random_workload(&mut oram, 1000);
+    }
+
+    #[test]
+    fn striped_oram_concurrent_accesses() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let num_threads = 4;
+        let oram = StripedOram::<u64>::new(64, num_threads, &mut rng).unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..num_threads {
+                let oram = &oram;
+                scope.spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(thread);
+                    for _ in 0..10 {
+                        for index in (thread..64).step_by(num_threads as usize) {
+                            oram.access(index, |v| v + index, &mut rng).unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        for index in 0..64 {
+            assert_eq!(oram.read(index, &mut rng).unwrap(), 10 * index);
+        }
+    }
+
+    #[test]
+    fn striped_oram_poisoned_shard() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let oram = StripedOram::<u64>::new(64, 4, &mut rng).unwrap();
+
+        std::thread::scope(|scope| {
+            let oram = &oram;
+            let panicked = scope
+                .spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(1);
+                    oram.access(1, |_| panic!("callback panicked"), &mut rng)
+                })
+                .join();
+            assert!(panicked.is_err());
+        });
+
+        // Only the shard that was being accessed is poisoned.
+        assert!(matches!(
+            oram.read(5, &mut rng),
+            Err(OramError::PoisonedError)
+        ));
+        assert_eq!(oram.write(2, 3, &mut rng).unwrap(), 0);
+        assert_eq!(oram.read(2, &mut rng).unwrap(), 3);
+    }
+
+    #[test]
+    fn striped_oram_rejects_invalid_shard_counts() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(StripedOram::<u64>::new(64, 3, &mut rng).is_err());
+        assert!(StripedOram::<u64>::new(64, 64, &mut rng).is_err());
+        assert!(StripedOram::<u64>::new(64, 0, &mut rng).is_err());
+    }
+}
@@ -11,9 +11,10 @@
 use std::fmt::Debug;
 use std::sync::Once;
 static INIT: Once = Once::new();
+pub(crate) use crate::monitors::StashSizeMonitor;
 use crate::path_oram::PathOram;
 use crate::{
-    Address, BlockSize, BucketSize, Oram, OramBlock, OramError, RecursionCutoff, StashSize,
+    Address, BlockSize, BucketSize, Oram, OramBlock, OramError, OramRng, RecursionCutoff, StashSize,
 };
 use rand::{
     distributions::{Distribution, Standard},
@@ -220,47 +221,17 @@ macro_rules! create_path_oram_stash_size_tests {
     };
 }
 
-#[derive(Debug)]
-pub(crate) struct StashSizeMonitor<V: OramBlock, const Z: BucketSize, const AB: BlockSize> {
-    oram: PathOram<V, Z, AB>,
-}
-
 impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> StashSizeMonitor<V, Z, AB> {
-    pub(crate) fn new_with_parameters<R: rand::RngCore + rand::CryptoRng>(
+    pub(crate) fn new_with_parameters<R: OramRng>(
         block_capacity: Address,
         rng: &mut R,
         overflow_size: StashSize,
         recursion_cutoff: RecursionCutoff,
     ) -> Result<Self, OramError> {
-        Ok(Self {
-            oram: PathOram::new_with_parameters(
-                block_capacity,
-                rng,
-                overflow_size,
-                recursion_cutoff,
-            )
-            .unwrap(),
-        })
-    }
-}
-
-impl<V: OramBlock, const Z: BucketSize, const AB: BlockSize> Oram for StashSizeMonitor<V, Z, AB> {
-    type V = V;
-
-    fn block_capacity(&self) -> Result<Address, OramError> {
-        self.oram.block_capacity()
-    }
-
-    fn access<R: rand::RngCore + rand::CryptoRng, F: Fn(&V) -> V>(
-        &mut self,
-        index: Address,
-        callback: F,
-        rng: &mut R,
-    ) -> Result<V, OramError> {
-        let result = self.oram.access(index, callback, rng);
-        let stash_size = self.oram.stash_occupancy();
-        assert!(stash_size < 10);
-        result
+        Ok(Self::new(
+            PathOram::new_with_parameters(block_capacity, rng, overflow_size, recursion_cutoff)?,
+            9,
+        ))
     }
 }
 
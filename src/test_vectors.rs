@@ -0,0 +1,199 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Generation of known-answer test transcripts (requires the `test-vectors` feature).
+//!
+//! A [`Transcript`] records, for a deterministic workload run against a deterministically seeded [`PathOram`],
+//! the physical buckets touched by each logical operation and a digest of the final tree.
+//! Pinning transcripts detects unintended changes to the protocol across versions,
+//! and the canonical text format of [`Transcript::to_text`] allows cross-checking against other implementations.
+
+use crate::{
+    bucket::BlockValue, path_oram::PathOram, Address, BlockSize, BucketSize, Oram, OramError,
+    RecursionCutoff, StashSize,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// A logical ORAM operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation<const B: BlockSize> {
+    /// A read of the given address.
+    Read(Address),
+    /// A write of the given value to the given address.
+    Write(Address, BlockValue<B>),
+}
+
+/// A logical operation together with the physical buckets it touched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranscriptEntry<const B: BlockSize> {
+    /// The logical operation performed.
+    pub operation: Operation<B>,
+    /// The indices of the buckets of the top-level tree read and written by the operation, from the root to a leaf.
+    /// The root has index 1, and the children of bucket `i` are `2i` and `2i + 1`.
+    pub buckets: Vec<u64>,
+}
+
+/// A known-answer transcript of a deterministic workload run against a deterministically seeded `PathOram`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript<const B: BlockSize> {
+    /// The operations performed, in order.
+    pub entries: Vec<TranscriptEntry<B>>,
+    /// The SHA-256 digest of the final contents of the tree (see [`tree_digest`]).
+    pub final_digest: [u8; 32],
+}
+
+/// The parameters of a known-answer transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TranscriptParameters {
+    /// The seed of the `StdRng` driving both the ORAM and the workload.
+    pub seed: u64,
+    /// The capacity in blocks of the ORAM.
+    pub block_capacity: Address,
+    /// The stash overflow size of the ORAM.
+    pub overflow_size: StashSize,
+    /// The recursion cutoff of the ORAM.
+    pub recursion_cutoff: RecursionCutoff,
+    /// The number of logical operations to perform.
+    pub number_of_operations: usize,
+}
+
+/// Runs a workload of uniformly random reads and writes against a `PathOram` with the given parameters,
+/// returning the resulting transcript. The result depends only on the parameters and the type parameters.
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`PathOram::new_with_parameters`].
+pub fn generate_transcript<const B: BlockSize, const Z: BucketSize, const AB: BlockSize>(
+    parameters: TranscriptParameters,
+) -> Result<Transcript<B>, OramError> {
+    let mut rng = StdRng::seed_from_u64(parameters.seed);
+    let mut oram = PathOram::<BlockValue<B>, Z, AB>::new_with_parameters(
+        parameters.block_capacity,
+        &mut rng,
+        parameters.overflow_size,
+        parameters.recursion_cutoff,
+    )?;
+
+    let mut entries = Vec::with_capacity(parameters.number_of_operations);
+    for _ in 0..parameters.number_of_operations {
+        let address = rng.gen_range(0..parameters.block_capacity);
+        let operation = if rng.gen() {
+            Operation::Write(address, rng.gen())
+        } else {
+            Operation::Read(address)
+        };
+
+        match operation {
+            Operation::Read(address) => oram.read(address, &mut rng)?,
+            Operation::Write(address, value) => oram.write(address, value, &mut rng)?,
+        };
+
+        let leaf = oram.last_leaf();
+        let height = leaf.ilog2();
+        let buckets = (0..=height).rev().map(|shift| leaf >> shift).collect();
+        entries.push(TranscriptEntry { operation, buckets });
+    }
+
+    Ok(Transcript {
+        entries,
+        final_digest: tree_digest(&oram),
+    })
+}
+
+/// Returns the SHA-256 digest of the contents of the tree of `oram`.
+///
+/// Each block of each bucket, in order of bucket index, contributes its address and position
+/// (as little-endian `u64`s) followed by its data.
+pub fn tree_digest<const B: BlockSize, const Z: BucketSize, const AB: BlockSize>(
+    oram: &PathOram<BlockValue<B>, Z, AB>,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for bucket in oram.buckets().iter().skip(1) {
+        for block in bucket.blocks.iter() {
+            hasher.update(block.address.to_le_bytes());
+            hasher.update(block.position.to_le_bytes());
+            hasher.update(block.value.data);
+        }
+    }
+    hasher.finalize().into()
+}
+
+impl<const B: BlockSize> Transcript<B> {
+    /// Returns the transcript in a canonical line-based text format. Each operation is written as
+    /// `read <address> <buckets>` or `write <address> <hex value> <buckets>`, where `<buckets>`
+    /// is a comma-separated list of bucket indices, and the last line is `digest <hex digest>`.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            match entry.operation {
+                Operation::Read(address) => write!(text, "read {address}"),
+                Operation::Write(address, value) => {
+                    write!(text, "write {address} {}", to_hex(&value.data))
+                }
+            }
+            .expect("writing to a String cannot fail");
+            let buckets: Vec<String> = entry.buckets.iter().map(u64::to_string).collect();
+            writeln!(text, " {}", buckets.join(",")).expect("writing to a String cannot fail");
+        }
+        writeln!(text, "digest {}", to_hex(&self.final_digest))
+            .expect("writing to a String cannot fail");
+        text
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMETERS: TranscriptParameters = TranscriptParameters {
+        seed: 0,
+        block_capacity: 64,
+        overflow_size: 40,
+        recursion_cutoff: 16,
+        number_of_operations: 100,
+    };
+
+    #[test]
+    fn transcripts_are_deterministic() {
+        let transcript = generate_transcript::<4, 4, 8>(PARAMETERS).unwrap();
+        assert_eq!(
+            transcript,
+            generate_transcript::<4, 4, 8>(PARAMETERS).unwrap()
+        );
+
+        let other_seed = TranscriptParameters {
+            seed: 1,
+            ..PARAMETERS
+        };
+        let other = generate_transcript::<4, 4, 8>(other_seed).unwrap();
+        assert_ne!(transcript.final_digest, other.final_digest);
+    }
+
+    #[test]
+    fn transcripts_touch_root_to_leaf_paths() {
+        let transcript = generate_transcript::<4, 4, 8>(PARAMETERS).unwrap();
+        assert_eq!(transcript.entries.len(), PARAMETERS.number_of_operations);
+        for entry in &transcript.entries {
+            // A tree with 64 buckets has height 5.
+            assert_eq!(entry.buckets.len(), 6);
+            assert_eq!(entry.buckets[0], 1);
+            for pair in entry.buckets.windows(2) {
+                assert_eq!(pair[1] >> 1, pair[0]);
+            }
+        }
+
+        let text = transcript.to_text();
+        assert_eq!(text.lines().count(), PARAMETERS.number_of_operations + 1);
+        assert!(text.lines().last().unwrap().starts_with("digest "));
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A small, fast ORAM caching the hot blocks of a large, slow ORAM.
+
+use crate::{path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng};
+use rand::Rng;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// A line of the cache. A tag of 0 marks an empty line; otherwise, the tag is the cached address plus 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct CacheLine<V> {
+    tag: Address,
+    value: V,
+    /// Whether the value was written since it was fetched from the slow tier.
+    dirty: u8,
+}
+
+impl<V: ConditionallySelectable> ConditionallySelectable for CacheLine<V> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            tag: Address::conditional_select(&a.tag, &b.tag, choice),
+            value: V::conditional_select(&a.value, &b.value, choice),
+            dirty: u8::conditional_select(&a.dirty, &b.dirty, choice),
+        }
+    }
+}
+
+impl<V: OramBlock> OramBlock for CacheLine<V> {}
+
+/// Wraps a large, slow ORAM (for example, one whose tree is on remote storage) with a small, fast [`DefaultOram`]
+/// caching recently used blocks, so that skewed workloads are mostly served at the latency of the fast tier.
+///
+/// The cache is direct-mapped and write-back: address `a` can only be cached in line `a mod cache_capacity`.
+/// A miss fetches the block from the slow tier into its line (applying the callback in both tiers),
+/// and writes the block it displaces back to the slow tier if it was written while cached.
+///
+/// ## Security
+///
+/// Every logical access makes the same four physical accesses, alternating between the tiers:
+/// a fast access to look up the line, a slow access to fetch the block, a fast access to install it,
+/// and a slow access to write back the displaced block. On a hit, the last three are dummy accesses to random
+/// addresses, and are deferred until the start of the next access (or a call to [`TieredOram::flush`]),
+/// so that the hit is answered after a single fast access. Each tier therefore observes the same sequence
+/// of accesses, in the same order relative to the other tier, whatever the addresses and hit rate;
+/// only the timing of the deferred accesses, relative to the requests of the application, depends on whether
+/// each access hits. Deployments in which that timing is visible should call `flush` at a fixed rate
+/// or after every access.
+#[derive(Debug)]
+pub struct TieredOram<S: Oram> {
+    cache: DefaultOram<CacheLine<S::V>>,
+    backing: S,
+    cache_capacity: Address,
+    /// Whether the dummy accesses of the last hit are yet to be made.
+    deferred: bool,
+    hits: u64,
+    misses: u64,
+}
+
+impl<S: Oram> TieredOram<S> {
+    /// Wraps `backing`, caching up to `cache_capacity` of its blocks in a new fast ORAM.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`DefaultOram::new`] with capacity `cache_capacity`.
+    pub fn new<R: OramRng>(
+        backing: S,
+        cache_capacity: Address,
+        rng: &mut R,
+    ) -> Result<Self, S::Error> {
+        log::info!("TieredOram::new(cache_capacity = {})", cache_capacity);
+
+        Ok(Self {
+            cache: DefaultOram::new(cache_capacity, rng)?,
+            backing,
+            cache_capacity,
+            deferred: false,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Returns the number of accesses served by the fast tier.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of accesses that fetched their block from the slow tier.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Makes the accesses deferred by the last access, if it was a hit. See the security notes of [`TieredOram`].
+    pub fn flush<R: OramRng>(&mut self, rng: &mut R) -> Result<(), S::Error> {
+        if self.deferred {
+            self.dummy_backing_access(rng)?;
+            self.cache
+                .read(rng.gen_range(0..self.cache_capacity), rng)?;
+            self.dummy_backing_access(rng)?;
+            self.deferred = false;
+        }
+        Ok(())
+    }
+
+    /// Writes every block written while cached back to the slow tier, and returns it.
+    /// This reads every line of the cache, so it does not reveal which blocks are cached.
+    pub fn into_inner<R: OramRng>(mut self, rng: &mut R) -> Result<S, S::Error> {
+        self.flush(rng)?;
+        for line in 0..self.cache_capacity {
+            let line = self.cache.read(line, rng)?;
+            if line.dirty == 1 {
+                self.backing.write(line.tag - 1, line.value, rng)?;
+            } else {
+                self.dummy_backing_access(rng)?;
+            }
+        }
+        Ok(self.backing)
+    }
+
+    fn dummy_backing_access<R: OramRng>(&mut self, rng: &mut R) -> Result<(), S::Error> {
+        let capacity = self.backing.block_capacity()?;
+        self.backing.read(rng.gen_range(0..capacity), rng)?;
+        Ok(())
+    }
+}
+
+impl<S: Oram> Oram for TieredOram<S> {
+    type V = S::V;
+    type Error = S::Error;
+
+    fn block_capacity(&self) -> Result<Address, S::Error> {
+        self.backing.block_capacity()
+    }
+
+    fn access<R: OramRng, F: Fn(&Self::V) -> Self::V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        rng: &mut R,
+    ) -> Result<Self::V, S::Error> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        let capacity = self.block_capacity()?;
+        if index >= capacity {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity,
+            }
+            .into());
+        }
+        self.flush(rng)?;
+
+        let tag = index + 1;
+        let line_index = index % self.cache_capacity;
+        let lookup = |line: &CacheLine<S::V>| {
+            let hit = line.tag.ct_eq(&tag);
+            let mut result = *line;
+            result.value.conditional_assign(&callback(&line.value), hit);
+            result.dirty.conditional_assign(&1, hit);
+            result
+        };
+        let line = self.cache.access(line_index, lookup, rng)?;
+
+        // This branch is not constant-time, but both sides make the same accesses, in the same order.
+        if line.tag == tag {
+            self.hits += 1;
+            self.deferred = true;
+            return Ok(line.value);
+        }
+
+        self.misses += 1;
+        let value = self.backing.access(index, &callback, rng)?;
+        let installed = CacheLine {
+            tag,
+            value: callback(&value),
+            dirty: 0,
+        };
+        self.cache.write(line_index, installed, rng)?;
+        if line.dirty == 1 {
+            self.backing.write(line.tag - 1, line.value, rng)?;
+        } else {
+            self.dummy_backing_access(rng)?;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{linear_time_oram::LinearTimeOram, test_utils::random_workload};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A linear-time ORAM counting its accesses.
+    #[derive(Debug)]
+    struct CountingOram {
+        oram: LinearTimeOram<u64>,
+        accesses: u64,
+    }
+
+    impl Oram for CountingOram {
+        type V = u64;
+        type Error = OramError;
+
+        fn block_capacity(&self) -> Result<Address, OramError> {
+            self.oram.block_capacity()
+        }
+
+        fn access<R: OramRng, F: Fn(&u64) -> u64>(
+            &mut self,
+            index: Address,
+            callback: F,
+            rng: &mut R,
+        ) -> Result<u64, OramError> {
+            self.accesses += 1;
+            self.oram.access(index, callback, rng)
+        }
+    }
+
+    #[test]
+    fn tiered_oram_correctness() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let backing = LinearTimeOram::<u64>::new(256).unwrap();
+        let mut oram = TieredOram::new(backing, 16, &mut rng).unwrap();
+        random_workload(&mut oram, 2000);
+        assert!(oram.hits() > 0);
+        assert!(oram.misses() > 0);
+
+        // Dirty lines are written back.
+        oram.write(5, 55, &mut rng).unwrap();
+        oram.write(5, 56, &mut rng).unwrap();
+        let mut backing = oram.into_inner(&mut rng).unwrap();
+        assert_eq!(backing.read(5, &mut rng).unwrap(), 56);
+    }
+
+    #[test]
+    fn tiered_oram_backing_accesses_are_independent_of_hits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let backing = CountingOram {
+            oram: LinearTimeOram::new(1024).unwrap(),
+            accesses: 0,
+        };
+        let mut oram = TieredOram::new(backing, 64, &mut rng).unwrap();
+
+        // A skewed workload: 90% of accesses go to 32 hot addresses.
+        for i in 0..1000 {
+            let index = if rng.gen_bool(0.9) {
+                rng.gen_range(0..32)
+            } else {
+                rng.gen_range(0..1024)
+            };
+            oram.write(index, i, &mut rng).unwrap();
+        }
+        oram.flush(&mut rng).unwrap();
+        assert!(oram.hits() > 800, "{}", oram.hits());
+        assert_eq!(oram.backing.accesses, 2 * 1000);
+        assert_eq!(oram.hits() + oram.misses(), 1000);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! A linear-scan ORAM stored in a fixed-size array, without heap allocation.
+
+use crate::{Address, Oram, OramBlock, OramError, OramRng};
+use subtle::ConstantTimeEq;
+
+/// An ORAM of exactly `N` blocks, stored inline in an array, that ensures obliviousness
+/// by reading and writing every block on each access, as [`crate::linear_time_oram::LinearTimeOram`] does.
+///
+/// `TinyOram` never allocates, so it can live on the stack or in a static, and be used in environments
+/// without a heap, such as embedded TEEs. It also suits small secret tables (lookup tables, S-boxes)
+/// inside constant-time code: [`TinyOram::get`] reads a value through a shared reference, without an RNG.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TinyOram<V: OramBlock, const N: usize> {
+    blocks: [V; N],
+}
+
+impl<V: OramBlock, const N: usize> Default for TinyOram<V, N> {
+    fn default() -> Self {
+        Self::new_with_fill_value(V::default())
+    }
+}
+
+impl<V: OramBlock, const N: usize> From<[V; N]> for TinyOram<V, N> {
+    fn from(blocks: [V; N]) -> Self {
+        Self { blocks }
+    }
+}
+
+impl<V: OramBlock, const N: usize> TinyOram<V, N> {
+    /// Returns a new `TinyOram` mapping addresses `0 <= address < N` to default `V` values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new `TinyOram` mapping addresses `0 <= address < N` to `fill_value`.
+    pub fn new_with_fill_value(fill_value: V) -> Self {
+        Self {
+            blocks: [fill_value; N],
+        }
+    }
+
+    /// Obliviously returns the value stored at `index`.
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn get(&self, index: Address) -> Result<V, OramError> {
+        self.check_bounds(index)?;
+
+        let mut result = V::default();
+        for (i, block) in self.blocks.iter().enumerate() {
+            result.conditional_assign(block, (i as Address).ct_eq(&index));
+        }
+        Ok(result)
+    }
+
+    /// Obliviously overwrites the value stored at `index` with `value`, returning the previous value.
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of bounds, returns an `AddressOutOfBoundsError`.
+    pub fn set(&mut self, index: Address, value: V) -> Result<V, OramError> {
+        self.scan(index, |_| value)
+    }
+
+    fn scan<F: Fn(&V) -> V>(&mut self, index: Address, callback: F) -> Result<V, OramError> {
+        self.check_bounds(index)?;
+
+        // This is a dummy value which will always be overwritten.
+        let mut result = V::default();
+        for (i, block) in self.blocks.iter_mut().enumerate() {
+            let is_requested_index = (i as Address).ct_eq(&index);
+            result.conditional_assign(block, is_requested_index);
+            let potential_new_value = callback(block);
+            block.conditional_assign(&potential_new_value, is_requested_index);
+        }
+        Ok(result)
+    }
+
+    fn check_bounds(&self, index: Address) -> Result<(), OramError> {
+        // This operation is not constant-time, but only leaks whether the ORAM index is well-formed or not.
+        if index >= N as Address {
+            return Err(OramError::AddressOutOfBoundsError {
+                attempted: index,
+                capacity: N as Address,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<V: OramBlock, const N: usize> Oram for TinyOram<V, N> {
+    type V = V;
+    type Error = OramError;
+
+    fn access<R: OramRng, F: Fn(&V) -> V>(
+        &mut self,
+        index: Address,
+        callback: F,
+        _: &mut R,
+    ) -> Result<V, OramError> {
+        self.scan(index, callback)
+    }
+
+    fn block_capacity(&self) -> Result<Address, OramError> {
+        Ok(N as Address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::BlockValue, test_utils::*};
+
+    #[test]
+    fn tiny_oram_correctness_random_workload() {
+        let mut oram = TinyOram::<BlockValue<1>, 64>::new();
+        random_workload(&mut oram, 1000);
+    }
+
+    #[test]
+    fn tiny_oram_lookup_table() {
+        let mut table = TinyOram::from([0x63u8, 0x7c, 0x77, 0x7b]);
+        assert_eq!(table.get(2).unwrap(), 0x77);
+        assert!(table.get(4).is_err());
+        assert_eq!(table.set(2, 0).unwrap(), 0x77);
+        assert_eq!(table.get(2).unwrap(), 0);
+        assert_eq!(table.block_capacity().unwrap(), 4);
+        assert_eq!(TinyOram::<u8, 4>::new_with_fill_value(9).get(3).unwrap(), 9);
+    }
+}
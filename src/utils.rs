@@ -7,11 +7,13 @@
 
 //! Utilities.
 
-use crate::OramError;
+use crate::{OramError, OramRng};
 use rand::seq::SliceRandom;
-use rand::{CryptoRng, Rng, RngCore};
+use rand::Rng;
 
-use subtle::{Choice, ConditionallySelectable, ConstantTimeGreater, ConstantTimeLess};
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
 
 use std::num::TryFromIntError;
 
@@ -23,7 +25,8 @@ where
     Self: Sized,
 {
     fn ct_node_on_path(&self, depth: TreeHeight, height: TreeHeight) -> Self;
-    fn random_leaf<R: RngCore + CryptoRng>(
+    fn ct_node_on_kary_path(&self, depth: TreeHeight, height: TreeHeight, arity_bits: u32) -> Self;
+    fn random_leaf<R: OramRng>(
         tree_height: TreeHeight,
         rng: &mut R,
     ) -> Result<Self, TryFromIntError>;
@@ -34,16 +37,22 @@ where
 impl CompleteBinaryTreeIndex for TreeIndex {
     // A TreeIndex can have any nonzero value.
     fn ct_node_on_path(&self, depth: TreeHeight, height: TreeHeight) -> Self {
+        self.ct_node_on_kary_path(depth, height, 1)
+    }
+
+    // A tree of arity 2^arity_bits is the binary tree of positions with only every arity_bits-th level kept,
+    // so the node at depth `depth` of a path is the binary ancestor at depth `arity_bits * depth`.
+    fn ct_node_on_kary_path(&self, depth: TreeHeight, height: TreeHeight, arity_bits: u32) -> Self {
         // We maintain the invariant that all TreeIndex values are nonzero.
         assert_ne!(*self, 0);
         // We only call this method when the receiver is a leaf.
-        assert!(self.is_leaf(height));
+        assert!(self.is_leaf(height * TreeHeight::from(arity_bits)));
 
-        let shift = height - depth;
+        let shift = (height - depth) * TreeHeight::from(arity_bits);
         self >> shift
     }
 
-    fn random_leaf<R: RngCore + CryptoRng>(
+    fn random_leaf<R: OramRng>(
         tree_height: TreeHeight,
         rng: &mut R,
     ) -> Result<Self, TryFromIntError> {
@@ -71,10 +80,25 @@ impl CompleteBinaryTreeIndex for TreeIndex {
     }
 }
 
+/// Returns the `counter`-th leaf (modulo the number of leaves) of a tree of height `height`
+/// in reverse-lexicographic order, i.e. the leaf whose offset is the bit reversal of `counter`.
+/// Consecutive leaves in this order share as few buckets as possible.
+pub(crate) fn reverse_lexicographic_leaf(counter: u64, height: TreeHeight) -> TreeIndex {
+    let first_leaf: TreeIndex = 1 << height;
+    let offset = counter
+        .reverse_bits()
+        .checked_shr(64 - u32::try_from(height).unwrap_or(64));
+    first_leaf + offset.unwrap_or(0)
+}
+
 /// Sorts `items` in ascending order of `keys`, obliviously and in constant time.
 /// Assumes that `keys.len() == items.len()`.
 /// The algorithm is bitonic sort, based on code written by Hans Werner Lang
 /// and available [here](https://hwlang.de/algorithmen/sortieren/bitonic/oddn.htm).
+/// It handles arbitrary lengths directly, without padding to a power of two.
+///
+/// This is the only oblivious sort in the crate; the stash, ORAM initialization,
+/// and [`crate::ct::sort_by_keys`] all use it.
 pub(crate) fn bitonic_sort_by_keys<
     T: ConditionallySelectable,
     K: Ord + ConditionallySelectable + ConstantTimeGreater + ConstantTimeLess,
@@ -132,7 +156,7 @@ fn helper_bitonic_merge_by_keys<
 }
 
 /// Returns a random permutation of 0 through n.
-pub(crate) fn random_permutation_of_0_through_n_exclusive<R: RngCore + CryptoRng>(
+pub(crate) fn random_permutation_of_0_through_n_exclusive<R: OramRng>(
     n: u64,
     rng: &mut R,
 ) -> Vec<u64> {
@@ -152,6 +176,63 @@ pub(crate) fn invert_permutation_oblivious(permutation: &[u64]) -> Result<Vec<u6
     Ok(result)
 }
 
+/// Moves `items[i]` to index `permutation[i]`, for every `i`, using oblivious (data-independent) operations.
+///
+/// # Errors
+///
+/// If `permutation` is not a permutation of `0..items.len()`, returns an `InvalidConfigurationError`
+/// and leaves `items` unchanged. This check is not constant-time, but only leaks whether the permutation
+/// is well-formed or not.
+pub(crate) fn apply_permutation_oblivious<T: ConditionallySelectable>(
+    items: &mut [T],
+    permutation: &[u64],
+) -> Result<(), OramError> {
+    check_permutation(permutation, u64::try_from(items.len())?)?;
+    let mut keys = permutation.to_owned();
+    bitonic_sort_by_keys(items, &mut keys);
+    Ok(())
+}
+
+/// Checks that `permutation` is a permutation of `0..len`, returning an `InvalidConfigurationError` if not.
+/// This check is not constant-time, but only leaks whether the permutation is well-formed or not.
+pub(crate) fn check_permutation(permutation: &[u64], len: u64) -> Result<(), OramError> {
+    let invalid_permutation = || OramError::InvalidConfigurationError {
+        parameter_name: "Permutation".to_string(),
+        parameter_value: format!("of length {}", permutation.len()),
+    };
+    if u64::try_from(permutation.len())? != len {
+        return Err(invalid_permutation());
+    }
+
+    // A permutation sorts to 0, 1, ..., len - 1.
+    let mut sorted = permutation.to_owned();
+    let mut keys = permutation.to_owned();
+    bitonic_sort_by_keys(&mut sorted, &mut keys);
+    let mut valid = Choice::from(1);
+    for (i, value) in sorted.iter().enumerate() {
+        valid &= value.ct_eq(&u64::try_from(i)?);
+    }
+    if !bool::from(valid) {
+        return Err(invalid_permutation());
+    }
+    Ok(())
+}
+
+/// Returns a vector of `len` copies of `value`.
+/// Unlike `vec!`, returns an `AllocationError` instead of aborting if `len` elements
+/// do not fit in the address space of the target or cannot be allocated.
+pub(crate) fn try_filled_vec<T: Clone>(len: u64, value: T) -> Result<Vec<T>, OramError> {
+    let requested_bytes = len.saturating_mul(u64::try_from(std::mem::size_of::<T>())?);
+    let allocation_error = || OramError::AllocationError { requested_bytes };
+    let len = usize::try_from(len).map_err(|_| allocation_error())?;
+    let mut result = Vec::new();
+    result
+        .try_reserve_exact(len)
+        .map_err(|_| allocation_error())?;
+    result.resize(len, value);
+    Ok(result)
+}
+
 /// Converts a `Vec<u64>` to a `Vec<usize>`.
 pub(crate) fn to_usize_vec(source: Vec<u64>) -> Result<Vec<usize>, OramError> {
     let mut result = Vec::new();
@@ -165,20 +246,32 @@ pub(crate) fn to_usize_vec(source: Vec<u64>) -> Result<Vec<usize>, OramError> {
 #[cfg(test)]
 mod tests {
     use super::TreeIndex;
-    use rand::{rngs::StdRng, SeedableRng};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use static_assertions::const_assert_eq;
     use std::mem::size_of;
 
     use super::{
         bitonic_sort_by_keys, invert_permutation_oblivious,
-        random_permutation_of_0_through_n_exclusive,
+        random_permutation_of_0_through_n_exclusive, reverse_lexicographic_leaf, try_filled_vec,
+        CompleteBinaryTreeIndex,
     };
+    use crate::{path_oram::MAXIMUM_TREE_HEIGHT, OramError};
 
     #[test]
     fn check_size_of_tree_index() {
         const_assert_eq!(size_of::<TreeIndex>(), 8);
     }
 
+    #[test]
+    fn test_reverse_lexicographic_leaf() {
+        assert_eq!(reverse_lexicographic_leaf(0, 0), 1);
+        assert_eq!(reverse_lexicographic_leaf(5, 0), 1);
+
+        let leaves: Vec<TreeIndex> = (0..8).map(|i| reverse_lexicographic_leaf(i, 3)).collect();
+        assert_eq!(leaves, vec![8, 12, 10, 14, 9, 13, 11, 15]);
+        assert_eq!(reverse_lexicographic_leaf(8, 3), 8);
+    }
+
     #[test]
     fn test_invert_permutation_oblivious() {
         let n = 16;
@@ -191,6 +284,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_bitonic_sort() {
         let mut rng = StdRng::seed_from_u64(0);
         let mut items: Vec<u64> = Vec::new();
@@ -207,4 +301,51 @@ mod tests {
             assert_eq!(keys[i], items[i] + (2 * (n as u64)));
         }
     }
+
+    #[test]
+    fn test_bitonic_sort_arbitrary_lengths() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in [0usize, 1, 2, 3, 5, 6, 7, 31, 33, 100] {
+            let mut keys: Vec<u64> = (0..n).map(|_| rng.gen_range(0..8)).collect();
+            let mut items: Vec<u64> = keys.iter().map(|key| 1000 + key).collect();
+            let mut expected = keys.clone();
+            expected.sort();
+
+            bitonic_sort_by_keys(&mut items, &mut keys);
+            assert_eq!(keys, expected);
+            for (item, key) in items.iter().zip(keys.iter()) {
+                assert_eq!(*item, 1000 + key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tree_index_math_at_maximum_height() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let height = MAXIMUM_TREE_HEIGHT;
+        for _ in 0..100 {
+            let leaf = TreeIndex::random_leaf(height, &mut rng).unwrap();
+            assert!(leaf.is_leaf(height));
+            assert_eq!(leaf.ct_node_on_path(0, height), 1);
+            assert_eq!(leaf.ct_node_on_path(height, height), leaf);
+            assert_eq!(leaf.ct_node_on_path(height - 1, height), leaf / 2);
+        }
+        assert_eq!(reverse_lexicographic_leaf(0, height), 1 << height);
+        assert_eq!(
+            reverse_lexicographic_leaf(1, height),
+            (1 << height) + (1 << (height - 1))
+        );
+        assert!(reverse_lexicographic_leaf(u64::MAX, height).is_leaf(height));
+    }
+
+    #[test]
+    fn test_try_filled_vec() {
+        assert_eq!(try_filled_vec(3, 7u64).unwrap(), vec![7, 7, 7]);
+        assert!(matches!(
+            try_filled_vec(1 << 60, 0u64),
+            Err(OramError::AllocationError {
+                requested_bytes: 0x8000_0000_0000_0000
+            })
+        ));
+    }
 }
@@ -0,0 +1,109 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! Reporting of the consistency checks made by [`crate::PathOram::verify`].
+
+use crate::{utils::TreeIndex, Address};
+use std::fmt;
+
+/// A violation of the structural invariants of a Path ORAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// A real block has a position that is not a leaf of the tree.
+    InvalidPosition {
+        /// The address of the block.
+        address: Address,
+        /// The position of the block.
+        position: TreeIndex,
+    },
+    /// A real block has an address outside of the ORAM.
+    AddressOutOfRange {
+        /// The address of the block.
+        address: Address,
+    },
+    /// More than one real block has the same address.
+    DuplicateBlock {
+        /// The address of the blocks.
+        address: Address,
+    },
+    /// A block of the tree is not in a bucket on the path to its position.
+    MisplacedBlock {
+        /// The address of the block.
+        address: Address,
+        /// The position of the block.
+        position: TreeIndex,
+        /// The index of the bucket holding the block, with the root at 1.
+        bucket: TreeIndex,
+    },
+    /// No real block has this address.
+    MissingBlock {
+        /// The missing address.
+        address: Address,
+    },
+    /// The position map records a different position for a block than the block itself.
+    PositionMismatch {
+        /// The address of the block.
+        address: Address,
+        /// The position recorded in the block.
+        block_position: TreeIndex,
+        /// The position recorded in the position map.
+        position_map_position: TreeIndex,
+    },
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPosition { address, position } => {
+                write!(f, "Block {address} has position {position}, which is not a leaf")
+            }
+            Self::AddressOutOfRange { address } => write!(f, "Block {address} is out of range"),
+            Self::DuplicateBlock { address } => write!(f, "Block {address} is duplicated"),
+            Self::MisplacedBlock {
+                address,
+                position,
+                bucket,
+            } => write!(
+                f,
+                "Block {address} is in bucket {bucket}, which is not on the path to its position {position}"
+            ),
+            Self::MissingBlock { address } => write!(f, "Block {address} is missing"),
+            Self::PositionMismatch {
+                address,
+                block_position,
+                position_map_position,
+            } => write!(
+                f,
+                "The position map disagrees with block {address}: {position_map_position} instead of {block_position}"
+            ),
+        }
+    }
+}
+
+/// The result of a consistency check of a Path ORAM (see [`crate::PathOram::verify`]).
+///
+/// The report describes the stored data, so it should not be revealed to untrusted parties.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The number of buckets of the tree that were checked.
+    pub buckets_checked: u64,
+    /// The number of stash entries (real or dummy) that were checked.
+    pub stash_entries_checked: u64,
+    /// The number of real blocks found in the tree.
+    pub tree_blocks: u64,
+    /// The number of real blocks found in the stash.
+    pub stash_blocks: u64,
+    /// The violations found, in the order in which they were found.
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+impl VerificationReport {
+    /// Returns true if no violation was found.
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright (c) Meta Platforms, Inc. and affiliates.
+//
+// This source code is dual-licensed under either the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree or the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree. You may select, at your option, one of the above-listed licenses.
+
+//! An ORAM retaining the last few versions of each block.
+
+use crate::{
+    allocator::BlockAllocator, path_oram::DefaultOram, Address, Oram, OramBlock, OramError, OramRng,
+};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
+
+/// The retained versions of a block, newest first, and the addresses of the slots storing them.
+/// A version of 0 marks an empty entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct VersionHistory<const K: usize> {
+    versions: [u64; K],
+    slots: [Address; K],
+    /// Whether a version has ever been evicted, so that older values are no longer known.
+    truncated: u8,
+}
+
+impl<const K: usize> Default for VersionHistory<K> {
+    fn default() -> Self {
+        Self {
+            versions: [0; K],
+            slots: [0; K],
+            truncated: 0,
+        }
+    }
+}
+
+impl<const K: usize> ConditionallySelectable for VersionHistory<K> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut result = Self::default();
+        for i in 0..K {
+            result.versions[i] = u64::conditional_select(&a.versions[i], &b.versions[i], choice);
+            result.slots[i] = Address::conditional_select(&a.slots[i], &b.slots[i], choice);
+        }
+        result.truncated = u8::conditional_select(&a.truncated, &b.truncated, choice);
+        result
+    }
+}
+
+impl<const K: usize> OramBlock for VersionHistory<K> {}
+
+impl<const K: usize> VersionHistory<K> {
+    /// Returns the slot of the newest retained version no newer than `version`, and whether there is one.
+    fn find(&self, version: u64) -> (Address, Choice) {
+        let mut slot = 0;
+        let mut found = Choice::from(0);
+        for i in 0..K {
+            let matches = !self.versions[i].ct_eq(&0) & !self.versions[i].ct_gt(&version) & !found;
+            slot.conditional_assign(&self.slots[i], matches);
+            found |= matches;
+        }
+        (slot, found)
+    }
+
+    /// Returns whether every entry is in use, so that recording a version evicts the oldest one.
+    fn is_full(&self) -> Choice {
+        !self.versions[K - 1].ct_eq(&0)
+    }
+
+    /// Returns this history with `version`, stored in `slot`, recorded as the newest version.
+    fn record(&self, version: u64, slot: Address) -> Self {
+        let mut result = Self::default();
+        result.versions[0] = version;
+        result.slots[0] = slot;
+        result.versions[1..].copy_from_slice(&self.versions[..K - 1]);
+        result.slots[1..].copy_from_slice(&self.slots[..K - 1]);
+        result.truncated = self.truncated | self.is_full().unwrap_u8();
+        result
+    }
+}
+
+/// An ORAM of `block_capacity` blocks that retains the last `K` versions of each block,
+/// and can obliviously read any block as of any earlier version of the whole ORAM.
+///
+/// Every write is a new version of the ORAM, numbered from 1; version 0 is the initial, all-default state.
+/// Writes are copy-on-write: the new value of a block goes to a fresh slot of a value ORAM of `slot_capacity` blocks,
+/// handed out by a [`BlockAllocator`], and a per-block history in another ORAM records which slot holds which version.
+/// Once a block has `K` versions, each write recycles the slot of its oldest version.
+/// A block written `w` times occupies `min(w, K)` slots, so `slot_capacity` only needs to cover the versions
+/// actually retained, rather than `K` slots for every block.
+///
+/// ## Security
+///
+/// `read`, `read_at_version`, and `write` each make two accesses to the histories, one to the allocator,
+/// and one to the values, so the type of each operation, its address, and the version read are all hidden.
+/// Only whether `write` fails (because the value ORAM is full) is revealed.
+#[derive(Debug)]
+pub struct VersionedOram<V: OramBlock, const K: usize> {
+    histories: DefaultOram<VersionHistory<K>>,
+    values: DefaultOram<V>,
+    allocator: BlockAllocator,
+    version: u64,
+}
+
+impl<V: OramBlock, const K: usize> VersionedOram<V, K> {
+    /// Returns a new `VersionedOram` of `block_capacity` default blocks,
+    /// storing up to `slot_capacity` written versions across all blocks.
+    ///
+    /// # Errors
+    ///
+    /// If `K` is 0, returns an `InvalidConfigurationError`.
+    /// Otherwise, fails under the same conditions as [`DefaultOram::new`], for both capacities.
+    pub fn new<R: OramRng>(
+        block_capacity: Address,
+        slot_capacity: Address,
+        rng: &mut R,
+    ) -> Result<Self, OramError> {
+        if K == 0 {
+            return Err(OramError::InvalidConfigurationError {
+                parameter_name: "Retained versions K".to_string(),
+                parameter_value: K.to_string(),
+            });
+        }
+
+        log::info!(
+            "VersionedOram::new(block_capacity = {}, slot_capacity = {}, K = {})",
+            block_capacity,
+            slot_capacity,
+            K
+        );
+
+        Ok(Self {
+            histories: DefaultOram::new(block_capacity, rng)?,
+            values: DefaultOram::new(slot_capacity, rng)?,
+            allocator: BlockAllocator::new(slot_capacity, rng)?,
+            version: 0,
+        })
+    }
+
+    /// Returns the number of blocks.
+    pub fn block_capacity(&self) -> Result<Address, OramError> {
+        self.histories.block_capacity()
+    }
+
+    /// Returns the number of the latest version, that is, the number of writes so far.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the number of value slots in use.
+    pub fn slots_in_use(&self) -> Address {
+        self.allocator.allocated()
+    }
+
+    /// Obliviously reads the latest value of `index`.
+    pub fn read<R: OramRng>(&mut self, index: Address, rng: &mut R) -> Result<V, OramError> {
+        // The latest version of a block is always retained.
+        Ok(self
+            .read_at_version(index, self.version, rng)?
+            .unwrap_or_default())
+    }
+
+    /// Obliviously reads the value of `index` as of `version`, that is, right after the `version`-th write.
+    /// Returns `None` if that value is older than the `K` retained versions of `index`.
+    pub fn read_at_version<R: OramRng>(
+        &mut self,
+        index: Address,
+        version: u64,
+        rng: &mut R,
+    ) -> Result<Option<V>, OramError> {
+        let history = self.histories.read(index, rng)?;
+        let (slot, found) = history.find(version);
+        self.allocator.dummy_access(rng)?;
+        // If the version is not found, this reads an arbitrary slot.
+        let value = self.values.read(slot, rng)?;
+        self.histories.write(index, history, rng)?;
+
+        if found.into() {
+            Ok(Some(value))
+        } else if history.truncated == 1 {
+            Ok(None)
+        } else {
+            Ok(Some(V::default()))
+        }
+    }
+
+    /// Obliviously writes `value` to `index` as a new version, and returns the number of that version.
+    ///
+    /// # Errors
+    ///
+    /// If `index` has fewer than `K` versions and every slot is in use, returns an `OutOfMemoryError`.
+    pub fn write<R: OramRng>(
+        &mut self,
+        index: Address,
+        value: V,
+        rng: &mut R,
+    ) -> Result<u64, OramError> {
+        let history = self.histories.read(index, rng)?;
+
+        // This branch is not constant-time, but both sides make a single access to the allocator.
+        let full = history.is_full();
+        let slot = if full.into() {
+            self.allocator.dummy_access(rng)?;
+            history.slots[K - 1]
+        } else {
+            self.allocator.allocate(rng)?
+        };
+
+        self.values.write(slot, value, rng)?;
+        let version = self.version + 1;
+        self.histories
+            .write(index, history.record(version, slot), rng)?;
+        self.version = version;
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn versioned_oram_reads_retained_versions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = VersionedOram::<u64, 4>::new(16, 64, &mut rng).unwrap();
+        let capacity = oram.block_capacity().unwrap();
+
+        // `mirror[v]` is the state of the ORAM as of version `v`.
+        let mut mirror = vec![vec![0u64; capacity as usize]];
+        let mut written = vec![Vec::new(); capacity as usize];
+        for _ in 0..300 {
+            let index = rng.gen_range(0..capacity);
+            let value = rng.gen::<u64>();
+            let version = oram.write(index, value, &mut rng).unwrap();
+            assert_eq!(version as usize, mirror.len());
+
+            let mut state = mirror[mirror.len() - 1].clone();
+            state[index as usize] = value;
+            mirror.push(state);
+            written[index as usize].push(version);
+
+            for _ in 0..4 {
+                let index = rng.gen_range(0..capacity);
+                let version = rng.gen_range(0..=oram.version());
+                let writes = &written[index as usize];
+                // The value as of `version` is retained if one of the last K writes is no newer than it,
+                // or if there have been at most K writes.
+                let retained =
+                    writes.len() <= 4 || writes[writes.len() - 4..].iter().any(|&w| w <= version);
+                let expected = retained.then_some(mirror[version as usize][index as usize]);
+                assert_eq!(
+                    oram.read_at_version(index, version, &mut rng).unwrap(),
+                    expected
+                );
+            }
+        }
+
+        for index in 0..capacity {
+            assert_eq!(
+                oram.read(index, &mut rng).unwrap(),
+                mirror[mirror.len() - 1][index as usize]
+            );
+        }
+        assert_eq!(oram.slots_in_use(), 4 * capacity);
+    }
+
+    #[test]
+    fn versioned_oram_runs_out_of_slots() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut oram = VersionedOram::<u64, 2>::new(8, 4, &mut rng).unwrap();
+        for i in 0..4 {
+            oram.write(i / 2, i, &mut rng).unwrap();
+        }
+        // Blocks 0 and 1 recycle their own slots, but block 2 needs a new one.
+        oram.write(0, 10, &mut rng).unwrap();
+        assert!(matches!(
+            oram.write(2, 10, &mut rng),
+            Err(OramError::OutOfMemoryError { capacity: 4 })
+        ));
+        assert_eq!(oram.version(), 5);
+        assert_eq!(oram.read_at_version(0, 5, &mut rng).unwrap(), Some(10));
+        assert_eq!(oram.read_at_version(0, 2, &mut rng).unwrap(), Some(1));
+        assert_eq!(oram.read_at_version(0, 1, &mut rng).unwrap(), None);
+        assert_eq!(oram.read_at_version(1, 2, &mut rng).unwrap(), Some(0));
+        assert_eq!(oram.read(2, &mut rng).unwrap(), 0);
+
+        assert!(VersionedOram::<u64, 0>::new(8, 4, &mut rng).is_err());
+    }
+}